@@ -17,6 +17,10 @@ fn create_sample_analytics_message(id: u64) -> AnalyticsMessage {
         country: Some("US".to_string()),
         device_type: Some("Desktop".to_string()),
         browser: Some("Chrome".to_string()),
+        request_id: Some(format!("req{}", id)),
+        user_agent: None,
+        variant: None,
+        deferred: false,
     }
 }
 
@@ -0,0 +1,83 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use hyperlinkr::{clock::SystemClock, config::settings::Settings, errors::AppError, services::cache::cache::CacheService, types::UrlData};
+use std::future::Future;
+use std::hint::black_box;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+
+// Opt-in: these talk to a real Dragonfly, e.g. `docker compose up -d dragonfly`
+const DRAGONFLY_URL_VAR: &str = "HYPERLINKR_BENCH_DRAGONFLY";
+
+fn sample_url_data() -> UrlData {
+  UrlData {
+    long_url: "https://example.com/products/summer-sale?ref=newsletter".to_string(),
+    created_at: "2025-06-01T12:00:00+00:00".to_string(),
+    ..Default::default()
+  }
+}
+
+// ==================== CacheService Tier Benchmarks ====================
+
+fn cache_service_benchmark(c: &mut Criterion) {
+  let Ok(url) = std::env::var(DRAGONFLY_URL_VAR) else {
+    eprintln!("Skipping CacheService benches; set {}=redis://127.0.0.1:6379 to run them", DRAGONFLY_URL_VAR);
+    return;
+  };
+  let rt = Runtime::new().unwrap();
+  let mut config = Settings::default();
+  config.database_urls = vec![url];
+  config.cache.use_sled = false; // Sled would answer misses that should reach Dragonfly
+  let cache = rt.block_on(CacheService::new(&config, Arc::new(SystemClock)));
+  let url_data = sample_url_data();
+  let key = "bench-tier".to_string();
+  rt.block_on(cache.insert(key.clone(), &url_data)).unwrap();
+
+  let mut group = c.benchmark_group("cache_service");
+
+  // Parsed link straight from L1
+  group.bench_function("get_l1_hit", |b| {
+    b.iter(|| rt.block_on(async { cache.get_url_data(black_box(&key)).await.unwrap() }));
+  });
+
+  // Bloom check, L2 hit, JSON parse and the L1 backfill
+  group.bench_function("get_l2_hit", |b| {
+    b.iter_custom(|iters| timed(&rt, iters, || cache.evict_l1(&key), || cache.get_url_data(&key)));
+  });
+
+  // Bloom check, a Dragonfly round trip, parse and both backfills
+  group.bench_function("get_dragonfly_hit", |b| {
+    b.iter_custom(|iters| timed(&rt, iters, || cache.evict_local(&key), || cache.get_url_data(&key)));
+  });
+
+  // Bloom, L1, L2 and the Dragonfly write, with a fresh key each time
+  group.bench_function("insert", |b| {
+    let next = AtomicU64::new(0);
+    b.iter(|| {
+      let key = format!("bench-insert-{}", next.fetch_add(1, Ordering::Relaxed));
+      rt.block_on(cache.insert(key, &url_data)).unwrap()
+    });
+  });
+
+  group.finish();
+}
+
+// Times only `read`; `reset` puts the key back in the tier under test before each iteration
+fn timed<R, G>(rt: &Runtime, iters: u64, reset: impl Fn() -> R, read: impl Fn() -> G) -> Duration
+where
+  R: Future<Output = ()>,
+  G: Future<Output = Result<Arc<UrlData>, AppError>>,
+{
+  let mut total = Duration::ZERO;
+  for _ in 0..iters {
+    rt.block_on(reset());
+    let start = Instant::now();
+    black_box(rt.block_on(read()).unwrap());
+    total += start.elapsed();
+  }
+  total
+}
+
+criterion_group!(benches, cache_service_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,97 @@
+use axum::{
+  body::{to_bytes, Body},
+  extract::connect_info::MockConnectInfo,
+  http::{header, Request, StatusCode},
+  Router,
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+use hyperlinkr::{config::settings::Settings, test_util::{self, MockStorage}};
+use serde_json::{json, Value};
+use std::hint::black_box;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::runtime::Runtime;
+use tower::ServiceExt;
+
+// The real router and middleware stack over in-memory storage, so handler changes show up here
+// rather than in copies of them
+async fn router(data_dir: &Path) -> Router {
+  let mut config = Settings::default();
+  config.cache.sled_path = data_dir.join("cache.sled").display().to_string();
+  config.analytics.sled_path = data_dir.join("analytics.sled").display().to_string();
+  // Every request comes from one address; the limiter still runs, it just never says no
+  config.rate_limit.shorten_requests_per_minute = u32::MAX;
+  config.rate_limit.redirect_requests_per_minute = u32::MAX;
+  let app = test_util::test_app(config, Arc::new(MockStorage::new())).await;
+  app.router.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))))
+}
+
+fn shorten_request(url: &str) -> Request<Body> {
+  Request::post("/v1/shorten")
+    .header(header::CONTENT_TYPE, "application/json")
+    .body(Body::from(json!({ "url": url }).to_string()))
+    .unwrap()
+}
+
+fn redirect_request(code: &str) -> Request<Body> {
+  Request::get(format!("/v1/redirect/{}", code)).body(Body::empty()).unwrap()
+}
+
+// ==================== HTTP Benchmarks ====================
+
+fn http_benchmark(c: &mut Criterion) {
+  let rt = Runtime::new().unwrap();
+  let data_dir = std::env::temp_dir().join(format!("hyperlinkr-http-bench-{}", std::process::id()));
+  let router = rt.block_on(router(&data_dir));
+
+  let code = rt.block_on(async {
+    let response = router.clone().oneshot(shorten_request("https://example.com/bench")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+    body["data"]["code"].as_str().unwrap().to_string()
+  });
+
+  // Validation, blocklist, code generation and the cache write, one new link per iteration
+  c.bench_function("http_shorten", |b| {
+    let next = AtomicU64::new(0);
+    b.iter(|| {
+      let url = format!("https://example.com/bench/{}", next.fetch_add(1, Ordering::Relaxed));
+      rt.block_on(async {
+        let response = router.clone().oneshot(shorten_request(&url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        black_box(response)
+      })
+    });
+  });
+
+  // A link that is already cached, plus the click being queued for analytics
+  c.bench_function("http_redirect_cached", |b| {
+    b.iter(|| {
+      rt.block_on(async {
+        let response = router.clone().oneshot(redirect_request(black_box(&code))).await.unwrap();
+        assert!(response.status().is_redirection());
+        black_box(response)
+      })
+    });
+  });
+
+  // Unknown codes stop at the bloom filter; only the burned-link check reaches storage
+  c.bench_function("http_redirect_unknown", |b| {
+    b.iter(|| {
+      rt.block_on(async {
+        let response = router.clone().oneshot(redirect_request(black_box("nope123"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        black_box(response)
+      })
+    });
+  });
+
+  // The analytics service flushes on drop, which needs the runtime
+  rt.block_on(async move { drop(router) });
+  std::fs::remove_dir_all(&data_dir).ok();
+}
+
+criterion_group!(benches, http_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,69 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use hyperlinkr::services::cache::{l1_cache::L1Cache, l2_cache::L2Cache};
+use hyperlinkr::services::metrics;
+use hyperlinkr::types::{OpenGraph, UrlData};
+use std::hint::black_box;
+use std::sync::{Arc, Once};
+use tokio::runtime::Runtime;
+
+static INIT: Once = Once::new();
+
+fn ensure_metrics_initialized() {
+  INIT.call_once(|| {
+    metrics::init_metrics();
+  });
+}
+
+// A link with the optional fields a busy account tends to fill in
+fn sample_url_data() -> UrlData {
+  UrlData {
+    long_url: "https://example.com/products/summer-sale?ref=newsletter&variant=blue".to_string(),
+    user_id: Some("ckx7q2m0r0000abcd1234efgh".to_string()),
+    created_at: "2025-06-01T12:00:00+00:00".to_string(),
+    expires_at: Some("2026-06-01T12:00:00+00:00".to_string()),
+    open_graph: Some(OpenGraph {
+      title: Some("Summer sale".to_string()),
+      description: Some("Everything blue is 20% off this week".to_string()),
+      image: Some("https://example.com/og/summer.png".to_string()),
+    }),
+    rotation: vec![
+      "https://mirror-1.example.com/products/summer-sale".to_string(),
+      "https://mirror-2.example.com/products/summer-sale".to_string(),
+    ],
+    ..Default::default()
+  }
+}
+
+// ==================== Redirect Lookup Benchmarks ====================
+
+fn redirect_lookup_benchmark(c: &mut Criterion) {
+  ensure_metrics_initialized();
+
+  let rt = Runtime::new().unwrap();
+  let json = serde_json::to_string(&sample_url_data()).unwrap();
+
+  // Below L1 links are still JSON, so a hit there pays for a full parse
+  c.bench_function("redirect_lookup_l2_json", |b| {
+    let cache = L2Cache::new(1000, 300);
+    rt.block_on(async { cache.insert("abc123".to_string(), json.clone()).await });
+
+    b.iter(|| {
+      rt.block_on(async {
+        let json = cache.get(black_box("abc123")).await.unwrap();
+        serde_json::from_str::<UrlData>(&json).unwrap()
+      })
+    });
+  });
+
+  // L1 holds the parsed link: the hit is a refcount bump
+  c.bench_function("redirect_lookup_l1_typed", |b| {
+    let cache = L1Cache::new(1000, 300);
+    let url_data = Arc::new(serde_json::from_str::<UrlData>(&json).unwrap());
+    rt.block_on(async { cache.insert("abc123".to_string(), url_data).await });
+
+    b.iter(|| rt.block_on(async { cache.get(black_box("abc123")).await.unwrap() }));
+  });
+}
+
+criterion_group!(benches, redirect_lookup_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,534 @@
+use axum::{routing::{delete, get, patch, post, put}, Router};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tracing::info;
+
+use crate::{
+    clock::{Clock, SystemClock},
+    config::{settings::Settings, storage::StorageBackend},
+    errors::AppError,
+    handlers::{
+        account::{change_password_handler, get_me_handler, rotate_signing_secret_handler, set_utm_template_handler, update_me_handler, verify_email_handler},
+        admin::{
+            add_blocklist_handler, add_node_handler, bloom_rebuild_status_handler, broken_links_handler, cache_stats_handler,
+            cache_warmup_handler, disable_link_handler, get_log_level_handler, impersonate_handler, list_audit_handler,
+            list_blocklist_handler, list_circuit_breakers_handler, list_reports_handler, list_reserved_aliases_handler, rebalance_nodes_handler,
+            rebuild_bloom_handler, remove_node_handler, remove_blocklist_handler, remove_reserved_alias_handler,
+            add_reserved_alias_handler, reset_circuit_breaker_handler, set_log_level_handler, trip_circuit_breaker_handler,
+            set_plan_handler, usage_rollup_handler, user_usage_handler,
+        },
+        analytics::{analytics_code_handler, metrics_handler},
+        api_keys::{create_api_key_handler, delete_api_key_handler, list_api_keys_handler},
+        campaigns::{
+            add_campaign_codes_handler, campaign_analytics_handler, create_campaign_handler, list_campaigns_handler,
+        },
+        auth::{self, jwks_handler},
+        codes::reserve_codes_handler,
+        dashboard::dashboard_handler,
+        notifications::list_notifications_handler,
+        redirect::redirect_handler,
+        robots::robots_txt_handler,
+        reports::report_handler,
+        shorten::{
+            accept_transfer_handler, cancel_transfer_handler, destination_history_handler, disable_url_handler,
+            enable_url_handler, list_urls_handler, quick_shorten_handler, request_transfer_handler, search_urls_handler,
+            shorten_handler, toggle_pin_handler, update_destination_handler,
+            AppState,
+        },
+        usage::usage_handler,
+    },
+    middleware::{
+        auth::{auth_middleware, init_auth_middleware},
+        device_info::device_info_middleware,
+        error_envelope::error_envelope_middleware,
+        rate_limit::{init_rate_limit_middleware, rate_limit_middleware},
+        tenant::{tenant_routing_middleware, TenantRouters},
+        usage::usage_middleware,
+    },
+    services::{
+        analytics::AnalyticsService,
+        blocklist::DomainBlocklist,
+        cache::{cache::CacheService, circuit_breaker::CircuitBreaker},
+        campaigns::CampaignService,
+        captcha::CaptchaGate,
+        codegen::generator::CodeGenerator,
+        discovery,
+        geo_lookup,
+        hooks::{Hooks, LifecycleHook},
+        link_checker::LinkChecker,
+        metrics,
+        otlp,
+        password_policy::PasswordPolicy,
+        reserved_aliases::ReservedAliases,
+        sled::{ExpiringKeys, SledStorage},
+        storage::{dragonfly::DatabaseClient, storage::Storage},
+        tokens::TokenService,
+        usage::UsageTracker,
+        shorten_dedup::ShortenCoalescer,
+        threat_intel::{ThreatIntel, ThreatRescanner},
+    },
+};
+
+const METRICS_SAMPLE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// A fully wired service: the routing table plus the state behind it.
+pub struct App {
+    pub state: AppState,
+    pub router: Router,
+}
+
+/// Builds an [`App`] from `Settings`, so tests and embedders don't have to copy `main.rs`.
+///
+/// Process-wide setup (tracing, the GeoIP reader, UA regexes) is left to the caller.
+pub struct Builder {
+    config: Arc<Settings>,
+    storage: Option<Arc<dyn Storage + Send + Sync>>,
+    clock: Option<Arc<dyn Clock>>,
+    background_tasks: bool,
+    hooks: Vec<Arc<dyn LifecycleHook>>,
+    tenant_storage: HashMap<String, Arc<dyn Storage + Send + Sync>>,
+}
+
+impl Builder {
+    pub fn new(config: impl Into<Arc<Settings>>) -> Self {
+        Self {
+            config: config.into(),
+            storage: None,
+            clock: None,
+            background_tasks: true,
+            hooks: Vec::new(),
+            tenant_storage: HashMap::new(),
+        }
+    }
+
+    /// Storage behind the cache, analytics and handlers alike; defaults to one Dragonfly client
+    /// per service from `database_urls`, or one Sled database when `storage.backend` is sled.
+    pub fn storage(mut self, storage: Arc<dyn Storage + Send + Sync>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Defaults to [`SystemClock`].
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Whether to spawn metric samplers, the GeoIP updater and the link checker. Defaults to on.
+    pub fn background_tasks(mut self, enabled: bool) -> Self {
+        self.background_tasks = enabled;
+        self
+    }
+
+    /// Adds a lifecycle hook; hooks run in the order they were added.
+    pub fn hook(mut self, hook: Arc<dyn LifecycleHook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Storage for one tenant instead of a Dragonfly client from its `database_urls`.
+    pub fn tenant_storage(mut self, tenant_id: impl Into<String>, storage: Arc<dyn Storage + Send + Sync>) -> Self {
+        self.tenant_storage.insert(tenant_id.into(), storage);
+        self
+    }
+
+    /// Wires up storage, state and the router for the shared stack and every tenant.
+    ///
+    /// # Errors
+    ///
+    /// Fails if node discovery or a storage connection fails, or a tenant's stack can't be built.
+    pub async fn build(mut self) -> Result<App, AppError> {
+        // Sled already holds every link, so the cache's own Sled tier would only be a second copy
+        let sled_backend = self.config.storage.backend == StorageBackend::Sled;
+        if sled_backend && self.config.cache.use_sled {
+            let mut config = (*self.config).clone();
+            config.cache.use_sled = false;
+            self.config = Arc::new(config);
+        }
+        // Nodes behind `discovery.url` stand in for `database_urls`, unless storage was injected
+        let discovered = self.storage.is_none() && !sled_backend && self.config.discovery.url.is_some();
+        if discovered && let Some(database_urls) = discovery::resolve(&self.config).await? {
+            info!("Discovered {} Dragonfly nodes", database_urls.len());
+            self.config = Arc::new(Settings { database_urls, ..(*self.config).clone() });
+        }
+        let config = Arc::clone(&self.config);
+        let clock = self.clock.take().unwrap_or_else(|| Arc::new(SystemClock));
+        let hooks = Arc::new(Hooks::new(std::mem::take(&mut self.hooks)));
+        let state = self.build_state(Arc::clone(&config), self.storage.clone(), &clock, &hooks).await?;
+
+        // Every tenant gets the whole stack over its own storage, down to the caches
+        let mut tenants = HashMap::new();
+        for tenant in &config.tenants {
+            let storage = self.tenant_storage.get(&tenant.id).cloned();
+            let tenant_state = self.build_state(Arc::new(config.for_tenant(tenant)), storage, &clock, &hooks).await?;
+            let router = build_router(tenant_state);
+            for domain in &tenant.domains {
+                tenants.insert(domain.to_ascii_lowercase(), router.clone());
+            }
+            info!("Routing {} to tenant {}", tenant.domains.join(", "), tenant.id);
+        }
+
+        if self.background_tasks {
+            metrics::spawn_runtime_metrics(METRICS_SAMPLE_INTERVAL);
+            otlp::spawn_otlp_exporter(&config);
+            geo_lookup::spawn_geoip_updater(&config);
+            if discovered {
+                discovery::spawn_node_discovery(&config, state.storage_clients());
+            }
+        }
+
+        let mut router = build_router(state.clone());
+        if !tenants.is_empty() {
+            router = router.layer(axum::middleware::from_fn_with_state(TenantRouters::new(tenants), tenant_routing_middleware));
+        }
+        Ok(App { router, state })
+    }
+
+    /// One tenant's services, plus the background tasks that belong to them.
+    async fn build_state(
+        &self,
+        config: Arc<Settings>,
+        storage: Option<Arc<dyn Storage + Send + Sync>>,
+        clock: &Arc<dyn Clock>,
+        hooks: &Arc<Hooks>,
+    ) -> Result<AppState, AppError> {
+        let storage = storage.or_else(|| {
+            (config.storage.backend == StorageBackend::Sled).then(|| {
+                let sled = Arc::new(SledStorage::new_storage(&config, Arc::clone(clock)));
+                if self.background_tasks {
+                    let sweep_every = Duration::from_secs(config.storage.sled_sweep_interval_secs.unwrap_or(300));
+                    Arc::clone(&sled).spawn_ttl_sweeper("storage", ExpiringKeys::Storage, sweep_every);
+                }
+                sled as Arc<dyn Storage + Send + Sync>
+            })
+        });
+        let (cache, analytics, rl_db) = match storage {
+            Some(storage) => (
+                CacheService::with_storage(&config, Arc::clone(&storage), Arc::clone(clock)).await,
+                AnalyticsService::with_storage(&config, Arc::clone(&storage), Arc::clone(clock)).await,
+                storage,
+            ),
+            None => (
+                CacheService::new(&config, Arc::clone(clock)).await,
+                AnalyticsService::new(&config, Arc::new(new_circuit_breaker(&config)), Arc::clone(clock)).await,
+                Arc::new(DatabaseClient::new(&config, Arc::new(new_circuit_breaker(&config))).await?) as Arc<dyn Storage + Send + Sync>,
+            ),
+        };
+        let (cache, analytics) = (Arc::new(cache), Arc::new(analytics));
+        // Flushed even without background tasks, like analytics, so no counted request is lost
+        let usage = Arc::new(UsageTracker::new(&config, Arc::clone(&rl_db), Arc::clone(clock)));
+        Arc::clone(&usage).spawn_flush(Duration::from_millis(config.usage.flush_interval_ms));
+
+        let blocklist = Arc::new(DomainBlocklist::new(&config)?);
+        let state = AppState {
+            config: Arc::clone(&config),
+            cache: Arc::clone(&cache),
+            codegen: Arc::new(CodeGenerator::new(&config)),
+            analytics,
+            rl_db: Arc::clone(&rl_db),
+            clock: Arc::clone(clock),
+            threat_intel: Arc::new(ThreatIntel::new(&config, Arc::clone(&blocklist))),
+            blocklist,
+            captcha: Arc::new(CaptchaGate::new(&config)),
+            tokens: Arc::new(TokenService::new(&config)?),
+            password_policy: Arc::new(PasswordPolicy::new(&config)?),
+            hooks: Arc::clone(hooks),
+            campaigns: Arc::new(CampaignService::new(Arc::clone(&rl_db))),
+            usage,
+            shortens_in_flight: Arc::new(ShortenCoalescer::new()),
+            link_checker: Arc::new(LinkChecker::new(&config, Arc::clone(&cache), Arc::clone(&rl_db), Arc::clone(clock))),
+            reserved_aliases: Arc::new(ReservedAliases::new(&config, Arc::clone(&rl_db))),
+        };
+
+        if self.background_tasks {
+            metrics::spawn_circuit_breaker_metrics(state.circuit_breakers(), METRICS_SAMPLE_INTERVAL);
+            metrics::spawn_pool_metrics(state.storage_clients(), METRICS_SAMPLE_INTERVAL);
+            Arc::clone(&cache).spawn_hot_set_refresh(Duration::from_secs(config.cache.hot_set_refresh_secs.unwrap_or(10)));
+            if config.link_health.enabled {
+                Arc::clone(&state.link_checker).spawn();
+            }
+            if let Some(secs) = config.security.threat_rescan_interval_secs {
+                Arc::new(ThreatRescanner::new(cache, rl_db, Arc::clone(&state.threat_intel))).spawn(Duration::from_secs(secs));
+            }
+        }
+        Ok(state)
+    }
+}
+
+fn new_circuit_breaker(config: &Settings) -> CircuitBreaker {
+    CircuitBreaker::from_config(config.database_urls.clone(), &config.cache)
+}
+
+/// The complete routing table and middleware stack, ready to serve or to `oneshot` in tests.
+pub fn build_router(state: AppState) -> Router {
+    init_auth_middleware();
+    init_rate_limit_middleware();
+    let v1_routes = Router::new()
+        .route("/urls", get(list_urls_handler))
+        .route("/urls/search", get(search_urls_handler))
+        .route("/urls/{code}", patch(update_destination_handler))
+        .route("/urls/{code}/history", get(destination_history_handler))
+        .route("/urls/{code}/transfer", post(request_transfer_handler).delete(cancel_transfer_handler))
+        .route("/urls/{code}/transfer/accept", post(accept_transfer_handler))
+        .route("/urls/{code}/pin", post(toggle_pin_handler))
+        .route("/urls/{code}/disable", post(disable_url_handler))
+        .route("/urls/{code}/enable", post(enable_url_handler))
+        .route("/shorten", post(shorten_handler))
+        .route("/quick", get(quick_shorten_handler))
+        .route("/codes/reserve", post(reserve_codes_handler))
+        .route("/redirect/{code}", get(redirect_handler))
+        .route("/analytics/{code}", get(analytics_code_handler))
+        .route("/report/{code}", post(report_handler))
+        .route("/notifications", get(list_notifications_handler))
+        .route("/usage", get(usage_handler))
+        .route("/dashboard", get(dashboard_handler))
+        .route("/me", get(get_me_handler).patch(update_me_handler))
+        .route("/me/email/verify", post(verify_email_handler))
+        .route("/me/password", post(change_password_handler))
+        .route("/me/signing-secret", post(rotate_signing_secret_handler))
+        .route("/me/utm", put(set_utm_template_handler))
+        .route("/api-keys", get(list_api_keys_handler).post(create_api_key_handler))
+        .route("/api-keys/{id}", delete(delete_api_key_handler))
+        .route("/campaigns", get(list_campaigns_handler).post(create_campaign_handler))
+        .route("/campaigns/{id}/codes", post(add_campaign_codes_handler))
+        .route("/campaigns/{id}/analytics", get(campaign_analytics_handler))
+        .route("/admin/reports", get(list_reports_handler))
+        .route("/admin/urls/{code}/disable", post(disable_link_handler))
+        .route("/admin/impersonate/{user_id}", post(impersonate_handler))
+        .route("/admin/audit", get(list_audit_handler))
+        .route("/admin/usage", get(usage_rollup_handler))
+        .route("/admin/usage/users/{user_id}", get(user_usage_handler))
+        .route("/admin/users/{user_id}/plan", put(set_plan_handler))
+        .route("/admin/cache/stats", get(cache_stats_handler))
+        .route("/admin/cache/warmup", post(cache_warmup_handler))
+        .route("/admin/bloom/rebuild", get(bloom_rebuild_status_handler).post(rebuild_bloom_handler))
+        .route("/admin/links/broken", get(broken_links_handler))
+        .route("/admin/loglevel", get(get_log_level_handler).put(set_log_level_handler))
+        .route("/admin/circuit-breakers", get(list_circuit_breakers_handler))
+        .route("/admin/circuit-breakers/trip", post(trip_circuit_breaker_handler))
+        .route("/admin/circuit-breakers/reset", post(reset_circuit_breaker_handler))
+        .route("/admin/nodes", post(add_node_handler).delete(remove_node_handler))
+        .route("/admin/nodes/rebalance", post(rebalance_nodes_handler))
+        .route(
+            "/admin/blocklist",
+            get(list_blocklist_handler).post(add_blocklist_handler).delete(remove_blocklist_handler),
+        )
+        .route(
+            "/admin/reserved-aliases",
+            get(list_reserved_aliases_handler).post(add_reserved_alias_handler).delete(remove_reserved_alias_handler),
+        )
+        .route("/metrics", get(metrics_handler));
+
+    // Layers run bottom-up: device info sets up the context, plain-text errors from below get the
+    // JSON envelope, auth fills in the user, usage counts whatever comes back (429s included),
+    // then rate limits apply
+    let router = Router::new()
+        .route("/.well-known/jwks.json", get(jwks_handler))
+        .route("/robots.txt", get(robots_txt_handler))
+        .nest("/v1", v1_routes)
+        .with_state(state.clone())
+        .merge(auth::routes(state.clone()))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), rate_limit_middleware))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), usage_middleware))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), auth_middleware))
+        .layer(axum::middleware::from_fn(error_envelope_middleware))
+        .layer(axum::middleware::from_fn_with_state(state, device_info_middleware));
+    // A static page that signs in through the API itself, so it sits outside auth
+    #[cfg(feature = "admin-ui")]
+    let router = router.merge(crate::handlers::admin_ui::routes());
+    router
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, MockClock, MockStorage};
+    use crate::config::tenant::TenantConfig;
+    use axum::{body::{to_bytes, Body}, extract::connect_info::MockConnectInfo, http::{header, Request, StatusCode}};
+    use chrono::Utc;
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn public_routes_are_reachable_through_the_full_stack() {
+        let app = test_util::test_app(Settings::default(), Arc::new(MockStorage::new())).await;
+        let router = app.router.clone().layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+
+        for uri in ["/.well-known/jwks.json", "/robots.txt", "/v1/metrics"] {
+            let response = router
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+            assert!(response.headers().contains_key("x-request-id"));
+        }
+
+        let response = router
+            .clone()
+            .oneshot(Request::post("/v1/shorten").body(Body::from("not json")).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let request_id = response.headers()["x-request-id"].to_str().unwrap().to_string();
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["success"], false);
+        assert_eq!(body["error"]["code"], "UNSUPPORTED_MEDIA_TYPE");
+        assert_eq!(body["error"]["request_id"], request_id.as_str());
+
+        let today = &app.state.usage.daily(crate::services::usage::ALL_SUBJECT, 1).await.unwrap()[0];
+        assert!(today.endpoints.iter().any(|usage| usage.endpoint == "GET /v1/metrics" && usage.status == 200));
+    }
+
+    #[tokio::test]
+    async fn rejections_and_handler_errors_share_the_error_envelope() {
+        let app = test_util::test_app(Settings::default(), Arc::new(MockStorage::new())).await;
+        let router = app.router.clone().layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+        let json_post = |body: &'static str| {
+            Request::post("/v1/shorten").header(header::CONTENT_TYPE, "application/json").body(Body::from(body)).unwrap()
+        };
+
+        let cases = [
+            (json_post("{"), StatusCode::BAD_REQUEST, "BAD_REQUEST"),
+            (json_post(r#"{"url": 5}"#), StatusCode::UNPROCESSABLE_ENTITY, "VALIDATION_FAILED"),
+            (Request::get("/v1/shorten").body(Body::empty()).unwrap(), StatusCode::METHOD_NOT_ALLOWED, "METHOD_NOT_ALLOWED"),
+            (Request::get("/v1/me").body(Body::empty()).unwrap(), StatusCode::UNAUTHORIZED, "UNAUTHORIZED"),
+        ];
+        for (request, status, code) in cases {
+            let uri = request.uri().to_string();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), status, "{}", uri);
+            assert!(response.headers()[header::CONTENT_TYPE].to_str().unwrap().starts_with("application/json"), "{}", uri);
+            let request_id = response.headers()["x-request-id"].to_str().unwrap().to_string();
+            let body: serde_json::Value = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+            assert_eq!((body["success"].clone(), body["data"].clone()), (serde_json::json!(false), serde_json::Value::Null), "{}", uri);
+            assert_eq!(body["error"]["code"], code, "{}", uri);
+            assert!(!body["error"]["message"].as_str().unwrap().is_empty(), "{}", uri);
+            assert_eq!(body["error"]["request_id"], request_id.as_str(), "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn services_read_the_injected_clock() {
+        let clock = MockClock::new(chrono::DateTime::from_timestamp(1_600_000_000, 0).unwrap());
+        let app = Builder::new(Settings::default())
+            .storage(Arc::new(MockStorage::new()))
+            .clock(Arc::new(clock.clone()))
+            .background_tasks(false)
+            .build()
+            .await
+            .unwrap();
+
+        app.state.analytics.record_click("abc", "127.0.0.1", None, None, None, None, None, None, None, None, false).await;
+        app.state.analytics.shutdown().await;
+        let clicks = app.state.analytics.get_analytics("abc", 1_600_000_000, 1_600_000_000).await.unwrap();
+        assert_eq!(clicks.len(), 1);
+
+        app.state.link_checker.scan_once().await.unwrap();
+        assert_eq!(app.state.link_checker.broken_links().scanned_at.as_deref(), Some("2020-09-13T12:26:40+00:00"));
+    }
+
+    #[tokio::test]
+    async fn tenant_domains_are_served_from_the_tenant_storage() {
+        let dir = std::env::temp_dir().join(format!("hyperlinkr-tenants-{}", std::process::id()));
+        let mut config = Settings::default();
+        config.cache.sled_path = dir.join("cache.sled").display().to_string();
+        config.analytics.sled_path = dir.join("analytics.sled").display().to_string();
+        config.tenants = vec![TenantConfig {
+            id: "acme".into(),
+            domains: vec!["go.acme.test".into()],
+            database_urls: vec!["redis://acme-dragonfly:6379".into()],
+        }];
+        let (shared, acme) = (Arc::new(MockStorage::new()), Arc::new(MockStorage::new()));
+        let app = Builder::new(config)
+            .storage(shared.clone())
+            .tenant_storage("acme", acme.clone())
+            .background_tasks(false)
+            .build()
+            .await
+            .unwrap();
+        let router = app.router.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+
+        let request = Request::post("/v1/shorten")
+            .header(header::HOST, "GO.acme.test:443")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"url":"https://example.com/acme"}"#))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        let code = body["data"]["code"].as_str().unwrap();
+
+        assert!(acme.get(code).await.is_ok());
+        assert!(shared.get(code).await.is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn tokens_are_only_accepted_by_the_tenant_that_issued_them() {
+        let dir = std::env::temp_dir().join(format!("hyperlinkr-tenant-tokens-{}", std::process::id()));
+        let mut config = Settings::default();
+        config.cache.sled_path = dir.join("cache.sled").display().to_string();
+        config.analytics.sled_path = dir.join("analytics.sled").display().to_string();
+        let tenant = TenantConfig {
+            id: "acme".into(),
+            domains: vec!["go.acme.test".into()],
+            database_urls: vec!["redis://acme-dragonfly:6379".into()],
+        };
+        config.tenants = vec![tenant.clone()];
+        let acme_tokens = TokenService::new(&config.for_tenant(&tenant)).unwrap();
+        let app = Builder::new(config)
+            .storage(Arc::new(MockStorage::new()))
+            .tenant_storage("acme", Arc::new(MockStorage::new()))
+            .background_tasks(false)
+            .build()
+            .await
+            .unwrap();
+        let router = app.router.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+        let audit = |host: &str, token: &str| {
+            Request::get("/v1/admin/audit")
+                .header(header::HOST, host)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let (acme_admin, _) = acme_tokens.issue("admin1", "admin", "admin@acme.test", true, Utc::now()).unwrap();
+        let (default_admin, _) = app.state.tokens.issue("admin1", "admin", "admin@acme.test", true, Utc::now()).unwrap();
+        let cases = [
+            ("go.acme.test", &acme_admin, StatusCode::OK),
+            ("hyperlinkr.cloud", &acme_admin, StatusCode::UNAUTHORIZED),
+            ("hyperlinkr.cloud", &default_admin, StatusCode::OK),
+            ("go.acme.test", &default_admin, StatusCode::UNAUTHORIZED),
+        ];
+        for (host, token, status) in cases {
+            let response = router.clone().oneshot(audit(host, token)).await.unwrap();
+            assert_eq!(response.status(), status, "{}", host);
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn sled_backend_serves_links_without_dragonfly() {
+        let dir = std::env::temp_dir().join(format!("hyperlinkr-sled-backend-{}", std::process::id()));
+        let mut config = Settings::default();
+        config.storage.backend = StorageBackend::Sled;
+        config.storage.sled_path = dir.join("storage.sled").display().to_string();
+        config.analytics.sled_path = dir.join("analytics.sled").display().to_string();
+        config.database_urls = vec!["redis://unreachable.invalid:6379".into()];
+        let app = Builder::new(config).background_tasks(false).build().await.unwrap();
+        let router = app.router.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+
+        let request = Request::post("/v1/shorten")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"url":"https://example.com/sled"}"#))
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        let code = body["data"]["code"].as_str().unwrap();
+
+        assert!(!app.state.config.cache.use_sled);
+        assert!(app.state.rl_db.get(code).await.is_ok());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,284 @@
+//! Drives shorten and redirect traffic against a running instance and reports latency
+//! percentiles.
+//!
+//! `cargo run --release --bin loadgen -- --target http://localhost:3000 --concurrency 64 --duration 30`
+//!
+//! Links are created up front and then redirected with a Zipf-distributed choice of code, so a
+//! few links take most of the traffic the way real campaigns do. The target's per-IP rate limits
+//! apply to the load generator too; raise them for capacity runs or the report fills with 429s.
+
+use clap::Parser;
+use rand::Rng;
+use reqwest::{redirect::Policy, Client, StatusCode};
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Parser)]
+#[command(name = "loadgen", about = "Load test a running Hyperlinkr instance")]
+struct Args {
+    /// Base URL of the instance under test
+    #[arg(short, long, default_value = "http://localhost:3000")]
+    target: String,
+
+    /// Requests in flight at once
+    #[arg(short, long, default_value_t = 64)]
+    concurrency: usize,
+
+    /// How long to generate load for, in seconds
+    #[arg(short, long, default_value_t = 30)]
+    duration: u64,
+
+    /// Links to create before the run starts
+    #[arg(short, long, default_value_t = 1_000)]
+    keys: usize,
+
+    /// Zipf exponent for picking which link to redirect; 0 is uniform
+    #[arg(long, default_value_t = 1.0)]
+    zipf_exponent: f64,
+
+    /// Fraction of requests during the run that shorten a new link instead of redirecting
+    #[arg(long, default_value_t = 0.0, value_parser = parse_ratio)]
+    write_ratio: f64,
+
+    /// API key sent as x-api-key, for instances that require authenticated shortening
+    #[arg(long)]
+    api_key: Option<String>,
+}
+
+fn parse_ratio(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(ratio) if (0.0..=1.0).contains(&ratio) => Ok(ratio),
+        _ => Err(format!("{} is not between 0 and 1", value)),
+    }
+}
+
+/// Samples ranks `0..n` with probability proportional to `1 / (rank + 1)^exponent`.
+struct Zipf {
+    cdf: Vec<f64>,
+}
+
+impl Zipf {
+    fn new(n: usize, exponent: f64) -> Self {
+        let mut total = 0.0;
+        let mut cdf: Vec<f64> = (1..=n)
+            .map(|rank| {
+                total += 1.0 / (rank as f64).powf(exponent);
+                total
+            })
+            .collect();
+        cdf.iter_mut().for_each(|p| *p /= total);
+        Self { cdf }
+    }
+
+    fn sample<R: Rng>(&self, rng: &mut R) -> usize {
+        let p: f64 = rng.random();
+        self.cdf.partition_point(|&c| c < p).min(self.cdf.len() - 1)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Op {
+    Shorten,
+    Redirect,
+}
+
+impl Op {
+    fn name(self) -> &'static str {
+        match self {
+            Op::Shorten => "shorten",
+            Op::Redirect => "redirect",
+        }
+    }
+}
+
+#[derive(Default)]
+struct Stats {
+    latencies_us: BTreeMap<Op, Vec<u64>>,
+    statuses: BTreeMap<(Op, String), u64>,
+}
+
+impl Stats {
+    fn record(&mut self, op: Op, elapsed: Duration, status: String) {
+        self.latencies_us.entry(op).or_default().push(elapsed.as_micros() as u64);
+        *self.statuses.entry((op, status)).or_default() += 1;
+    }
+
+    fn merge(&mut self, other: Stats) {
+        for (op, latencies) in other.latencies_us {
+            self.latencies_us.entry(op).or_default().extend(latencies);
+        }
+        for (key, count) in other.statuses {
+            *self.statuses.entry(key).or_default() += count;
+        }
+    }
+}
+
+async fn shorten(client: &Client, args: &Args, id: u64) -> (Result<String, String>, Duration) {
+    let mut request = client
+        .post(format!("{}/v1/shorten", args.target))
+        .json(&json!({ "url": format!("https://example.com/loadgen/{}", id) }));
+    if let Some(api_key) = &args.api_key {
+        request = request.header("x-api-key", api_key);
+    }
+    let start = Instant::now();
+    let result = match request.send().await {
+        Ok(response) if response.status() == StatusCode::OK => match response.json::<Value>().await {
+            Ok(body) => body["data"]["code"].as_str().map(str::to_owned).ok_or_else(|| "bad body".to_string()),
+            Err(e) => Err(e.to_string()),
+        },
+        Ok(response) => Err(response.status().as_u16().to_string()),
+        Err(e) => Err(error_kind(&e)),
+    };
+    (result, start.elapsed())
+}
+
+async fn redirect(client: &Client, args: &Args, code: &str) -> (String, Duration) {
+    let start = Instant::now();
+    let status = match client.get(format!("{}/v1/redirect/{}", args.target, code)).send().await {
+        Ok(response) => response.status().as_u16().to_string(),
+        Err(e) => error_kind(&e),
+    };
+    (status, start.elapsed())
+}
+
+fn error_kind(error: &reqwest::Error) -> String {
+    if error.is_timeout() {
+        "timeout".into()
+    } else if error.is_connect() {
+        "connect error".into()
+    } else {
+        "request error".into()
+    }
+}
+
+fn percentile(sorted: &[u64], p: f64) -> f64 {
+    let index = ((sorted.len() as f64 * p).ceil() as usize).clamp(1, sorted.len()) - 1;
+    sorted[index] as f64 / 1000.0
+}
+
+fn report(stats: &mut Stats, elapsed: Duration) {
+    println!(
+        "\n{:<10} {:>10} {:>10} {:>9} {:>9} {:>9} {:>9} {:>9}",
+        "op", "requests", "req/s", "p50 ms", "p90 ms", "p99 ms", "p99.9 ms", "max ms"
+    );
+    for (op, latencies) in stats.latencies_us.iter_mut() {
+        latencies.sort_unstable();
+        println!(
+            "{:<10} {:>10} {:>10.0} {:>9.2} {:>9.2} {:>9.2} {:>9.2} {:>9.2}",
+            op.name(),
+            latencies.len(),
+            latencies.len() as f64 / elapsed.as_secs_f64(),
+            percentile(latencies, 0.50),
+            percentile(latencies, 0.90),
+            percentile(latencies, 0.99),
+            percentile(latencies, 0.999),
+            percentile(latencies, 1.0),
+        );
+    }
+    println!("\nResponses:");
+    for ((op, status), count) in &stats.statuses {
+        println!("  {:<10} {:<15} {}", op.name(), status, count);
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Arc::new(Args::parse());
+    if args.keys == 0 || args.concurrency == 0 {
+        eprintln!("--keys and --concurrency must be at least 1");
+        std::process::exit(2);
+    }
+    // Redirects are measured on their own, not on the destination they point at
+    let client = Client::builder()
+        .redirect(Policy::none())
+        .pool_max_idle_per_host(args.concurrency)
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("Failed to build HTTP client");
+
+    println!("Creating {} links on {}", args.keys, args.target);
+    let mut codes = Vec::with_capacity(args.keys);
+    let mut setup = tokio::task::JoinSet::new();
+    for worker in 0..args.concurrency.min(args.keys) {
+        let (client, args) = (client.clone(), Arc::clone(&args));
+        setup.spawn(async move {
+            let mut codes = Vec::new();
+            for id in (worker..args.keys).step_by(args.concurrency) {
+                match shorten(&client, &args, id as u64).await.0 {
+                    Ok(code) => codes.push(code),
+                    Err(e) => return Err(format!("Failed to create link {}: {}", id, e)),
+                }
+            }
+            Ok(codes)
+        });
+    }
+    while let Some(result) = setup.join_next().await {
+        match result.expect("Setup worker panicked") {
+            Ok(worker_codes) => codes.extend(worker_codes),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let codes = Arc::new(codes);
+    let zipf = Arc::new(Zipf::new(codes.len(), args.zipf_exponent));
+
+    println!(
+        "Running for {}s with {} workers (zipf exponent {}, write ratio {})",
+        args.duration, args.concurrency, args.zipf_exponent, args.write_ratio
+    );
+    let start = Instant::now();
+    let deadline = start + Duration::from_secs(args.duration);
+    let mut workers = tokio::task::JoinSet::new();
+    for worker in 0..args.concurrency {
+        let (client, args, codes, zipf) = (client.clone(), Arc::clone(&args), Arc::clone(&codes), Arc::clone(&zipf));
+        workers.spawn(async move {
+            let mut stats = Stats::default();
+            // Shortened URLs stay distinct across workers without any coordination
+            let mut next_id = (args.keys + worker) as u64;
+            while Instant::now() < deadline {
+                let (shorten_next, rank) = {
+                    let mut rng = rand::rng();
+                    (rng.random_bool(args.write_ratio), zipf.sample(&mut rng))
+                };
+                if shorten_next {
+                    let (result, elapsed) = shorten(&client, &args, next_id).await;
+                    next_id += args.concurrency as u64;
+                    stats.record(Op::Shorten, elapsed, result.map_or_else(|e| e, |_| "200".into()));
+                } else {
+                    let (status, elapsed) = redirect(&client, &args, &codes[rank]).await;
+                    stats.record(Op::Redirect, elapsed, status);
+                }
+            }
+            stats
+        });
+    }
+
+    let mut stats = Stats::default();
+    while let Some(worker_stats) = workers.join_next().await {
+        stats.merge(worker_stats.expect("Load worker panicked"));
+    }
+    report(&mut stats, start.elapsed());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zipf_favours_low_ranks_and_stays_in_range() {
+        let zipf = Zipf::new(100, 1.2);
+        let mut rng = rand::rng();
+        let mut counts = [0u32; 100];
+        for _ in 0..20_000 {
+            counts[zipf.sample(&mut rng)] += 1;
+        }
+        assert!(counts[0] > counts[9] && counts[9] > counts[99]);
+        assert_eq!(Zipf::new(1, 1.0).sample(&mut rng), 0);
+    }
+}
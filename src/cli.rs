@@ -0,0 +1,27 @@
+use clap::Parser;
+
+/// Command-line options. Anything not set here still comes from `config.{ENVIRONMENT}.toml`
+/// and `HYPERLINKR_*` environment variables.
+#[derive(Debug, Parser)]
+#[command(name = "hyperlinkr", version, about = "A URL shortener service built with Rust and Axum")]
+pub struct Cli {
+    /// Config file to load instead of config.{ENVIRONMENT}.toml
+    #[arg(short, long, value_name = "PATH")]
+    pub config: Option<String>,
+
+    /// Port to listen on, overriding app_port
+    #[arg(short, long, value_parser = clap::value_parser!(u16).range(1024..))]
+    pub port: Option<u16>,
+
+    /// Apply pending storage migrations, then exit
+    #[arg(long, conflicts_with = "check_config")]
+    pub migrate: bool,
+
+    /// Load and validate the configuration, then exit
+    #[arg(long)]
+    pub check_config: bool,
+
+    /// Preload up to N links into the caches and bloom filter before serving
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "100000")]
+    pub warmup: Option<usize>,
+}
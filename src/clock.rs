@@ -1,10 +1,8 @@
 use chrono::{DateTime, Utc};
+use std::sync::Arc;
 
 pub trait Clock: Send + Sync {
     fn now(&self) -> DateTime<Utc>;
-    fn clone(&self) -> Self
-    where
-        Self: Sized;
 }
 
 #[derive(Clone)]
@@ -14,30 +12,11 @@ impl Clock for SystemClock {
     fn now(&self) -> DateTime<Utc> {
         Utc::now()
     }
-
-    fn clone(&self) -> Self {
-        SystemClock
-    }
 }
 
-#[cfg(test)]
-#[derive(Clone)]
-pub struct MockClock(chrono::DateTime<chrono::Utc>);
-
-#[cfg(test)]
-impl MockClock {
-    pub fn new(time: chrono::DateTime<chrono::Utc>) -> Self {
-        Self(time)
+/// Lets services share the one clock injected through `Builder::clock`.
+impl Clock for Arc<dyn Clock> {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
     }
 }
-
-#[cfg(test)]
-impl Clock for MockClock {
-    fn now(&self) -> chrono::DateTime<chrono::Utc> {
-        self.0
-    }
-
-    fn clone(&self) -> Self {
-        MockClock(self.0)
-    }
-}
\ No newline at end of file
@@ -2,7 +2,7 @@ use serde::Deserialize;
 use validator::Validate;
 
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Clone, Deserialize, Validate)]
 pub struct AnalyticsConfig {
     #[validate(range(min = 100))]
     pub flush_interval_ms: u64,
@@ -15,6 +15,12 @@ pub struct AnalyticsConfig {
 
     #[validate(range(min = 1000))]
     pub max_queue_size: Option<usize>, // Optional, defaults to 100K if not set
+
+    pub privacy_mode: Option<bool>, // Optional, defaults to false; skips geo lookup and UA parsing for every request
+
+    pub unfurl_previews: Option<bool>, // Optional, defaults to true; chat-app unfurl bots get a preview page instead of a counted redirect
+
+    pub deferred_enrichment: Option<bool>, // Optional, defaults to false; redirects capture only the IP and user agent and the flush worker does the geo lookup and UA parsing
     
     #[validate(length(min = 1))]
     pub sled_path: String,
@@ -28,6 +34,9 @@ impl Default for AnalyticsConfig {
             max_batch_size_ms: 1_000,
             max_batch_size: 10_000,
             max_queue_size: Some(100_000), // Default to 100K
+            privacy_mode: Some(false),
+            unfurl_previews: Some(true),
+            deferred_enrichment: Some(false),
             sled_path: "./data/analytics.sled".into(),
         }
     }
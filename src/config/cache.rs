@@ -3,6 +3,53 @@
 use serde::Deserialize;
 use validator::Validate;
 
+/// Which entry an in-process tier drops when it is full.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EvictionPolicy {
+    /// Admits new keys only if they look hotter than the victim; best for long-tail traffic
+    #[default]
+    TinyLfu,
+    /// Drops the least recently used key; best when a small hot head changes quickly
+    Lru,
+}
+
+/// How an in-process tier ages entries out.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheExpiry {
+    /// Entries expire `ttl_seconds` after they are written
+    #[default]
+    Ttl,
+    /// Entries expire once unread for the tier's idle time; hot links never go stale on their own
+    Tti,
+}
+
+/// How the Dragonfly client spaces out attempts to reconnect to a node.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconnectStrategy {
+    /// Waits redis_reconnect_delay_ms before every attempt
+    Constant,
+    /// Waits redis_reconnect_delay_ms longer each attempt, up to redis_reconnect_max_delay_ms
+    Linear,
+    /// Doubles the wait each attempt, from redis_reconnect_delay_ms up to redis_reconnect_max_delay_ms
+    #[default]
+    Exponential,
+}
+
+/// When a link write reaches Dragonfly.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WriteMode {
+    /// Writes wait for Dragonfly before they are acknowledged
+    #[default]
+    WriteThrough,
+    /// Writes are acknowledged once Sled and the in-process tiers have them, and reach
+    /// Dragonfly from a queue every write_back_flush_ms
+    WriteBack,
+}
+
 #[derive(Debug, Deserialize, Validate, Clone)]
 pub struct CacheConfig {
     #[validate(range(min = 1000))]
@@ -25,6 +72,9 @@ pub struct CacheConfig {
     pub max_failures: u32,
     #[validate(range(min = 10))]
     pub retry_interval_secs: u64,
+    /// Optional, requests let through to a node whose breaker has gone half-open, defaults to 1
+    #[validate(range(min = 1))]
+    pub half_open_max_probes: Option<u32>,
     #[validate(range(min = 1))]
     pub redis_command_timeout_secs: u64,
     #[validate(range(min = 1))]
@@ -41,6 +91,11 @@ pub struct CacheConfig {
     pub redis_reconnect_delay_ms: u64,
     #[validate(range(min = 100))]
     pub redis_reconnect_max_delay_ms: u64,
+    /// Optional, "constant", "linear" or "exponential", defaults to exponential
+    pub redis_reconnect_strategy: Option<ReconnectStrategy>,
+    /// Optional, up to this many random milliseconds are added to each reconnect wait so
+    /// instances that lost a node together don't all reconnect at once, defaults to 50
+    pub redis_reconnect_jitter_ms: Option<u32>,
 
     #[validate(length(min = 1))]
     pub sled_path: String,
@@ -72,6 +127,68 @@ pub struct CacheConfig {
     /// How often (in seconds) to run the hot‐cache eviction sweep
     #[validate(range(min = 1))]
     pub geo_evict_interval_secs: u64,
+
+    /// MaxMind account for scheduled GeoLite2 downloads; updates are off unless both are set
+    pub geoip_account_id: Option<String>,
+    pub geoip_license_key: Option<String>,
+    /// Optional, edition to download, defaults to "GeoLite2-City"
+    pub geoip_edition_id: Option<String>,
+    /// Optional, seconds between update checks, defaults to 24 hours
+    #[validate(range(min = 3600))]
+    pub geoip_update_interval_secs: Option<u64>,
+
+    // ─── UA PARSING SETTINGS ─────────────────────────────────────────────────────
+    /// Optional, path to uap-core's regexes.yaml; a built-in subset is used if not set
+    pub ua_regexes_path: Option<String>,
+    /// Optional, distinct User-Agent strings to memoize, defaults to 10k
+    #[validate(range(min = 1))]
+    pub ua_cache_capacity: Option<u64>,
+
+    /// Optional, storage calls slower than this are logged and counted, defaults to 25ms
+    #[validate(range(min = 1))]
+    pub slow_op_threshold_ms: Option<u64>,
+
+    /// Optional, most-clicked links served from the lock-free hot set, defaults to 100; 0 disables it
+    pub hot_set_size: Option<usize>,
+    /// Optional, seconds between hot-set rebuilds from recent clicks, defaults to 10
+    #[validate(range(min = 1))]
+    pub hot_set_refresh_secs: Option<u64>,
+    /// Optional, milliseconds a redirect read waits on its node before also asking another
+    /// healthy node, defaults to unset (no hedging)
+    #[validate(range(min = 1))]
+    pub hedge_after_ms: Option<u64>,
+    /// Optional, defaults to true; while every Dragonfly node's breaker is open, redirects are
+    /// served from Sled and link writes are queued for Dragonfly. Needs use_sled
+    pub degraded_mode: Option<bool>,
+    /// Optional, link writes held while degraded before new ones are refused, defaults to 10k
+    #[validate(range(min = 1))]
+    pub degraded_queue_size: Option<usize>,
+    /// Optional, links `POST /v1/admin/cache/warmup` reads from storage per second, defaults to 1000
+    #[validate(range(min = 1))]
+    pub warmup_keys_per_sec: Option<usize>,
+    /// Optional, "write_through" or "write_back", defaults to write_through. Write-back needs
+    /// use_sled and shares degraded_queue_size; a write that finds the queue full goes through
+    pub write_mode: Option<WriteMode>,
+    /// Optional, milliseconds between write-back queue flushes, defaults to 50
+    #[validate(range(min = 1, max = 60_000))]
+    pub write_back_flush_ms: Option<u64>,
+
+    // ─── IN-PROCESS TIER POLICY ──────────────────────────────────────────────────
+    /// Optional, "tiny_lfu" or "lru", defaults to tiny_lfu
+    pub l1_eviction_policy: Option<EvictionPolicy>,
+    /// Optional, "ttl" or "tti", defaults to ttl
+    pub l1_expiry: Option<CacheExpiry>,
+    /// Optional, seconds an unread L1 entry survives; with ttl expiry it applies on top of
+    /// ttl_seconds, with tti it defaults to ttl_seconds
+    #[validate(range(min = 1))]
+    pub l1_idle_secs: Option<u64>,
+    /// Optional, "tiny_lfu" or "lru", defaults to tiny_lfu
+    pub l2_eviction_policy: Option<EvictionPolicy>,
+    /// Optional, "ttl" or "tti", defaults to ttl
+    pub l2_expiry: Option<CacheExpiry>,
+    /// Optional, seconds an unread L2 entry survives, as for l1_idle_secs
+    #[validate(range(min = 1))]
+    pub l2_idle_secs: Option<u64>,
 }
 
 impl Default for CacheConfig {
@@ -87,6 +204,7 @@ impl Default for CacheConfig {
             ttl_seconds: 3_600,
             max_failures: 5,
             retry_interval_secs: 10,
+            half_open_max_probes: Some(1),
             redis_command_timeout_secs: 1,
             redis_max_feed_count: 200,
             redis_broadcast_channel_capacity: 32,
@@ -95,6 +213,8 @@ impl Default for CacheConfig {
             redis_reconnect_max_attempts: 3,
             redis_reconnect_delay_ms: 100,
             redis_reconnect_max_delay_ms: 500,
+            redis_reconnect_strategy: Some(ReconnectStrategy::Exponential),
+            redis_reconnect_jitter_ms: Some(50),
 
             sled_path: "/tmp/sled_hyperlinkr".to_string(),
             sled_cache_bytes: 64 * 1024 * 1024, // 64MB
@@ -109,6 +229,32 @@ impl Default for CacheConfig {
             geo_hot_capacity: 200_000,       // ~20 MB of RAM for ~200k entries
             geo_ttl_seconds: 3_600,          // 1 hour TTL
             geo_evict_interval_secs: 60,     // sweep every minute
+            geoip_account_id: None,
+            geoip_license_key: None,
+            geoip_edition_id: Some("GeoLite2-City".to_string()),
+            geoip_update_interval_secs: Some(86_400),
+
+            // ─── UA PARSING DEFAULTS ───────────────────────────────────────────────
+            ua_regexes_path: None,
+            ua_cache_capacity: Some(10_000),
+
+            slow_op_threshold_ms: Some(25),
+
+            hot_set_size: Some(100),
+            hot_set_refresh_secs: Some(10),
+            hedge_after_ms: None,
+            degraded_mode: Some(true),
+            degraded_queue_size: Some(10_000),
+            warmup_keys_per_sec: Some(1_000),
+            write_mode: Some(WriteMode::WriteThrough),
+            write_back_flush_ms: Some(50),
+
+            l1_eviction_policy: Some(EvictionPolicy::TinyLfu),
+            l1_expiry: Some(CacheExpiry::Ttl),
+            l1_idle_secs: None,
+            l2_eviction_policy: Some(EvictionPolicy::TinyLfu),
+            l2_expiry: Some(CacheExpiry::Ttl),
+            l2_idle_secs: None,
         }
     }
 }
@@ -1,12 +1,23 @@
 use serde::Deserialize;
 use validator::Validate;
 
-#[derive(Debug, Deserialize, Validate)]
+/// Matched case-insensitively anywhere in a generated code; `blocked_substrings` replaces these.
+pub const DEFAULT_BLOCKED_SUBSTRINGS: [&str; 14] = [
+    "fuck", "fck", "shit", "cunt", "dick", "cock", "piss", "slut", "whore", "fag", "nigg", "rape", "porn", "bitch",
+];
+
+#[derive(Debug, Clone, Deserialize, Validate)]
 pub struct CodeGenConfig {
     #[validate(range(min = 8, max = 16))]
     pub shard_bits: usize,
     #[validate(range(min = 3, max = 10))]
     pub max_attempts: usize,
+
+    #[validate(range(min = 1, max = 10000))]
+    pub max_reserve_batch: Option<usize>, // Optional, defaults to 1000 codes per reserve call
+    #[validate(range(min = 60))]
+    pub reservation_ttl_secs: Option<u64>, // Optional, defaults to 7 days if not set
+    pub blocked_substrings: Option<Vec<String>>, // Optional, generated codes containing any of these are skipped; defaults to DEFAULT_BLOCKED_SUBSTRINGS, [] turns the filter off
 }
 
 impl Default for CodeGenConfig {
@@ -14,6 +25,9 @@ impl Default for CodeGenConfig {
         Self {
             shard_bits: 12,
             max_attempts: 5,
+            max_reserve_batch: Some(1_000),
+            reservation_ttl_secs: Some(7 * 24 * 3600),
+            blocked_substrings: None,
         }
     }
 }
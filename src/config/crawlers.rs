@@ -0,0 +1,40 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrawlerPolicy {
+    #[default]
+    SkipAnalytics, // Redirect as usual but record no click
+    Deny, // Answer 403 instead of redirecting
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[serde(default)]
+pub struct CrawlerConfig {
+    #[validate(length(min = 1))]
+    pub robots_txt: String, // Served verbatim at /robots.txt
+    pub disallowed: Vec<String>, // Bot names as the UA parser reports them, e.g. "Googlebot"; "*" for every crawler
+    pub policy: CrawlerPolicy, // What a redirect does for a disallowed crawler
+}
+
+impl Default for CrawlerConfig {
+    fn default() -> Self {
+        Self {
+            robots_txt: "User-agent: *\nAllow: /v1/redirect/\nDisallow: /v1/\n".into(),
+            disallowed: Vec::new(),
+            policy: CrawlerPolicy::default(),
+        }
+    }
+}
+
+impl CrawlerConfig {
+    /// Whether `policy` applies to a request classified as `is_bot` / `bot_name`. Link
+    /// unfurlers get their preview card before this is asked.
+    pub fn disallows(&self, is_bot: bool, bot_name: Option<&str>) -> bool {
+        is_bot
+            && self.disallowed.iter().any(|name| {
+                name == "*" || bot_name.is_some_and(|bot_name| bot_name.eq_ignore_ascii_case(name))
+            })
+    }
+}
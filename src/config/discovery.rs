@@ -0,0 +1,22 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[serde(default)]
+pub struct DiscoveryConfig {
+    // Optional, e.g. "redis://dragonfly-headless.default.svc.cluster.local:6379"; when set, every
+    // address its host resolves to is a node, in place of `database_urls`
+    #[validate(url)]
+    pub url: Option<String>,
+    #[validate(range(min = 1, max = 3600))]
+    pub refresh_secs: u64, // How often the name is resolved again and nodes added or drained
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            refresh_secs: 30,
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[serde(default)]
+pub struct LinkHealthConfig {
+    pub enabled: bool,
+    #[validate(range(min = 60))]
+    pub scan_interval_secs: u64,
+    #[validate(range(min = 100, max = 30000))]
+    pub request_timeout_ms: u64,
+    #[validate(range(min = 1, max = 256))]
+    pub concurrency: usize,
+    pub warn_on_dead: bool, // Serve an interstitial instead of redirecting to dead destinations
+}
+
+impl Default for LinkHealthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            scan_interval_secs: 3_600,
+            request_timeout_ms: 5_000,
+            concurrency: 16,
+            warn_on_dead: false,
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use validator::Validate;
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[serde(default)]
+pub struct MetricsConfig {
+    #[validate(url)]
+    pub otlp_endpoint: Option<String>, // Optional, e.g. "http://collector:4318/v1/metrics"; nothing is pushed when unset
+    #[validate(range(min = 1, max = 3600))]
+    pub otlp_interval_secs: u64,
+    #[validate(range(min = 100, max = 60000))]
+    pub otlp_timeout_ms: u64,
+    pub otlp_headers: HashMap<String, String>, // Sent with every push, e.g. an API key for a hosted collector
+    #[validate(length(min = 1))]
+    pub service_name: String, // The `service.name` resource attribute
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            otlp_interval_secs: 15,
+            otlp_timeout_ms: 5_000,
+            otlp_headers: HashMap::new(),
+            service_name: "hyperlinkr".into(),
+        }
+    }
+}
@@ -4,4 +4,15 @@ pub mod rate_limit;
 pub mod analytics;
 pub mod codegen;
 pub mod security;
-pub mod storage;
\ No newline at end of file
+pub mod storage;
+pub mod link_health;
+pub mod password_policy;
+pub mod replication;
+pub mod tenant;
+pub mod metrics;
+pub mod usage;
+pub mod quota;
+pub mod discovery;
+pub mod trial;
+pub mod crawlers;
+pub mod redirect;
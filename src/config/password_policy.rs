@@ -0,0 +1,30 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[serde(default)]
+pub struct PasswordPolicyConfig {
+    #[validate(range(min = 8, max = 100))]
+    pub min_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    pub breached_hashes_path: Option<String>, // One hex SHA-256 per line, loaded into a bloom filter at startup
+    #[validate(range(min = 1))]
+    pub breached_expected: usize, // Sizing hint for the bloom filter
+}
+
+impl Default for PasswordPolicyConfig {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            require_lowercase: true,
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: false,
+            breached_hashes_path: None,
+            breached_expected: 1_000_000,
+        }
+    }
+}
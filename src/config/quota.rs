@@ -0,0 +1,37 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use validator::Validate;
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[serde(default)]
+pub struct QuotaConfig {
+    #[validate(length(min = 1))]
+    pub default_plan: String, // Plan of users who have none set, or one that is no longer configured
+    #[validate(nested)]
+    pub plans: HashMap<String, PlanConfig>, // No quotas are enforced when empty
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+#[serde(default)]
+pub struct PlanConfig {
+    #[validate(range(min = 1))]
+    pub max_links: Option<u64>, // Links a user can own at once; unlimited when unset
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        Self {
+            default_plan: "free".into(),
+            plans: HashMap::new(),
+        }
+    }
+}
+
+impl QuotaConfig {
+    /// The plan named `plan`, falling back to the default plan. `None` when no plans are set.
+    pub fn plan<'a>(&'a self, plan: Option<&'a str>) -> Option<(&'a str, &'a PlanConfig)> {
+        plan.and_then(|name| self.plans.get_key_value(name))
+            .or_else(|| self.plans.get_key_value(self.default_plan.as_str()))
+            .map(|(name, limits)| (name.as_str(), limits))
+    }
+}
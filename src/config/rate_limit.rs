@@ -1,7 +1,7 @@
 use serde::Deserialize;
 use validator::Validate;
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Clone, Deserialize, Validate)]
 pub struct RateLimitConfig {
     #[validate(range(min = 1))]
     pub shorten_requests_per_minute: u32,
@@ -11,6 +11,12 @@ pub struct RateLimitConfig {
     // Additional fields can be added here as needed
     #[validate(range(min = 1, max = 3600))]
     pub window_size_seconds: Option<u64>, // Optional, defaults to 60 seconds if not set
+    #[validate(range(min = 1))]
+    pub auth_requests_per_ip: Option<u32>, // Optional, login/register attempts per IP per auth window, defaults to 20
+    #[validate(range(min = 1))]
+    pub auth_requests_per_username: Option<u32>, // Optional, attempts per username per auth window, defaults to 5
+    #[validate(range(min = 1, max = 86400))]
+    pub auth_window_seconds: Option<u64>, // Optional, defaults to 300 seconds
 }
 
 impl Default for RateLimitConfig {
@@ -19,6 +25,9 @@ impl Default for RateLimitConfig {
             shorten_requests_per_minute: 10,
             redirect_requests_per_minute: 1_000,
             window_size_seconds: Some(60), // Default to 60 seconds
+            auth_requests_per_ip: Some(20),
+            auth_requests_per_username: Some(5),
+            auth_window_seconds: Some(300),
         }
     }
 }
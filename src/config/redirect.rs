@@ -0,0 +1,16 @@
+use serde::Deserialize;
+use validator::Validate;
+use crate::validator::validate_redirect_status;
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[serde(default)]
+pub struct RedirectConfig {
+    #[validate(custom(function = "validate_redirect_status"))]
+    pub default_status: u16, // 301, 302, 307 or 308, for links that don't choose their own
+}
+
+impl Default for RedirectConfig {
+    fn default() -> Self {
+        Self { default_status: 302 }
+    }
+}
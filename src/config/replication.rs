@@ -0,0 +1,24 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[serde(default)]
+pub struct ReplicationConfig {
+    pub enabled: bool,
+    pub peers: Vec<String>, // Dragonfly URL of each secondary region
+    #[validate(range(min = 1))]
+    pub queue_size: usize, // Mutations held per peer before new ones are dropped
+    #[validate(range(min = 100, max = 60000))]
+    pub retry_interval_ms: u64,
+}
+
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            peers: Vec::new(),
+            queue_size: 100_000,
+            retry_interval_ms: 1_000,
+        }
+    }
+}
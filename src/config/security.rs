@@ -1,21 +1,66 @@
 use serde::Deserialize;
 use validator::Validate;
-use crate::validator::validate_email_list;
+use crate::validator::{validate_captcha_provider, validate_email_list, validate_jwt_algorithm};
 
+/// Custom aliases that would shadow pages of the site; `reserved_aliases` adds to these.
+pub const DEFAULT_RESERVED_ALIASES: [&str; 16] = [
+    "home", "about", "contact", "help", "terms", "privacy", "login", "signup",
+    "dashboard", "settings", "profile", "admin", "api", "docs", "support", "blog"
+];
 
+#[derive(Clone, Debug, Deserialize, Validate)]
+pub struct JwtKey {
+    #[validate(length(min = 1))]
+    pub kid: String,
+    #[validate(custom(function = "validate_jwt_algorithm"))]
+    pub algorithm: Option<String>, // Optional, "HS256" (default), "RS256" or "ES256"
+    #[validate(length(min = 32))]
+    pub secret: Option<String>, // HS256 only
+    pub private_key_path: Option<String>, // RS256/ES256 PEM (PKCS#8), only needed where tokens are signed
+    pub public_key_path: Option<String>, // RS256/ES256 PEM (SubjectPublicKeyInfo), published via JWKS
+}
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Clone, Deserialize, Validate)]
 pub struct SecurityConfig {
     #[validate(custom(function = "validate_email_list"))]
     pub global_admins: Vec<String>,
     #[validate(length(min = 32))]
-    pub jwt_secret: String,
+    pub jwt_secret: String, // Signing key with kid "default"
+    #[validate(nested)]
+    pub jwt_keys: Option<Vec<JwtKey>>, // Optional, extra keys accepted for verification during rotation
+    pub jwt_active_kid: Option<String>, // Optional, kid used to sign new tokens, defaults to "default"
+    pub jwt_audience: Option<String>, // Optional, `aud` stamped on and required of every token, defaults to the domain; tenants get their own
     #[validate(range(min = 60))]
     pub token_expiry_secs: u64,
     #[validate(length(min = 1))]
     pub domain: String, // e.g., "hyperlinkr.com"
     #[validate(length(min = 1))]
     pub subdomains: Vec<String>, // e.g., ["api", "auth"]
+
+    pub safe_browsing_api_key: Option<String>, // Optional, reputation checks are skipped if not set
+    pub safe_browsing_quarantine: Option<bool>, // Optional, store flagged links disabled instead of rejecting them
+    #[validate(range(min = 50, max = 10000))]
+    pub safe_browsing_timeout_ms: Option<u64>, // Optional, defaults to 1500ms
+
+    pub blocked_domains: Option<Vec<String>>, // Optional, e.g. ["spam.example", "*.tk"]
+    pub blocklist_path: Option<String>, // Optional, one pattern per line; admin changes are written back here
+    pub known_shorteners: Option<Vec<String>>, // Optional, defaults to a built-in list (bit.ly, t.co, ...)
+    pub reserved_aliases: Option<Vec<String>>, // Optional, e.g. brand names or route prefixes, reserved on top of DEFAULT_RESERVED_ALIASES
+    #[validate(range(min = 300))]
+    pub threat_rescan_interval_secs: Option<u64>, // Optional, re-screen stored links this often and quarantine flagged ones; off if not set
+
+    #[validate(custom(function = "validate_captcha_provider"))]
+    pub captcha_provider: Option<String>, // Optional, "hcaptcha" or "turnstile"; challenges are off if not set
+    pub captcha_secret: Option<String>,
+    pub captcha_for_anonymous: Option<bool>, // Optional, defaults to true
+    #[validate(range(min = 60))]
+    pub captcha_flag_ttl_secs: Option<u64>, // Optional, how long a rejected IP must keep solving challenges, defaults to 1h
+    #[validate(range(min = 1))]
+    pub lockout_threshold: Option<u64>, // Optional, failed logins per username or IP before locking, defaults to 5
+    pub lockout_window_secs: Option<u64>, // Optional, window failures are counted over, defaults to 15m
+    pub lockout_duration_secs: Option<u64>, // Optional, defaults to 15m
+    #[validate(range(min = 60, max = 3600))]
+    pub impersonation_ttl_secs: Option<u64>, // Optional, lifetime of support impersonation tokens, defaults to 15m
 }
 
 impl Default for SecurityConfig {
@@ -23,9 +68,28 @@ impl Default for SecurityConfig {
         Self {
             global_admins: vec![],
             jwt_secret: "0ecEuxack4XAdudiWTWXT3UocVEFhPZBaE0PhIJk3M3PNIfk5BnM+1WSYb0PaPaDCpApBRCPmrH89wDJNjQdyvkl6rEHoebJbmnYf+GqHA2WM6LqhNG+LCAHke8NFRnnlyHEhvr3KiJpQSKR0yWA8jqENpdLjVury+OknAJvQptoANSdIY8uF0FXU0kHLpnxdJ9HXRdyH0A3NTYX+EP9x8Jo3G5ymweJdLp/KUSHBjJGnAsHZAWlg9bOrqIEjau1VwUdDuFrv7yRMZYLBQsa6MRCZ09eRABl5MvqBMs/B8O3tYwUKeP04GqxwI2k5mk2qgMBPpij/zi5iKhDQ=".to_string(),
+            jwt_keys: None,
+            jwt_active_kid: None,
+            jwt_audience: None,
             token_expiry_secs: 3600 * 24 * 1, // 1 days
             domain: "hyperlinkr.cloud".to_string(),
             subdomains: vec!["api".to_string()],
+            safe_browsing_api_key: None,
+            safe_browsing_quarantine: Some(false),
+            safe_browsing_timeout_ms: Some(1_500),
+            blocked_domains: None,
+            blocklist_path: None,
+            known_shorteners: None,
+            reserved_aliases: None,
+            threat_rescan_interval_secs: None,
+            captcha_provider: None,
+            captcha_secret: None,
+            captcha_for_anonymous: Some(true),
+            captcha_flag_ttl_secs: Some(3_600),
+            lockout_threshold: Some(5),
+            lockout_window_secs: Some(900),
+            lockout_duration_secs: Some(900),
+            impersonation_ttl_secs: Some(900),
         }
     }
 }
@@ -3,13 +3,24 @@ use serde::Deserialize;
 use std::env;
 use validator::Validate;
 use super::analytics::AnalyticsConfig;
-use super::cache::CacheConfig;
+use super::cache::{CacheConfig, WriteMode};
 use super::rate_limit::RateLimitConfig;
 use super::codegen::CodeGenConfig;
 use super::security::SecurityConfig;
 use super::storage::StorageConfig;
+use super::link_health::LinkHealthConfig;
+use super::password_policy::PasswordPolicyConfig;
+use super::replication::ReplicationConfig;
+use super::tenant::TenantConfig;
+use super::metrics::MetricsConfig;
+use super::usage::UsageConfig;
+use super::quota::QuotaConfig;
+use super::discovery::DiscoveryConfig;
+use super::trial::TrialConfig;
+use super::crawlers::CrawlerConfig;
+use super::redirect::RedirectConfig;
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Clone, Deserialize, Validate)]
 pub struct Settings {
     #[validate(length(min = 1))]
     pub environment: String,
@@ -34,6 +45,39 @@ pub struct Settings {
 
      #[validate(nested)]
     pub security: SecurityConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub link_health: LinkHealthConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub password_policy: PasswordPolicyConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub replication: ReplicationConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub tenants: Vec<TenantConfig>, // Optional, tenants kept in their own Dragonfly, see `for_tenant`
+    #[serde(default)]
+    #[validate(nested)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub usage: UsageConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub quota: QuotaConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub discovery: DiscoveryConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub trial: TrialConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub crawlers: CrawlerConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub redirects: RedirectConfig,
 }
 
 impl Default for Settings {
@@ -55,16 +99,59 @@ impl Default for Settings {
             codegen: CodeGenConfig::default(),
             analytics: AnalyticsConfig::default(),
             security: SecurityConfig::default(),
+            link_health: LinkHealthConfig::default(),
+            password_policy: PasswordPolicyConfig::default(),
+            replication: ReplicationConfig::default(),
+            tenants: Vec::new(),
+            metrics: MetricsConfig::default(),
+            usage: UsageConfig::default(),
+            quota: QuotaConfig::default(),
+            discovery: DiscoveryConfig::default(),
+            trial: TrialConfig::default(),
+            crawlers: CrawlerConfig::default(),
+            redirects: RedirectConfig::default(),
         }
     }
 }
 
+impl Settings {
+    /// These settings pointed at `tenant`'s Dragonfly, with Sled paths of its own so tenants
+    /// never share a file either, and a JWT audience of its own so tokens don't cross tenants.
+    pub fn for_tenant(&self, tenant: &TenantConfig) -> Settings {
+        let mut settings = self.clone();
+        settings.database_urls = tenant.database_urls.clone();
+        settings.cache.sled_path = format!("{}-{}", self.cache.sled_path, tenant.id);
+        settings.analytics.sled_path = format!("{}-{}", self.analytics.sled_path, tenant.id);
+        settings.storage.sled_path = format!("{}-{}", self.storage.sled_path, tenant.id);
+        let audience = self.security.jwt_audience.as_deref().unwrap_or(&self.security.domain);
+        settings.security.jwt_audience = Some(format!("{}/tenants/{}", audience, tenant.id));
+        settings.tenants = Vec::new();
+        // Peers are the default tenant's other regions; shipping this tenant's links there would
+        // undo the isolation
+        settings.replication = ReplicationConfig::default();
+        settings
+    }
+}
+
 pub fn load() -> Result<Settings, ConfigError> {
+    load_from(None, None)
+}
+
+/// Like `load`, but reads `config_path` (which must exist) instead of `config.{ENVIRONMENT}.toml`
+/// and lets `app_port` win over both the file and the environment.
+///
+/// # Errors
+///
+/// Fails if `config_path` is missing or the merged settings don't deserialize or validate.
+pub fn load_from(config_path: Option<&str>, app_port: Option<u16>) -> Result<Settings, ConfigError> {
     let env = env::var("ENVIRONMENT").unwrap_or("development".into());
-    let file = format!("config.{}.toml", env);
+    let file = match config_path {
+        Some(path) => File::with_name(path).required(true),
+        None => File::with_name(&format!("config.{}.toml", env)).required(false),
+    };
 
     let cfg = Config::builder()
-        .add_source(File::with_name(&file).required(false))
+        .add_source(file)
         .add_source(Environment::with_prefix("HYPERLINKR").separator("_").try_parsing(true))
         .set_default("environment", env)?
         .set_default("database_urls", vec![
@@ -76,6 +163,7 @@ pub fn load() -> Result<Settings, ConfigError> {
         .set_default("base_url", "http://localhost:3000")?
         .set_default("app_port", 3000)?
         .set_default("rust_log", "debug")?
+        .set_override_option("app_port", app_port)?
         .build()?;
 
     let settings: Settings = cfg.try_deserialize()?;
@@ -86,6 +174,30 @@ pub fn load() -> Result<Settings, ConfigError> {
             return Err(ConfigError::Message(format!("Invalid Redis URL[{}]: {}", i, url)));
         }
     }
+    if let Some(url) = settings.discovery.url.as_ref().filter(|url| !url.starts_with("redis://")) {
+        return Err(ConfigError::Message(format!("Invalid Redis URL for discovery: {}", url)));
+    }
+    let mut tenant_domains = std::collections::HashSet::new();
+    for tenant in &settings.tenants {
+        if let Some(url) = tenant.database_urls.iter().find(|url| !url.starts_with("redis://")) {
+            return Err(ConfigError::Message(format!("Invalid Redis URL for tenant {}: {}", tenant.id, url)));
+        }
+        if let Some(domain) = tenant.domains.iter().find(|domain| !tenant_domains.insert(domain.to_ascii_lowercase())) {
+            return Err(ConfigError::Message(format!("Domain {} is mapped to more than one tenant", domain)));
+        }
+    }
+
+    if settings.cache.redis_reconnect_max_delay_ms < settings.cache.redis_reconnect_delay_ms {
+        return Err(ConfigError::Message("cache.redis_reconnect_max_delay_ms is below cache.redis_reconnect_delay_ms".into()));
+    }
+
+    if settings.cache.write_mode == Some(WriteMode::WriteBack) && !settings.cache.use_sled {
+        return Err(ConfigError::Message("cache.write_mode = \"write_back\" needs cache.use_sled".into()));
+    }
+
+    if !settings.quota.plans.is_empty() && !settings.quota.plans.contains_key(&settings.quota.default_plan) {
+        return Err(ConfigError::Message(format!("Default plan {} is not one of quota.plans", settings.quota.default_plan)));
+    }
 
     // Set RUST_LOG environment variable
     unsafe { env::set_var("RUST_LOG", &settings.rust_log) };
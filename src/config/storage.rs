@@ -1,8 +1,22 @@
 use serde::Deserialize;
 use validator::Validate;
 
-#[derive(Debug, Deserialize, Validate)]
+/// Where links, users, rate limits and analytics are kept.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// Dragonfly at database_urls, with Sled as an optional cold tier
+    #[default]
+    Dragonfly,
+    /// Embedded Sled at sled_path, for single-container deployments without Redis
+    Sled,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
 pub struct StorageConfig {
+    /// Optional, "dragonfly" or "sled", defaults to dragonfly
+    #[serde(default)]
+    pub backend: StorageBackend,
     #[validate(length(min = 1))]
     pub sled_path: String,
     #[validate(range(min = 1048576))] // min 1MB
@@ -12,16 +26,23 @@ pub struct StorageConfig {
     #[validate(range(min = 1))]
     pub sled_snapshot_ttl_secs: u64,
     pub sled_compression: bool,
+    /// Optional, seconds between sweeps that delete expired entries from every Sled database,
+    /// defaults to 300
+    #[serde(default)]
+    #[validate(range(min = 1, max = 86400))]
+    pub sled_sweep_interval_secs: Option<u64>,
 }
 
 impl Default for StorageConfig {
     fn default() -> Self {
         Self {
+            backend: StorageBackend::Dragonfly,
             sled_path: "./data/storage.sled".into(),
             sled_cache_bytes: 67_108_864, // 64MB
             sled_flush_ms: 300_000,       // 5 minutes
             sled_snapshot_ttl_secs: 5,
             sled_compression: true,
+            sled_sweep_interval_secs: Some(300),
         }
     }
 }
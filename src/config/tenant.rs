@@ -0,0 +1,13 @@
+use serde::Deserialize;
+use validator::Validate;
+
+/// A customer whose links live in their own Dragonfly, served on their own domains.
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct TenantConfig {
+    #[validate(length(min = 1))]
+    pub id: String, // Also suffixes the tenant's Sled paths
+    #[validate(length(min = 1))]
+    pub domains: Vec<String>, // Hosts routed to this tenant, e.g. ["go.acme.com"]
+    #[validate(length(min = 1))]
+    pub database_urls: Vec<String>,
+}
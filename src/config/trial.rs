@@ -0,0 +1,25 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[serde(default)]
+pub struct TrialConfig {
+    pub enabled: bool, // Anonymous links become trial links; anonymous shortening is unrestricted when off
+    #[validate(range(min = 1))]
+    pub links_per_ip: u64, // Trial links one IP can create per window
+    #[validate(range(min = 60, max = 2_592_000))]
+    pub window_secs: u64, // Counted in the rate-limit storage, like the per-minute limits
+    #[validate(range(min = 60, max = 2_592_000))]
+    pub link_ttl_secs: u64, // Trial links expire this long after creation, or sooner if asked to
+}
+
+impl Default for TrialConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            links_per_ip: 5,
+            window_secs: 86_400,
+            link_ttl_secs: 86_400,
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[serde(default)]
+pub struct UsageConfig {
+    pub enabled: bool,
+    #[validate(range(min = 1, max = 400))]
+    pub retention_days: u64, // Daily counts are kept this long, and `/v1/usage` can look back this far
+    #[validate(range(min = 100, max = 60000))]
+    pub flush_interval_ms: u64, // Counts are buffered in memory between writes to storage
+}
+
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retention_days: 90,
+            flush_interval_ms: 5_000,
+        }
+    }
+}
@@ -1,7 +1,14 @@
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
 use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use thiserror::Error;
 use validator::ValidationErrors;
+use crate::{
+    middleware::device_info::current_request_id,
+    types::{ApiResponse, ErrorResponse},
+};
 
 #[derive(Error, Debug)]
 pub enum AppError {
@@ -36,7 +43,7 @@ pub enum AppError {
     RateLimitExceeded,
 
     #[error("Rate limit exceeded with response")]
-    RateLimitExceededWithResponse(Response),
+    RateLimitExceededWithResponse(Box<Response>), // Boxed so every Result<_, AppError> stays small
 
     #[error("Not found: {0}")]
     NotFound(String),
@@ -64,31 +71,203 @@ pub enum AppError {
 
     #[error("Forbidden access")]
     Forbidden(String),
+
+    #[error("Gone: {0}")]
+    Gone(String),
+
+    #[error("Blocked: {0}")]
+    Blocked(String),
+
+    #[error("Locked: {message}")]
+    Locked { message: String, retry_after_secs: u64 },
+
+    #[error("Quota exceeded: {quota}")]
+    QuotaExceeded { quota: &'static str, plan: String, limit: u64, used: u64 },
+}
+
+/// Stable, machine-readable reason for a failed request, sent as `error.code` alongside the
+/// human-readable `error.message`. Clients should branch on the code; messages may change.
+/// Rejections from axum itself, e.g. a body that isn't valid JSON, keep their own status and
+/// get the code closest to it.
+///
+/// | Code                     | Status | Raised for                                                 |
+/// |--------------------------|--------|------------------------------------------------------------|
+/// | `VALIDATION_FAILED`      | 400    | A request body or query that fails validation              |
+/// | `INVALID_URL`            | 400    | A destination that is malformed or internal                |
+/// | `BAD_REQUEST`            | 400    | Any other malformed request                                |
+/// | `UNAUTHORIZED`           | 401    | Missing, invalid, expired or revoked credentials           |
+/// | `FORBIDDEN`              | 403    | Authenticated but not allowed                              |
+/// | `DESTINATION_BLOCKED`    | 403    | A blocklisted or unsafe destination, or a quarantined link |
+/// | `QUOTA_EXCEEDED`         | 403    | A plan quota is used up                                    |
+/// | `NOT_FOUND`              | 404    | No such link or resource, or no such route                 |
+/// | `METHOD_NOT_ALLOWED`     | 405    | The route exists but not for this method                   |
+/// | `ALIAS_TAKEN`            | 409    | The custom alias already belongs to another link           |
+/// | `CONFLICT`               | 409    | Any other clash with existing state                        |
+/// | `URL_EXPIRED`            | 410    | The link's expiration date has passed                      |
+/// | `GONE`                   | 410    | Disabled, burned or otherwise retired links                |
+/// | `PAYLOAD_TOO_LARGE`      | 413    | A request body over the size limit                         |
+/// | `UNSUPPORTED_MEDIA_TYPE` | 415    | A body without `Content-Type: application/json`            |
+/// | `ACCOUNT_LOCKED`         | 423    | Too many failed logins; see `details.retry_after_secs`     |
+/// | `RATE_LIMITED`           | 429    | A rate limit was hit; see the `Retry-After` header         |
+/// | `INTERNAL_ERROR`         | 500    | Unexpected failures in the service or its storage          |
+/// | `STORAGE_UNAVAILABLE`    | 503    | Dragonfly is unreachable or its circuit breaker is open    |
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    ValidationFailed,
+    InvalidUrl,
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    DestinationBlocked,
+    QuotaExceeded,
+    NotFound,
+    MethodNotAllowed,
+    AliasTaken,
+    Conflict,
+    UrlExpired,
+    Gone,
+    PayloadTooLarge,
+    UnsupportedMediaType,
+    AccountLocked,
+    RateLimited,
+    InternalError,
+    StorageUnavailable,
+}
+
+impl ErrorCode {
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorCode::ValidationFailed | ErrorCode::InvalidUrl | ErrorCode::BadRequest => StatusCode::BAD_REQUEST,
+            ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden | ErrorCode::DestinationBlocked | ErrorCode::QuotaExceeded => StatusCode::FORBIDDEN,
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            ErrorCode::AliasTaken | ErrorCode::Conflict => StatusCode::CONFLICT,
+            ErrorCode::UrlExpired | ErrorCode::Gone => StatusCode::GONE,
+            ErrorCode::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorCode::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ErrorCode::AccountLocked => StatusCode::LOCKED,
+            ErrorCode::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::StorageUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    /// The code for an error response that didn't come from an `AppError`.
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::BAD_REQUEST => ErrorCode::BadRequest,
+            // axum's JSON extractor answers well-formed bodies of the wrong shape with a 422
+            StatusCode::UNPROCESSABLE_ENTITY => ErrorCode::ValidationFailed,
+            StatusCode::UNAUTHORIZED => ErrorCode::Unauthorized,
+            StatusCode::FORBIDDEN => ErrorCode::Forbidden,
+            StatusCode::NOT_FOUND => ErrorCode::NotFound,
+            StatusCode::METHOD_NOT_ALLOWED => ErrorCode::MethodNotAllowed,
+            StatusCode::CONFLICT => ErrorCode::Conflict,
+            StatusCode::GONE => ErrorCode::Gone,
+            StatusCode::PAYLOAD_TOO_LARGE => ErrorCode::PayloadTooLarge,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => ErrorCode::UnsupportedMediaType,
+            StatusCode::LOCKED => ErrorCode::AccountLocked,
+            StatusCode::TOO_MANY_REQUESTS => ErrorCode::RateLimited,
+            StatusCode::SERVICE_UNAVAILABLE | StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT => ErrorCode::StorageUnavailable,
+            status if status.is_client_error() => ErrorCode::BadRequest,
+            _ => ErrorCode::InternalError,
+        }
+    }
+}
+
+impl AppError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::Validation(_) => ErrorCode::ValidationFailed,
+            AppError::InvalidUrl(_) => ErrorCode::InvalidUrl,
+            AppError::BadRequest(_) => ErrorCode::BadRequest,
+            AppError::Unauthorized(_) => ErrorCode::Unauthorized,
+            AppError::Forbidden(_) => ErrorCode::Forbidden,
+            AppError::Blocked(_) => ErrorCode::DestinationBlocked,
+            AppError::QuotaExceeded { .. } => ErrorCode::QuotaExceeded,
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::DuplicateAlias(_) => ErrorCode::AliasTaken,
+            AppError::Conflict(_) => ErrorCode::Conflict,
+            AppError::Expired => ErrorCode::UrlExpired,
+            AppError::Gone(_) => ErrorCode::Gone,
+            AppError::Locked { .. } => ErrorCode::AccountLocked,
+            AppError::RateLimitExceeded | AppError::RateLimitExceededWithResponse(_) => ErrorCode::RateLimited,
+            AppError::RedisConnection(_) | AppError::CircuitBreaker(_) => ErrorCode::StorageUnavailable,
+            AppError::CodeGen(_)
+            | AppError::Cache(_)
+            | AppError::RedisOperation(_)
+            | AppError::Sled(_)
+            | AppError::GeoLookup(_)
+            | AppError::Analytics(_)
+            | AppError::Internal(_) => ErrorCode::InternalError,
+        }
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        match self {
-            AppError::RateLimitExceededWithResponse(resp) => resp,
-            AppError::Validation(err) => (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
-            AppError::CodeGen(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
-            AppError::Cache(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg).into_response(),
-            AppError::RedisConnection(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg).into_response(),
-            AppError::RedisOperation(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg).into_response(),
-            AppError::CircuitBreaker(node) => (StatusCode::SERVICE_UNAVAILABLE, format!("Circuit breaker open for node: {}", node)).into_response(),
-            AppError::Sled(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
-            AppError::GeoLookup(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
-            AppError::Analytics(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg).into_response(),
-            AppError::RateLimitExceeded => (StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded".to_string()).into_response(),
-            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg).into_response(),
-            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg).into_response(),
-            AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg).into_response(),
-            AppError::Expired => (StatusCode::GONE, "URL expired".to_string()).into_response(),
-            AppError::DuplicateAlias(alias) => (StatusCode::CONFLICT, format!("Duplicate alias: {}", alias)).into_response(),
-            AppError::InvalidUrl(msg) => (StatusCode::BAD_REQUEST, msg).into_response(),
-            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg).into_response(),
-            AppError::Conflict(msg) => (StatusCode::CONFLICT, msg).into_response(),
-            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg).into_response(),
+        let code = self.code();
+        let mut retry_after = None;
+        let (message, details) = match self {
+            // Already a complete response, e.g. a 429 with its Retry-After
+            AppError::RateLimitExceededWithResponse(resp) => return *resp,
+            AppError::Validation(err) => (err.to_string(), serde_json::to_value(&err).ok()),
+            AppError::Locked { message, retry_after_secs } => {
+                retry_after = Some(retry_after_secs);
+                (message, Some(json!({ "retry_after_secs": retry_after_secs })))
+            }
+            AppError::QuotaExceeded { quota, plan, limit, used } => (
+                format!("The {} quota of the {} plan is used up ({}/{})", quota, plan, used, limit),
+                Some(json!({ "quota": quota, "plan": plan, "limit": limit, "used": used })),
+            ),
+            AppError::Cache(msg)
+            | AppError::RedisConnection(msg)
+            | AppError::RedisOperation(msg)
+            | AppError::Analytics(msg)
+            | AppError::NotFound(msg)
+            | AppError::BadRequest(msg)
+            | AppError::Internal(msg)
+            | AppError::InvalidUrl(msg)
+            | AppError::Unauthorized(msg)
+            | AppError::Conflict(msg)
+            | AppError::Forbidden(msg)
+            | AppError::Blocked(msg)
+            | AppError::Gone(msg) => (msg, None),
+            other => (other.to_string(), None),
+        };
+        let body = Json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(ErrorResponse { code, message, details, request_id: current_request_id() }),
+        });
+        match retry_after {
+            Some(secs) => (code.status(), [(header::RETRY_AFTER, secs.to_string())], body).into_response(),
+            None => (code.status(), body).into_response(),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+
+    #[tokio::test]
+    async fn errors_carry_their_code_in_the_envelope() {
+        let response = AppError::Locked { message: "Too many attempts".into(), retry_after_secs: 30 }.into_response();
+        assert_eq!(response.status(), StatusCode::LOCKED);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "30");
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["success"], false);
+        assert_eq!(body["error"]["code"], "ACCOUNT_LOCKED");
+        assert_eq!(body["error"]["message"], "Too many attempts");
+        assert_eq!(body["error"]["details"]["retry_after_secs"], 30);
+
+        let response = AppError::DuplicateAlias("promo".into()).into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["error"]["code"], "ALIAS_TAKEN");
+    }
+}
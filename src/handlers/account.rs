@@ -0,0 +1,354 @@
+use axum::{
+    extract::{Json, State},
+    Extension,
+    response::IntoResponse,
+};
+use bcrypt::{hash, verify, DEFAULT_COST};
+use cuid::cuid2;
+use serde_json::json;
+use tracing::{info, warn};
+use validator::Validate;
+use crate::{
+    errors::AppError,
+    handlers::{auth::{record_session, revoke_sessions}, shorten::AppState},
+    middleware::RequestContext,
+    services::link_signing,
+    types::{
+        ApiResponse, AuthResponse, ChangePasswordRequest, EmailVerification, SigningSecretResponse, UpdateProfileRequest, User,
+        UserProfile, UtmDefaults, VerifyEmailRequest,
+    },
+};
+
+const EMAIL_VERIFICATION_TTL_SECS: u64 = 24 * 3600;
+
+async fn current_user(state: &AppState, request_context: &RequestContext) -> Result<User, AppError> {
+    let user_id = request_context
+        .user_id
+        .as_deref()
+        .ok_or_else(|| AppError::Unauthorized("Authentication required for /v1/me".into()))?;
+    state
+        .rl_db
+        .get_user(user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".into()))
+}
+
+fn verify_password(password: &str, user: &User) -> Result<(), AppError> {
+    if !verify(password, &user.password_hash).map_err(|e| AppError::Internal(e.to_string()))? {
+        warn!("Current password check failed for {}", user.id);
+        return Err(AppError::Unauthorized("Current password is incorrect".into()));
+    }
+    Ok(())
+}
+
+#[axum::debug_handler]
+pub(crate) async fn get_me_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = current_user(&state, &request_context).await?;
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(UserProfile::from(user)),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn update_me_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<UpdateProfileRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    request_context.reject_impersonation("change the account's email or username")?;
+    req.validate().map_err(AppError::Validation)?;
+    let mut user = current_user(&state, &request_context).await?;
+    verify_password(&req.current_password, &user)?;
+
+    let email = req.email.filter(|email| *email != user.email);
+    let username = req.username.filter(|username| *username != user.username);
+    if let Some(email) = &email
+        && state.rl_db.get_user(email).await?.is_some()
+    {
+        return Err(AppError::Conflict("Email already registered".into()));
+    }
+    if let Some(username) = &username
+        && state.rl_db.get_user(username).await?.is_some()
+    {
+        return Err(AppError::Conflict("Username already taken".into()));
+    }
+
+    if let Some(email) = &email {
+        // The address signs in and may grant admin rights, so it only applies once proven
+        let token = cuid2();
+        let verification = EmailVerification { user_id: user.id.clone(), email: email.clone(), previous_email: user.email.clone() };
+        let data = serde_json::to_string(&verification).map_err(|e| AppError::Internal(e.to_string()))?;
+        state
+            .rl_db
+            .set_ex(&format!("email_verification:{}", token), &data, EMAIL_VERIFICATION_TTL_SECS)
+            .await?;
+        state.hooks.on_email_verification(&user, email, &token).await;
+        info!("User {} requested an email change", user.id);
+    }
+    if let Some(username) = username {
+        user.username = username;
+        state.rl_db.set_user(&user).await?;
+        // Tokens carry the username, so other devices sign in again to pick up the new one
+        let revoked = revoke_sessions(&state, &user.id, request_context.jti.as_deref()).await?;
+        info!("User {} changed username, {} other sessions revoked", user.id, revoked);
+    }
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(UserProfile { pending_email: email, ..UserProfile::from(user) }),
+        error: None,
+    }))
+}
+
+/// Applies an email change requested through `PATCH /v1/me`. Tokens carry the address, which
+/// decides admin rights, so every session is revoked and the caller gets a fresh token.
+#[axum::debug_handler]
+pub(crate) async fn verify_email_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<VerifyEmailRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    request_context.reject_impersonation("change the account's email or username")?;
+    req.validate().map_err(AppError::Validation)?;
+    let mut user = current_user(&state, &request_context).await?;
+    let invalid = || AppError::BadRequest("Invalid or expired verification token".into());
+    let verification: EmailVerification = match state.rl_db.get(&format!("email_verification:{}", req.token)).await {
+        Ok(data) => serde_json::from_str(&data).map_err(|e| AppError::Internal(e.to_string()))?,
+        Err(AppError::NotFound(_)) => return Err(invalid()),
+        Err(e) => return Err(e),
+    };
+    // Also retires tokens from before a later change, which would otherwise move the account back
+    if verification.user_id != user.id || verification.previous_email != user.email {
+        return Err(invalid());
+    }
+    if state.rl_db.get_user(&verification.email).await?.is_some() {
+        return Err(AppError::Conflict("Email already registered".into()));
+    }
+
+    let old_email = std::mem::replace(&mut user.email, verification.email);
+    state.rl_db.set_user(&user).await?;
+    if !old_email.is_empty() {
+        // The old address must stop resolving to this account for logins
+        state.rl_db.delete_user_email(&old_email).await?;
+    }
+    let revoked = revoke_sessions(&state, &user.id, None).await?;
+    let is_admin = state.rl_db.is_global_admin(&user.email).await?;
+    let (token, claims) = state
+        .tokens
+        .issue(&user.id, &user.username, &user.email, is_admin, state.clock.now())?;
+    record_session(&state, &user.id, &claims, &request_context).await?;
+    info!("User {} verified a new email, {} sessions revoked", user.id, revoked);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(AuthResponse { token, user_id: user.id, is_admin }),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn change_password_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<ChangePasswordRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    request_context.reject_impersonation("change the password")?;
+    req.validate().map_err(AppError::Validation)?;
+    let mut user = current_user(&state, &request_context).await?;
+    verify_password(&req.current_password, &user)?;
+    state.password_policy.check(&req.new_password, &user.username)?;
+
+    user.password_hash = hash(&req.new_password, DEFAULT_COST)
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    state.rl_db.set_user(&user).await?;
+
+    // Other devices signed in with the old password are logged out; this one stays
+    let revoked = revoke_sessions(&state, &user.id, request_context.jti.as_deref()).await?;
+    info!("User {} changed password, {} other sessions revoked", user.id, revoked);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(json!({ "revoked_sessions": revoked })),
+        error: None,
+    }))
+}
+
+/// Sets the UTM parameters added to every one of the caller's links on redirect; an empty
+/// template removes it. Links and campaigns with UTM parameters of their own take precedence.
+#[axum::debug_handler]
+pub(crate) async fn set_utm_template_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<UtmDefaults>,
+) -> Result<impl IntoResponse, AppError> {
+    req.validate().map_err(AppError::Validation)?;
+    let mut user = current_user(&state, &request_context).await?;
+    user.utm = (!req.is_empty()).then_some(req);
+    state.rl_db.set_user(&user).await?;
+    state.campaigns.forget_user_template(&user.id).await;
+    info!("User {} updated their UTM template", user.id);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(UserProfile::from(user)),
+        error: None,
+    }))
+}
+
+/// Issues a fresh secret for signing redirect URLs; the previous one stops verifying.
+#[axum::debug_handler]
+pub(crate) async fn rotate_signing_secret_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    request_context.reject_impersonation("rotate the signing secret")?;
+    let user = current_user(&state, &request_context).await?;
+    let secret = link_signing::generate_secret();
+    state.rl_db.set_signing_secret(&user.id, &secret).await?;
+    info!("User {} rotated their link signing secret", user.id);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(SigningSecretResponse { secret }),
+        error: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use crate::{
+        app::Builder,
+        config::settings::Settings,
+        services::hooks::LifecycleHook,
+        test_util::{self, MockStorage},
+        types::Session,
+    };
+
+    #[derive(Default)]
+    struct Outbox(Mutex<Vec<(String, String)>>);
+
+    #[async_trait]
+    impl LifecycleHook for Outbox {
+        async fn on_email_verification(&self, _user: &User, email: &str, token: &str) {
+            self.0.lock().push((email.to_string(), token.to_string()));
+        }
+    }
+
+    async fn body(response: impl IntoResponse) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_response().into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    fn session(jti: &str, now: u64) -> Session {
+        Session { jti: jti.into(), issued_at: now, expires_at: now + 3600, ip: None, user_agent: None }
+    }
+
+    #[tokio::test]
+    async fn email_changes_apply_only_once_verified_and_reissue_sessions() {
+        let outbox = Arc::new(Outbox::default());
+        let state = Builder::new(Settings::default())
+            .storage(Arc::new(MockStorage::new()))
+            .hook(outbox.clone())
+            .background_tasks(false)
+            .build()
+            .await
+            .unwrap()
+            .state;
+        let user = test_util::user_with_password("alice", "correct horse");
+        state.rl_db.set_user(&user).await.unwrap();
+        let now = state.clock.now().timestamp() as u64;
+        for jti in ["current", "laptop"] {
+            state.rl_db.add_session(&user.id, &session(jti, now), 3600).await.unwrap();
+        }
+        let context = RequestContext { user_id: Some(user.id.clone()), jti: Some("current".into()), ..Default::default() };
+        let update = |body: serde_json::Value| {
+            update_me_handler(State(state.clone()), Extension(context.clone()), Json(serde_json::from_value(body).unwrap()))
+        };
+
+        let profile = body(update(json!({ "email": "alice@new.example", "current_password": "correct horse" })).await.unwrap()).await;
+        assert_eq!((profile["data"]["email"].as_str(), profile["data"]["pending_email"].as_str()), (Some("alice@example.com"), Some("alice@new.example")));
+        assert!(state.rl_db.get_user("alice@new.example").await.unwrap().is_none());
+        let (email, token) = outbox.0.lock()[0].clone();
+        assert_eq!(email, "alice@new.example");
+
+        let verify = |context: RequestContext, token: &str| {
+            verify_email_handler(State(state.clone()), Extension(context), Json(VerifyEmailRequest { token: token.to_string() }))
+        };
+        let mallory = test_util::user("mallory");
+        state.rl_db.set_user(&mallory).await.unwrap();
+        let as_mallory = RequestContext { user_id: Some(mallory.id.clone()), ..Default::default() };
+        assert!(matches!(verify(as_mallory, &token).await, Err(AppError::BadRequest(_))));
+        assert!(matches!(verify(context.clone(), "unknown").await, Err(AppError::BadRequest(_))));
+
+        let issued = body(verify(context.clone(), &token).await.unwrap()).await;
+        assert_eq!(state.rl_db.get_user("alice@new.example").await.unwrap().unwrap().id, user.id);
+        assert!(state.rl_db.get_user("alice@example.com").await.unwrap().is_none());
+        let claims = state.tokens.verify(issued["data"]["token"].as_str().unwrap()).unwrap();
+        assert_eq!(claims.email, "alice@new.example");
+        for jti in ["current", "laptop"] {
+            assert!(state.rl_db.is_token_blacklisted(jti).await.unwrap(), "{} still valid", jti);
+        }
+        let sessions = state.rl_db.list_sessions(&user.id).await.unwrap();
+        assert_eq!(sessions.iter().map(|session| session.jti.as_str()).collect::<Vec<_>>(), [claims.jti.as_str()]);
+        // Spent once the address has changed
+        assert!(matches!(verify(context, &token).await, Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn taken_usernames_are_refused_and_renames_revoke_other_sessions() {
+        let state = test_util::test_state().await;
+        let user = test_util::user_with_password("alice", "correct horse");
+        state.rl_db.set_user(&user).await.unwrap();
+        state.rl_db.set_user(&test_util::user("bob")).await.unwrap();
+        let now = state.clock.now().timestamp() as u64;
+        for jti in ["current", "laptop"] {
+            state.rl_db.add_session(&user.id, &session(jti, now), 3600).await.unwrap();
+        }
+        let context = RequestContext { user_id: Some(user.id.clone()), jti: Some("current".into()), ..Default::default() };
+        let update = |body: serde_json::Value| {
+            update_me_handler(State(state.clone()), Extension(context.clone()), Json(serde_json::from_value(body).unwrap()))
+        };
+
+        // Users are looked up by id or email, as registration does
+        assert!(matches!(update(json!({ "username": "user-bob", "current_password": "correct horse" })).await, Err(AppError::Conflict(_))));
+        assert!(matches!(update(json!({ "username": "alicia", "current_password": "wrong password" })).await, Err(AppError::Unauthorized(_))));
+
+        update(json!({ "username": "alicia", "current_password": "correct horse" })).await.unwrap();
+        assert_eq!(state.rl_db.get_user(&user.id).await.unwrap().unwrap().username, "alicia");
+        assert!(state.rl_db.is_token_blacklisted("laptop").await.unwrap());
+        assert!(!state.rl_db.is_token_blacklisted("current").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn impersonation_tokens_cannot_take_over_the_account() {
+        let state = test_util::test_state().await;
+        let user = test_util::user_with_password("alice", "correct horse");
+        state.rl_db.set_user(&user).await.unwrap();
+        let context = RequestContext { user_id: Some(user.id.clone()), impersonator: Some("user-root".into()), ..Default::default() };
+
+        let password = serde_json::from_value(json!({ "current_password": "correct horse", "new_password": "battery staple" })).unwrap();
+        let result = change_password_handler(State(state.clone()), Extension(context.clone()), Json(password)).await;
+        assert!(matches!(result, Err(AppError::Forbidden(_))));
+        let result = rotate_signing_secret_handler(State(state.clone()), Extension(context.clone())).await;
+        assert!(matches!(result, Err(AppError::Forbidden(_))));
+        let profile = serde_json::from_value(json!({ "email": "mallory@example.com", "current_password": "correct horse" })).unwrap();
+        let result = update_me_handler(State(state.clone()), Extension(context.clone()), Json(profile)).await;
+        assert!(matches!(result, Err(AppError::Forbidden(_))));
+        let key = serde_json::from_value(json!({ "name": "backdoor", "scopes": ["links:read"] })).unwrap();
+        let result = crate::handlers::api_keys::create_api_key_handler(State(state.clone()), Extension(context), Json(key)).await;
+        assert!(matches!(result, Err(AppError::Forbidden(_))));
+
+        assert!(state.rl_db.get_signing_secret(&user.id).await.unwrap().is_none());
+        assert!(state.rl_db.list_api_keys(&user.id).await.unwrap().is_empty());
+        assert_eq!(state.rl_db.get_user(&user.id).await.unwrap().unwrap().password_hash, user.password_hash);
+    }
+}
@@ -0,0 +1,681 @@
+use axum::{
+    extract::{Json, OriginalUri, Path, Query, State},
+    Extension,
+    response::IntoResponse,
+};
+use cuid::cuid2;
+use serde_json::json;
+use std::sync::Arc;
+use tracing::info;
+use validator::Validate;
+use crate::{
+    errors::AppError,
+    handlers::{pagination::pagination_headers, shorten::AppState, usage::usage_report},
+    middleware::RequestContext,
+    services::{cache::circuit_breaker::redact_node, discovery, log_level, metrics, usage::ALL_SUBJECT},
+    types::{
+        ApiResponse, AuditEvent, BlocklistEntryRequest, BlocklistResponse, CacheStatsResponse, CacheTierStats,
+        CacheWarmupRequest, CacheWarmupResponse,
+        AddNodeRequest, CircuitBreakerActionRequest, CircuitBreakerStatus, NodeChangeResponse, RemoveNodeRequest,
+        DisableLinkRequest, ImpersonationResponse, LogLevelRequest, LogLevelResponse, Notification, PageQuery,
+        ReservedAliasRequest, ReservedAliasesResponse, SetPlanRequest, UsageQuery,
+    },
+};
+
+fn require_admin(request_context: &RequestContext) -> Result<(), AppError> {
+    if request_context.user_id.is_none() {
+        return Err(AppError::Unauthorized("Authentication required for /v1/admin".into()));
+    }
+    if !request_context.is_admin {
+        return Err(AppError::Forbidden("Admin access required".into()));
+    }
+    Ok(())
+}
+
+#[axum::debug_handler]
+pub(crate) async fn list_blocklist_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(BlocklistResponse { patterns: state.blocklist.list() }),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn add_blocklist_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<BlocklistEntryRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    req.validate().map_err(AppError::Validation)?;
+
+    if state.blocklist.add(&req.pattern)? {
+        info!("Blocklist pattern {} added by {:?}", req.pattern, request_context.user_id);
+    }
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(BlocklistResponse { patterns: state.blocklist.list() }),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn remove_blocklist_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<BlocklistEntryRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    req.validate().map_err(AppError::Validation)?;
+
+    if !state.blocklist.remove(&req.pattern)? {
+        return Err(AppError::NotFound(format!("Pattern {} is not blocklisted", req.pattern)));
+    }
+    info!("Blocklist pattern {} removed by {:?}", req.pattern, request_context.user_id);
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(BlocklistResponse { patterns: state.blocklist.list() }),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn list_reserved_aliases_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(ReservedAliasesResponse { aliases: state.reserved_aliases.list().await? }),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn add_reserved_alias_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<ReservedAliasRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    req.validate().map_err(AppError::Validation)?;
+
+    if state.reserved_aliases.add(&req.alias).await? {
+        info!("Alias {} reserved by {:?}", req.alias, request_context.user_id);
+    }
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(ReservedAliasesResponse { aliases: state.reserved_aliases.list().await? }),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn remove_reserved_alias_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<ReservedAliasRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    req.validate().map_err(AppError::Validation)?;
+
+    if !state.reserved_aliases.remove(&req.alias).await? {
+        return Err(AppError::NotFound(format!("Alias {} is not reserved", req.alias)));
+    }
+    info!("Alias {} released by {:?}", req.alias, request_context.user_id);
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(ReservedAliasesResponse { aliases: state.reserved_aliases.list().await? }),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn list_reports_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<PageQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    let reports = state
+        .rl_db
+        .list_reports(query.page.unwrap_or(1), query.per_page.unwrap_or(20))
+        .await?;
+    Ok((
+        pagination_headers(&state.config.base_url, &uri, &reports),
+        Json(ApiResponse {
+            success: true,
+            data: Some(reports),
+            error: None,
+        }),
+    ))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn disable_link_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Path(code): Path<String>,
+    Json(req): Json<DisableLinkRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    req.validate().map_err(AppError::Validation)?;
+
+    let mut url_data = state
+        .cache
+        .get_url_data(&code)
+        .await
+        .map(Arc::unwrap_or_clone)
+        .map_err(|_| AppError::NotFound("URL not found".into()))?;
+
+    // Tombstone rather than delete so the owner and analytics keep the record
+    let now = state.clock.now().to_rfc3339();
+    url_data.disabled_at = Some(now.clone());
+    url_data.disabled_reason = Some(req.reason.clone());
+    state.cache.insert(code.clone(), &url_data).await?;
+
+    let resolved = state.rl_db.resolve_reports(&code).await?;
+    if let Some(owner) = &url_data.user_id {
+        let notification = Notification {
+            id: cuid2(),
+            code: Some(code.clone()),
+            message: format!("Your link /{} was disabled after review: {}", code, req.reason),
+            created_at: now,
+        };
+        state.rl_db.add_notification(owner, &notification).await?;
+    }
+    metrics::record_abuse_report("actioned");
+    info!("Link {} disabled by {:?}, {} reports resolved", code, request_context.user_id, resolved);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(json!({ "code": code, "resolved_reports": resolved })),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn impersonate_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Path(user_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    request_context.reject_impersonation("start another impersonation")?;
+    // Re-check the admin list so a demoted admin can't use a token minted before the change
+    let email = request_context.email.as_deref().unwrap_or_default();
+    if !state.rl_db.is_global_admin(email).await? {
+        return Err(AppError::Forbidden("Global admin access required".into()));
+    }
+    let admin_id = request_context.user_id.clone().unwrap_or_default();
+
+    let user = state
+        .rl_db
+        .get_user(&user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User {} not found", user_id)))?;
+    let ttl_secs = state.config.security.impersonation_ttl_secs.unwrap_or(900);
+    let (token, claims) = state
+        .tokens
+        .issue_impersonation(&user, &admin_id, ttl_secs, state.clock.now())?;
+
+    let event = AuditEvent {
+        id: cuid2(),
+        actor_id: admin_id.clone(),
+        acting_as: Some(user.id.clone()),
+        action: "impersonate.start".into(),
+        status: None,
+        ip: request_context.ip.clone(),
+        created_at: state.clock.now().to_rfc3339(),
+    };
+    state.rl_db.add_audit_event(&event).await?;
+    info!("Admin {} started impersonating {}", admin_id, user.id);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(ImpersonationResponse {
+            token,
+            user_id: user.id,
+            impersonator: admin_id,
+            expires_at: claims.exp,
+        }),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn list_audit_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Query(query): Query<PageQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    let events = state.rl_db.list_audit_events(query.per_page.unwrap_or(100)).await?;
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(events),
+        error: None,
+    }))
+}
+
+/// Moves a user onto another quota plan.
+#[axum::debug_handler]
+pub(crate) async fn set_plan_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Path(user_id): Path<String>,
+    Json(req): Json<SetPlanRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    if let Some(plan) = req.plan.as_deref().filter(|plan| !state.config.quota.plans.contains_key(*plan)) {
+        return Err(AppError::BadRequest(format!("Unknown plan {}", plan)));
+    }
+    let mut user = state
+        .rl_db
+        .get_user(&user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User {} not found", user_id)))?;
+    user.plan = req.plan;
+    state.rl_db.set_user(&user).await?;
+    info!("User {} moved to plan {:?} by {:?}", user.id, user.plan, request_context.user_id);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(json!({ "user_id": user.id, "plan": user.plan })),
+        error: None,
+    }))
+}
+
+/// Requests across every user, API key and anonymous caller, per day.
+#[axum::debug_handler]
+pub(crate) async fn usage_rollup_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Query(query): Query<UsageQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    let days = state.usage.lookback(query.days);
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(state.usage.daily(ALL_SUBJECT, days).await?),
+        error: None,
+    }))
+}
+
+/// The `/v1/usage` report of any user, for support.
+#[axum::debug_handler]
+pub(crate) async fn user_usage_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Path(user_id): Path<String>,
+    Query(query): Query<UsageQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    if state.rl_db.get_user(&user_id).await?.is_none() {
+        return Err(AppError::NotFound(format!("User {} not found", user_id)));
+    }
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(usage_report(&state, &user_id, query.days).await?),
+        error: None,
+    }))
+}
+
+// Lookup order of CacheService::get
+const CACHE_TIERS: [&str; 5] = ["l1", "bloom", "l2", "dragonfly", "sled"];
+
+#[axum::debug_handler]
+pub(crate) async fn cache_stats_handler(
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    let tiers = CACHE_TIERS
+        .iter()
+        .map(|tier| {
+            let (hits, misses, inserts, evictions) = metrics::cache_tier_counts(tier);
+            let lookups = hits + misses;
+            CacheTierStats {
+                tier: tier.to_string(),
+                hits,
+                misses,
+                inserts,
+                evictions,
+                hit_ratio: (lookups > 0).then(|| hits as f64 / lookups as f64),
+            }
+        })
+        .collect();
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(CacheStatsResponse { tiers }),
+        error: None,
+    }))
+}
+
+// Links a pattern warms when the request sets no limit
+const DEFAULT_WARMUP_LIMIT: usize = 10_000;
+
+/// Warms the caches with the links given by code or matching a pattern, ahead of a traffic
+/// spike. Warming runs in the background at `cache.warmup_keys_per_sec`.
+#[axum::debug_handler]
+pub(crate) async fn cache_warmup_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<CacheWarmupRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    req.validate().map_err(AppError::Validation)?;
+
+    let keys = match (req.pattern, req.codes) {
+        (Some(pattern), None) => state.cache.matching_links(&pattern, req.limit.unwrap_or(DEFAULT_WARMUP_LIMIT)).await?,
+        (None, Some(codes)) => codes,
+        _ => return Err(AppError::BadRequest("Give either a pattern or a list of codes".into())),
+    };
+    info!("Warming {} links, requested by {:?}", keys.len(), request_context.user_id);
+    let response = CacheWarmupResponse { keys: keys.len(), keys_per_sec: state.cache.warmup_keys_per_sec() };
+    let cache = Arc::clone(&state.cache);
+    tokio::spawn(async move { cache.warmup_throttled(keys).await });
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(response),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn rebuild_bloom_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    let status = state.cache.start_bloom_rebuild()?;
+    info!("Bloom filter rebuild started by {:?}", request_context.user_id);
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(status),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn bloom_rebuild_status_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(state.cache.bloom_rebuild_status()),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn broken_links_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(state.link_checker.broken_links()),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn get_log_level_handler(
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(LogLevelResponse { filter: log_level::current_filter()? }),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn set_log_level_handler(
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<LogLevelRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    req.validate().map_err(AppError::Validation)?;
+
+    let filter = log_level::set_filter(&req.filter)?;
+    tracing::warn!("Log filter changed to '{}' by {:?}", filter, request_context.user_id);
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(LogLevelResponse { filter }),
+        error: None,
+    }))
+}
+
+async fn circuit_breaker_statuses(state: &AppState) -> Vec<CircuitBreakerStatus> {
+    let mut statuses = Vec::new();
+    for (breaker, circuit_breaker) in state.circuit_breakers() {
+        statuses.push(CircuitBreakerStatus {
+            breaker: breaker.to_string(),
+            nodes: circuit_breaker.snapshot().await,
+        });
+    }
+    statuses
+}
+
+#[axum::debug_handler]
+pub(crate) async fn list_circuit_breakers_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(circuit_breaker_statuses(&state).await),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn trip_circuit_breaker_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<CircuitBreakerActionRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    req.validate().map_err(AppError::Validation)?;
+    apply_circuit_breaker_action(&state, &req, true).await?;
+    info!("Circuit breaker for {} tripped by {:?}", req.node, request_context.user_id);
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(circuit_breaker_statuses(&state).await),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn reset_circuit_breaker_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<CircuitBreakerActionRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    req.validate().map_err(AppError::Validation)?;
+    apply_circuit_breaker_action(&state, &req, false).await?;
+    info!("Circuit breaker for {} reset by {:?}", req.node, request_context.user_id);
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(circuit_breaker_statuses(&state).await),
+        error: None,
+    }))
+}
+
+async fn apply_circuit_breaker_action(state: &AppState, req: &CircuitBreakerActionRequest, trip: bool) -> Result<(), AppError> {
+    let mut matched = false;
+    for (name, circuit_breaker) in state.circuit_breakers() {
+        if req.breaker.as_deref().is_some_and(|wanted| wanted != name) {
+            continue;
+        }
+        matched |= if trip {
+            circuit_breaker.trip(&req.node).await
+        } else {
+            circuit_breaker.reset(&req.node).await
+        };
+    }
+    if !matched {
+        return Err(AppError::NotFound(format!("No circuit breaker tracks node {}", req.node)));
+    }
+    Ok(())
+}
+
+/// Adds a Dragonfly node to every storage client's ring. Clients that already have it are
+/// skipped, so a failed add can be retried.
+#[axum::debug_handler]
+pub(crate) async fn add_node_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<AddNodeRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    req.validate().map_err(AppError::Validation)?;
+
+    let keys_moved = discovery::add_node_to_all(&state.storage_clients(), &req.url).await?;
+    let node = redact_node(&req.url);
+    info!("Node {} added by {:?}, {} keys moved", node, request_context.user_id, keys_moved);
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(NodeChangeResponse { node: Some(node), keys_moved }),
+        error: None,
+    }))
+}
+
+/// Drains a Dragonfly node into the rest of the ring and disconnects every storage client from it.
+#[axum::debug_handler]
+pub(crate) async fn remove_node_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<RemoveNodeRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    req.validate().map_err(AppError::Validation)?;
+
+    let keys_moved = discovery::remove_node_from_all(&state.storage_clients(), &req.node).await?;
+    info!("Node {} removed by {:?}, {} keys moved", req.node, request_context.user_id, keys_moved);
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(NodeChangeResponse { node: Some(req.node), keys_moved }),
+        error: None,
+    }))
+}
+
+/// Moves every key onto the node the ring assigns it, e.g. after upgrading a multi-node
+/// deployment from modulo sharding.
+#[axum::debug_handler]
+pub(crate) async fn rebalance_nodes_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    require_admin(&request_context)?;
+    let mut keys_moved = 0;
+    for (_, client) in state.storage_clients() {
+        keys_moved += client.rebalance().await?;
+    }
+    info!("Ring rebalanced by {:?}, {} keys moved", request_context.user_id, keys_moved);
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(NodeChangeResponse { node: None, keys_moved }),
+        error: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::{
+        config::settings::Settings,
+        handlers::{notifications::list_notifications_handler, reports::report_handler, shorten::create_short_link},
+        test_util::{self, MockStorage},
+    };
+
+    async fn json_body(response: impl IntoResponse) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_response().into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn disabling_a_reported_link_resolves_its_reports_and_notifies_the_owner() {
+        let state = test_util::test_state().await;
+        let owner = RequestContext { user_id: Some("user-alice".into()), ..Default::default() };
+        let shorten = serde_json::from_value(json!({ "url": "https://example.com/prize" })).unwrap();
+        let code = create_short_link(&state, &owner, shorten).await.unwrap().code;
+
+        let reporter = RequestContext { ip: Some("203.0.113.9".into()), ..Default::default() };
+        let report = |code: String| {
+            let req = serde_json::from_value(json!({ "reason": "phishing", "details": "asks for a bank login" })).unwrap();
+            report_handler(State(state.clone()), Extension(reporter.clone()), Path(code), Json(req))
+        };
+        assert!(matches!(report("missing".into()).await, Err(AppError::NotFound(_))));
+        report(code.clone()).await.unwrap();
+        report(code.clone()).await.unwrap();
+        let queue = state.rl_db.list_reports(1, 20).await.unwrap();
+        assert_eq!(queue.items.len(), 2);
+        assert_eq!((queue.items[0].code.as_str(), queue.items[0].reporter_ip.as_deref()), (code.as_str(), Some("203.0.113.9")));
+
+        let admin = RequestContext { user_id: Some("user-root".into()), is_admin: true, ..Default::default() };
+        let disable = |context: RequestContext| {
+            let req = serde_json::from_value(json!({ "reason": "Phishing" })).unwrap();
+            disable_link_handler(State(state.clone()), Extension(context), Path(code.clone()), Json(req))
+        };
+        assert!(matches!(disable(owner.clone()).await, Err(AppError::Forbidden(_))));
+        let body = json_body(disable(admin).await.unwrap()).await;
+        assert_eq!(body["data"]["resolved_reports"], 2);
+        assert!(state.rl_db.list_reports(1, 20).await.unwrap().items.is_empty());
+        let url_data = state.cache.get_url_data(&code).await.unwrap();
+        assert_eq!(url_data.disabled_reason.as_deref(), Some("Phishing"));
+        assert!(url_data.disabled_at.is_some());
+
+        let body = json_body(list_notifications_handler(State(state.clone()), Extension(owner)).await.unwrap()).await;
+        let notifications = body["data"].as_array().unwrap();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0]["code"], code.as_str());
+        assert_eq!(notifications[0]["message"], format!("Your link /{} was disabled after review: Phishing", code));
+    }
+
+    #[tokio::test]
+    async fn impersonation_needs_a_current_admin_and_is_audited() {
+        let storage = MockStorage::new().with_global_admins(vec!["root@example.com".into()]);
+        let state = test_util::test_app(Settings::default(), Arc::new(storage)).await.state;
+        state.rl_db.set_user(&test_util::user("alice")).await.unwrap();
+        let admin = RequestContext {
+            user_id: Some("user-root".into()),
+            email: Some("root@example.com".into()),
+            is_admin: true,
+            ..Default::default()
+        };
+        let impersonate = |context: RequestContext| impersonate_handler(State(state.clone()), Extension(context), Path("user-alice".to_string()));
+
+        // A token minted before its holder left the admin list still says is_admin
+        let demoted = RequestContext { email: Some("former@example.com".into()), ..admin.clone() };
+        assert!(matches!(impersonate(demoted).await, Err(AppError::Forbidden(_))));
+        let chained = RequestContext { impersonator: Some("user-root".into()), ..admin.clone() };
+        assert!(matches!(impersonate(chained).await, Err(AppError::Forbidden(_))));
+        assert!(state.rl_db.list_audit_events(10).await.unwrap().is_empty());
+
+        let body = json_body(impersonate(admin).await.unwrap()).await;
+        let claims = state.tokens.verify(body["data"]["token"].as_str().unwrap()).unwrap();
+        assert_eq!((claims.user_id.as_deref(), claims.impersonator.as_deref()), (Some("user-alice"), Some("user-root")));
+        assert!(!claims.is_admin);
+
+        let events = state.rl_db.list_audit_events(10).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].action.as_str(), events[0].actor_id.as_str()), ("impersonate.start", "user-root"));
+        assert_eq!(events[0].acting_as.as_deref(), Some("user-alice"));
+    }
+}
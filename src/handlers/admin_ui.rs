@@ -0,0 +1,28 @@
+//! The admin dashboard: one static page, compiled into the binary, that signs in and calls the
+//! JSON API like any other client. Only built with the `admin-ui` feature.
+
+use axum::{
+    http::header,
+    response::{Html, IntoResponse},
+    routing::get,
+    Router,
+};
+
+const INDEX_HTML: &str = include_str!("../../assets/admin/index.html");
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/admin", get(index_handler))
+        .route("/admin/", get(index_handler))
+}
+
+async fn index_handler() -> impl IntoResponse {
+    (
+        [
+            (header::CACHE_CONTROL, "no-cache"),
+            // The page only talks to this origin and keeps its token out of reach of other frames
+            (header::CONTENT_SECURITY_POLICY, "default-src 'self'; style-src 'unsafe-inline'; script-src 'unsafe-inline'; frame-ancestors 'none'"),
+        ],
+        Html(INDEX_HTML),
+    )
+}
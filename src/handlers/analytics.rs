@@ -2,8 +2,13 @@ use axum::{extract::State, response::IntoResponse, http::StatusCode};
 use prometheus::Encoder;
 use crate::handlers::shorten::AppState;
 
-use axum::{extract::Path, Json};
-use crate::types::ApiResponse;
+use axum::{extract::{OriginalUri, Path, Query}, Json};
+use crate::{
+    handlers::pagination::pagination_headers,
+    types::{ApiResponse, PageQuery, Paginate},
+};
+
+const MAX_CLICKS_PER_PAGE: u64 = 10_000;
 
 #[axum::debug_handler]
 pub async fn metrics_handler(
@@ -15,21 +20,47 @@ pub async fn metrics_handler(
     (StatusCode::OK, buffer)
 }
 
+/// Clicks on `code` over the last 30 days, oldest first, up to 10k per page, plus a click count
+/// per variant for A/B links.
 #[axum::debug_handler]
 pub async fn analytics_code_handler(
     State(state): State<AppState>,
     Path(code): Path<String>,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<PageQuery>,
 ) -> Result<impl IntoResponse, crate::errors::AppError> {
-    // Example: fetch analytics for a given code
-    // Use available analytics method (stub: last 30 days)
-    let now = chrono::Utc::now().timestamp();
+    let now = state.clock.now().timestamp();
     let thirty_days_ago = now - 30 * 24 * 3600;
     let analytics = state.analytics.get_analytics(&code, thirty_days_ago, now).await.map_err(|e| crate::errors::AppError::Internal(e.to_string()))?;
-    Ok(Json(ApiResponse {
-        success: true,
-        data: Some(serde_json::json!({"analytics": analytics})),
-        error: None,
-    }))
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(MAX_CLICKS_PER_PAGE).clamp(1, MAX_CLICKS_PER_PAGE);
+    let total_items = analytics.len() as u64;
+    let clicks: Vec<(u64, u64)> = analytics
+        .into_iter()
+        .skip(((page - 1) * per_page) as usize)
+        .take(per_page as usize)
+        .collect();
+    let clicks = Paginate::new(clicks, page, per_page, total_items);
+    // A/B links also report each variant's clicks over the same window
+    let mut variants = serde_json::Map::new();
+    if let Ok(url_data) = state.cache.get_url_data(&code).await {
+        for variant in &url_data.variants {
+            let variant_clicks = state.analytics.get_variant_analytics(&code, &variant.name, thirty_days_ago, now).await?;
+            variants.insert(variant.name.clone(), variant_clicks.len().into());
+        }
+    }
+    let mut data = serde_json::json!({"analytics": clicks.items});
+    if !variants.is_empty() {
+        data["variants"] = variants.into();
+    }
+    Ok((
+        pagination_headers(&state.config.base_url, &uri, &clicks),
+        Json(ApiResponse {
+            success: true,
+            data: Some(data),
+            error: None,
+        }),
+    ))
 }
 
 #[cfg(test)]
@@ -42,35 +73,52 @@ mod tests {
         config::settings::Settings,
         services::{
             analytics::AnalyticsService,
-            cache::{cache::CacheService, circuit_breaker::CircuitBreaker},
+            cache::cache::CacheService,
             codegen::generator::CodeGenerator,
-            storage::dragonfly::DatabaseClient,
+            blocklist::DomainBlocklist,
+            campaigns::CampaignService,
+            captcha::CaptchaGate,
+            link_checker::LinkChecker,
+            reserved_aliases::ReservedAliases,
+            tokens::TokenService,
+            password_policy::PasswordPolicy,
+            threat_intel::ThreatIntel,
+            storage::storage::Storage,
+            usage::UsageTracker,
         },
-        clock::SystemClock,
+        clock::{Clock, SystemClock},
         handlers::shorten::AppState,
+        test_util::MockStorage,
     };
 
     #[tokio::test]
     async fn test_metrics_handler() {
         let config = Arc::new(Settings::default());
-        let cache = Arc::new(CacheService::new(&config).await);
-        let circuit_breaker = Arc::new(CircuitBreaker::new(
-            config.database_urls.clone(),
-            config.cache.max_failures,
-            std::time::Duration::from_secs(config.cache.retry_interval_secs),
-        ));
-        let analytics = Arc::new(AnalyticsService::new(&config, Arc::clone(&circuit_breaker), SystemClock).await);
+        let rl_db: Arc<dyn Storage + Send + Sync> = Arc::new(MockStorage::new());
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        let cache = Arc::new(CacheService::with_storage(&config, rl_db.clone(), Arc::clone(&clock)).await);
+        let analytics = Arc::new(AnalyticsService::with_storage(&config, rl_db.clone(), Arc::clone(&clock)).await);
         let codegen = Arc::new(CodeGenerator::new(&config));
-        let clock = Arc::new(SystemClock);
-        let rl_db = Arc::new(DatabaseClient::new(&config, Arc::clone(&circuit_breaker)).await.unwrap());
 
+        let blocklist = Arc::new(DomainBlocklist::new(&config).unwrap());
         let state = AppState {
             config: Arc::clone(&config),
             cache: Arc::clone(&cache),
             analytics: Arc::clone(&analytics),
             codegen: Arc::clone(&codegen),
             clock: Arc::clone(&clock),
-            rl_db: Arc::clone(&rl_db),
+            rl_db: rl_db.clone(),
+            threat_intel: Arc::new(ThreatIntel::new(&config, blocklist.clone())),
+            blocklist,
+            captcha: Arc::new(CaptchaGate::new(&config)),
+            tokens: Arc::new(TokenService::new(&config).unwrap()),
+            password_policy: Arc::new(PasswordPolicy::new(&config).unwrap()),
+            hooks: Default::default(),
+            campaigns: Arc::new(CampaignService::new(rl_db.clone())),
+            usage: Arc::new(UsageTracker::new(&config, rl_db.clone(), Arc::clone(&clock))),
+            shortens_in_flight: Default::default(),
+            link_checker: Arc::new(LinkChecker::new(&config, cache.clone(), rl_db.clone(), Arc::clone(&clock))),
+            reserved_aliases: Arc::new(ReservedAliases::new(&config, rl_db.clone())),
         };
 
         let app = Router::new()
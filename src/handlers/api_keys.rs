@@ -0,0 +1,99 @@
+use axum::{
+    extract::{Json, Path, State},
+    Extension,
+    response::IntoResponse,
+};
+use cuid::cuid2;
+use serde_json::json;
+use tracing::info;
+use validator::Validate;
+use crate::{
+    errors::AppError,
+    handlers::shorten::AppState,
+    middleware::RequestContext,
+    services::api_keys,
+    types::{ApiKey, ApiKeyCreatedResponse, ApiKeyInfo, ApiResponse, CreateApiKeyRequest},
+};
+
+const MAX_KEYS_PER_USER: usize = 20;
+
+fn require_user(request_context: &RequestContext) -> Result<&str, AppError> {
+    request_context
+        .user_id
+        .as_deref()
+        .ok_or_else(|| AppError::Unauthorized("Authentication required for /v1/api-keys".into()))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn create_api_key_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<CreateApiKeyRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = require_user(&request_context)?;
+    request_context.reject_impersonation("create API keys")?;
+    req.validate().map_err(AppError::Validation)?;
+    if state.rl_db.list_api_keys(user_id).await?.len() >= MAX_KEYS_PER_USER {
+        return Err(AppError::Conflict(format!("At most {} API keys per account", MAX_KEYS_PER_USER)));
+    }
+
+    let id = cuid2();
+    let (key, key_hash) = api_keys::generate(&id);
+    let mut scopes = req.scopes;
+    scopes.sort();
+    scopes.dedup();
+    let api_key = ApiKey {
+        id,
+        user_id: user_id.to_string(),
+        name: req.name,
+        key_hash,
+        scopes,
+        created_at: state.clock.now().to_rfc3339(),
+    };
+    state.rl_db.set_api_key(&api_key).await?;
+    info!("API key {} created for {} with scopes {:?}", api_key.id, user_id, api_key.scopes);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(ApiKeyCreatedResponse { key, api_key: api_key.into() }),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn list_api_keys_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = require_user(&request_context)?;
+    let keys: Vec<ApiKeyInfo> = state
+        .rl_db
+        .list_api_keys(user_id)
+        .await?
+        .into_iter()
+        .map(ApiKeyInfo::from)
+        .collect();
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(keys),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn delete_api_key_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = require_user(&request_context)?;
+    if !state.rl_db.delete_api_key(user_id, &id).await? {
+        return Err(AppError::NotFound(format!("API key {} not found", id)));
+    }
+    info!("API key {} revoked by {}", id, user_id);
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(json!({ "id": id })),
+        error: None,
+    }))
+}
@@ -1,54 +1,125 @@
 use axum::{
-    extract::{Json, State}, http::HeaderMap, response::IntoResponse, routing::post, Router
+    extract::{Json, State}, http::HeaderMap, response::IntoResponse, routing::{get, post}, Extension, Router
 };
 use bcrypt::{hash, verify, DEFAULT_COST};
-use chrono::Duration;
-use jsonwebtoken::{encode, EncodingKey, Header};
-use std::sync::Arc;
 use tracing::{info, warn};
 use cuid::cuid2;
 use validator::Validate;
 
+use serde_json::json;
+
 use crate::{
-    clock::{Clock, SystemClock}, config::settings::Settings, errors::AppError, services::{
-        analytics::AnalyticsService,
-        cache::cache::CacheService,
-        codegen::generator::CodeGenerator,
-        storage::{dragonfly::DatabaseClient, storage::Storage},
-    }, types::{ApiResponse, AuthAction, AuthResponse, AuthToken, User, AuthRequest, DeleteAccountRequest}
+    errors::AppError, handlers::shorten::AppState,
+    middleware::{rate_limit::auth_rate_limit_middleware, RequestContext},
+    services::metrics,
+    types::{ApiResponse, AuthAction, AuthResponse, AuthToken, User, AuthRequest, DeleteAccountRequest, Session, SessionsResponse}
 };
 
-#[derive(Clone)]
-pub struct AppState {
-    pub config: Arc<Settings>,
-    pub cache: Arc<CacheService>,
-    pub analytics: Arc<AnalyticsService>,
-    pub codegen: Arc<CodeGenerator>,
-    pub clock: Arc<SystemClock>,
-    pub rl_db: Arc<DatabaseClient>,
-}
-
 
 
 pub fn routes(state: AppState) -> Router {
-    Router::new()
+    // Endpoints that take a password get the stricter credential limiter
+    let credential_routes = Router::new()
         .route("/v1/auth/register", post(register_handler))
         .route("/v1/auth/login", post(login_handler))
-        .route("/v1/auth/logout", post(logout_handler))
         .route("/v1/auth/delete-account", post(delete_account_handler))
-    // .layer(axum::middleware::from_fn_with_state(state.clone(), auth_rate_limit_middleware))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), auth_rate_limit_middleware));
+
+    Router::new()
+        .merge(credential_routes)
+        .route("/v1/auth/logout", post(logout_handler))
+        .route("/v1/auth/sessions", get(list_sessions_handler))
+        .route("/v1/auth/sessions/revoke-all", post(revoke_all_sessions_handler))
         .with_state(state)
 }
 
+/// Public signing keys so other services can verify our tokens without the HMAC secret.
+#[axum::debug_handler]
+pub async fn jwks_handler(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.tokens.jwks().clone())
+}
+
+async fn check_lockout(state: &AppState, subjects: &[(&'static str, String)]) -> Result<(), AppError> {
+    let now = state.clock.now().timestamp().max(0) as u64;
+    for (scope, subject) in subjects {
+        if let Some(until) = state.rl_db.get_account_lock(subject).await?.filter(|&until| until > now) {
+            metrics::record_account_lockout(scope, "rejected");
+            return Err(AppError::Locked {
+                message: "Too many failed login attempts, try again later".into(),
+                retry_after_secs: until - now,
+            });
+        }
+    }
+    Ok(())
+}
+
+async fn record_failed_login(state: &AppState, subjects: &[(&'static str, String)]) -> Result<(), AppError> {
+    let security = &state.config.security;
+    let threshold = security.lockout_threshold.unwrap_or(5);
+    let window_secs = security.lockout_window_secs.unwrap_or(900);
+    let duration_secs = security.lockout_duration_secs.unwrap_or(900);
+    for (scope, subject) in subjects {
+        let failures = state.rl_db.record_login_failure(subject, window_secs).await?;
+        if failures >= threshold {
+            let until = state.clock.now().timestamp().max(0) as u64 + duration_secs;
+            state.rl_db.lock_account(subject, until, duration_secs).await?;
+            state.rl_db.clear_login_failures(subject).await?;
+            metrics::record_account_lockout(scope, "locked");
+            warn!("Locked {} after {} failed logins", subject, failures);
+        }
+    }
+    Ok(())
+}
+
+pub(crate) async fn record_session(
+    state: &AppState,
+    user_id: &str,
+    claims: &AuthToken,
+    request_context: &RequestContext,
+) -> Result<(), AppError> {
+    let session = Session {
+        jti: claims.jti.clone(),
+        issued_at: claims.iat,
+        expires_at: claims.exp,
+        ip: request_context.ip.clone(),
+        user_agent: request_context.user_agent.clone(),
+    };
+    state
+        .rl_db
+        .add_session(user_id, &session, state.config.security.token_expiry_secs)
+        .await
+}
+
+/// Blacklists each live session's jti until its token would have expired anyway, optionally
+/// keeping one (the caller's own) alive. Returns how many were revoked.
+pub(crate) async fn revoke_sessions(state: &AppState, user_id: &str, keep_jti: Option<&str>) -> Result<u64, AppError> {
+    let now = state.clock.now().timestamp().max(0) as u64;
+    let mut revoked = 0;
+    for session in state.rl_db.remove_sessions(user_id).await? {
+        if session.expires_at <= now {
+            continue;
+        }
+        if keep_jti == Some(session.jti.as_str()) {
+            state.rl_db.add_session(user_id, &session, session.expires_at - now).await?;
+            continue;
+        }
+        state.rl_db.blacklist_token(&session.jti, session.expires_at - now).await?;
+        revoked += 1;
+    }
+    Ok(revoked)
+}
+
 #[axum::debug_handler]
 pub async fn register_handler(
     State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
     Json(req): Json<AuthRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     req.validate().map_err(AppError::Validation)?;
     if req.action != AuthAction::Register {
         return Err(AppError::BadRequest("Invalid action for register".into()));
     }
+    state.password_policy.check(&req.password, &req.username)?;
 
     // Check if username or email exists
     if let Some(email) = &req.email {
@@ -72,29 +143,21 @@ pub async fn register_handler(
         email: req.email.unwrap_or_default(),
         password_hash,
         created_at: state.clock.now().to_rfc3339(),
+        plan: None,
+        utm: None,
     };
     state.rl_db.set_user(&user).await?;
 
     // Generate JWT
-    let expires_at = state.clock.now() + Duration::hours(24);
     let is_admin = if !user.email.is_empty() {
         state.rl_db.is_global_admin(&user.email).await?
     } else {
         false
     };
-    let claims = AuthToken {
-        user_id: Some(user_id),
-        expires_at: expires_at.to_rfc3339(),
-        username: user.username.clone(),
-        email: user.email.clone(),
-    is_admin,
-    };
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(state.config.security.jwt_secret.as_ref()),
-    )
-    .map_err(|e| AppError::Internal(e.to_string()))?;
+    let (token, claims) = state
+        .tokens
+        .issue(&user_id, &user.username, &user.email, is_admin, state.clock.now())?;
+    record_session(&state, &user.id, &claims, &request_context).await?;
 
     info!("Registered user: {}", user.id);
         Ok(Json(ApiResponse {
@@ -111,6 +174,7 @@ pub async fn register_handler(
 #[axum::debug_handler]
 pub async fn login_handler(
     State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
     Json(req): Json<AuthRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     req.validate().map_err(AppError::Validation)?;
@@ -118,40 +182,37 @@ pub async fn login_handler(
         return Err(AppError::BadRequest("Invalid action for login".into()));
     }
 
+    let identifier = req.email.as_ref().unwrap_or(&req.username);
+    let mut subjects = vec![("user", format!("user:{}", identifier.to_lowercase()))];
+    if let Some(ip) = &request_context.ip {
+        subjects.push(("ip", format!("ip:{}", ip)));
+    }
+    check_lockout(&state, &subjects).await?;
+
     // Find user by username or email
-    let user = state
-        .rl_db
-        .get_user(req.email.as_ref().unwrap_or(&req.username))
-        .await?
-        .ok_or_else(|| {
-            warn!("Login failed: User not found");
-            AppError::Unauthorized("Invalid credentials".into())
-        })?;
+    let Some(user) = state.rl_db.get_user(identifier).await? else {
+        warn!("Login failed: User not found");
+        record_failed_login(&state, &subjects).await?;
+        return Err(AppError::Unauthorized("Invalid credentials".into()));
+    };
 
     // Verify password
     if !verify(&req.password, &user.password_hash)
         .map_err(|e| AppError::Internal(e.to_string()))?
     {
         warn!("Login failed: Invalid password for {}", user.id);
+        record_failed_login(&state, &subjects).await?;
         return Err(AppError::Unauthorized("Invalid credentials".into()));
     }
+    // Only the account's counter resets; a shared IP keeps its history
+    state.rl_db.clear_login_failures(&subjects[0].1).await?;
 
     // Generate JWT
-    let expires_at = state.clock.now() + Duration::hours(24);
     let is_admin = state.rl_db.is_global_admin(&user.email).await?;
-    let claims = AuthToken {
-        user_id: Some(user.id.clone()),
-        expires_at: expires_at.to_rfc3339(),
-        username: user.username.clone(),
-        email: user.email.clone(),
-    is_admin: false,
-    };
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(state.config.security.jwt_secret.as_ref()),
-    )
-    .map_err(|e| AppError::Internal(e.to_string()))?;
+    let (token, claims) = state
+        .tokens
+        .issue(&user.id, &user.username, &user.email, is_admin, state.clock.now())?;
+    record_session(&state, &user.id, &claims, &request_context).await?;
 
     info!("User logged in: {}", user.id);
         Ok(Json(ApiResponse {
@@ -179,9 +240,15 @@ pub async fn logout_handler(
         .and_then(|s| s.strip_prefix("Bearer "))
         .ok_or_else(|| AppError::Unauthorized("Missing Bearer token".into()))?;
 
+    let claims = state.tokens.verify(token)?;
+
     // Blacklist token
     let ttl_secs = state.config.security.token_expiry_secs;
     state.rl_db.blacklist_token(token, ttl_secs).await?;
+    // And stop listing it among the account's sessions
+    if let Some(user_id) = &claims.user_id {
+        state.rl_db.remove_session(user_id, &claims.jti).await?;
+    }
 
     info!("User logged out");
     Ok(Json(ApiResponse {
@@ -256,4 +323,171 @@ pub async fn delete_account_handler(
         }),
         error: None,
     }))
-}
\ No newline at end of file
+}
+
+#[axum::debug_handler]
+pub(crate) async fn list_sessions_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = request_context
+        .user_id
+        .as_ref()
+        .ok_or_else(|| AppError::Unauthorized("Authentication required".into()))?;
+
+    let now = state.clock.now().timestamp().max(0) as u64;
+    let mut sessions: Vec<Session> = state
+        .rl_db
+        .list_sessions(user_id)
+        .await?
+        .into_iter()
+        .filter(|session| session.expires_at > now)
+        .collect();
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.issued_at));
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(SessionsResponse {
+            current: request_context.jti.clone(),
+            sessions,
+        }),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn revoke_all_sessions_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = request_context
+        .user_id
+        .as_ref()
+        .ok_or_else(|| AppError::Unauthorized("Authentication required".into()))?;
+
+    let revoked = revoke_sessions(&state, user_id, None).await?;
+
+    info!("Revoked {} sessions for user {}", revoked, user_id);
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(json!({ "revoked": revoked })),
+        error: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::{
+        app::Builder,
+        config::settings::Settings,
+        test_util::{self, MockClock, MockStorage},
+    };
+
+    async fn lockout_state(clock: &MockClock) -> AppState {
+        let mut config = Settings::default();
+        config.security.lockout_threshold = Some(3);
+        config.security.lockout_duration_secs = Some(600);
+        let storage = MockStorage::new().with_clock(Arc::new(clock.clone()));
+        let state = Builder::new(config)
+            .storage(Arc::new(storage))
+            .clock(Arc::new(clock.clone()))
+            .background_tasks(false)
+            .build()
+            .await
+            .unwrap()
+            .state;
+        state.rl_db.set_user(&test_util::user_with_password("alice", "correct horse")).await.unwrap();
+        state
+    }
+
+    async fn login(state: &AppState, ip: &str, email: &str, password: &str) -> Result<(), AppError> {
+        let context = RequestContext { ip: Some(ip.into()), ..Default::default() };
+        let req = AuthRequest { username: "ignored".into(), password: password.into(), email: Some(email.into()), action: AuthAction::Login };
+        login_handler(State(state.clone()), Extension(context), Json(req)).await.map(|_| ())
+    }
+
+    #[tokio::test]
+    async fn accounts_lock_after_repeated_failures_until_the_duration_passes() {
+        let clock = MockClock::new(chrono::Utc::now());
+        let state = lockout_state(&clock).await;
+        for _ in 0..3 {
+            assert!(matches!(login(&state, "203.0.113.1", "alice@example.com", "wrong password").await, Err(AppError::Unauthorized(_))));
+        }
+
+        // Even the right password is refused while locked, from any address
+        match login(&state, "198.51.100.2", "alice@example.com", "correct horse").await {
+            Err(AppError::Locked { retry_after_secs, .. }) => assert_eq!(retry_after_secs, 600),
+            other => panic!("expected Locked, got {:?}", other),
+        }
+        clock.advance(chrono::Duration::seconds(599));
+        assert!(matches!(login(&state, "198.51.100.2", "alice@example.com", "correct horse").await, Err(AppError::Locked { retry_after_secs: 1, .. })));
+
+        clock.advance(chrono::Duration::seconds(1));
+        login(&state, "198.51.100.2", "alice@example.com", "correct horse").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn successful_logins_clear_the_account_counter_but_not_the_ip_counter() {
+        let clock = MockClock::new(chrono::Utc::now());
+        let state = lockout_state(&clock).await;
+        let ip = "203.0.113.1";
+        for _ in 0..2 {
+            assert!(login(&state, ip, "alice@example.com", "wrong password").await.is_err());
+        }
+        login(&state, ip, "alice@example.com", "correct horse").await.unwrap();
+
+        // The address reaches three failures while the account starts over from its reset
+        assert!(matches!(login(&state, ip, "nobody@example.com", "wrong password").await, Err(AppError::Unauthorized(_))));
+        assert!(matches!(login(&state, ip, "alice@example.com", "correct horse").await, Err(AppError::Locked { .. })));
+        assert!(login(&state, "198.51.100.2", "alice@example.com", "wrong password").await.is_err());
+        login(&state, "198.51.100.2", "alice@example.com", "correct horse").await.unwrap();
+    }
+
+    async fn body(response: impl IntoResponse) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_response().into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn logout_drops_its_session_and_revoke_all_ends_the_rest() {
+        let state = test_util::test_state().await;
+        state.rl_db.set_user(&test_util::user_with_password("alice", "correct horse")).await.unwrap();
+        let sign_in = || async {
+            let req = AuthRequest { username: "ignored".into(), password: "correct horse".into(), email: Some("alice@example.com".into()), action: AuthAction::Login };
+            let response = login_handler(State(state.clone()), Extension(RequestContext::default()), Json(req)).await.unwrap();
+            let token = body(response).await["data"]["token"].as_str().unwrap().to_string();
+            let jti = state.tokens.verify(&token).unwrap().jti;
+            (token, jti)
+        };
+        let context = |jti: &str| RequestContext { user_id: Some("user-alice".into()), jti: Some(jti.into()), ..Default::default() };
+        let listed = |jti: &str| {
+            let context = context(jti);
+            async { body(list_sessions_handler(State(state.clone()), Extension(context)).await.unwrap()).await["data"].clone() }
+        };
+
+        let (phone_token, _) = sign_in().await;
+        let (_, laptop) = sign_in().await;
+        let sessions = listed(&laptop).await;
+        assert_eq!(sessions["current"], laptop.as_str());
+        assert_eq!(sessions["sessions"].as_array().unwrap().len(), 2);
+
+        let logout = axum::http::Request::builder()
+            .header("Authorization", format!("Bearer {}", phone_token))
+            .body(axum::body::Body::empty())
+            .unwrap();
+        logout_handler(State(state.clone()), logout).await.unwrap();
+        let sessions = listed(&laptop).await;
+        let jtis: Vec<&str> = sessions["sessions"].as_array().unwrap().iter().map(|session| session["jti"].as_str().unwrap()).collect();
+        assert_eq!(jtis, [laptop.as_str()]);
+
+        let (_, tablet) = sign_in().await;
+        let revoked = body(revoke_all_sessions_handler(State(state.clone()), Extension(context(&tablet))).await.unwrap()).await;
+        assert_eq!(revoked["data"]["revoked"], 2);
+        for jti in [&laptop, &tablet] {
+            assert!(state.rl_db.is_token_blacklisted(jti).await.unwrap());
+        }
+        assert!(listed(&tablet).await["sessions"].as_array().unwrap().is_empty());
+    }
+}
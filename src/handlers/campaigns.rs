@@ -0,0 +1,171 @@
+use axum::{
+    extract::{Json, Path, State},
+    Extension,
+    response::IntoResponse,
+};
+use chrono::DateTime;
+use cuid::cuid2;
+use std::{collections::BTreeMap, sync::Arc};
+use tracing::info;
+use validator::Validate;
+use crate::{
+    errors::AppError,
+    handlers::shorten::AppState,
+    middleware::RequestContext,
+    types::{
+        ApiResponse, Campaign, CampaignAnalyticsResponse, CampaignCodeStats, CampaignCodesRequest,
+        CreateCampaignRequest,
+    },
+};
+
+const MAX_CODES_PER_CAMPAIGN: usize = 500;
+const ANALYTICS_WINDOW_SECS: i64 = 30 * 24 * 3600;
+
+fn require_user(request_context: &RequestContext) -> Result<&str, AppError> {
+    request_context
+        .user_id
+        .as_deref()
+        .ok_or_else(|| AppError::Unauthorized("Authentication required for /v1/campaigns".into()))
+}
+
+/// Loads a campaign the caller owns; other users' campaigns look missing.
+async fn owned_campaign(state: &AppState, user_id: &str, id: &str) -> Result<Campaign, AppError> {
+    state
+        .campaigns
+        .get(id)
+        .await?
+        .filter(|campaign| campaign.user_id == user_id)
+        .map(|campaign| (*campaign).clone())
+        .ok_or_else(|| AppError::NotFound(format!("Campaign {} not found", id)))
+}
+
+/// Tags each link with the campaign. Links must belong to the caller and not to another campaign.
+async fn attach_codes(state: &AppState, campaign: &mut Campaign, codes: Vec<String>) -> Result<(), AppError> {
+    for code in codes {
+        if campaign.codes.contains(&code) {
+            continue;
+        }
+        if campaign.codes.len() >= MAX_CODES_PER_CAMPAIGN {
+            return Err(AppError::Conflict(format!("At most {} links per campaign", MAX_CODES_PER_CAMPAIGN)));
+        }
+        let mut url_data = state
+            .cache
+            .get_url_data(&code)
+            .await
+            .map(Arc::unwrap_or_clone)
+            .map_err(|_| AppError::NotFound(format!("URL {} not found", code)))?;
+        if url_data.user_id.as_deref() != Some(campaign.user_id.as_str()) {
+            return Err(AppError::NotFound(format!("URL {} not found", code)));
+        }
+        match url_data.campaign_id.as_deref() {
+            Some(other) if other != campaign.id => {
+                return Err(AppError::Conflict(format!("URL {} already belongs to campaign {}", code, other)));
+            }
+            Some(_) => {}
+            None => {
+                url_data.campaign_id = Some(campaign.id.clone());
+                state.cache.insert(code.clone(), &url_data).await?;
+            }
+        }
+        campaign.codes.push(code);
+    }
+    Ok(())
+}
+
+#[axum::debug_handler]
+pub(crate) async fn create_campaign_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<CreateCampaignRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = require_user(&request_context)?;
+    req.validate().map_err(AppError::Validation)?;
+
+    let mut campaign = Campaign {
+        id: cuid2(),
+        user_id: user_id.to_string(),
+        name: req.name,
+        codes: Vec::new(),
+        utm: req.utm,
+        created_at: state.clock.now().to_rfc3339(),
+    };
+    attach_codes(&state, &mut campaign, req.codes).await?;
+    let campaign = state.campaigns.save(campaign).await?;
+    info!("Campaign {} created for {} with {} links", campaign.id, user_id, campaign.codes.len());
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(campaign),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn list_campaigns_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = require_user(&request_context)?;
+    let campaigns = state.campaigns.list(user_id).await?;
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(campaigns),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn add_campaign_codes_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Path(id): Path<String>,
+    Json(req): Json<CampaignCodesRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = require_user(&request_context)?;
+    req.validate().map_err(AppError::Validation)?;
+
+    let mut campaign = owned_campaign(&state, user_id, &id).await?;
+    attach_codes(&state, &mut campaign, req.codes).await?;
+    let campaign = state.campaigns.save(campaign).await?;
+    info!("Campaign {} now has {} links", campaign.id, campaign.codes.len());
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(campaign),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn campaign_analytics_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = require_user(&request_context)?;
+    let campaign = owned_campaign(&state, user_id, &id).await?;
+
+    let now = state.clock.now().timestamp();
+    let mut codes = Vec::with_capacity(campaign.codes.len());
+    let mut daily: BTreeMap<String, u64> = BTreeMap::new();
+    for code in &campaign.codes {
+        let clicks = state.analytics.get_analytics(code, now - ANALYTICS_WINDOW_SECS, now).await?;
+        for (timestamp, _) in &clicks {
+            if let Some(day) = DateTime::from_timestamp(*timestamp as i64, 0) {
+                *daily.entry(day.date_naive().to_string()).or_default() += 1;
+            }
+        }
+        codes.push(CampaignCodeStats { code: code.clone(), clicks: clicks.len() as u64 });
+    }
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(CampaignAnalyticsResponse {
+            campaign_id: campaign.id,
+            total_clicks: codes.iter().map(|stats| stats.clicks).sum(),
+            codes,
+            daily,
+        }),
+        error: None,
+    }))
+}
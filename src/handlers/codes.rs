@@ -0,0 +1,55 @@
+use axum::{
+    extract::{Json, State},
+    Extension,
+    response::IntoResponse,
+};
+use tracing::info;
+use validator::Validate;
+use crate::{
+    errors::AppError,
+    handlers::shorten::AppState,
+    middleware::RequestContext,
+    types::{ApiResponse, ReserveCodesRequest, ReserveCodesResponse},
+};
+
+#[axum::debug_handler]
+pub(crate) async fn reserve_codes_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<ReserveCodesRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    req.validate().map_err(AppError::Validation)?;
+    let user_id = request_context.user_id.ok_or_else(|| {
+        AppError::Unauthorized("Authentication required for /v1/codes/reserve".into())
+    })?;
+
+    let max_batch = state.config.codegen.max_reserve_batch.unwrap_or(1_000);
+    if req.count > max_batch {
+        return Err(AppError::BadRequest(format!("Cannot reserve more than {} codes at once", max_batch)));
+    }
+
+    let mut codes = Vec::with_capacity(req.count);
+    while codes.len() < req.count {
+        let code = state.codegen.next().map_err(AppError::CodeGen)?.to_string();
+        // Skip anything that already resolves to a link
+        if state.cache.contains_key(&code) && state.cache.get_url_data(&code).await.is_ok() {
+            continue;
+        }
+        codes.push(code);
+    }
+
+    let ttl_secs = state.config.codegen.reservation_ttl_secs.unwrap_or(7 * 24 * 3600);
+    state.rl_db.reserve_codes(&user_id, &codes, ttl_secs).await?;
+
+    let expires_at = state.clock.now() + chrono::Duration::seconds(ttl_secs as i64);
+    info!("Reserved {} codes for user {}", codes.len(), user_id);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(ReserveCodesResponse {
+            codes,
+            expires_at: expires_at.to_rfc3339(),
+        }),
+        error: None,
+    }))
+}
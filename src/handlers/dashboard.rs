@@ -0,0 +1,168 @@
+use axum::{
+    extract::{Json, State},
+    Extension,
+    response::IntoResponse,
+};
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt, TryStreamExt};
+use std::{collections::BTreeMap, sync::Arc};
+use crate::{
+    errors::AppError,
+    handlers::shorten::AppState,
+    middleware::RequestContext,
+    types::{ApiResponse, DashboardActivity, DashboardLink, DashboardLinkCounts, DashboardResponse, UrlData},
+};
+
+const DAY_SECS: i64 = 24 * 3600;
+const TOP_LINKS: usize = 5;
+const RECENT_ACTIVITY: usize = 10;
+// Links whose record and clicks are loaded at once
+const LOAD_CONCURRENCY: usize = 16;
+
+// A link's code, its record and its last 30 days of clicks
+type LoadedLink = (String, Arc<UrlData>, Vec<(u64, u64)>);
+
+fn require_user(request_context: &RequestContext) -> Result<&str, AppError> {
+    request_context
+        .user_id
+        .as_deref()
+        .ok_or_else(|| AppError::Unauthorized("Authentication required for /v1/dashboard".into()))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn dashboard_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = require_user(&request_context)?;
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(build_dashboard(&state, user_id).await?),
+        error: None,
+    }))
+}
+
+/// Everything the dashboard shows, from the owner's link index, each link's clicks over the
+/// last 30 days and their notifications. Links that have since expired out of storage are skipped.
+pub(crate) async fn build_dashboard(state: &AppState, user_id: &str) -> Result<DashboardResponse, AppError> {
+    let now = state.clock.now();
+    let month_ago = now.timestamp() - 30 * DAY_SECS;
+    let week_ago = now.timestamp() - 7 * DAY_SECS;
+
+    let codes = state.rl_db.list_user_codes(user_id).await?;
+    let links: Vec<LoadedLink> = stream::iter(codes)
+        .map(|code| async move {
+            let Ok(url_data) = state.cache.get_url_data(&code).await else {
+                return Ok(None);
+            };
+            let clicks = state.analytics.get_analytics(&code, month_ago, now.timestamp()).await?;
+            Ok::<_, AppError>(Some((code, url_data, clicks)))
+        })
+        .buffer_unordered(LOAD_CONCURRENCY)
+        .try_filter_map(|link| async move { Ok(link) })
+        .try_collect()
+        .await?;
+
+    let mut counts = DashboardLinkCounts { total: links.len() as u64, ..Default::default() };
+    let mut daily: BTreeMap<String, u64> = BTreeMap::new();
+    let (mut clicks_7d, mut clicks_30d) = (0, 0);
+    for (_, url_data, clicks) in &links {
+        counts.active += u64::from(is_active(url_data, now));
+        counts.pinned += u64::from(url_data.pinned);
+        clicks_30d += clicks.len() as u64;
+        for (timestamp, _) in clicks {
+            clicks_7d += u64::from(*timestamp as i64 >= week_ago);
+            if let Some(day) = DateTime::from_timestamp(*timestamp as i64, 0) {
+                *daily.entry(day.date_naive().to_string()).or_default() += 1;
+            }
+        }
+    }
+
+    let mut top_links: Vec<DashboardLink> = links
+        .iter()
+        .map(|(code, url_data, clicks)| DashboardLink {
+            code: code.clone(),
+            long_url: url_data.long_url.clone(),
+            created_at: url_data.created_at.clone(),
+            clicks_30d: clicks.len() as u64,
+        })
+        .collect();
+    top_links.sort_by(|a, b| b.clicks_30d.cmp(&a.clicks_30d).then_with(|| b.created_at.cmp(&a.created_at)));
+    top_links.truncate(TOP_LINKS);
+
+    let mut recent_activity: Vec<DashboardActivity> = links
+        .iter()
+        .map(|(code, url_data, _)| DashboardActivity {
+            kind: "link_created".into(),
+            code: Some(code.clone()),
+            message: format!("Shortened {}", url_data.long_url),
+            at: url_data.created_at.clone(),
+        })
+        .chain(
+            state
+                .rl_db
+                .list_notifications(user_id, RECENT_ACTIVITY as u64)
+                .await?
+                .into_iter()
+                .map(|notification| DashboardActivity {
+                    kind: "notification".into(),
+                    code: notification.code,
+                    message: notification.message,
+                    at: notification.created_at,
+                }),
+        )
+        .collect();
+    // Timestamps are all RFC 3339 in UTC, so they sort as strings
+    recent_activity.sort_by(|a, b| b.at.cmp(&a.at));
+    recent_activity.truncate(RECENT_ACTIVITY);
+
+    Ok(DashboardResponse {
+        links: counts,
+        clicks_7d,
+        clicks_30d,
+        daily,
+        top_links,
+        recent_activity,
+    })
+}
+
+fn is_active(url_data: &UrlData, now: DateTime<Utc>) -> bool {
+    let expired = url_data
+        .expires_at
+        .as_deref()
+        .and_then(|expires_at| DateTime::parse_from_rfc3339(expires_at).ok())
+        .is_some_and(|expires_at| expires_at <= now);
+    url_data.is_active && url_data.disabled_at.is_none() && !url_data.quarantined && !url_data.dead && !expired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::settings::Settings, handlers::shorten::create_short_link,
+        services::storage::storage::Storage, test_util::{self, MockStorage}, types::ShortenRequest,
+    };
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn dashboard_counts_links_and_recent_clicks() {
+        let storage = Arc::new(MockStorage::new());
+        let state = test_util::test_app(Settings::default(), storage.clone()).await.state;
+        let context = RequestContext { user_id: Some("user-alice".into()), ..Default::default() };
+        let mut codes = Vec::new();
+        for url in ["https://example.com/one", "https://example.com/two"] {
+            let req: ShortenRequest = serde_json::from_value(json!({ "url": url })).unwrap();
+            codes.push(create_short_link(&state, &context, req).await.unwrap().code);
+        }
+        let now = state.clock.now().timestamp() as u64;
+        for (age_days, member) in [(1, 1), (2, 2), (10, 3)] {
+            storage.zadd(&format!("stats:{}", codes[1]), now - age_days * DAY_SECS as u64, member).await.unwrap();
+        }
+
+        let dashboard = build_dashboard(&state, "user-alice").await.unwrap();
+        assert_eq!((dashboard.links.total, dashboard.links.active), (2, 2));
+        assert_eq!((dashboard.clicks_7d, dashboard.clicks_30d), (2, 3));
+        assert_eq!(dashboard.top_links[0].code, codes[1]);
+        assert_eq!(dashboard.recent_activity.len(), 2);
+    }
+}
@@ -1,4 +1,17 @@
 pub mod analytics;
 pub mod redirect;
 pub mod shorten;
-pub mod auth;
\ No newline at end of file
+pub mod auth;
+pub mod codes;
+pub mod admin;
+pub mod reports;
+pub mod notifications;
+pub mod api_keys;
+pub mod account;
+pub mod campaigns;
+pub mod usage;
+pub mod dashboard;
+pub mod pagination;
+pub mod robots;
+#[cfg(feature = "admin-ui")]
+pub mod admin_ui;
@@ -0,0 +1,28 @@
+use axum::{
+    extract::{Json, State},
+    Extension,
+    response::IntoResponse,
+};
+use crate::{
+    errors::AppError,
+    handlers::shorten::AppState,
+    middleware::RequestContext,
+    types::ApiResponse,
+};
+
+#[axum::debug_handler]
+pub(crate) async fn list_notifications_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = request_context.user_id.ok_or_else(|| {
+        AppError::Unauthorized("Authentication required for /v1/notifications".into())
+    })?;
+
+    let notifications = state.rl_db.list_notifications(&user_id, 100).await?;
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(notifications),
+        error: None,
+    }))
+}
@@ -0,0 +1,59 @@
+use axum::http::{header::LINK, HeaderMap, HeaderName, HeaderValue, Uri};
+use url::form_urlencoded;
+use crate::types::Paginate;
+
+const TOTAL_COUNT_HEADER: HeaderName = HeaderName::from_static("x-total-count");
+
+/// `Link` (RFC 8288, née 5988) and `X-Total-Count` headers for one page of a listing, so clients
+/// can follow `rel="next"` instead of reading the paging fields out of the body. Links repeat
+/// `uri`'s other query parameters.
+pub fn pagination_headers<T>(base_url: &str, uri: &Uri, page: &Paginate<T>) -> HeaderMap {
+    let params: Vec<(String, String)> = form_urlencoded::parse(uri.query().unwrap_or("").as_bytes())
+        .filter(|(name, _)| name != "page" && name != "per_page")
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    let link = |number: u64, rel: &str| {
+        let query = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(&params)
+            .append_pair("page", &number.to_string())
+            .append_pair("per_page", &page.per_page.to_string())
+            .finish();
+        format!("<{}{}?{}>; rel=\"{}\"", base_url.trim_end_matches('/'), uri.path(), query, rel)
+    };
+
+    let mut links = vec![link(1, "first")];
+    if page.page > 1 {
+        links.push(link((page.page - 1).min(page.total_pages), "prev"));
+    }
+    if page.page < page.total_pages {
+        links.push(link(page.page + 1, "next"));
+    }
+    links.push(link(page.total_pages, "last"));
+
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&links.join(", ")) {
+        headers.insert(LINK, value);
+    }
+    headers.insert(TOTAL_COUNT_HEADER, HeaderValue::from(page.total_items));
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_point_at_neighbouring_pages_and_keep_other_parameters() {
+        let page = Paginate::new(vec![(); 20], 2, 20, 45);
+        let uri: Uri = "/v1/urls?page=2&per_page=20&sort=new".parse().unwrap();
+        let headers = pagination_headers("https://hl.test/", &uri, &page);
+        assert_eq!(
+            headers[LINK],
+            "<https://hl.test/v1/urls?sort=new&page=1&per_page=20>; rel=\"first\", \
+             <https://hl.test/v1/urls?sort=new&page=1&per_page=20>; rel=\"prev\", \
+             <https://hl.test/v1/urls?sort=new&page=3&per_page=20>; rel=\"next\", \
+             <https://hl.test/v1/urls?sort=new&page=3&per_page=20>; rel=\"last\""
+        );
+        assert_eq!(headers[TOTAL_COUNT_HEADER], "45");
+    }
+}
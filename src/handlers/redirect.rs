@@ -1,35 +1,617 @@
-use axum::{extract::{Path, State}, response::Redirect};
-use crate::{clock::Clock, errors::AppError, handlers::shorten::AppState};
+use axum::{extract::{Path, Query, RawQuery, State}, http::{header, HeaderMap, HeaderValue, StatusCode}, response::{Html, IntoResponse, Response}, Extension, Json};
+use crate::{config::crawlers::CrawlerPolicy, errors::AppError, handlers::shorten::AppState, middleware::{device_info::enrich_context, rate_limit::check_link_rate_limit, RequestContext}};
 use tracing::info;
-use crate::types::UrlData;
+use rand::Rng;
+use xxhash_rust::xxh3::xxh3_64;
+use crate::{services::{campaigns::apply_utm_defaults, link_signing, metrics, ua_parser}, types::{ApiResponse, OpenGraph, ResolveQuery, ResolvedLink, SignedLinkQuery, SplitMode, SplitVariant, UrlData}};
+
+/// Swallows `error` while the cache is degraded, for lookups a redirect can do without.
+async fn unless_degraded(state: &AppState, error: AppError) -> Result<(), AppError> {
+    if state.cache.is_degraded().await {
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
 
 #[axum::debug_handler]
 pub async fn redirect_handler(
     Path(code): Path<String>,
     State(state): State<AppState>,
-) -> Result<Redirect, AppError> {
-    let url_data_json = state.cache.get(&code).await?;
-    let url_data: UrlData = serde_json::from_str(&url_data_json)
-        .map_err(|e| AppError::Internal(e.to_string()))?;
+    Extension(mut request_context): Extension<RequestContext>,
+    Query(signature): Query<SignedLinkQuery>,
+    Query(resolve): Query<ResolveQuery>,
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    // Viral links are answered from the hot set without touching any cache lock
+    let url_data = match state.cache.hot_set().get(&code) {
+        Some(url_data) => url_data,
+        None => match state.cache.get_url_data(&code).await {
+            Ok(url_data) => url_data,
+            // A burned one-time link has no record left, but visitors should learn it existed
+            Err(_) if state.rl_db.is_code_burned(&code).await.unwrap_or(false) => {
+                return Err(AppError::Gone("Link has already been used".to_string()));
+            }
+            Err(e) => return Err(e),
+        },
+    };
+    state.cache.hot_set().record_click(&code);
+
+    if url_data.disabled_at.is_some() {
+        return Err(AppError::Gone("Link has been disabled".to_string()));
+    }
+    if url_data.quarantined {
+        return Err(AppError::Blocked("Link has been quarantined".to_string()));
+    }
+    if !url_data.is_active {
+        return Err(AppError::Gone("Link has been deactivated by its owner".to_string()));
+    }
 
     // Check expiration
-    if let Some(expires_at) = url_data.expires_at {
-        let expiry = chrono::DateTime::parse_from_rfc3339(&expires_at)
-            .map_err(|e| AppError::Internal(e.to_string()))?;
-        if expiry < state.clock.now() {
-            return Err(AppError::NotFound("URL not found".to_string()));
-        }
-    }
-
-    // Dummy/default values for required analytics fields
-    state.analytics.record_click(
-        &code,
-        "0.0.0.0", // ip
-        None,        // referrer
-        None,        // country
-        None,        // device_type
-        None         // browser
-    ).await;
-    info!("Redirecting code {} to {}", code, url_data.long_url);
-    Ok(Redirect::to(&url_data.long_url))
+    let now = state.clock.now();
+    if let Some(expires_at) = &url_data.expires_at
+        && parse_time(expires_at)? < now
+    {
+        return Err(AppError::Expired);
+    }
+    if let Some(active_from) = &url_data.active_from
+        && now < parse_time(active_from)?
+    {
+        return Err(AppError::NotFound("Link is not active yet".to_string()));
+    }
+    if let Some(active_until) = &url_data.active_until
+        && parse_time(active_until)? <= now
+    {
+        return Err(AppError::Gone("Link is no longer active".to_string()));
+    }
+    // A scheduled landing page stands in for long_url once it has started
+    let primary = scheduled_destination(&url_data, now).unwrap_or(&url_data.long_url);
+    let forwarded = query.as_deref().filter(|_| url_data.forward_query);
+
+    if url_data.signed {
+        check_signature(&state, &code, &url_data, &signature).await?;
+    }
+
+    if let Some(per_minute) = url_data.max_redirects_per_minute {
+        check_link_rate_limit(&state, &code, per_minute).await?;
+    }
+
+    state.hooks.on_redirect(&code, &url_data, &request_context).await?;
+
+    // API clients and link checkers get the target as JSON, and only count as a click if they ask
+    let wants_json = accepts_json(&headers);
+    if wants_json && !resolve.count {
+        if url_data.burn_after_read || url_data.max_clicks.is_some() {
+            return Err(AppError::Forbidden("One-time and limited links only resolve with count=true".to_string()));
+        }
+        let fallback = url_data.fallback_url.as_deref().filter(|_| url_data.dead);
+        let variant = fallback.is_none().then(|| pick_variant(&url_data, &code, request_context.ip.as_deref())).flatten();
+        let target = fallback.or(variant.map(|v| v.url.as_str())).unwrap_or(primary);
+        let destination = utm_destination(&state, &url_data, &forward_query(target, forwarded)).await?;
+        let variant = variant.map(|v| v.name.clone());
+        return Ok(resolved_link(code, &url_data, destination, variant, false));
+    }
+
+    // Private links and privacy-mode instances record the bare click only
+    let private = url_data.privacy_mode || state.config.analytics.privacy_mode.unwrap_or(false);
+    // Deferred enrichment leaves the geo lookup and UA parsing to the analytics flush worker
+    let deferred = !private && state.config.analytics.deferred_enrichment.unwrap_or(false);
+    if !private && !deferred {
+        enrich_context(&mut request_context, &headers).await;
+    }
+    let user_agent = headers.get(header::USER_AGENT).and_then(|v| v.to_str().ok());
+    let fallback = url_data.fallback_url.as_deref().filter(|_| url_data.dead);
+    // A dead link's fallback serves every visitor, so no variant gets the click
+    let variant = fallback.is_none().then(|| pick_variant(&url_data, &code, request_context.ip.as_deref())).flatten();
+    let target = if let Some(fallback) = fallback {
+        fallback
+    } else if let Some(variant) = variant {
+        variant.url.as_str()
+    } else if url_data.rotation.is_empty() {
+        primary
+    } else {
+        // The cursor lives in storage so every instance shares one rotation
+        match state.rl_db.next_rotation_cursor(&code).await {
+            Ok(cursor) => {
+                let index = ((cursor - 1) % (url_data.rotation.len() as u64 + 1)) as usize;
+                index.checked_sub(1).map_or(primary, |i| url_data.rotation[i].as_str())
+            }
+            // Without Dragonfly every visitor gets the primary URL until it is back
+            Err(e) => {
+                unless_degraded(&state, e).await?;
+                primary
+            }
+        }
+    };
+    let destination = utm_destination(&state, &url_data, &forward_query(target, forwarded)).await?;
+
+    let (is_bot, bot_name) = if private || deferred {
+        // Enrichment was skipped, so classify the raw UA just for this
+        user_agent
+            .map(ua_parser::parse_user_agent)
+            .map_or((false, None), |info| (info.is_bot, info.bot_name))
+    } else {
+        (request_context.is_bot, request_context.bot_name.clone())
+    };
+    // Unfurlers and scanners would use up a one-time or limited link before its recipients do
+    if (url_data.burn_after_read || url_data.max_clicks.is_some()) && is_bot {
+        return Err(AppError::Forbidden("One-time and limited links are not served to bots".to_string()));
+    }
+
+    // Chat apps fetch a link every time it is pasted; answer with a preview card, not a click
+    let serve_previews = url_data.open_graph.is_some() || state.config.analytics.unfurl_previews.unwrap_or(true);
+    if let Some(bot_name) = bot_name.as_deref().filter(|name| !wants_json && serve_previews && ua_parser::is_link_preview_bot(name)) {
+        info!("Serving preview card for code {} to {}", code, bot_name);
+        metrics::record_unfurl_hit(bot_name);
+        let open_graph = url_data.open_graph.clone().unwrap_or_default();
+        return Ok(open_graph_page(&open_graph, &url_data.long_url, &destination).into_response());
+    }
+
+    // Crawlers that ignore robots.txt would otherwise drown out people in the click counts
+    let disallowed_crawler = state.config.crawlers.disallows(is_bot, bot_name.as_deref());
+    if disallowed_crawler && state.config.crawlers.policy == CrawlerPolicy::Deny {
+        metrics::record_crawler_hit(bot_name.as_deref().unwrap_or("unknown"), "deny");
+        return Err(AppError::Forbidden("Crawlers may not follow this link".to_string()));
+    }
+
+    if url_data.burn_after_read {
+        let burned_at = state.clock.now().timestamp() as u64;
+        if !state.cache.burn(&code, burned_at).await? {
+            return Err(AppError::Gone("Link has already been used".to_string()));
+        }
+        info!("Burned one-time link {}", code);
+    }
+    if let Some(max_clicks) = url_data.max_clicks {
+        // Counted before redirecting, so concurrent visitors can't slip past the limit together
+        if state.rl_db.incr_click_count(&code).await? > max_clicks {
+            return Err(AppError::Gone("Link has reached its click limit".to_string()));
+        }
+    }
+
+    let recorded = if disallowed_crawler {
+        metrics::record_crawler_hit(bot_name.as_deref().unwrap_or("unknown"), "skip_analytics");
+        false
+    } else {
+        state.analytics.record_click(
+            &code,
+            request_context.ip.as_deref().filter(|_| !private).unwrap_or("0.0.0.0"),
+            request_context.referrer.as_deref(),
+            request_context.country.as_deref(),
+            request_context.device_type.as_deref(),
+            request_context.browser.as_deref(),
+            is_bot.then(|| bot_name.as_deref().unwrap_or("unknown")),
+            request_context.request_id.as_deref(),
+            user_agent,
+            variant.map(|v| v.name.as_str()),
+            deferred,
+        ).await
+    };
+    if recorded {
+        state.hooks.on_click_recorded(&code, &request_context).await;
+    }
+    if fallback.is_some() {
+        info!("Destination for code {} is dead, using its fallback", code);
+        metrics::record_fallback_redirect();
+    }
+    if wants_json {
+        let variant = variant.map(|v| v.name.clone());
+        return Ok(resolved_link(code, &url_data, destination, variant, true));
+    }
+    if fallback.is_none() && url_data.dead && state.config.link_health.warn_on_dead {
+        info!("Serving dead-link warning for code {}", code);
+        return Ok(dead_link_page(&url_data).into_response());
+    }
+    let status = url_data.redirect_status.unwrap_or(state.config.redirects.default_status);
+    info!("Redirecting code {} to {} with {}", code, destination, status);
+    // The same URL answers JSON clients differently, so shared caches must key on Accept
+    Ok(redirect_response(status, &destination))
+    }
+
+/// A redirect to `destination` with `status`, one of the validated 301, 302, 307 or 308.
+fn redirect_response(status: u16, destination: &str) -> Response {
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::FOUND);
+    let Ok(location) = HeaderValue::try_from(destination) else {
+        return AppError::Internal(format!("Invalid redirect target {}", destination)).into_response();
+    };
+    (status, [(header::LOCATION, location), (header::VARY, HeaderValue::from_static("Accept"))]).into_response()
+}
+
+fn parse_time(value: &str) -> Result<chrono::DateTime<chrono::FixedOffset>, AppError> {
+    chrono::DateTime::parse_from_rfc3339(value).map_err(|e| AppError::Internal(e.to_string()))
+}
+
+/// The latest entry of the link's schedule that has started by `now`, if any.
+fn scheduled_destination(url_data: &UrlData, now: chrono::DateTime<chrono::Utc>) -> Option<&str> {
+    url_data
+        .schedule
+        .iter()
+        .rev()
+        .find(|entry| parse_time(&entry.starts_at).is_ok_and(|starts_at| starts_at <= now))
+        .map(|entry| entry.url.as_str())
+}
+
+/// The A/B variant this visit lands on, drawn by weight; sticky links draw from a hash of the
+/// visitor's IP so repeat visits agree.
+fn pick_variant<'a>(url_data: &'a UrlData, code: &str, ip: Option<&str>) -> Option<&'a SplitVariant> {
+    let total: u64 = url_data.variants.iter().map(|v| u64::from(v.weight)).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut roll = match (url_data.split_mode, ip) {
+        // Salted with the code so one visitor isn't in the same bucket on every link
+        (SplitMode::Sticky, Some(ip)) => xxh3_64(format!("{}:{}", code, ip).as_bytes()) % total,
+        _ => rand::rng().random_range(0..total),
+    };
+    for variant in &url_data.variants {
+        let weight = u64::from(variant.weight);
+        if roll < weight {
+            return Some(variant);
+        }
+        roll -= weight;
+    }
+    None
+}
+
+// Parameters the redirect endpoint reads itself, never forwarded
+const REDIRECT_PARAMS: [&str; 3] = ["exp", "sig", "count"];
+
+/// `target` with the visitor's query parameters merged in; they replace any the destination
+/// already has under the same name.
+fn forward_query(target: &str, query: Option<&str>) -> String {
+    let Some(query) = query else {
+        return target.to_string();
+    };
+    let Ok(mut url) = url::Url::parse(target) else {
+        return target.to_string();
+    };
+    let incoming: Vec<(String, String)> = url::form_urlencoded::parse(query.as_bytes())
+        .filter(|(name, _)| !REDIRECT_PARAMS.contains(&name.as_ref()))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    if incoming.is_empty() {
+        return target.to_string();
+    }
+    let kept: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| !incoming.iter().any(|(incoming, _)| incoming == name))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    url.query_pairs_mut().clear().extend_pairs(kept).extend_pairs(incoming);
+    url.to_string()
+}
+
+/// `target` with the link's own UTM parameters, then its campaign's, then its owner's template,
+/// each only filling in what is still missing.
+async fn utm_destination(state: &AppState, url_data: &UrlData, target: &str) -> Result<String, AppError> {
+    let mut destination = match &url_data.utm {
+        Some(utm) => apply_utm_defaults(target, utm),
+        None => target.to_string(),
+    };
+    if let Some(campaign_id) = &url_data.campaign_id {
+        match state.campaigns.get(campaign_id).await {
+            Ok(Some(campaign)) => destination = apply_utm_defaults(&destination, &campaign.utm),
+            Ok(None) => {}
+            Err(e) => unless_degraded(state, e).await?,
+        }
+    }
+    if let Some(user_id) = &url_data.user_id {
+        match state.campaigns.user_template(user_id).await {
+            Ok(Some(utm)) => destination = apply_utm_defaults(&destination, &utm),
+            Ok(None) => {}
+            Err(e) => unless_degraded(state, e).await?,
+        }
+    }
+    Ok(destination)
+}
+
+/// Whether the caller listed `application/json` in `Accept`; browsers never do.
+fn accepts_json(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|range| {
+            let mut params = range.split(';').map(str::trim);
+            let json = params.next().is_some_and(|media| media.eq_ignore_ascii_case("application/json"));
+            // `q=0` means "not acceptable"
+            json && !params.any(|param| param.strip_prefix("q=").is_some_and(|q| q.parse::<f32>() == Ok(0.0)))
+        })
+}
+
+fn resolved_link(code: String, url_data: &UrlData, destination: String, variant: Option<String>, counted: bool) -> Response {
+    let link = ResolvedLink {
+        code,
+        long_url: url_data.long_url.clone(),
+        destination,
+        created_at: url_data.created_at.clone(),
+        expires_at: url_data.expires_at.clone(),
+        health_status: url_data.health_status.clone(),
+        dead: url_data.dead,
+        burn_after_read: url_data.burn_after_read,
+        max_clicks: url_data.max_clicks,
+        active_from: url_data.active_from.clone(),
+        active_until: url_data.active_until.clone(),
+        variant,
+        signed: url_data.signed,
+        trial: url_data.trial,
+        campaign_id: url_data.campaign_id.clone(),
+        open_graph: url_data.open_graph.clone(),
+        counted,
+    };
+    let body = Json(ApiResponse { success: true, data: Some(link), error: None });
+    ([(header::VARY, "Accept")], body).into_response()
+}
+
+/// Signed links only resolve with an unexpired `exp` and a `sig` made with the owner's secret.
+async fn check_signature(
+    state: &AppState,
+    code: &str,
+    url_data: &UrlData,
+    query: &SignedLinkQuery,
+) -> Result<(), AppError> {
+    let invalid = || AppError::Forbidden("Link requires a valid signature".to_string());
+    let (Some(exp), Some(sig)) = (query.exp, query.sig.as_deref()) else {
+        return Err(invalid());
+    };
+    let owner = url_data.user_id.as_deref().ok_or_else(invalid)?;
+    let secret = state.rl_db.get_signing_secret(owner).await?.ok_or_else(invalid)?;
+    if !link_signing::verify(&secret, code, exp, sig) {
+        return Err(invalid());
+    }
+    if exp < state.clock.now().timestamp() {
+        return Err(AppError::Gone("Signed link has expired".to_string()));
+    }
+    Ok(())
+}
+
+fn dead_link_page(url_data: &UrlData) -> Html<String> {
+    let url = html_escape(&url_data.long_url);
+    Html(format!(
+        "<!doctype html><html><head><meta charset=\"utf-8\"><title>Link may be broken</title></head>\
+         <body><h1>This link may be broken</h1>\
+         <p>The destination looked unavailable the last time we checked ({}).</p>\
+         <p><a href=\"{}\" rel=\"noopener noreferrer\">Continue to {}</a></p></body></html>",
+        html_escape(url_data.health_status.as_deref().unwrap_or("unknown")),
+        url,
+        url,
+    ))
+}
+
+fn open_graph_page(open_graph: &OpenGraph, long_url: &str, destination: &str) -> Html<String> {
+    let title = html_escape(open_graph.title.as_deref().unwrap_or(long_url));
+    let mut meta = format!(
+        "<meta property=\"og:title\" content=\"{}\"><meta property=\"og:url\" content=\"{}\">",
+        title,
+        html_escape(destination),
+    );
+    if let Some(description) = &open_graph.description {
+        let description = html_escape(description);
+        meta.push_str(&format!(
+            "<meta property=\"og:description\" content=\"{}\"><meta name=\"description\" content=\"{}\">",
+            description, description,
+        ));
+    }
+    if let Some(image) = &open_graph.image {
+        meta.push_str(&format!(
+            "<meta property=\"og:image\" content=\"{}\"><meta name=\"twitter:card\" content=\"summary_large_image\">",
+            html_escape(image),
+        ));
+    }
+    // Misclassified browsers still end up at the destination
+    Html(format!(
+        "<!doctype html><html><head><meta charset=\"utf-8\"><title>{}</title>{}\
+         <meta http-equiv=\"refresh\" content=\"0;url={}\"></head><body></body></html>",
+        title,
+        meta,
+        html_escape(destination),
+    ))
+}
+
+fn html_escape(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::Builder, config::settings::Settings, handlers::shorten::{delete_shorten_handler, disable_url_handler, enable_url_handler}, services::storage::storage::Storage, types::User, test_util::{self, MockStorage}, types::{ScheduledDestination, SplitVariant, UtmDefaults}};
+    use axum::{body::{to_bytes, Body}, extract::connect_info::MockConnectInfo, http::{Request, StatusCode}};
+    use std::{net::SocketAddr, sync::Arc};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn json_clients_resolve_links_without_being_redirected() {
+        let storage = Arc::new(MockStorage::new());
+        let app = test_util::test_app(Settings::default(), storage).await;
+        app.state.cache.insert("docs".into(), &test_util::url_data("https://example.com/docs")).await.unwrap();
+        let router = app.router.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+
+        let request = Request::get("/v1/redirect/docs").header(header::ACCEPT, "application/json").body(Body::empty()).unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["data"]["destination"], "https://example.com/docs");
+        assert_eq!(body["data"]["counted"], false);
+
+        let request = Request::get("/v1/redirect/docs").header(header::ACCEPT, "text/html,*/*;q=0.8").body(Body::empty()).unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert!(response.status().is_redirection());
+        assert_eq!(response.headers()[header::VARY], "Accept");
+    }
+
+    #[tokio::test]
+    async fn disallowed_crawlers_are_denied_under_the_deny_policy() {
+        let mut config = Settings::default();
+        config.crawlers.disallowed = vec!["googlebot".into()];
+        config.crawlers.policy = CrawlerPolicy::Deny;
+        let app = test_util::test_app(config, Arc::new(MockStorage::new())).await;
+        app.state.cache.insert("docs".into(), &test_util::url_data("https://example.com/docs")).await.unwrap();
+        let router = app.router.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+
+        let visit = |user_agent: &'static str| {
+            Request::get("/v1/redirect/docs").header(header::USER_AGENT, user_agent).body(Body::empty()).unwrap()
+        };
+        let response = router.clone().oneshot(visit("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = router.oneshot(visit("curl/8.5.0")).await.unwrap();
+        assert!(response.status().is_redirection());
+    }
+
+    #[tokio::test]
+    async fn limited_links_are_gone_after_their_last_click() {
+        let app = test_util::test_app(Settings::default(), Arc::new(MockStorage::new())).await;
+        let url_data = UrlData { max_clicks: Some(2), ..test_util::url_data("https://example.com/limited") };
+        app.state.cache.insert("limited".into(), &url_data).await.unwrap();
+        let router = app.router.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+
+        for expected in [StatusCode::FOUND, StatusCode::FOUND, StatusCode::GONE] {
+            let response = router.clone().oneshot(Request::get("/v1/redirect/limited").body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn links_redirect_with_their_own_status_or_the_configured_default() {
+        let mut config = Settings::default();
+        config.redirects.default_status = 307;
+        let app = test_util::test_app(config, Arc::new(MockStorage::new())).await;
+        let permanent = UrlData { redirect_status: Some(301), ..test_util::url_data("https://example.com/moved") };
+        app.state.cache.insert("moved".into(), &permanent).await.unwrap();
+        app.state.cache.insert("plain".into(), &test_util::url_data("https://example.com/plain")).await.unwrap();
+        let router = app.router.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+        let visit = |code: &str| router.clone().oneshot(Request::get(format!("/v1/redirect/{}", code)).body(Body::empty()).unwrap());
+
+        let response = visit("moved").await.unwrap();
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(response.headers()[header::LOCATION], "https://example.com/moved");
+        assert_eq!(visit("plain").await.unwrap().status(), StatusCode::TEMPORARY_REDIRECT);
+    }
+
+    #[tokio::test]
+    async fn disabled_links_are_gone_until_their_owner_enables_them() {
+        let app = test_util::test_app(Settings::default(), Arc::new(MockStorage::new())).await;
+        let url_data = UrlData { user_id: Some("user-alice".into()), ..test_util::url_data("https://example.com/paused") };
+        app.state.cache.insert("paused".into(), &url_data).await.unwrap();
+        let router = app.router.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+        let visit = || router.clone().oneshot(Request::get("/v1/redirect/paused").body(Body::empty()).unwrap());
+        let owner = || Extension(RequestContext { user_id: Some("user-alice".into()), ..Default::default() });
+        let stranger = Extension(RequestContext { user_id: Some("user-mallory".into()), ..Default::default() });
+
+        assert!(disable_url_handler(State(app.state.clone()), stranger, Path("paused".into())).await.is_err());
+        disable_url_handler(State(app.state.clone()), owner(), Path("paused".into())).await.unwrap();
+        assert_eq!(visit().await.unwrap().status(), StatusCode::GONE);
+        assert!(app.state.cache.get_url_data("paused").await.is_ok());
+
+        enable_url_handler(State(app.state.clone()), owner(), Path("paused".into())).await.unwrap();
+        assert_eq!(visit().await.unwrap().status(), StatusCode::FOUND);
+    }
+
+    #[tokio::test]
+    async fn scheduled_links_follow_the_injected_clock() {
+        let start = chrono::Utc::now();
+        let clock = test_util::MockClock::new(start);
+        let app = Builder::new(Settings::default())
+            .storage(Arc::new(MockStorage::new()))
+            .clock(Arc::new(Clone::clone(&clock)))
+            .background_tasks(false)
+            .build()
+            .await
+            .unwrap();
+        let at = |hours: i64| (start + chrono::Duration::hours(hours)).to_rfc3339();
+        let url_data = UrlData {
+            active_from: Some(at(1)),
+            active_until: Some(at(4)),
+            schedule: vec![ScheduledDestination { url: "https://example.com/sale".into(), starts_at: at(2) }],
+            ..test_util::url_data("https://example.com/teaser")
+        };
+        app.state.cache.insert("launch".into(), &url_data).await.unwrap();
+        let router = app.router.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+        let visit = || router.clone().oneshot(Request::get("/v1/redirect/launch").body(Body::empty()).unwrap());
+
+        assert_eq!(visit().await.unwrap().status(), StatusCode::NOT_FOUND);
+        clock.advance(chrono::Duration::minutes(90));
+        assert_eq!(visit().await.unwrap().headers()[header::LOCATION], "https://example.com/teaser");
+        clock.advance(chrono::Duration::hours(1));
+        assert_eq!(visit().await.unwrap().headers()[header::LOCATION], "https://example.com/sale");
+        clock.advance(chrono::Duration::hours(2));
+        assert_eq!(visit().await.unwrap().status(), StatusCode::GONE);
+    }
+
+    #[tokio::test]
+    async fn utm_templates_fill_in_what_the_destination_lacks() {
+        let storage = Arc::new(MockStorage::new());
+        let template = UtmDefaults { utm_medium: Some("owner".into()), utm_campaign: Some("spring".into()), ..Default::default() };
+        storage.set_user(&User { utm: Some(template), ..test_util::user("ada") }).await.unwrap();
+        let app = test_util::test_app(Settings::default(), storage).await;
+        let url_data = UrlData {
+            user_id: Some("user-ada".into()),
+            utm: Some(UtmDefaults { utm_source: Some("link".into()), utm_medium: Some("link".into()), ..Default::default() }),
+            ..test_util::url_data("https://example.com/a?utm_source=explicit&x=1#top")
+        };
+        app.state.cache.insert("utm".into(), &url_data).await.unwrap();
+        let router = app.router.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+
+        let response = router.oneshot(Request::get("/v1/redirect/utm").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            "https://example.com/a?utm_source=explicit&x=1&utm_medium=link&utm_campaign=spring#top"
+        );
+    }
+
+    #[tokio::test]
+    async fn deleted_links_stop_redirecting_from_every_tier() {
+        let app = test_util::test_app(Settings::default(), Arc::new(MockStorage::new())).await;
+        let url_data = UrlData { user_id: Some("user-alice".into()), ..test_util::url_data("https://example.com/viral") };
+        app.state.cache.insert("viral".into(), &url_data).await.unwrap();
+        let router = app.router.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+        let visit = || router.clone().oneshot(Request::get("/v1/redirect/viral").body(Body::empty()).unwrap());
+
+        assert!(visit().await.unwrap().status().is_redirection());
+        assert_eq!(app.state.cache.refresh_hot_set().await, 1);
+        let alice = RequestContext { user_id: Some("user-alice".into()), ..Default::default() };
+        delete_shorten_handler(State(app.state.clone()), Extension(alice), Path("viral".into())).await.unwrap();
+
+        assert!(app.state.cache.hot_set().get("viral").is_none());
+        assert_eq!(visit().await.unwrap().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn forwarded_parameters_replace_the_destinations_own() {
+        assert_eq!(
+            forward_query("https://example.com/a?ref=site&x=1#top", Some("x=2&gclid=abc&sig=s&exp=9")),
+            "https://example.com/a?ref=site&x=2&gclid=abc#top"
+        );
+        assert_eq!(forward_query("https://example.com/a", Some("count=true")), "https://example.com/a");
+        assert_eq!(forward_query("https://example.com/a", None), "https://example.com/a");
+    }
+
+    #[test]
+    fn sticky_variants_follow_the_ip_and_the_weights() {
+        let variant = |name: &str, weight| SplitVariant { name: name.into(), url: format!("https://example.com/{}", name), weight };
+        let url_data = UrlData {
+            variants: vec![variant("a", 1), variant("b", 3)],
+            ..test_util::url_data("https://example.com")
+        };
+        let pick = |ip: &str| pick_variant(&url_data, "ab", Some(ip)).unwrap().name.clone();
+        assert!((0..20).all(|_| pick("203.0.113.7") == pick("203.0.113.7")));
+
+        let to_b = (0..2000).filter(|n| pick(&format!("10.0.{}.{}", n / 256, n % 256)) == "b").count();
+        assert!((1300..1700).contains(&to_b), "{} of 2000 visitors got b", to_b);
+    }
+
+    #[test]
+    fn only_an_acceptable_json_media_range_counts() {
+        let accept = |value: &'static str| HeaderMap::from_iter([(header::ACCEPT, value.parse().unwrap())]);
+        assert!(accepts_json(&accept("application/json")));
+        assert!(accepts_json(&accept("text/plain, Application/JSON; q=0.5")));
+        assert!(!accepts_json(&accept("application/json;q=0, text/html")));
+        assert!(!accepts_json(&accept("*/*")));
     }
+}
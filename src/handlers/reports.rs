@@ -0,0 +1,51 @@
+use axum::{
+    extract::{Json, Path, State},
+    Extension,
+    response::IntoResponse,
+};
+use cuid::cuid2;
+use serde_json::json;
+use tracing::info;
+use validator::Validate;
+use crate::{
+    errors::AppError,
+    handlers::shorten::AppState,
+    middleware::RequestContext,
+    services::metrics,
+    types::{AbuseReport, ApiResponse, ReportRequest},
+};
+
+#[axum::debug_handler]
+pub(crate) async fn report_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Path(code): Path<String>,
+    Json(req): Json<ReportRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    req.validate().map_err(AppError::Validation)?;
+
+    // Only accept reports for links that exist
+    state
+        .cache
+        .get_url_data(&code)
+        .await
+        .map_err(|_| AppError::NotFound("URL not found".into()))?;
+
+    let report = AbuseReport {
+        id: cuid2(),
+        code: code.clone(),
+        reason: req.reason,
+        details: req.details,
+        reporter_ip: request_context.ip.clone(),
+        created_at: state.clock.now().to_rfc3339(),
+    };
+    state.rl_db.add_report(&report).await?;
+    metrics::record_abuse_report("filed");
+    info!("Abuse report {} filed against code {}: {}", report.id, code, report.reason);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(json!({ "report_id": report.id })),
+        error: None,
+    }))
+}
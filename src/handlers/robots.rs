@@ -0,0 +1,13 @@
+use axum::{extract::State, http::header, response::IntoResponse};
+use crate::handlers::shorten::AppState;
+
+/// `crawlers.robots_txt`, so well-behaved crawlers skip millions of short links on their own.
+pub async fn robots_txt_handler(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8"),
+            (header::CACHE_CONTROL, "public, max-age=3600"),
+        ],
+        state.config.crawlers.robots_txt.clone(),
+    )
+}
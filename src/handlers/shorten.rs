@@ -1,85 +1,356 @@
 use axum::{
-    extract::{Json, Path, State},
+    extract::{Json, OriginalUri, Path, Query, State},
     Extension,
     response::IntoResponse,
 };
+use cuid::cuid2;
 use serde_json::json;
 use std::sync::Arc;
-use tracing::{info, warn};
-use validator::Validate;
+use tracing::{debug, info, warn};
+use validator::{Validate, ValidateArgs};
 use crate::{
-    clock::{Clock, SystemClock}, config::settings::Settings, errors::AppError, services::{
+    clock::Clock, config::settings::Settings, errors::AppError, handlers::pagination::pagination_headers, services::{
         analytics::AnalyticsService,
-        cache::cache::CacheService,
+        blocklist::DomainBlocklist,
+        cache::{cache::CacheService, circuit_breaker::CircuitBreaker},
+        campaigns::CampaignService,
+        captcha::CaptchaGate,
+        tokens::TokenService,
+        password_policy::PasswordPolicy,
         codegen::generator::CodeGenerator,
-        storage::{dragonfly::DatabaseClient, storage::Storage},
-    }, types::{ApiResponse, ShortenRequest, ShortenResponse, UrlData, AuthResponse},
-    middleware::RequestContext,
+        hooks::Hooks,
+        link_checker::LinkChecker,
+        link_search::{query_terms, search_terms},
+        reserved_aliases::ReservedAliases,
+        safe_browsing::Verdict,
+        storage::storage::{pinned_first, Storage},
+        shorten_dedup::ShortenCoalescer,
+        threat_intel::{Threat, ThreatIntel},
+        url_guard::check_destination,
+        usage::UsageTracker,
+    }, types::{
+        ApiResponse, DestinationChange, DestinationHistoryResponse, LinkListItem, Notification, PageQuery, Paginate,
+        PendingTransfer, QuickShortenQuery, ShortenRequest, ShortenResponse, TransferRequest, UpdateDestinationRequest, UrlData, UrlFilterQuery,
+        AuthResponse, SearchQuery,
+    },
+    middleware::{rate_limit::check_trial_link_limit, RequestContext},
+    validator::is_redirect_loop_host,
 };
 
+// Oldest destinations are dropped beyond this
+const MAX_DESTINATION_HISTORY: usize = 50;
+
 #[derive(Clone)]
 pub struct AppState {
     pub config: Arc<Settings>,
     pub cache: Arc<CacheService>,
-    pub analytics: Arc<AnalyticsService>,
+    pub analytics: Arc<AnalyticsService<Arc<dyn Clock>>>,
     pub codegen: Arc<CodeGenerator>,
-    pub clock: Arc<SystemClock>,
-    pub rl_db: Arc<DatabaseClient>,
+    pub clock: Arc<dyn Clock>,
+    pub rl_db: Arc<dyn Storage + Send + Sync>,
+    pub threat_intel: Arc<ThreatIntel>,
+    pub blocklist: Arc<DomainBlocklist>,
+    pub captcha: Arc<CaptchaGate>,
+    pub tokens: Arc<TokenService>,
+    pub password_policy: Arc<PasswordPolicy>,
+    pub hooks: Arc<Hooks>,
+    pub campaigns: Arc<CampaignService>,
+    pub usage: Arc<UsageTracker>,
+    pub shortens_in_flight: Arc<ShortenCoalescer>,
+    pub link_checker: Arc<LinkChecker>,
+    pub reserved_aliases: Arc<ReservedAliases>,
+}
+
+impl AppState {
+    /// Each Dragonfly client keeps its own breaker; these are the names admins and metrics use.
+    pub fn circuit_breakers(&self) -> Vec<(&'static str, Arc<CircuitBreaker>)> {
+        let mut breakers = vec![("cache", Arc::clone(self.cache.circuit_breaker()))];
+        if let Some(circuit_breaker) = self.rl_db.circuit_breaker() {
+            breakers.push(("rate_limit", Arc::clone(circuit_breaker)));
+        }
+        breakers.push(("analytics", Arc::clone(self.analytics.circuit_breaker())));
+        breakers
+    }
+
+    /// Every storage client whose connection pools feed the redis_pool_* metrics.
+    pub fn storage_clients(&self) -> Vec<(&'static str, Arc<dyn Storage + Send + Sync>)> {
+        vec![
+            ("cache", Arc::clone(self.cache.dragonfly())),
+            ("rate_limit", Arc::clone(&self.rl_db)),
+            ("analytics", Arc::clone(self.analytics.db())),
+        ]
+    }
 }
 
 #[axum::debug_handler]
 pub async fn list_urls_handler(
     State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<PageQuery>,
+    Query(filter): Query<UrlFilterQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    // Example: fetch all URL codes from cache/storage
-    // Use available cache method for listing URLs (pagination stub: page 1, 100 per page)
-    let urls_page = state.cache.list_urls_cache(None, 1, 100).await.map_err(|e| AppError::Internal(e.to_string()))?;
-    let urls = urls_page.map(|p| p.items).unwrap_or_default();
+    let user_id = request_context
+        .user_id
+        .as_deref()
+        .ok_or_else(|| AppError::Unauthorized("Authentication required for /v1/urls".into()))?;
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(20).clamp(1, 100);
+
+    let mut codes = match filter.tag.as_deref() {
+        Some(tag) => state.rl_db.list_tagged_codes(user_id, tag).await?,
+        None => state.rl_db.list_user_codes(user_id).await?,
+    };
+    // The indexes are sets with no stable order, so sort before paging or pages would overlap
+    codes.sort_unstable();
+    let codes = pinned_first(codes, &state.rl_db.list_pinned(user_id).await?);
+    let total_items = codes.len() as u64;
+    let mut items = Vec::new();
+    for code in codes.into_iter().skip(((page - 1) * per_page) as usize).take(per_page as usize) {
+        // Links that have since expired out of storage are skipped
+        if let Ok(url_data) = state.cache.get_url_data(&code).await {
+            items.push(LinkListItem { code, url_data: (*url_data).clone() });
+        }
+    }
+    let urls = Paginate::new(items, page, per_page, total_items);
+    Ok((
+        pagination_headers(&state.config.base_url, &uri, &urls),
+        Json(ApiResponse {
+            success: true,
+            data: Some(urls),
+            error: None,
+        }),
+    ))
+}
+
+/// Finds the caller's links by code, destination host or tag; every word of `q` must match.
+#[axum::debug_handler]
+pub(crate) async fn search_urls_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<PageQuery>,
+    Query(search): Query<SearchQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = request_context
+        .user_id
+        .as_deref()
+        .ok_or_else(|| AppError::Unauthorized("Authentication required for /v1/urls/search".into()))?;
+    let terms = query_terms(&search.q);
+    if terms.is_empty() {
+        return Err(AppError::BadRequest("Search query q is empty".into()));
+    }
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(20).clamp(1, 100);
+
+    let mut codes: Option<Vec<String>> = None;
+    for term in &terms {
+        let matches = state.rl_db.search_codes(user_id, term).await?;
+        codes = Some(match codes {
+            Some(codes) => codes.into_iter().filter(|code| matches.contains(code)).collect(),
+            None => matches,
+        });
+    }
+    let mut codes = codes.unwrap_or_default();
+    codes.sort();
+    let total_items = codes.len() as u64;
+    let mut items = Vec::new();
+    for code in codes.into_iter().skip(((page - 1) * per_page) as usize).take(per_page as usize) {
+        // Links that have since expired out of storage are skipped
+        if let Ok(url_data) = state.cache.get_url_data(&code).await {
+            items.push(LinkListItem { code, url_data: (*url_data).clone() });
+        }
+    }
+    let urls = Paginate::new(items, page, per_page, total_items);
+    Ok((
+        pagination_headers(&state.config.base_url, &uri, &urls),
+        Json(ApiResponse {
+            success: true,
+            data: Some(urls),
+            error: None,
+        }),
+    ))
+}
+
+#[axum::debug_handler]
+pub async fn shorten_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(req): Json<ShortenRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = create_short_link(&state, &request_context, req).await?;
     Ok(Json(ApiResponse {
         success: true,
-        data: Some(json!({"urls": urls})),
+        data: Some(response),
         error: None,
     }))
 }
 
+/// Plain-text variant of `/v1/shorten` for browser extensions and shell aliases.
 #[axum::debug_handler]
-pub async fn shorten_handler(
+pub(crate) async fn quick_shorten_handler(
     State(state): State<AppState>,
     Extension(request_context): Extension<RequestContext>,
-    Json(req): Json<ShortenRequest>,
+    Query(query): Query<QuickShortenQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    req.validate().map_err(AppError::Validation)?;
+    if request_context.user_id.is_none() {
+        return Err(AppError::Unauthorized("Authentication required for /v1/quick".into()));
+    }
+    let req = ShortenRequest {
+        url: query.url,
+        custom_alias: None,
+        expiration_date: None,
+        captcha_token: None,
+        privacy_mode: None,
+        open_graph: None,
+        burn_after_read: None,
+        max_clicks: None,
+        active_from: None,
+        active_until: None,
+        schedule: None,
+        rotation: None,
+        variants: None,
+        split_mode: None,
+        utm: None,
+        forward_query: None,
+        redirect_status: None,
+        fallback_url: None,
+        max_redirects_per_minute: None,
+        signed: None,
+        tags: None,
+    };
+    let response = create_short_link(&state, &request_context, req).await?;
+    Ok(response.short_url)
+}
+
+/// Validates, screens and stores a new link; shared by the JSON and quick endpoints. A request
+/// identical to one still in flight from the same caller gets that request's link.
+pub(crate) async fn create_short_link(
+    state: &AppState,
+    request_context: &RequestContext,
+    req: ShortenRequest,
+) -> Result<ShortenResponse, AppError> {
+    match in_flight_key(request_context, &req) {
+        Some(key) => state.shortens_in_flight.run(key, || mint_short_link(state, request_context, req)).await,
+        None => mint_short_link(state, request_context, req).await,
+    }
+}
+
+/// The caller, the normalized destination and every other option but the CAPTCHA token, which
+/// a double-click sends twice but can only spend once.
+fn in_flight_key(request_context: &RequestContext, req: &ShortenRequest) -> Option<String> {
+    let caller = match (&request_context.user_id, &request_context.ip) {
+        (Some(user_id), _) => format!("user:{}", user_id),
+        (None, Some(ip)) => format!("ip:{}", ip),
+        (None, None) => return None,
+    };
+    let url = url::Url::parse(&req.url).ok()?;
+    let mut options = serde_json::to_value(req).ok()?;
+    let options = options.as_object_mut()?;
+    options.remove("url");
+    options.remove("captcha_token");
+    Some(format!("{}\n{}\n{}", caller, url, serde_json::Value::Object(std::mem::take(options))))
+}
+
+async fn mint_short_link(
+    state: &AppState,
+    request_context: &RequestContext,
+    req: ShortenRequest,
+) -> Result<ShortenResponse, AppError> {
+    req.validate_with_args(&state.clock).map_err(AppError::Validation)?;
 
     // Authentication is optional - if user is authenticated, associate URL with them
     let user_id = request_context.user_id.clone(); // Optional user ID
+    let client_ip = request_context.ip.as_deref();
+    state
+        .captcha
+        .check(user_id.is_some(), client_ip, req.captcha_token.as_deref())
+        .await?;
+
+    let signed = req.signed.unwrap_or(false);
+    if signed && user_id.is_none() {
+        // The signing secret belongs to an account, so anonymous links have nothing to sign with
+        return Err(AppError::BadRequest("Signed links require authentication".into()));
+    }
+    if let Some(user_id) = &user_id {
+        check_link_quota(state, user_id).await?;
+    }
+    let trial = user_id.is_none() && state.config.trial.enabled;
+    let expires_at = if trial {
+        if req.custom_alias.is_some() {
+            return Err(AppError::Unauthorized("Custom aliases require an account".into()));
+        }
+        check_trial_link_limit(state, client_ip.unwrap_or("unknown")).await?;
+        Some(trial_expiry(state, req.expiration_date.as_deref()))
+    } else {
+        req.expiration_date.clone()
+    };
+
+    // Reputation check runs before a code is minted so rejected URLs don't burn codes
+    let (verdict, quarantined) = screen_destination(state, &req.url, client_ip).await?;
+    if let (Some(from), Some(until)) = (&req.active_from, &req.active_until) {
+        // Both already validated as RFC 3339
+        let parse = |date: &str| chrono::DateTime::parse_from_rfc3339(date).ok();
+        if parse(from) >= parse(until) {
+            return Err(AppError::BadRequest("active_from must be before active_until".into()));
+        }
+    }
+    let rotation = req.rotation.unwrap_or_default();
+    let variants = req.variants.unwrap_or_default();
+    if !variants.is_empty() && (!rotation.is_empty() || req.schedule.is_some()) {
+        // Each of these picks the destination on its own; combined, no one could tell which won
+        return Err(AppError::BadRequest("variants cannot be combined with rotation or schedule".into()));
+    }
+    let mut schedule = req.schedule.unwrap_or_default();
+    schedule.sort_by_key(|entry| chrono::DateTime::parse_from_rfc3339(&entry.starts_at).ok());
+    let scheduled = schedule.iter().map(|entry| &entry.url);
+    let variant_urls = variants.iter().map(|variant| &variant.url);
+    for mirror in rotation.iter().chain(&req.fallback_url).chain(scheduled).chain(variant_urls) {
+        // Mirrors, fallbacks, scheduled pages and variants reach visitors too, so they get the same screening; quarantine isn't per-URL
+        if let (_, true) = screen_destination(state, mirror, client_ip).await? {
+            return Err(AppError::Blocked(format!("URL flagged as unsafe: {}", mirror)));
+        }
+    }
 
+    let mut claims_reservation = false;
     let code = match req.custom_alias {
-        Some(alias) => alias,
-        None => state
-            .codegen
-            .next()
-            .map_err(AppError::CodeGen)?
-            .to_string(),
+        Some(alias) => {
+            state.reserved_aliases.check(&alias).await?;
+            // Codes handed out by /v1/codes/reserve can only be claimed by their owner
+            if let Some(owner) = state.rl_db.get_code_reservation(&alias).await? {
+                if user_id.as_deref() != Some(owner.as_str()) {
+                    return Err(AppError::Conflict("Code is reserved".into()));
+                }
+                claims_reservation = true;
+            }
+            // A burned code stays dead; reusing it would resurrect printed one-time links
+            if state.rl_db.is_code_burned(&alias).await? {
+                return Err(AppError::Conflict("Code is no longer available".into()));
+            }
+            alias
+        }
+        None => loop {
+            let code = state.codegen.next().map_err(AppError::CodeGen)?.to_string();
+            // Other replicas' generators hand out the same codes, and one of them may have reserved this one
+            if state.rl_db.get_code_reservation(&code).await?.is_none() {
+                break code;
+            }
+            debug!("Skipping generated code {}: it is reserved", code);
+        },
     };
 
     // Check for existing code
     if state.cache.contains_key(&code) {
-        if let Ok(existing_data) = state.cache.get(&code).await {
-            let existing_url_data: UrlData = serde_json::from_str(&existing_data)
-                .map_err(|e| AppError::Internal(e.to_string()))?;
+        if let Ok(existing_url_data) = state.cache.get_url_data(&code).await {
             if existing_url_data.long_url == req.url && existing_url_data.user_id == user_id {
                 let short_url = format!("{}/v1/redirect/{}", state.config.base_url, code);
-                return Ok(Json(ApiResponse {
-                    success: true,
-                    data: Some(ShortenResponse {
-                        short_url,
-                        code,
-                        expiration_date: req.expiration_date,
-                    }),
-                    error: None,
-                }));
+                return Ok(ShortenResponse {
+                    short_url,
+                    code,
+                    expiration_date: existing_url_data.expires_at.clone(),
+                });
             } else {
-                return Err(AppError::Conflict("Code already in use".into()));
+                return Err(AppError::DuplicateAlias(code));
             }
         }
     }
@@ -89,29 +360,451 @@ pub async fn shorten_handler(
         long_url: req.url.clone(),
         user_id: user_id.clone(),
         created_at: state.clock.now().to_rfc3339(),
-        expires_at: req.expiration_date.clone(),
+        expires_at: expires_at.clone(),
+        reputation: state.threat_intel.safe_browsing_enabled().then(|| verdict.as_str().to_string()),
+        quarantined,
+        privacy_mode: req.privacy_mode.unwrap_or(false),
+        open_graph: req.open_graph,
+        burn_after_read: req.burn_after_read.unwrap_or(false),
+        max_clicks: req.max_clicks,
+        active_from: req.active_from,
+        active_until: req.active_until,
+        schedule,
+        rotation,
+        variants,
+        split_mode: req.split_mode.unwrap_or_default(),
+        utm: req.utm.filter(|utm| !utm.is_empty()),
+        forward_query: req.forward_query.unwrap_or(false),
+        redirect_status: req.redirect_status,
+        fallback_url: req.fallback_url,
+        max_redirects_per_minute: req.max_redirects_per_minute,
+        signed,
+        trial,
+        tags: req.tags.unwrap_or_default(),
+        ..Default::default()
     };
+    state.hooks.on_shorten(&code, &url_data, request_context).await?;
 
-    // Store UrlData as JSON
-    let url_data_json = serde_json::to_string(&url_data)
-        .map_err(|e| AppError::Internal(e.to_string()))?;
-    state.cache.insert(code.clone(), url_data_json).await?;
+    state.cache.insert(code.clone(), &url_data).await?;
+    if let Some(user_id) = &user_id {
+        // Only the quota relies on the index, so a failure here shouldn't fail the link
+        if let Err(e) = state.rl_db.add_user_url(user_id, &code).await {
+            warn!("Failed to index {} under user {}: {}", code, user_id, e);
+        }
+        if let Err(e) = state.rl_db.tag_url(user_id, &code, &url_data.tags).await {
+            warn!("Failed to index {} under its tags: {}", code, e);
+        }
+        let terms: Vec<String> = search_terms(&code, &url_data).into_iter().collect();
+        if let Err(e) = state.rl_db.index_search_terms(user_id, &code, &terms).await {
+            warn!("Failed to index {} for search: {}", code, e);
+        }
+    }
+    if claims_reservation {
+        state.rl_db.release_code_reservation(&code).await?;
+    }
 
     let short_url = format!("{}/v1/redirect/{}", state.config.base_url, code);
     let user_display = user_id.as_deref().unwrap_or("anonymous");
     info!("Shortened URL: {} -> {} for user {}", req.url, short_url, user_display);
 
+    Ok(ShortenResponse {
+        short_url,
+        code,
+        expiration_date: expires_at,
+    })
+}
+
+/// When a trial link expires: `trial.link_ttl_secs` from now, or earlier if the caller asked.
+fn trial_expiry(state: &AppState, requested: Option<&str>) -> String {
+    let latest = state.clock.now() + chrono::Duration::seconds(state.config.trial.link_ttl_secs as i64);
+    // Already validated as a future RFC 3339 date
+    let requested = requested.and_then(|date| chrono::DateTime::parse_from_rfc3339(date).ok());
+    match requested {
+        Some(requested) if requested < latest => requested.to_rfc3339(),
+        _ => latest.to_rfc3339(),
+    }
+}
+
+/// Refuses another link once its owner has as many as their plan's `max_links`. Counted from the
+/// owner's link index, so concurrent requests can overshoot by a link or two. The index lives in
+/// Dragonfly, so like rate limits the quota isn't enforced while it is unreachable.
+async fn check_link_quota(state: &AppState, user_id: &str) -> Result<(), AppError> {
+    let quota = &state.config.quota;
+    if quota.plans.is_empty() || state.cache.is_degraded().await {
+        return Ok(());
+    }
+    let user = state.rl_db.get_user(user_id).await?;
+    let Some((plan, limits)) = quota.plan(user.as_ref().and_then(|user| user.plan.as_deref())) else {
+        return Ok(());
+    };
+    let Some(max_links) = limits.max_links else {
+        return Ok(());
+    };
+    let used = state.rl_db.count_urls(Some(user_id)).await?;
+    if used >= max_links {
+        warn!("User {} is at the {} plan's limit of {} links", user_id, plan, max_links);
+        return Err(AppError::QuotaExceeded { quota: "max_links", plan: plan.to_string(), limit: max_links, used });
+    }
+    Ok(())
+}
+
+/// Rejects loops, blocklisted hosts and unsafe URLs. Returns the reputation verdict and
+/// whether the link should be stored quarantined.
+async fn screen_destination(
+    state: &AppState,
+    url: &str,
+    client_ip: Option<&str>,
+) -> Result<(Verdict, bool), AppError> {
+    let destination = check_destination(url)?;
+    let host = destination.host_str().unwrap_or_default().to_string();
+    if is_redirect_loop_host(&host, &state.config) {
+        warn!("Rejected {}: host {} would create a redirect loop", url, host);
+        return Err(AppError::InvalidUrl("URL points back at a link shortener".into()));
+    }
+
+    match state.threat_intel.assess(url, &host).await {
+        Ok(verdict) => Ok((verdict, false)),
+        Err(Threat::Flagged(threat)) if state.config.security.safe_browsing_quarantine.unwrap_or(false) => {
+            warn!("Quarantining {} flagged as {}", url, threat);
+            Ok((Verdict::Malicious(threat), true))
+        }
+        Err(threat) => {
+            if let Some(ip) = client_ip {
+                state.captcha.flag(ip);
+            }
+            Err(match threat {
+                Threat::Blocklisted(pattern) => {
+                    warn!("Rejected {}: host {} matches blocklist pattern {}", url, host, pattern);
+                    AppError::Blocked(format!("Destination domain {} is blocked", host))
+                }
+                Threat::Flagged(threat) => {
+                    warn!("Rejected {} flagged as {}", url, threat);
+                    AppError::Blocked(format!("URL flagged as unsafe: {}", threat))
+                }
+            })
+        }
+    }
+}
+
+fn require_user(request_context: &RequestContext) -> Result<&str, AppError> {
+    request_context.user_id.as_deref().ok_or_else(|| {
+        AppError::Unauthorized("Authentication required for /v1/urls/:code".into())
+    })
+}
+
+async fn load_url(state: &AppState, code: &str) -> Result<UrlData, AppError> {
+    let url_data = state
+        .cache
+        .get_url_data(code)
+        .await
+        .map_err(|_| AppError::NotFound("URL not found".into()))?;
+    Ok(Arc::unwrap_or_clone(url_data))
+}
+
+async fn save_url(state: &AppState, code: &str, url_data: &UrlData) -> Result<(), AppError> {
+    state.cache.insert(code.to_string(), url_data).await
+}
+
+/// Loads a link the caller owns; other users' links look missing.
+async fn owned_url(state: &AppState, request_context: &RequestContext, code: &str) -> Result<UrlData, AppError> {
+    let user_id = require_user(request_context)?;
+    let url_data = load_url(state, code).await?;
+    if url_data.user_id.as_deref() != Some(user_id) {
+        return Err(AppError::NotFound("URL not found".into()));
+    }
+    Ok(url_data)
+}
+
+/// Repoints a link at a new destination, keeping the code (and any printed QR codes) valid.
+#[axum::debug_handler]
+pub(crate) async fn update_destination_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Path(code): Path<String>,
+    Json(req): Json<UpdateDestinationRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    req.validate_with_args(&state.clock).map_err(AppError::Validation)?;
+    if req.url.is_none() && req.expiration_date.is_none() && req.tags.is_none() {
+        return Err(AppError::BadRequest("Nothing to update: send url, expiration_date or tags".into()));
+    }
+    let mut url_data = owned_url(&state, &request_context, &code).await?;
+    if url_data.disabled_at.is_some() {
+        return Err(AppError::Gone("Link has been disabled".into()));
+    }
+    let repoint = req.url.filter(|url| *url != url_data.long_url);
+    if repoint.is_none() && req.expiration_date.is_none() && req.tags.is_none() {
+        return Err(AppError::Conflict("Link already points at this URL".into()));
+    }
+    let terms_before = search_terms(&code, &url_data);
+
+    if let Some(url) = repoint {
+        let (verdict, quarantined) = screen_destination(&state, &url, request_context.ip.as_deref()).await?;
+        let now = state.clock.now().to_rfc3339();
+        let previous = std::mem::replace(&mut url_data.long_url, url);
+        url_data.history.push(DestinationChange { url: previous, replaced_at: now });
+        if url_data.history.len() > MAX_DESTINATION_HISTORY {
+            let excess = url_data.history.len() - MAX_DESTINATION_HISTORY;
+            url_data.history.drain(..excess);
+        }
+        url_data.reputation = state.threat_intel.safe_browsing_enabled().then(|| verdict.as_str().to_string());
+        url_data.quarantined = quarantined;
+        // The health scan applied to the old destination
+        url_data.dead = false;
+        url_data.health_status = None;
+        url_data.health_checked_at = None;
+        info!("Repointed code {} to {} ({} previous destinations)", code, url_data.long_url, url_data.history.len());
+    }
+    if let Some(expires_at) = req.expiration_date {
+        info!("Expiry of code {} changed from {:?} to {:?}", code, url_data.expires_at, expires_at);
+        url_data.expires_at = expires_at;
+    }
+    if let Some(tags) = req.tags {
+        // owned_url already made sure there is a signed-in owner
+        let user_id = require_user(&request_context)?;
+        let removed: Vec<String> = url_data.tags.iter().filter(|tag| !tags.contains(tag)).cloned().collect();
+        let added: Vec<String> = tags.iter().filter(|tag| !url_data.tags.contains(tag)).cloned().collect();
+        state.rl_db.untag_url(user_id, &code, &removed).await?;
+        state.rl_db.tag_url(user_id, &code, &added).await?;
+        url_data.tags = tags;
+    }
+    // The destination host and the tags are both search terms
+    let terms_after = search_terms(&code, &url_data);
+    if terms_after != terms_before {
+        let user_id = require_user(&request_context)?;
+        let removed: Vec<String> = terms_before.difference(&terms_after).cloned().collect();
+        let added: Vec<String> = terms_after.difference(&terms_before).cloned().collect();
+        state.rl_db.remove_search_terms(user_id, &code, &removed).await?;
+        state.rl_db.index_search_terms(user_id, &code, &added).await?;
+    }
+
+    // Overwrites this instance's cache tiers, the bloom filter and storage in one go
+    save_url(&state, &code, &url_data).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(json!({
+            "code": code,
+            "url": url_data.long_url,
+            "expires_at": url_data.expires_at,
+            "tags": url_data.tags,
+            "quarantined": url_data.quarantined,
+        })),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn destination_history_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Path(code): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let url_data = owned_url(&state, &request_context, &code).await?;
     Ok(Json(ApiResponse {
         success: true,
-        data: Some(ShortenResponse {
-            short_url,
+        data: Some(DestinationHistoryResponse {
             code,
-            expiration_date: req.expiration_date,
+            current: url_data.long_url,
+            history: url_data.history,
         }),
         error: None,
     }))
 }
 
+/// Flips whether a link is pinned to the top of its owner's list.
+#[axum::debug_handler]
+pub(crate) async fn toggle_pin_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Path(code): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut url_data = owned_url(&state, &request_context, &code).await?;
+    let user_id = require_user(&request_context)?;
+    url_data.pinned = !url_data.pinned;
+    if url_data.pinned {
+        let pinned_at = state.clock.now().timestamp() as u64;
+        state.rl_db.pin_url(user_id, &code, pinned_at).await?;
+    } else {
+        state.rl_db.unpin_url(user_id, &code).await?;
+    }
+    save_url(&state, &code, &url_data).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(json!({ "code": code, "pinned": url_data.pinned })),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn disable_url_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Path(code): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    set_active(&state, &request_context, code, false).await
+}
+
+#[axum::debug_handler]
+pub(crate) async fn enable_url_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Path(code): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    set_active(&state, &request_context, code, true).await
+}
+
+/// Switches a link's redirects on or off; the record and its analytics are kept either way.
+async fn set_active(
+    state: &AppState,
+    request_context: &RequestContext,
+    code: String,
+    active: bool,
+) -> Result<Json<ApiResponse<serde_json::Value>>, AppError> {
+    let mut url_data = owned_url(state, request_context, &code).await?;
+    // A moderator's tombstone isn't the owner's to lift
+    if url_data.disabled_at.is_some() {
+        return Err(AppError::Gone("Link has been disabled".into()));
+    }
+    if url_data.is_active != active {
+        url_data.is_active = active;
+        save_url(state, &code, &url_data).await?;
+        info!("Code {} {} by its owner", code, if active { "enabled" } else { "disabled" });
+    }
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(json!({ "code": code, "is_active": url_data.is_active })),
+        error: None,
+    }))
+}
+
+/// Offers a link to another account; ownership only moves once they accept.
+#[axum::debug_handler]
+pub(crate) async fn request_transfer_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Path(code): Path<String>,
+    Json(req): Json<TransferRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    req.validate().map_err(AppError::Validation)?;
+    let mut url_data = owned_url(&state, &request_context, &code).await?;
+    let recipient = state
+        .rl_db
+        .get_user(&req.to)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Recipient not found".into()))?;
+    if url_data.user_id.as_deref() == Some(recipient.id.as_str()) {
+        return Err(AppError::BadRequest("Link already belongs to this account".into()));
+    }
+
+    let now = state.clock.now().to_rfc3339();
+    url_data.pending_transfer = Some(PendingTransfer { to_user_id: recipient.id.clone(), requested_at: now.clone() });
+    save_url(&state, &code, &url_data).await?;
+
+    let sender = request_context.username.as_deref().unwrap_or("Another user");
+    let notification = Notification {
+        id: cuid2(),
+        code: Some(code.clone()),
+        message: format!("{} wants to transfer /{} to you", sender, code),
+        created_at: now,
+    };
+    state.rl_db.add_notification(&recipient.id, &notification).await?;
+    info!("Transfer of code {} offered to user {}", code, recipient.id);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(json!({ "code": code, "to_user_id": recipient.id })),
+        error: None,
+    }))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn accept_transfer_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Path(code): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = require_user(&request_context)?;
+    let mut url_data = load_url(&state, &code).await?;
+    let offered_to_caller = url_data
+        .pending_transfer
+        .as_ref()
+        .is_some_and(|transfer| transfer.to_user_id == user_id);
+    let Some(from_user_id) = url_data.user_id.clone().filter(|_| offered_to_caller) else {
+        return Err(AppError::NotFound("No pending transfer for this link".into()));
+    };
+
+    if url_data.pinned {
+        // Pins are personal; the new owner decides their own
+        state.rl_db.unpin_url(&from_user_id, &code).await?;
+        url_data.pinned = false;
+    }
+    let terms: Vec<String> = search_terms(&code, &url_data).into_iter().collect();
+    state.rl_db.remove_search_terms(&from_user_id, &code, &terms).await?;
+    if !url_data.tags.is_empty() {
+        // Tags file the link in the previous owner's folders
+        state.rl_db.untag_url(&from_user_id, &code, &url_data.tags).await?;
+        url_data.tags.clear();
+    }
+    url_data.user_id = Some(user_id.to_string());
+    url_data.pending_transfer = None;
+    // Campaigns belong to the previous owner: their UTM defaults stop applying and their
+    // analytics stop counting the new owner's clicks
+    if let Some(campaign_id) = url_data.campaign_id.take()
+        && let Some(campaign) = state.campaigns.get(&campaign_id).await?
+    {
+        let mut campaign = (*campaign).clone();
+        campaign.codes.retain(|campaign_code| *campaign_code != code);
+        state.campaigns.save(campaign).await?;
+    }
+    state.rl_db.transfer_url(&code, &url_data, &from_user_id).await?;
+    state.cache.evict_local(&code).await;
+    let terms: Vec<String> = search_terms(&code, &url_data).into_iter().collect();
+    state.rl_db.index_search_terms(user_id, &code, &terms).await?;
+
+    let notification = Notification {
+        id: cuid2(),
+        code: Some(code.clone()),
+        message: format!("Your transfer of /{} was accepted", code),
+        created_at: state.clock.now().to_rfc3339(),
+    };
+    state.rl_db.add_notification(&from_user_id, &notification).await?;
+    info!("Code {} transferred from user {} to user {}", code, from_user_id, user_id);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(json!({ "code": code, "user_id": user_id })),
+        error: None,
+    }))
+}
+
+/// Withdraws a pending offer; either the owner or the recipient may call it.
+#[axum::debug_handler]
+pub(crate) async fn cancel_transfer_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Path(code): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = require_user(&request_context)?;
+    let mut url_data = load_url(&state, &code).await?;
+    let involved = url_data.user_id.as_deref() == Some(user_id)
+        || url_data.pending_transfer.as_ref().is_some_and(|transfer| transfer.to_user_id == user_id);
+    if url_data.pending_transfer.is_none() || !involved {
+        return Err(AppError::NotFound("No pending transfer for this link".into()));
+    }
+
+    url_data.pending_transfer = None;
+    save_url(&state, &code, &url_data).await?;
+    info!("Transfer of code {} cancelled by user {}", code, user_id);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(json!({ "code": code })),
+        error: None,
+    }))
+}
+
 #[axum::debug_handler]
 pub async fn delete_shorten_handler(
     State(state): State<AppState>,
@@ -123,13 +816,7 @@ pub async fn delete_shorten_handler(
     })?;
 
     // Fetch URL data to verify ownership
-    let url_data_json = state
-        .cache
-        .get(&code)
-        .await
-        .map_err(|_| AppError::NotFound("URL not found".into()))?;
-    let url_data: UrlData = serde_json::from_str(&url_data_json)
-        .map_err(|e| AppError::Internal(e.to_string()))?;
+    let url_data = load_url(&state, &code).await?;
 
     // Check ownership
     match url_data.user_id {
@@ -139,6 +826,8 @@ pub async fn delete_shorten_handler(
                 .rl_db
                 .delete_url(&code, Some(&user_id), "")
                 .await?;
+            // Otherwise the hot set, L1, L2 and Sled keep serving it until it expires there
+            state.cache.purge(&code).await?;
             info!("Deleted URL code {} for user {}", code, user_id);
             Ok(Json(ApiResponse {
                 success: true,
@@ -159,4 +848,337 @@ pub async fn delete_shorten_handler(
             Err(AppError::Forbidden("URL has no owner".into()))
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::quota::PlanConfig,
+        handlers::campaigns::{campaign_analytics_handler, create_campaign_handler},
+        test_util::{self, MockStorage},
+        types::User,
+    };
+
+    fn shorten_request(url: &str) -> ShortenRequest {
+        serde_json::from_value(json!({ "url": url })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn links_past_the_plan_quota_are_refused() {
+        let mut config = Settings::default();
+        config.quota.plans.insert("free".into(), PlanConfig { max_links: Some(1) });
+        config.quota.plans.insert("pro".into(), PlanConfig { max_links: None });
+        let storage = Arc::new(MockStorage::new());
+        let user = test_util::user("alice");
+        storage.set_user(&user).await.unwrap();
+        let state = test_util::test_app(config, storage.clone()).await.state;
+        let context = RequestContext { user_id: Some(user.id.clone()), ..Default::default() };
+
+        create_short_link(&state, &context, shorten_request("https://example.com/one")).await.unwrap();
+        match create_short_link(&state, &context, shorten_request("https://example.com/two")).await {
+            Err(AppError::QuotaExceeded { plan, limit, used, .. }) => assert_eq!((plan.as_str(), limit, used), ("free", 1, 1)),
+            other => panic!("expected QuotaExceeded, got {:?}", other.map(|response| response.code)),
+        }
+
+        storage.set_user(&User { plan: Some("pro".into()), ..user }).await.unwrap();
+        create_short_link(&state, &context, shorten_request("https://example.com/two")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn safe_browsing_verdicts_reject_or_quarantine_flagged_destinations() {
+        let mut config = Settings::default();
+        config.security.safe_browsing_api_key = Some("test-key".into());
+        let state = test_util::test_state_with(config.clone()).await;
+        let context = RequestContext { user_id: Some("user-alice".into()), ..Default::default() };
+        let (flagged, clean) = ("https://login.example.net/verify", "https://example.com/docs");
+        state.threat_intel.remember_verdict(flagged, Verdict::Malicious("SOCIAL_ENGINEERING".into())).await;
+        state.threat_intel.remember_verdict(clean, Verdict::Clean).await;
+
+        match create_short_link(&state, &context, shorten_request(flagged)).await {
+            Err(AppError::Blocked(message)) => assert!(message.contains("SOCIAL_ENGINEERING"), "{}", message),
+            other => panic!("expected Blocked, got {:?}", other.map(|response| response.code)),
+        }
+        assert_eq!(state.rl_db.count_urls(Some("user-alice")).await.unwrap(), 0);
+        let code = create_short_link(&state, &context, shorten_request(clean)).await.unwrap().code;
+        let url_data = state.cache.get_url_data(&code).await.unwrap();
+        assert_eq!((url_data.reputation.as_deref(), url_data.quarantined), (Some("clean"), false));
+
+        // A second state can't share the first one's sled stores
+        let dir = std::env::temp_dir().join(format!("hyperlinkr-quarantine-{}", std::process::id()));
+        config.cache.sled_path = dir.join("cache.sled").display().to_string();
+        config.analytics.sled_path = dir.join("analytics.sled").display().to_string();
+        config.security.safe_browsing_quarantine = Some(true);
+        let state = test_util::test_state_with(config).await;
+        state.threat_intel.remember_verdict(flagged, Verdict::Malicious("SOCIAL_ENGINEERING".into())).await;
+        let code = create_short_link(&state, &context, shorten_request(flagged)).await.unwrap().code;
+        let url_data = state.cache.get_url_data(&code).await.unwrap();
+        assert_eq!((url_data.reputation.as_deref(), url_data.quarantined), (Some("SOCIAL_ENGINEERING"), true));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    struct AcceptsPass;
+
+    #[async_trait::async_trait]
+    impl crate::services::captcha::ChallengeVerifier for AcceptsPass {
+        async fn verify(&self, token: &str, _remote_ip: Option<&str>) -> Result<bool, AppError> {
+            Ok(token == "pass")
+        }
+    }
+
+    #[tokio::test]
+    async fn captchas_are_required_from_anonymous_and_flagged_callers() {
+        crate::middleware::rate_limit::init_rate_limit_middleware();
+        let mut config = Settings::default();
+        config.trial.enabled = true;
+        config.security.safe_browsing_api_key = Some("test-key".into());
+        let mut state = test_util::test_state_with(config.clone()).await;
+        state.captcha = Arc::new(CaptchaGate::with_verifier(&config, Some(Arc::new(AcceptsPass))));
+        let shorten = |context: RequestContext, url: &str, token: Option<&str>| {
+            let req = serde_json::from_value(json!({ "url": url, "captcha_token": token })).unwrap();
+            let state = state.clone();
+            async move { create_short_link(&state, &context, req).await }
+        };
+        let anonymous = RequestContext { ip: Some("203.0.113.20".into()), ..Default::default() };
+        let alice = RequestContext { user_id: Some("user-alice".into()), ip: Some("203.0.113.21".into()), ..Default::default() };
+
+        assert!(matches!(shorten(anonymous.clone(), "https://example.com/a", None).await, Err(AppError::Forbidden(_))));
+        assert!(matches!(shorten(anonymous.clone(), "https://example.com/a", Some("fail")).await, Err(AppError::Forbidden(_))));
+        shorten(anonymous, "https://example.com/a", Some("pass")).await.unwrap();
+        shorten(alice.clone(), "https://example.com/b", None).await.unwrap();
+
+        // A rejected destination flags the caller's IP, signed in or not
+        let flagged = "https://login.example.net/verify";
+        state.threat_intel.remember_verdict(flagged, Verdict::Malicious("SOCIAL_ENGINEERING".into())).await;
+        assert!(matches!(shorten(alice.clone(), flagged, None).await, Err(AppError::Blocked(_))));
+        assert!(matches!(shorten(alice.clone(), "https://example.com/c", None).await, Err(AppError::Forbidden(_))));
+        shorten(alice, "https://example.com/c", Some("pass")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn anonymous_trial_links_expire_and_are_limited_per_ip() {
+        crate::middleware::rate_limit::init_rate_limit_middleware();
+        let mut config = Settings::default();
+        config.trial.enabled = true;
+        config.trial.links_per_ip = 2;
+        let storage = Arc::new(MockStorage::new());
+        let state = test_util::test_app(config, storage).await.state;
+        let context = RequestContext { ip: Some("203.0.113.7".into()), ..Default::default() };
+
+        let aliased = serde_json::from_value(json!({ "url": "https://example.com/a", "custom_alias": "mine" })).unwrap();
+        assert!(matches!(create_short_link(&state, &context, aliased).await, Err(AppError::Unauthorized(_))));
+
+        let created = create_short_link(&state, &context, shorten_request("https://example.com/one")).await.unwrap();
+        let url_data = state.cache.get_url_data(&created.code).await.unwrap();
+        assert!(url_data.trial);
+        let expires_at = chrono::DateTime::parse_from_rfc3339(url_data.expires_at.as_deref().unwrap()).unwrap();
+        assert!((1439..=1440).contains(&(expires_at.with_timezone(&chrono::Utc) - state.clock.now()).num_minutes()));
+        assert_eq!(created.expiration_date, url_data.expires_at);
+
+        create_short_link(&state, &context, shorten_request("https://example.com/two")).await.unwrap();
+        assert!(matches!(
+            create_short_link(&state, &context, shorten_request("https://example.com/three")).await,
+            Err(AppError::RateLimitExceededWithResponse(_))
+        ));
+        let elsewhere = RequestContext { ip: Some("198.51.100.1".into()), ..Default::default() };
+        create_short_link(&state, &elsewhere, shorten_request("https://example.com/three")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn owners_can_repoint_and_change_the_expiry_in_place() {
+        let state = test_util::test_state().await;
+        let context = RequestContext { user_id: Some("user-alice".into()), ..Default::default() };
+        let code = create_short_link(&state, &context, shorten_request("https://example.com/old")).await.unwrap().code;
+        let update = |body: serde_json::Value| {
+            update_destination_handler(State(state.clone()), Extension(context.clone()), Path(code.clone()), Json(serde_json::from_value(body).unwrap()))
+        };
+
+        let expires_at = (state.clock.now() + chrono::Duration::days(7)).to_rfc3339();
+        update(json!({ "url": "https://example.com/new", "expiration_date": expires_at })).await.unwrap();
+        let url_data = state.cache.get_url_data(&code).await.unwrap();
+        assert_eq!((url_data.long_url.as_str(), url_data.expires_at.as_deref()), ("https://example.com/new", Some(expires_at.as_str())));
+        assert_eq!(url_data.history[0].url, "https://example.com/old");
+
+        update(json!({ "expiration_date": null })).await.unwrap();
+        let url_data = state.cache.get_url_data(&code).await.unwrap();
+        assert_eq!((url_data.long_url.as_str(), url_data.expires_at.as_deref()), ("https://example.com/new", None));
+        assert!(matches!(update(json!({})).await, Err(AppError::BadRequest(_))));
+        assert!(matches!(update(json!({ "expiration_date": "2000-01-01T00:00:00Z" })).await, Err(AppError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn destination_history_keeps_the_latest_changes_for_the_owner_only() {
+        let state = test_util::test_state().await;
+        let owner = RequestContext { user_id: Some("user-alice".into()), ..Default::default() };
+        let code = create_short_link(&state, &owner, shorten_request("https://example.com/v0")).await.unwrap().code;
+        let repoint = |url: String| {
+            let req = serde_json::from_value(json!({ "url": url })).unwrap();
+            update_destination_handler(State(state.clone()), Extension(owner.clone()), Path(code.clone()), Json(req))
+        };
+        for version in 1..=MAX_DESTINATION_HISTORY + 2 {
+            repoint(format!("https://example.com/v{}", version)).await.unwrap();
+        }
+        let latest = format!("https://example.com/v{}", MAX_DESTINATION_HISTORY + 2);
+        assert!(matches!(repoint(latest.clone()).await, Err(AppError::Conflict(_))));
+
+        let history = |context: RequestContext| destination_history_handler(State(state.clone()), Extension(context), Path(code.clone()));
+        let stranger = RequestContext { user_id: Some("user-bob".into()), ..Default::default() };
+        assert!(matches!(history(stranger).await, Err(AppError::NotFound(_))));
+        let response = history(owner.clone()).await.unwrap().into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["data"]["current"], latest.as_str());
+        let urls: Vec<&str> = body["data"]["history"].as_array().unwrap().iter().map(|change| change["url"].as_str().unwrap()).collect();
+        assert_eq!(urls.len(), MAX_DESTINATION_HISTORY);
+        // The two oldest destinations fell off; the rest are oldest first
+        assert_eq!(urls[0], "https://example.com/v2");
+        assert_eq!(urls[MAX_DESTINATION_HISTORY - 1], format!("https://example.com/v{}", MAX_DESTINATION_HISTORY + 1));
+    }
+
+    #[tokio::test]
+    async fn tags_index_links_for_their_owner_until_changed() {
+        let state = test_util::test_state().await;
+        let context = RequestContext { user_id: Some("user-alice".into()), ..Default::default() };
+        let tagged = serde_json::from_value(json!({ "url": "https://example.com/q3", "tags": ["clients/acme", "q3"] })).unwrap();
+        let code = create_short_link(&state, &context, tagged).await.unwrap().code;
+        create_short_link(&state, &context, shorten_request("https://example.com/untagged")).await.unwrap();
+        assert_eq!(state.rl_db.list_tagged_codes("user-alice", "clients/acme").await.unwrap(), [code.clone()]);
+        assert!(state.rl_db.list_tagged_codes("user-alice", "clients").await.unwrap().is_empty());
+
+        let retag = serde_json::from_value(json!({ "tags": ["q4"] })).unwrap();
+        update_destination_handler(State(state.clone()), Extension(context.clone()), Path(code.clone()), Json(retag)).await.unwrap();
+        assert!(state.rl_db.list_tagged_codes("user-alice", "q3").await.unwrap().is_empty());
+        assert_eq!(state.rl_db.list_tagged_codes("user-alice", "q4").await.unwrap(), [code.clone()]);
+        assert_eq!(state.cache.get_url_data(&code).await.unwrap().tags, ["q4"]);
+
+        let duplicate = serde_json::from_value(json!({ "url": "https://example.com/dup", "tags": ["a", "a"] })).unwrap();
+        assert!(matches!(create_short_link(&state, &context, duplicate).await, Err(AppError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn search_index_follows_destination_and_tag_changes() {
+        let state = test_util::test_state().await;
+        let context = RequestContext { user_id: Some("user-alice".into()), ..Default::default() };
+        let req = serde_json::from_value(json!({ "url": "https://shop.example.com/sale", "tags": ["Summer"] })).unwrap();
+        let code = create_short_link(&state, &context, req).await.unwrap().code;
+        for term in [code.to_lowercase().as_str(), "shop", "example", "shop.example.com", "summer"] {
+            assert_eq!(state.rl_db.search_codes("user-alice", term).await.unwrap(), [code.clone()], "term {}", term);
+        }
+
+        let repoint = serde_json::from_value(json!({ "url": "https://blog.other.org/post" })).unwrap();
+        update_destination_handler(State(state.clone()), Extension(context.clone()), Path(code.clone()), Json(repoint)).await.unwrap();
+        assert!(state.rl_db.search_codes("user-alice", "example").await.unwrap().is_empty());
+        assert_eq!(state.rl_db.search_codes("user-alice", "other").await.unwrap(), [code.clone()]);
+        assert_eq!(state.rl_db.search_codes("user-alice", "summer").await.unwrap(), [code]);
+    }
+
+    #[tokio::test]
+    async fn generated_codes_skip_reserved_ones_unless_claimed() {
+        let mut config = Settings::default();
+        // A single shard makes the sequence predictable
+        config.codegen.shard_bits = 0;
+        let peek = CodeGenerator::new(&config);
+        let state = test_util::test_state_with(config).await;
+        let reserved: Vec<String> = (0..2).map(|_| peek.next().unwrap().to_string()).collect();
+        state.rl_db.reserve_codes("user-bob", &reserved, 3600).await.unwrap();
+        let alice = RequestContext { user_id: Some("user-alice".into()), ..Default::default() };
+
+        let created = create_short_link(&state, &alice, shorten_request("https://example.com/a")).await.unwrap();
+        assert_eq!(created.code, peek.next().unwrap().as_str());
+        let claim = |alias: &str| serde_json::from_value(json!({ "url": "https://example.com/b", "custom_alias": alias })).unwrap();
+        assert!(matches!(create_short_link(&state, &alice, claim(&reserved[0])).await, Err(AppError::Conflict(_))));
+
+        let bob = RequestContext { user_id: Some("user-bob".into()), ..Default::default() };
+        create_short_link(&state, &bob, claim(&reserved[0])).await.unwrap();
+        assert_eq!(state.rl_db.get_code_reservation(&reserved[0]).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn accepted_transfers_move_the_link_between_owners_indexes() {
+        let state = test_util::test_state().await;
+        state.rl_db.set_user(&test_util::user("bob")).await.unwrap();
+        let alice = RequestContext { user_id: Some("user-alice".into()), username: Some("alice".into()), ..Default::default() };
+        let bob = RequestContext { user_id: Some("user-bob".into()), ..Default::default() };
+        let req = serde_json::from_value(json!({ "url": "https://example.com/deck", "tags": ["q3"] })).unwrap();
+        let code = create_short_link(&state, &alice, req).await.unwrap().code;
+        toggle_pin_handler(State(state.clone()), Extension(alice.clone()), Path(code.clone())).await.unwrap();
+        let accept = |context: &RequestContext| accept_transfer_handler(State(state.clone()), Extension(context.clone()), Path(code.clone()));
+
+        assert!(matches!(accept(&bob).await, Err(AppError::NotFound(_))));
+        let offer = TransferRequest { to: "bob@example.com".into() };
+        request_transfer_handler(State(state.clone()), Extension(alice.clone()), Path(code.clone()), Json(offer)).await.unwrap();
+        assert!(matches!(accept(&alice).await, Err(AppError::NotFound(_))));
+        accept(&bob).await.unwrap();
+
+        let url_data = state.cache.get_url_data(&code).await.unwrap();
+        assert_eq!(url_data.user_id.as_deref(), Some("user-bob"));
+        assert!(url_data.pending_transfer.is_none() && url_data.tags.is_empty());
+        assert!(state.rl_db.list_user_codes("user-alice").await.unwrap().is_empty());
+        assert_eq!(state.rl_db.list_user_codes("user-bob").await.unwrap(), [code.clone()]);
+        assert!(state.rl_db.list_pinned("user-alice").await.unwrap().is_empty());
+        assert!(state.rl_db.list_tagged_codes("user-alice", "q3").await.unwrap().is_empty());
+        assert!(state.rl_db.search_codes("user-alice", "example").await.unwrap().is_empty());
+        assert_eq!(state.rl_db.search_codes("user-bob", "example").await.unwrap(), [code.clone()]);
+        let notifications = state.rl_db.list_notifications("user-alice", 10).await.unwrap();
+        assert!(notifications.iter().any(|notification| notification.message.contains("was accepted")));
+        // The offer was consumed
+        assert!(matches!(accept(&bob).await, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn accepted_transfers_leave_the_previous_owners_campaign() {
+        let state = test_util::test_state().await;
+        state.rl_db.set_user(&test_util::user("bob")).await.unwrap();
+        let alice = RequestContext { user_id: Some("user-alice".into()), ..Default::default() };
+        let bob = RequestContext { user_id: Some("user-bob".into()), ..Default::default() };
+        let code = create_short_link(&state, &alice, shorten_request("https://example.com/launch")).await.unwrap().code;
+        let req = serde_json::from_value(json!({ "name": "Launch", "codes": [code] })).unwrap();
+        let response = create_campaign_handler(State(state.clone()), Extension(alice.clone()), Json(req)).await.unwrap().into_response();
+        let body: serde_json::Value = serde_json::from_slice(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        let campaign_id = body["data"]["id"].as_str().unwrap().to_string();
+
+        let offer = TransferRequest { to: "bob@example.com".into() };
+        request_transfer_handler(State(state.clone()), Extension(alice.clone()), Path(code.clone()), Json(offer)).await.unwrap();
+        accept_transfer_handler(State(state.clone()), Extension(bob), Path(code.clone())).await.unwrap();
+
+        assert!(state.cache.get_url_data(&code).await.unwrap().campaign_id.is_none());
+        assert!(state.campaigns.get(&campaign_id).await.unwrap().unwrap().codes.is_empty());
+        let response = campaign_analytics_handler(State(state.clone()), Extension(alice), Path(campaign_id)).await.unwrap().into_response();
+        let body: serde_json::Value = serde_json::from_slice(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["data"]["codes"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn listing_pages_are_stable_and_lead_with_pinned_links() {
+        let state = test_util::test_state().await;
+        let context = RequestContext { user_id: Some("user-alice".into()), ..Default::default() };
+        let mut codes = Vec::new();
+        for n in 0..5 {
+            codes.push(create_short_link(&state, &context, shorten_request(&format!("https://example.com/{}", n))).await.unwrap().code);
+        }
+        let pinned = codes[3].clone();
+        toggle_pin_handler(State(state.clone()), Extension(context.clone()), Path(pinned.clone())).await.unwrap();
+
+        let mut listed = Vec::new();
+        for page in [1, 2, 3] {
+            let response = list_urls_handler(
+                State(state.clone()),
+                Extension(context.clone()),
+                OriginalUri("/v1/urls".parse().unwrap()),
+                Query(PageQuery { page: Some(page), per_page: Some(2) }),
+                Query(UrlFilterQuery { tag: None }),
+            )
+            .await
+            .unwrap()
+            .into_response();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["data"]["total_items"], 5);
+            listed.extend(body["data"]["items"].as_array().unwrap().iter().map(|item| item["code"].as_str().unwrap().to_string()));
+        }
+        assert_eq!(listed[0], pinned);
+        let mut rest = codes.clone();
+        rest.retain(|code| *code != pinned);
+        rest.sort();
+        assert_eq!(listed[1..], rest[..]);
+    }
+}
@@ -0,0 +1,76 @@
+use axum::{
+    extract::{Json, Query, State},
+    Extension,
+    response::IntoResponse,
+};
+use crate::{
+    errors::AppError,
+    handlers::shorten::AppState,
+    middleware::RequestContext,
+    services::usage::{key_subject, user_subject},
+    types::{ApiKeyUsage, ApiResponse, RateLimitUsage, UsageQuery, UsageReport},
+};
+
+// Buckets of the per-user rate limiter, see middleware::rate_limit
+const RATE_CLASSES: [&str; 3] = ["shorten", "redirect", "other"];
+
+fn require_user(request_context: &RequestContext) -> Result<&str, AppError> {
+    request_context
+        .user_id
+        .as_deref()
+        .ok_or_else(|| AppError::Unauthorized("Authentication required for /v1/usage".into()))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn usage_handler(
+    State(state): State<AppState>,
+    Extension(request_context): Extension<RequestContext>,
+    Query(query): Query<UsageQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = require_user(&request_context)?;
+    let report = usage_report(&state, user_id, query.days).await?;
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(report),
+        error: None,
+    }))
+}
+
+/// Daily counts for `user_id` and each of their API keys, plus where they stand against the
+/// per-user rate limits right now.
+pub(crate) async fn usage_report(state: &AppState, user_id: &str, days: Option<u64>) -> Result<UsageReport, AppError> {
+    let days = state.usage.lookback(days);
+    let mut api_keys = Vec::new();
+    for key in state.rl_db.list_api_keys(user_id).await? {
+        let days = state.usage.daily(&key_subject(&key.id), days).await?;
+        api_keys.push(ApiKeyUsage { id: key.id, name: key.name, days });
+    }
+
+    let config = &state.config.rate_limit;
+    let used = state.usage.current_window(user_id).await?;
+    let rate_limits = RATE_CLASSES
+        .iter()
+        .map(|class| {
+            let limit = if *class == "shorten" {
+                config.shorten_requests_per_minute
+            } else {
+                config.redirect_requests_per_minute
+            } as u64;
+            let used = used.get(*class).copied().unwrap_or(0);
+            RateLimitUsage {
+                endpoint: class.to_string(),
+                limit,
+                used,
+                remaining: limit.saturating_sub(used),
+                window_seconds: state.usage.window_secs(),
+            }
+        })
+        .collect();
+
+    Ok(UsageReport {
+        user_id: user_id.to_string(),
+        days: state.usage.daily(&user_subject(user_id), days).await?,
+        api_keys,
+        rate_limits,
+    })
+}
@@ -5,4 +5,8 @@ pub mod handlers;
 pub mod middleware;
 pub mod errors;
 pub  mod  clock;
-pub mod validator;
\ No newline at end of file
+pub mod validator;
+pub mod cli;
+pub mod app;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
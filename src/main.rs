@@ -1,85 +1,59 @@
-use axum::{routing::{get, post}, Router};
 use axum_server::{bind, Handle};
+use clap::Parser;
 use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tracing::info;
 
 use hyperlinkr::{
-    clock::SystemClock,
-    config::settings::load,
-    handlers::{analytics::metrics_handler, redirect::redirect_handler, shorten::{shorten_handler, AppState}},
-    middleware::{rate_limit::rate_limit_middleware, device_info::device_info_middleware},
-    services::{
-        analytics::AnalyticsService,
-        cache::{cache::CacheService, circuit_breaker::CircuitBreaker},
-        codegen::generator::CodeGenerator,
-        geo_lookup,
-        storage::dragonfly::DatabaseClient,
-    },
+    app::Builder,
+    cli::Cli,
+    config::settings::load_from,
+    services::{geo_lookup, log_level, migrations, ua_parser},
 };
 
-use hyperlinkr::handlers::shorten::list_urls_handler;
-use hyperlinkr::handlers::analytics::analytics_code_handler;
-
 #[tokio::main]
 async fn main() {
-    let config = Arc::new(load().expect("Failed to load configuration"));
+    let cli = Cli::parse();
+    let config = match load_from(cli.config.as_deref(), cli.port) {
+        Ok(config) => Arc::new(config),
+        Err(e) if cli.check_config => {
+            eprintln!("Invalid configuration: {}", e);
+            std::process::exit(1);
+        }
+        Err(e) => panic!("Failed to load configuration: {}", e),
+    };
+    if cli.check_config {
+        println!("Configuration OK ({} environment, port {})", config.environment, config.app_port);
+        return;
+    }
     // dbg!(&config);
-    tracing_subscriber::fmt::init(); // Must be after load() to use RUST_LOG
+    #[cfg(feature = "tokio-console")]
+    console_subscriber::init(); // Also installs a fmt layer honouring RUST_LOG
+    #[cfg(not(feature = "tokio-console"))]
+    log_level::init_tracing(); // Must be after load() to use RUST_LOG
 
     // Initialize geo lookup service
     geo_lookup::init_geo_lookup(&config)
         .expect("Failed to initialize geo lookup service");
+    ua_parser::init_ua_parser(&config)
+        .expect("Failed to load User-Agent regexes");
 
-    let cache = Arc::new(CacheService::new(&config).await);
-    let codegen = Arc::new(CodeGenerator::new(&config));
-
-    let clock = Arc::new(SystemClock);
-
-    let analytics_cb = Arc::new(CircuitBreaker::new(
-        config.database_urls.clone(),
-        config.cache.max_failures,
-        Duration::from_secs(config.cache.retry_interval_secs),
-    ));
-    let _analytics_db = Arc::new(
-        DatabaseClient::new(&config, Arc::clone(&analytics_cb))
-            .await
-            .expect("Failed to create Analytics DB client"),
-    );
-    let analytics = Arc::new(AnalyticsService::new(&config, analytics_cb.clone(), SystemClock).await);
-
-    let rl_cb = Arc::new(CircuitBreaker::new(
-        config.database_urls.clone(),
-        config.cache.max_failures,
-        Duration::from_secs(config.cache.retry_interval_secs),
-    ));
-    let rl_db = Arc::new(
-        DatabaseClient::new(&config, Arc::clone(&rl_cb))
-            .await
-            .expect("Failed to create Rate-Limit DB client"),
-    );
-
-    let state = AppState {
-        config: Arc::clone(&config),
-        cache: Arc::clone(&cache),
-        codegen: Arc::clone(&codegen),
-        analytics: Arc::clone(&analytics),
-        rl_db: Arc::clone(&rl_db),
-        clock: Arc::clone(&clock),
-    };
-
-    let v1_routes = Router::new()
-        .route("/urls", get(list_urls_handler))
-        .route("/shorten", post(shorten_handler))
-        .route("/redirect/{code}", get(redirect_handler))
-        .route("/analytics/{code}", get(analytics_code_handler))
-        .route("/metrics", get(metrics_handler));
+    // Migrations only need storage; skip samplers and the link checker
+    let app = Builder::new(Arc::clone(&config))
+        .background_tasks(!cli.migrate)
+        .build()
+        .await
+        .expect("Failed to build application");
 
-    let app = Router::new()
-        .nest("/v1", v1_routes)
-        
-        .layer(axum::middleware::from_fn_with_state(state.clone(), rate_limit_middleware))
-        .layer(axum::middleware::from_fn(device_info_middleware))
-        .with_state(state);
+    if cli.migrate {
+        migrations::run(app.state.rl_db.as_ref()).await.expect("Storage migration failed");
+        return;
+    }
+    if let Some(limit) = cli.warmup {
+        match app.state.cache.warmup_from_storage(limit).await {
+            Ok(count) => info!("Warmed {} links", count),
+            Err(e) => tracing::warn!("Cache warmup failed: {}", e),
+        }
+    }
 
     let addr: SocketAddr = format!("0.0.0.0:{}", config.app_port)
         .parse()
@@ -96,7 +70,7 @@ async fn main() {
 
     bind(addr)
         .handle(handle)
-        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .serve(app.router.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
 }
@@ -5,27 +5,45 @@ use axum::{
 };
 use once_cell::sync::OnceCell;
 use std::collections::HashSet;
-use jsonwebtoken::{decode, DecodingKey, Validation};
 use tracing::warn;
+use cuid::cuid2;
 use crate::{
-    clock::Clock,
     errors::AppError,
     handlers::shorten::AppState,
-    services::storage::storage::Storage,
-    types::AuthToken,
     middleware::RequestContext,
+    services::api_keys::{hash_key, key_id, required_scope},
+    types::{ApiKey, AuditEvent},
 };
 
+const API_KEY_HEADER: &str = "x-api-key";
+const QUICK_SHORTEN_PATH: &str = "/v1/quick";
+
 static PUBLIC_ENDPOINTS: OnceCell<HashSet<&'static str>> = OnceCell::new();
+// Credentials are honoured when presented, but anonymous callers are let through
+static OPTIONAL_AUTH_ENDPOINTS: OnceCell<HashSet<&'static str>> = OnceCell::new();
 
 pub fn init_auth_middleware() {
     PUBLIC_ENDPOINTS.get_or_init(|| {
         HashSet::from([
             "/v1/redirect",
+            "/v1/report",
+            "/v1/metrics",
             "/v1/auth/login",
             "/v1/auth/register",
+            "/.well-known/jwks.json",
+            "/robots.txt",
         ])
     });
+    OPTIONAL_AUTH_ENDPOINTS.get_or_init(|| HashSet::from(["/v1/shorten"]));
+}
+
+/// Matches `path` against an endpoint set, treating each entry as a prefix for its sub-paths.
+fn matches_endpoint(endpoints: &OnceCell<HashSet<&'static str>>, path: &str) -> bool {
+    endpoints.get().is_some_and(|endpoints| {
+        endpoints.iter().any(|endpoint| {
+            path.strip_prefix(endpoint).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    })
 }
 
 pub async fn auth_middleware(
@@ -34,7 +52,13 @@ pub async fn auth_middleware(
     next: Next,
 ) -> Result<Response<axum::body::Body>, AppError> {
     let path = req.uri().path();
-    if PUBLIC_ENDPOINTS.get().map_or(false, |endpoints| endpoints.contains(path)) {
+    if matches_endpoint(&PUBLIC_ENDPOINTS, path) {
+        return Ok(next.run(req).await);
+    }
+    let has_credentials = req.headers().contains_key(API_KEY_HEADER)
+        || req.headers().contains_key(header::AUTHORIZATION)
+        || query_api_key(&req).is_some();
+    if !has_credentials && matches_endpoint(&OPTIONAL_AUTH_ENDPOINTS, path) {
         return Ok(next.run(req).await);
     }
 
@@ -44,6 +68,35 @@ pub async fn auth_middleware(
         .cloned()
         .unwrap_or_default();
 
+    // API keys authenticate as their owner, limited to the key's scopes
+    let presented_key = req
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| query_api_key(&req));
+    if let Some(raw_key) = presented_key {
+        let scope = required_scope(req.method(), path)
+            .ok_or_else(|| AppError::Forbidden(format!("API keys cannot access {}", path)))?;
+        let api_key = authenticate_api_key(&state, &raw_key).await?;
+        if !api_key.scopes.iter().any(|granted| granted == scope) {
+            warn!("API key {} lacks scope {} for {}", api_key.id, scope, path);
+            return Err(AppError::Forbidden(format!("API key lacks the {} scope", scope)));
+        }
+        let user = state
+            .rl_db
+            .get_user(&api_key.user_id)
+            .await?
+            .ok_or_else(|| AppError::Unauthorized("Invalid API key".into()))?;
+        context.user_id = Some(user.id);
+        context.email = Some(user.email);
+        context.username = Some(user.username);
+        context.is_admin = false;
+        context.api_key_id = Some(api_key.id);
+        req.extensions_mut().insert(context);
+        return Ok(next.run(req).await);
+    }
+
     // Extract and validate JWT
     let token = req
         .headers()
@@ -61,20 +114,16 @@ pub async fn auth_middleware(
         return Err(AppError::Unauthorized("Token is blacklisted".into()));
     }
 
-    // Decode JWT
-    let token_data = decode::<AuthToken>(
-        token,
-        &DecodingKey::from_secret(state.config.security.jwt_secret.as_ref()),
-        &Validation::default(),
-    ).map_err(|e| {
-        warn!("Invalid JWT for {}: {}", path, e);
-        AppError::Unauthorized("Invalid JWT".into())
+    // Decode JWT; signature, kid and exp are checked by the token service
+    let auth_token = state.tokens.verify(token).map_err(|e| {
+        warn!("Rejected JWT for {}: {}", path, e);
+        e
     })?;
 
-    let auth_token = token_data.claims;
-    if state.clock.now().timestamp() as u64 > auth_token.expires_at.parse::<u64>().unwrap_or(0) {
-        warn!("Expired JWT for {}", path);
-        return Err(AppError::Unauthorized("Expired JWT".into()));
+    // Sessions revoked via /v1/auth/sessions/revoke-all are blacklisted by jti
+    if state.rl_db.is_token_blacklisted(&auth_token.jti).await? {
+        warn!("Revoked session used for {}", path);
+        return Err(AppError::Unauthorized("Session has been revoked".into()));
     }
 
     // Populate RequestContext
@@ -82,8 +131,51 @@ pub async fn auth_middleware(
     context.email = Some(auth_token.email);
     context.username = Some(auth_token.username);
     context.is_admin = auth_token.is_admin;
+    context.jti = Some(auth_token.jti);
+    context.impersonator = auth_token.impersonator;
 
     // Inject RequestContext
+    let impersonation = context.impersonator.clone().map(|admin_id| {
+        (admin_id, context.user_id.clone(), context.ip.clone(), format!("{} {}", req.method(), path))
+    });
     req.extensions_mut().insert(context);
-    Ok(next.run(req).await)
-}
\ No newline at end of file
+    let response = next.run(req).await;
+
+    // Every request made with an impersonation token lands in the audit log
+    if let Some((actor_id, acting_as, ip, action)) = impersonation {
+        let event = AuditEvent {
+            id: cuid2(),
+            actor_id,
+            acting_as,
+            action,
+            status: Some(response.status().as_u16()),
+            ip,
+            created_at: state.clock.now().to_rfc3339(),
+        };
+        if let Err(e) = state.rl_db.add_audit_event(&event).await {
+            warn!("Failed to record impersonated action {}: {}", event.action, e);
+        }
+    }
+    Ok(response)
+}
+
+/// `/v1/quick` also takes the key as `?key=`, for clients that can only build a URL.
+fn query_api_key(req: &Request<axum::body::Body>) -> Option<String> {
+    if req.uri().path() != QUICK_SHORTEN_PATH {
+        return None;
+    }
+    url::form_urlencoded::parse(req.uri().query()?.as_bytes())
+        .find(|(name, _)| name == "key")
+        .map(|(_, value)| value.into_owned())
+}
+
+async fn authenticate_api_key(state: &AppState, raw_key: &str) -> Result<ApiKey, AppError> {
+    let invalid = || AppError::Unauthorized("Invalid API key".into());
+    let id = key_id(raw_key).ok_or_else(invalid)?;
+    let api_key = state.rl_db.get_api_key(id).await?.ok_or_else(invalid)?;
+    if api_key.key_hash != hash_key(raw_key) {
+        warn!("API key {} presented with a wrong secret", id);
+        return Err(invalid());
+    }
+    Ok(api_key)
+}
@@ -1,91 +1,119 @@
 use axum::{
-  extract::ConnectInfo,
-  http::{header, Request, Response},
+  extract::{ConnectInfo, State},
+  http::{header, HeaderMap, HeaderValue, Request, Response},
   middleware::Next,
 };
+use cuid::cuid2;
 use std::net::{IpAddr, SocketAddr};
+use tracing::{warn, Instrument};
 use crate::{
   errors::AppError,
+  handlers::shorten::AppState,
   services::geo_lookup,
   middleware::RequestContext,
   services::ua_parser,
 };
 
 pub async fn device_info_middleware(
+  State(state): State<AppState>,
   ConnectInfo(addr): ConnectInfo<SocketAddr>,
-  mut req: Request<axum::body::Body>,
+  req: Request<axum::body::Body>,
   next: Next,
 ) -> Result<Response<axum::body::Body>, AppError> {
-  eprintln!("🔍 device_info_middleware called for IP: {}", addr.ip());
-  
   let ip = addr.ip().to_string();
+  let request_id = request_id(req.headers());
+  let mut context = RequestContext {
+    request_id: Some(request_id.clone()),
+    ip: Some(ip.clone()),
+    ..Default::default()
+  };
 
-  let referrer = req
-    .headers()
-    .get(header::REFERER)
+  // Redirects enrich in the handler, once the link's own privacy flag is known
+  let deferred = req.uri().path().starts_with("/v1/redirect/");
+  let (mut parts, body) = req.into_parts();
+  if !deferred && !state.config.analytics.privacy_mode.unwrap_or(false) {
+    enrich_context(&mut context, &parts.headers).await;
+  }
+
+  parts.extensions.insert(context);
+  // Every log line emitted while handling the request carries its ID
+  let span = tracing::info_span!("request", request_id = %request_id);
+  let mut response = CURRENT_REQUEST_ID
+    .scope(request_id.clone(), next.run(Request::from_parts(parts, body)).instrument(span))
+    .await;
+  if let Ok(value) = HeaderValue::from_str(&request_id) {
+    response.headers_mut().insert(REQUEST_ID_HEADER, value);
+  }
+  Ok(response)
+}
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+  static CURRENT_REQUEST_ID: String;
+}
+
+/// ID of the request being handled, for code like error responses that has no `RequestContext`.
+pub fn current_request_id() -> Option<String> {
+  CURRENT_REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Reuses the caller's (or load balancer's) x-request-id when it is sane, otherwise mints one.
+fn request_id(headers: &HeaderMap) -> String {
+  headers
+    .get(REQUEST_ID_HEADER)
     .and_then(|v| v.to_str().ok())
-    .map(str::to_owned);
+    .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+    .filter(|id| id.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':')))
+    .map(str::to_owned)
+    .unwrap_or_else(cuid2)
+}
 
-  let (user_agent, browser, os, device_type) = req
-    .headers()
-    .get(header::USER_AGENT)
+/// Fills the UA- and geo-derived fields of `context`. Privacy mode skips this entirely.
+pub async fn enrich_context(context: &mut RequestContext, headers: &HeaderMap) {
+  context.referrer = headers
+    .get(header::REFERER)
     .and_then(|v| v.to_str().ok())
-    .map(|ua| {
-      let info = ua_parser::parse_user_agent(ua);
-      (
-        Some(ua.to_owned()),
-        info.browser,
-        info.os,
-        Some(info.device_type),
-      )
-    })
-    .unwrap_or((None, None, None, None));
+    .map(str::to_owned);
 
-  // Simplified geo lookup with error handling
-  let (country, continent_code, city_name, timezone, latitude, longitude) = match ip.parse::<IpAddr>() {
-    Ok(ip_addr) => {
-      // Try geo lookup, but don't fail if it errors
-      match geo_lookup::lookup_geo(ip_addr).await {
-        Ok(Some(geo)) => (
-          geo.country_iso,
-          geo.continent_code,
-          geo.city_name,
-          geo.timezone,
-          geo.latitude,
-          geo.longitude,
-        ),
-        Ok(None) => (None, None, None, None, None, None),
-        Err(e) => {
-          // Log the error but continue processing
-          eprintln!("Geo lookup error: {}", e);
-          (None, None, None, None, None, None)
-        }
-      }
-    }
-    Err(_) => (None, None, None, None, None, None),
+  let header_str = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+  let hints = ua_parser::ClientHints {
+    brands: header_str("sec-ch-ua"),
+    platform: header_str("sec-ch-ua-platform"),
+    mobile: header_str("sec-ch-ua-mobile"),
   };
+  let raw_ua = header_str(header::USER_AGENT.as_str());
+  if raw_ua.is_some() || !hints.is_empty() {
+    let info = ua_parser::apply_client_hints(ua_parser::parse_user_agent(raw_ua.unwrap_or_default()), &hints);
+    context.user_agent = raw_ua.map(str::to_owned);
+    context.browser = info.browser;
+    context.browser_version = info.browser_version;
+    context.os = info.os;
+    context.os_version = info.os_version;
+    context.device_type = Some(info.device_type);
+    context.is_bot = info.is_bot;
+    context.bot_name = info.bot_name;
+  }
 
-  let context = RequestContext {
-    user_id: None,
-    email: None,
-    username: None,
-    is_admin: false,
-    ip: Some(ip.clone()),
-    referrer,
-    user_agent,
-    browser,
-    os,
-    device_type,
-    country,
-    continent_code,
-    city_name,
-    timezone,
-    latitude,
-    longitude,
+  // Simplified geo lookup with error handling
+  let Some(ip_addr) = context.ip.as_deref().and_then(|ip| ip.parse::<IpAddr>().ok()) else {
+    return;
   };
-
-  eprintln!("🔍 Inserting RequestContext for IP: {}", ip);
-  req.extensions_mut().insert(context);
-  eprintln!("✅ RequestContext inserted successfully");
-  Ok(next.run(req).await)
-}
\ No newline at end of file
+  // Try geo lookup, but don't fail if it errors
+  match geo_lookup::lookup_geo(ip_addr).await {
+    Ok(Some(geo)) => {
+      context.country = geo.country_iso;
+      context.continent_code = geo.continent_code;
+      context.city_name = geo.city_name;
+      context.timezone = geo.timezone;
+      context.latitude = geo.latitude;
+      context.longitude = geo.longitude;
+    }
+    Ok(None) => {}
+    Err(e) => {
+      // Log the error but continue processing
+      warn!("Geo lookup error: {}", e);
+    }
+  }
+}
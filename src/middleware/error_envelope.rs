@@ -0,0 +1,50 @@
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use crate::{
+    errors::ErrorCode,
+    middleware::device_info::current_request_id,
+    types::{ApiResponse, ErrorResponse},
+};
+
+// Plain-text rejections are a line or two; anything longer is cut off rather than buffered
+const MAX_MESSAGE_BYTES: usize = 4096;
+
+/// Wraps error responses that didn't come from `AppError`, such as axum's extractor rejections
+/// and unmatched routes, in the same envelope, keeping their status.
+pub async fn error_envelope_middleware(req: Request<Body>, next: Next) -> Response {
+    let response = next.run(req).await;
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json"));
+    if !(status.is_client_error() || status.is_server_error()) || is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let message = match to_bytes(body, MAX_MESSAGE_BYTES).await {
+        Ok(bytes) if !bytes.is_empty() => String::from_utf8_lossy(&bytes).into_owned(),
+        _ => status.canonical_reason().unwrap_or("Request failed").to_string(),
+    };
+    let body = Json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        error: Some(ErrorResponse {
+            code: ErrorCode::from_status(status),
+            message,
+            details: None,
+            request_id: current_request_id(),
+        }),
+    });
+    // The old body's length and type no longer apply
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.remove(header::CONTENT_TYPE);
+    (parts, body).into_response()
+}
@@ -1,7 +1,11 @@
 pub mod rate_limit;
 pub mod device_info;
 pub mod auth;
+pub mod tenant;
+pub mod usage;
+pub mod error_envelope;
 
+use crate::errors::AppError;
 
 #[derive(Clone, Default)]
 pub struct RequestContext {
@@ -9,16 +13,35 @@ pub struct RequestContext {
     pub email: Option<String>,        // From JWT
     pub username: Option<String>,     // From JWT
     pub is_admin: bool,               // From JWT
+    pub jti: Option<String>,          // From JWT, identifies the session
+    pub impersonator: Option<String>, // From JWT, admin acting as user_id
+    pub api_key_id: Option<String>,   // Set when the request authenticated with an API key
+    pub request_id: Option<String>,   // From x-request-id, or generated
     pub ip: Option<String>,           // From ConnectInfo
     pub referrer: Option<String>,     // From Referer header
     pub user_agent: Option<String>,   // Raw User-Agent header
     pub browser: Option<String>,      // From UA parser
+    pub browser_version: Option<String>, // From UA parser
     pub os: Option<String>,           // From UA parser
+    pub os_version: Option<String>,   // From UA parser
     pub device_type: Option<String>,  // From UA parser
+    pub is_bot: bool,                 // From UA parser
+    pub bot_name: Option<String>,     // From UA parser, e.g. "Googlebot"
     pub country: Option<String>,      // From GeoLocation.country_iso
     pub continent_code: Option<String>, // From GeoLocation
     pub city_name: Option<String>,    // From GeoLocation
     pub timezone: Option<String>,     // From GeoLocation
     pub latitude: Option<f64>,        // From GeoLocation
     pub longitude: Option<f64>,       // From GeoLocation
-}
\ No newline at end of file
+}
+
+impl RequestContext {
+    /// Refuses `action` to impersonation tokens. Support admins act as the user, but nothing
+    /// they do should outlive the impersonation or lock the account holder out.
+    pub(crate) fn reject_impersonation(&self, action: &str) -> Result<(), AppError> {
+        if self.impersonator.is_some() {
+            return Err(AppError::Forbidden(format!("Impersonation tokens cannot {}", action)));
+        }
+        Ok(())
+    }
+}
@@ -1,16 +1,18 @@
 use axum::{
     extract::{State, Extension},
-    http::{header, Request, Response, StatusCode},
+    http::{header, HeaderValue, Request, Response},
     middleware::Next,
+    response::IntoResponse,
 };
 use once_cell::sync::OnceCell;
+use serde::Deserialize;
 use prometheus::IntCounter;
 use tracing::warn;
 use crate::{
-    clock::Clock,
+    config::storage::StorageBackend,
     errors::AppError,
     handlers::shorten::AppState,
-    middleware::RequestContext, services::storage::storage::Storage,
+    middleware::RequestContext,
 };
 
 static RATE_LIMIT_EXCEEDED: OnceCell<IntCounter> = OnceCell::new();
@@ -30,8 +32,27 @@ async fn check_rate_limit(
     window: i64,
     state: &AppState,
 ) -> Result<bool, AppError> {
-    if state.config.cache.use_sled {
-        state.rl_db.rate_limit(&key, limit, window).await
+    match count_request(&key, limit, window, state).await {
+        // Counters live in Dragonfly; while it is down, requests served from Sled go unlimited
+        Err(e) => {
+            if state.cache.is_degraded().await {
+                warn!("Rate limit check for {} skipped while degraded: {}", key, e);
+                return Ok(true);
+            }
+            Err(e)
+        }
+        allowed => allowed,
+    }
+}
+
+async fn count_request(
+    key: &str,
+    limit: u64,
+    window: i64,
+    state: &AppState,
+) -> Result<bool, AppError> {
+    if state.config.cache.use_sled || state.config.storage.backend == StorageBackend::Sled {
+        state.rl_db.rate_limit(key, limit, window).await
     } else {
         let lua_script = r#"
             local key = KEYS[1]
@@ -56,14 +77,14 @@ async fn check_rate_limit(
         let now = state.clock.now().timestamp() as u64;
         let result: i64 = state.rl_db.eval_lua(
             lua_script,
-            vec![key],
+            vec![key.to_string()],
             vec![limit.to_string(), window.to_string(), now.to_string()],
         ).await?;
         Ok(result == 1)
     }
 }
 
-fn get_endpoint(path: &str) -> &'static str {
+pub(crate) fn get_endpoint(path: &str) -> &'static str {
     if path.starts_with("/v1/shorten") {
         "shorten"
     } else if path.starts_with("/v1/redirect") {
@@ -73,12 +94,36 @@ fn get_endpoint(path: &str) -> &'static str {
     }
 }
 
-fn build_rate_limit_response(window: i64) -> Result<Response<axum::body::Body>, AppError> {
-    Response::builder()
-        .status(StatusCode::TOO_MANY_REQUESTS)
-        .header(header::RETRY_AFTER, window.to_string())
-        .body(axum::body::Body::empty())
-        .map_err(|e| AppError::Internal(e.to_string()))
+fn build_rate_limit_response(window: i64) -> Result<Box<Response<axum::body::Body>>, AppError> {
+    let mut response = AppError::RateLimitExceeded.into_response();
+    response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(window));
+    Ok(Box::new(response))
+}
+
+/// Enforces an owner-set cap on one link, counted across all of its visitors.
+pub(crate) async fn check_link_rate_limit(state: &AppState, code: &str, per_minute: u64) -> Result<(), AppError> {
+    let window = 60;
+    let key = format!("rate:redirect:code:{}", code);
+    if check_rate_limit(key, per_minute, window, state).await? {
+        return Ok(());
+    }
+    RATE_LIMIT_EXCEEDED.get().unwrap().inc();
+    warn!("Link rate limit exceeded for code {}", code);
+    Err(AppError::RateLimitExceededWithResponse(build_rate_limit_response(window)?))
+}
+
+/// Counts a trial link against its creator's IP; refused once `trial.links_per_ip` were made
+/// within `trial.window_secs`.
+pub(crate) async fn check_trial_link_limit(state: &AppState, ip: &str) -> Result<(), AppError> {
+    let trial = &state.config.trial;
+    let window = trial.window_secs as i64;
+    let key = format!("rate:trial:ip:{}", ip);
+    if check_rate_limit(key, trial.links_per_ip, window, state).await? {
+        return Ok(());
+    }
+    RATE_LIMIT_EXCEEDED.get().unwrap().inc();
+    warn!("Trial link limit exceeded for IP {}", ip);
+    Err(AppError::RateLimitExceededWithResponse(build_rate_limit_response(window)?))
 }
 
 pub async fn rate_limit_middleware(
@@ -122,3 +167,53 @@ pub async fn rate_limit_middleware(
 
     Ok(next.run(req).await)
 }
+
+// Credential payloads are small; anything bigger is rejected before it is buffered
+const MAX_AUTH_BODY_BYTES: usize = 16 * 1024;
+
+#[derive(Deserialize)]
+struct AuthIdentity {
+    username: Option<String>,
+    email: Option<String>,
+}
+
+/// Stricter limiter for login/register/reset, keyed per IP and per submitted username so credential
+/// stuffing is slowed whether it is spread across accounts or across addresses.
+pub(crate) async fn auth_rate_limit_middleware(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    req: Request<axum::body::Body>,
+    next: Next,
+) -> Result<Response<axum::body::Body>, AppError> {
+    let config = &state.config.rate_limit;
+    let window = config.auth_window_seconds.unwrap_or(300) as i64;
+    let ip = context.ip.as_deref().unwrap_or("unknown");
+
+    let ip_key = format!("rate:auth:ip:{}", ip);
+    if !check_rate_limit(ip_key, config.auth_requests_per_ip.unwrap_or(20) as u64, window, &state).await? {
+        RATE_LIMIT_EXCEEDED.get().unwrap().inc();
+        warn!("Auth rate limit exceeded for IP {}", ip);
+        return Err(AppError::RateLimitExceededWithResponse(build_rate_limit_response(window)?));
+    }
+
+    // The username lives in the JSON body, so buffer it and hand the handler a fresh copy
+    let (parts, body) = req.into_parts();
+    let bytes = axum::body::to_bytes(body, MAX_AUTH_BODY_BYTES)
+        .await
+        .map_err(|_| AppError::BadRequest("Request body too large".into()))?;
+    let identity = serde_json::from_slice::<AuthIdentity>(&bytes)
+        .ok()
+        .and_then(|identity| identity.email.or(identity.username));
+    if let Some(identity) = identity {
+        let user_key = format!("rate:auth:username:{}", identity.to_lowercase());
+        let limit = config.auth_requests_per_username.unwrap_or(5) as u64;
+        if !check_rate_limit(user_key, limit, window, &state).await? {
+            RATE_LIMIT_EXCEEDED.get().unwrap().inc();
+            warn!("Auth rate limit exceeded for username {}", identity);
+            return Err(AppError::RateLimitExceededWithResponse(build_rate_limit_response(window)?));
+        }
+    }
+
+    let req = Request::from_parts(parts, axum::body::Body::from(bytes));
+    Ok(next.run(req).await)
+}
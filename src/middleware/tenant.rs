@@ -0,0 +1,52 @@
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, Request},
+    middleware::Next,
+    response::Response,
+    Router,
+};
+use std::{collections::HashMap, sync::Arc};
+use tower::ServiceExt;
+
+/// Each tenant's own router, keyed by the lowercase hosts it serves.
+#[derive(Clone, Default)]
+pub struct TenantRouters {
+    by_host: Arc<HashMap<String, Router>>,
+}
+
+impl TenantRouters {
+    pub fn new(by_host: HashMap<String, Router>) -> Self {
+        Self { by_host: Arc::new(by_host) }
+    }
+}
+
+/// Hands requests for a tenant's domain to that tenant's router, whose state is backed by its
+/// own Dragonfly; every other host carries on to the default stack.
+pub async fn tenant_routing_middleware(
+    State(tenants): State<TenantRouters>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let router = request_host(&req).and_then(|host| tenants.by_host.get(&host)).cloned();
+    match router {
+        Some(router) => match router.oneshot(req).await {
+            Ok(response) => response,
+            Err(infallible) => match infallible {},
+        },
+        None => next.run(req).await,
+    }
+}
+
+/// The Host header (the URI authority over HTTP/2), lowercased and without a port.
+fn request_host(req: &Request<Body>) -> Option<String> {
+    let host = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| req.uri().host())?;
+    let host = host.rsplit_once(':').map_or(host, |(name, port)| {
+        if port.chars().all(|c| c.is_ascii_digit()) { name } else { host }
+    });
+    Some(host.to_ascii_lowercase())
+}
@@ -0,0 +1,36 @@
+use axum::{
+    extract::{MatchedPath, State},
+    http::Request,
+    middleware::Next,
+    response::Response,
+};
+use crate::{
+    handlers::shorten::AppState,
+    middleware::{rate_limit::get_endpoint, RequestContext},
+};
+
+/// Counts every request, 429s included, against its user and API key once the response is known.
+pub async fn usage_middleware(
+    State(state): State<AppState>,
+    req: Request<axum::body::Body>,
+    next: Next,
+) -> Response {
+    let context = req.extensions().get::<RequestContext>();
+    let user_id = context.and_then(|context| context.user_id.clone());
+    let api_key_id = context.and_then(|context| context.api_key_id.clone());
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|matched| format!("{} {}", req.method(), matched.as_str()));
+    let rate_class = get_endpoint(req.uri().path());
+
+    let response = next.run(req).await;
+    state.usage.record(
+        user_id.as_deref(),
+        api_key_id.as_deref(),
+        route.as_deref(),
+        rate_class,
+        response.status().as_u16(),
+    );
+    response
+}
@@ -1,16 +1,17 @@
 use crossbeam_queue::SegQueue;
 use tokio::time::{interval, Duration};
+use std::net::IpAddr;
 use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
 use std::time::Instant;
 use crate::config::settings::Settings;
 use crate::services::cache::circuit_breaker::CircuitBreaker;
-use crate::services::metrics;
+use crate::services::{geo_lookup, metrics, ua_parser};
 use crate::services::storage::dragonfly::DatabaseClient;
 use crate::services::sled::SledStorage;
 use crate::services::storage::storage::Storage;
 use crate::errors::AppError;
 use crate::clock::{Clock, SystemClock};
-use tracing::{error, info};
+use tracing::{debug, error, info};
 use tokio::task::JoinHandle;
 
 #[derive(Debug)]
@@ -24,15 +25,20 @@ pub enum AnalyticsMessage {
         country: Option<String>,
         device_type: Option<String>,
         browser: Option<String>,
+        request_id: Option<String>, // x-request-id of the redirect, to match clicks against traces
+        user_agent: Option<String>, // Raw header, kept only when enrichment is deferred to the flush worker
+        variant: Option<String>, // A/B variant the visitor was sent to
+        deferred: bool,
     },
     Shutdown,
 }
 
-pub struct AnalyticsService<C: Clock + Send + Sync + 'static = SystemClock> {
+pub struct AnalyticsService<C: Clock + Clone + Send + Sync + 'static = SystemClock> {
     queue: Arc<SegQueue<AnalyticsMessage>>,
     flush_task: Arc<tokio::sync::Mutex<Option<JoinHandle<()>>>>,
     max_queue_size: usize,
-    db: Arc<DatabaseClient>,
+    db: Arc<dyn Storage + Send + Sync>,
+    circuit_breaker: Arc<CircuitBreaker>,
     sled: Option<Arc<SledStorage<C>>>, // Generic Sled service with specific path
     is_shutdown: Arc<AtomicBool>,
     clock: C,
@@ -41,11 +47,20 @@ pub struct AnalyticsService<C: Clock + Send + Sync + 'static = SystemClock> {
     sled_flush_ms: u64,
 }
 
-impl<C: Clock + Send + Sync + 'static> AnalyticsService<C> {
+impl<C: Clock + Clone + Send + Sync + 'static> AnalyticsService<C> {
     pub async fn new(config: &Settings, circuit_breaker: Arc<CircuitBreaker>, clock: C) -> Self {
+        let db = DatabaseClient::new(config, circuit_breaker).await.unwrap();
+        Self::with_storage(config, Arc::new(db), clock).await
+    }
+
+    /// Flushes clicks to `storage` instead of Dragonfly. Storage without a breaker of its own
+    /// gets one that never trips.
+    pub async fn with_storage(config: &Settings, db: Arc<dyn Storage + Send + Sync>, clock: C) -> Self {
         let queue = Arc::new(SegQueue::new());
         let max_queue_size = config.analytics.max_queue_size.unwrap_or(100_000);
-        let db = Arc::new(DatabaseClient::new(config, Arc::clone(&circuit_breaker)).await.unwrap());
+        let circuit_breaker = db.circuit_breaker().cloned().unwrap_or_else(|| {
+            Arc::new(CircuitBreaker::from_config(config.database_urls.clone(), &config.cache))
+        });
         let sled = if config.cache.use_sled {
             // Create analytics-specific sled with the analytics path
             Some(Arc::new(SledStorage::with_clock(&config.analytics.sled_path, config, clock.clone())))
@@ -64,6 +79,7 @@ impl<C: Clock + Send + Sync + 'static> AnalyticsService<C> {
             flush_task: Arc::new(tokio::sync::Mutex::new(Some(flush_task))),
             max_queue_size,
             db,
+            circuit_breaker,
             sled,
             is_shutdown: Arc::new(AtomicBool::new(false)),
             clock,
@@ -72,6 +88,8 @@ impl<C: Clock + Send + Sync + 'static> AnalyticsService<C> {
         }
     }
 
+    /// Returns whether the click was queued; bot clicks and overflow are not.
+    #[allow(clippy::too_many_arguments)]
     pub async fn record_click(
         &self,
         code: &str,
@@ -80,12 +98,22 @@ impl<C: Clock + Send + Sync + 'static> AnalyticsService<C> {
         country: Option<&str>,
         device_type: Option<&str>,
         browser: Option<&str>,
-    ) {
+        bot: Option<&str>,
+        request_id: Option<&str>,
+        user_agent: Option<&str>,
+        variant: Option<&str>,
+        deferred: bool, // Geo and UA enrichment is left to the flush worker
+    ) -> bool {
+        // Crawlers and link unfurlers would inflate click stats; count them separately
+        if let Some(name) = bot {
+            metrics::record_bot_click(name);
+            return false;
+        }
         if self.queue.len() >= self.max_queue_size {
             error!("Dropped click for code {}: queue full", code);
             metrics::record_analytics_dropped();
             metrics::update_queue_length(self.queue.len() as u64);
-            return;
+            return false;
         }
         let timestamp = self.clock.now().timestamp() as u64;
         self.queue.push(AnalyticsMessage::Click {
@@ -96,26 +124,52 @@ impl<C: Clock + Send + Sync + 'static> AnalyticsService<C> {
             country: country.map(String::from),
             device_type: device_type.map(String::from),
             browser: browser.map(String::from),
+            request_id: request_id.map(String::from),
+            user_agent: user_agent.filter(|_| deferred).map(String::from),
+            variant: variant.map(String::from),
+            deferred,
         });
         metrics::record_click();
         metrics::update_queue_length(self.queue.len() as u64);
+        true
     }
 
+    pub fn circuit_breaker(&self) -> &Arc<CircuitBreaker> {
+        &self.circuit_breaker
+    }
+
+    pub fn db(&self) -> &Arc<dyn Storage + Send + Sync> {
+        &self.db
+    }
+
+    /// Clicks on `code` timestamped within `start..=end` (Unix seconds).
     pub async fn get_analytics(&self, code: &str, start: i64, end: i64) -> Result<Vec<(u64, u64)>, AppError> {
-        let key = format!("stats:{}", code);
-        match self.db.zrange(&key, start, end).await {
-            Ok(data) if !data.is_empty() => Ok(data),
+        self.clicks_in_window(format!("stats:{}", code), start, end).await
+    }
+
+    /// Clicks on `code` that were sent to its A/B `variant`, within `start..=end` (Unix seconds).
+    pub(crate) async fn get_variant_analytics(&self, code: &str, variant: &str, start: i64, end: i64) -> Result<Vec<(u64, u64)>, AppError> {
+        self.clicks_in_window(variant_key(code, variant), start, end).await
+    }
+
+    async fn clicks_in_window(&self, key: String, start: i64, end: i64) -> Result<Vec<(u64, u64)>, AppError> {
+        // The sorted sets are read by rank, so the time window is applied here
+        let in_window = |data: Vec<(u64, u64)>| -> Vec<(u64, u64)> {
+            data.into_iter().filter(|(ts, _)| (start..=end).contains(&(*ts as i64))).collect()
+        };
+        match self.db.zrange(&key, 0, -1).await {
+            Ok(data) if !data.is_empty() => Ok(in_window(data)),
             _ => {
                 if self.use_sled {
                     if let Some(sled) = &self.sled {
-                        match sled.zrange(&key, start, end).await {
+                        match sled.zrange(&key, 0, -1).await {
                             Ok(data) if !data.is_empty() => {
                                 let operations = data.iter().map(|(score, member)| (key.clone(), *score, *member)).collect();
                                 if let Err(e) = self.db.zadd_batch(operations, 90 * 24 * 3600).await {
                                     error!("Failed to restore analytics to DragonflyDB: {}", e);
                                     metrics::record_analytics_error("restore");
                                 }
-                                Ok(data)
+                                Ok(in_window(data))
                             }
                             Ok(_) => Ok(vec![]),
                             Err(e) => {
@@ -149,7 +203,7 @@ impl<C: Clock + Send + Sync + 'static> AnalyticsService<C> {
     async fn start_flush_task(
         queue: Arc<SegQueue<AnalyticsMessage>>,
         config: &Settings,
-        db: Arc<DatabaseClient>,
+        db: Arc<dyn Storage + Send + Sync>,
         sled: Option<Arc<SledStorage<C>>>,
     ) -> JoinHandle<()> {
         let batch_size = config.analytics.max_batch_size;
@@ -163,8 +217,11 @@ impl<C: Clock + Send + Sync + 'static> AnalyticsService<C> {
                 interval.tick().await;
                 while let Some(msg) = queue.pop() {
                     match msg {
-                        AnalyticsMessage::Click { code, timestamp, ip: _, referrer: _, country: _, device_type: _, browser: _ } => {
-                            batch.push((code, timestamp));
+                        AnalyticsMessage::Click { code, timestamp, request_id, ip, user_agent, variant, deferred, .. } => {
+                            if deferred {
+                                enrich_click(&code, &ip, user_agent.as_deref(), request_id.as_deref()).await;
+                            }
+                            batch.push((code, timestamp, request_id, variant));
                             if batch.len() >= batch_size {
                                 Self::flush_batch(&db, &sled, &mut batch, use_sled).await;
                             }
@@ -184,14 +241,18 @@ impl<C: Clock + Send + Sync + 'static> AnalyticsService<C> {
         })
     }
 
-    async fn flush_batch(db: &Arc<DatabaseClient>, sled: &Option<Arc<SledStorage<C>>>, batch: &mut Vec<(String, u64)>, use_sled: bool) {
+    async fn flush_batch(db: &Arc<dyn Storage + Send + Sync>, sled: &Option<Arc<SledStorage<C>>>, batch: &mut Vec<ClickRecord>, use_sled: bool) {
         if batch.is_empty() {
             return;
         }
         let start = Instant::now();
+        // A/B clicks also go to a per-variant set, so variants can be compared by click-through
         let operations: Vec<(String, u64, u64)> = batch
             .iter()
-            .map(|(code, ts)| (format!("stats:{}", code), *ts, *ts))
+            .flat_map(|(code, ts, _, variant)| {
+                let per_variant = variant.as_deref().map(|variant| (variant_key(code, variant), *ts, *ts));
+                std::iter::once((format!("stats:{}", code), *ts, *ts)).chain(per_variant)
+            })
             .collect();
 
         let dragonfly_result = db.zadd_batch(operations.clone(), 90 * 24 * 3600).await;
@@ -217,15 +278,47 @@ impl<C: Clock + Send + Sync + 'static> AnalyticsService<C> {
 
         if dragonfly_result.is_ok() || sled_success {
             info!("Flushed {} analytics events in {:?}", batch.len(), start.elapsed());
+            for (code, timestamp, request_id, _) in batch.iter() {
+                debug!(request_id = request_id.as_deref().unwrap_or("-"), code = %code, timestamp, "Flushed click");
+            }
             metrics::record_batch_flush(batch.len());
             batch.clear();
         } else {
+            let request_ids: Vec<&str> = batch.iter().filter_map(|(_, _, id, _)| id.as_deref()).collect();
+            debug!(request_ids = ?request_ids, "Analytics batch kept for retry");
             metrics::record_analytics_error("flush_failed");
         }
     }
 }
 
-impl<C: Clock + Send + Sync + 'static> Drop for AnalyticsService<C> {
+// Code, timestamp, request id and A/B variant of a queued click
+type ClickRecord = (String, u64, Option<String>, Option<String>);
+
+fn variant_key(code: &str, variant: &str) -> String {
+    format!("stats:{}:variant:{}", code, variant)
+}
+
+/// The geo lookup and UA parsing a redirect skipped when enrichment is deferred.
+async fn enrich_click(code: &str, ip: &str, user_agent: Option<&str>, request_id: Option<&str>) {
+    let info = user_agent.map(ua_parser::parse_user_agent);
+    let geo = match ip.parse::<IpAddr>() {
+        Ok(ip) => geo_lookup::lookup_geo(ip).await.unwrap_or_else(|e| {
+            debug!("Deferred geo lookup failed for {}: {}", ip, e);
+            None
+        }),
+        Err(_) => None,
+    };
+    debug!(
+        request_id = request_id.unwrap_or("-"),
+        code = %code,
+        country = geo.as_ref().and_then(|g| g.country_iso.as_deref()).unwrap_or("-"),
+        device_type = info.as_ref().map_or("-", |i| i.device_type.as_str()),
+        browser = info.as_ref().and_then(|i| i.browser.as_deref()).unwrap_or("-"),
+        "Enriched deferred click"
+    );
+}
+
+impl<C: Clock + Clone + Send + Sync + 'static> Drop for AnalyticsService<C> {
     fn drop(&mut self) {
         if self.is_shutdown.load(Ordering::SeqCst) {
             return;
@@ -238,8 +331,8 @@ impl<C: Clock + Send + Sync + 'static> Drop for AnalyticsService<C> {
         tokio::spawn(async move {
             let mut batch = Vec::with_capacity(1000);
             while let Some(msg) = queue.pop() {
-                if let AnalyticsMessage::Click { code, timestamp, .. } = msg {
-                    batch.push((code, timestamp));
+                if let AnalyticsMessage::Click { code, timestamp, request_id, variant, .. } = msg {
+                    batch.push((code, timestamp, request_id, variant));
                     if batch.len() >= 1000 {
                         Self::flush_batch(&db, &sled, &mut batch, use_sled).await;
                     }
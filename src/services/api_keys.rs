@@ -0,0 +1,75 @@
+use axum::http::Method;
+use rand::{distr::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+
+pub const KEY_PREFIX: &str = "hlk_";
+
+pub const SCOPE_LINKS_READ: &str = "links:read";
+pub const SCOPE_LINKS_WRITE: &str = "links:write";
+pub const SCOPE_ANALYTICS_READ: &str = "analytics:read";
+pub const SCOPE_NOTIFICATIONS_READ: &str = "notifications:read";
+pub const SCOPES: [&str; 4] = [SCOPE_LINKS_READ, SCOPE_LINKS_WRITE, SCOPE_ANALYTICS_READ, SCOPE_NOTIFICATIONS_READ];
+
+/// Mints a new key as `hlk_<id>_<secret>`. Only the SHA-256 of the full key is stored; the id
+/// prefix lets us find the record without scanning.
+pub fn generate(id: &str) -> (String, String) {
+    let secret: String = rand::rng().sample_iter(&Alphanumeric).take(40).map(char::from).collect();
+    let key = format!("{}{}_{}", KEY_PREFIX, id, secret);
+    let hash = hash_key(&key);
+    (key, hash)
+}
+
+pub fn hash_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// Extracts the key id from a presented key, or None if it isn't shaped like one of ours.
+pub fn key_id(key: &str) -> Option<&str> {
+    let (id, secret) = key.strip_prefix(KEY_PREFIX)?.split_once('_')?;
+    (!id.is_empty() && !secret.is_empty()).then_some(id)
+}
+
+/// Scope an API key needs for a route. None means the route is closed to API keys entirely
+/// (admin, account and key management stay behind interactive logins).
+pub fn required_scope(method: &Method, path: &str) -> Option<&'static str> {
+    let path = path.strip_prefix("/v1").unwrap_or(path);
+    if ["/admin", "/auth", "/api-keys", "/me"].iter().any(|prefix| path.starts_with(prefix)) {
+        return None;
+    }
+    if path.starts_with("/analytics") || (path.starts_with("/campaigns") && path.ends_with("/analytics")) {
+        return Some(SCOPE_ANALYTICS_READ);
+    }
+    if path.starts_with("/quick") {
+        return Some(SCOPE_LINKS_WRITE);
+    }
+    if path.starts_with("/notifications") {
+        return Some(SCOPE_NOTIFICATIONS_READ);
+    }
+    match *method {
+        Method::GET | Method::HEAD => Some(SCOPE_LINKS_READ),
+        _ => Some(SCOPE_LINKS_WRITE),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_keys_parse_back_to_their_id() {
+        let (key, hash) = generate("key123");
+        assert_eq!(key_id(&key), Some("key123"));
+        assert_eq!(hash_key(&key), hash);
+        assert_eq!(key_id("hlk_missing-secret"), None);
+        assert_eq!(key_id("Bearer abc"), None);
+    }
+
+    #[test]
+    fn routes_map_to_scopes() {
+        assert_eq!(required_scope(&Method::GET, "/v1/urls"), Some(SCOPE_LINKS_READ));
+        assert_eq!(required_scope(&Method::POST, "/v1/shorten"), Some(SCOPE_LINKS_WRITE));
+        assert_eq!(required_scope(&Method::GET, "/v1/analytics/abc"), Some(SCOPE_ANALYTICS_READ));
+        assert_eq!(required_scope(&Method::POST, "/v1/admin/blocklist"), None);
+        assert_eq!(required_scope(&Method::POST, "/v1/api-keys"), None);
+    }
+}
@@ -0,0 +1,143 @@
+use parking_lot::RwLock;
+use std::collections::BTreeSet;
+use tracing::{info, warn};
+
+use crate::{config::settings::Settings, errors::AppError};
+
+/// Destination domain/TLD blocklist.
+///
+/// Patterns are either exact hosts (`spam.example`) or wildcards (`*.example`, `*.tk`) matching any
+/// subdomain of the suffix. Entries come from `security.blocked_domains` plus an optional file with one
+/// pattern per line; admin changes are written back to that file when configured.
+pub struct DomainBlocklist {
+    patterns: RwLock<BTreeSet<String>>,
+    path: Option<String>,
+}
+
+impl DomainBlocklist {
+    pub(crate) fn new(config: &Settings) -> Result<Self, AppError> {
+        let mut patterns = BTreeSet::new();
+        for raw in config.security.blocked_domains.iter().flatten() {
+            patterns.insert(normalize_pattern(raw)?);
+        }
+
+        let path = config.security.blocklist_path.clone();
+        if let Some(path) = &path {
+            match std::fs::read_to_string(path) {
+                Ok(contents) => {
+                    for line in contents.lines().map(str::trim) {
+                        if line.is_empty() || line.starts_with('#') {
+                            continue;
+                        }
+                        patterns.insert(normalize_pattern(line)?);
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    warn!("Blocklist file {} not found, starting empty", path);
+                }
+                Err(e) => return Err(AppError::Internal(format!("Failed to read blocklist {}: {}", path, e))),
+            }
+        }
+
+        info!("Loaded {} blocklist patterns", patterns.len());
+        Ok(Self {
+            patterns: RwLock::new(patterns),
+            path,
+        })
+    }
+
+    /// Returns the pattern blocking `host`, if any.
+    pub fn matching_pattern(&self, host: &str) -> Option<String> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let patterns = self.patterns.read();
+        if patterns.contains(&host) {
+            return Some(host);
+        }
+        let mut rest = host.as_str();
+        while let Some((_, suffix)) = rest.split_once('.') {
+            let wildcard = format!("*.{}", suffix);
+            if patterns.contains(&wildcard) {
+                return Some(wildcard);
+            }
+            rest = suffix;
+        }
+        None
+    }
+
+    pub fn list(&self) -> Vec<String> {
+        self.patterns.read().iter().cloned().collect()
+    }
+
+    pub(crate) fn add(&self, pattern: &str) -> Result<bool, AppError> {
+        let pattern = normalize_pattern(pattern)?;
+        let added = self.patterns.write().insert(pattern);
+        if added {
+            self.persist()?;
+        }
+        Ok(added)
+    }
+
+    pub(crate) fn remove(&self, pattern: &str) -> Result<bool, AppError> {
+        let pattern = normalize_pattern(pattern)?;
+        let removed = self.patterns.write().remove(&pattern);
+        if removed {
+            self.persist()?;
+        }
+        Ok(removed)
+    }
+
+    fn persist(&self) -> Result<(), AppError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut contents = self.list().join("\n");
+        contents.push('\n');
+        std::fs::write(path, contents)
+            .map_err(|e| AppError::Internal(format!("Failed to write blocklist {}: {}", path, e)))
+    }
+}
+
+fn normalize_pattern(raw: &str) -> Result<String, AppError> {
+    let pattern = raw.trim().trim_end_matches('.').to_ascii_lowercase();
+    let host = pattern.strip_prefix("*.").unwrap_or(&pattern);
+    let valid = !host.is_empty()
+        && host
+            .split('.')
+            .all(|label| !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'));
+    if !valid {
+        return Err(AppError::BadRequest(format!("Invalid blocklist pattern: {}", raw)));
+    }
+    Ok(pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocklist(patterns: &[&str]) -> DomainBlocklist {
+        let mut config = Settings::default();
+        config.security.blocked_domains = Some(patterns.iter().map(|p| p.to_string()).collect());
+        DomainBlocklist::new(&config).unwrap()
+    }
+
+    #[test]
+    fn exact_and_wildcard_patterns() {
+        let list = blocklist(&["spam.example", "*.tk", "*.evil.com"]);
+        assert_eq!(list.matching_pattern("spam.example"), Some("spam.example".into()));
+        assert_eq!(list.matching_pattern("www.spam.example"), None);
+        assert_eq!(list.matching_pattern("free.TK"), Some("*.tk".into()));
+        assert_eq!(list.matching_pattern("a.b.evil.com"), Some("*.evil.com".into()));
+        assert_eq!(list.matching_pattern("evil.com"), None);
+        assert_eq!(list.matching_pattern("example.com"), None);
+    }
+
+    #[test]
+    fn rejects_malformed_patterns() {
+        let list = blocklist(&[]);
+        assert!(list.add("bad domain").is_err());
+        assert!(list.add("*.").is_err());
+        assert!(list.add("a..b").is_err());
+        assert!(list.add("Good.Example.").unwrap());
+        assert_eq!(list.list(), vec!["good.example".to_string()]);
+    }
+}
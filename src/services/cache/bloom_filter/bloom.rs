@@ -1,6 +1,7 @@
 use std::hash::{Hash, Hasher};
 use std::collections::hash_map::DefaultHasher;
 use super::atomic_shard::AtomicBloomShard;
+use arc_swap::{ArcSwap, ArcSwapOption};
 use std::sync::Arc;
 
 #[derive(Clone)]
@@ -45,3 +46,60 @@ impl CacheBloom {
 
 
 }
+
+/// The live filter, which a rebuild can replace without a pause. Bloom filters can't forget a
+/// key, so deleted and expired links pile up until one is rebuilt from storage.
+pub struct SwappableBloom {
+    live: ArcSwap<CacheBloom>,
+    next: ArcSwapOption<CacheBloom>, // Filled while a rebuild runs
+    size: usize,
+    expected: usize,
+    block_size: usize,
+}
+
+impl SwappableBloom {
+    pub fn new(size: usize, expected: usize, block_size: usize) -> Self {
+        Self {
+            live: ArcSwap::from_pointee(CacheBloom::new(size, expected, block_size)),
+            next: ArcSwapOption::empty(),
+            size,
+            expected,
+            block_size,
+        }
+    }
+
+    #[inline]
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.live.load().contains(key)
+    }
+
+    /// Inserts into the filter being rebuilt as well, so a key written mid-rebuild survives the
+    /// swap. That one goes first: once it is gone, `finish_rebuild` has already made it live.
+    #[inline]
+    pub fn insert(&self, key: &[u8]) {
+        if let Some(next) = self.next.load().as_ref() {
+            next.insert(key);
+        }
+        self.live.load().insert(key)
+    }
+
+    /// Starts an empty filter of the same size for the caller to fill from storage.
+    pub fn begin_rebuild(&self) -> Arc<CacheBloom> {
+        let next = Arc::new(CacheBloom::new(self.size, self.expected, self.block_size));
+        self.next.store(Some(Arc::clone(&next)));
+        next
+    }
+
+    /// Makes the rebuilt filter live.
+    pub fn finish_rebuild(&self) {
+        if let Some(next) = self.next.load_full() {
+            self.live.store(next);
+        }
+        self.next.store(None);
+    }
+
+    /// Drops a rebuild that failed, leaving the live filter as it was.
+    pub fn abort_rebuild(&self) {
+        self.next.store(None);
+    }
+}
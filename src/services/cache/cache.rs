@@ -1,24 +1,28 @@
-use std::{sync::Arc, time::Instant};
+use std::{collections::VecDeque, sync::{atomic::{AtomicBool, Ordering}, Arc}, time::Instant};
 use futures::future;
-use tracing::info;
+use tracing::{info, warn};
 use tokio::time::Duration;
 use once_cell::sync::Lazy;
 use prometheus::IntCounter;
 use crate::{
-    config::settings::Settings,
+    clock::Clock,
+    config::{cache::WriteMode, settings::Settings},
     errors::AppError,
     services::{
         cache::{
-            bloom_filter::bloom::CacheBloom,
+            bloom_filter::bloom::{CacheBloom, SwappableBloom},
             circuit_breaker::CircuitBreaker,
+            hot_set::HotSet,
             l1_cache::L1Cache,
             l2_cache::L2Cache,
+            policy::TierPolicy,
         },
         metrics,
+        replication::Replicator,
         storage::{dragonfly::DatabaseClient, storage::Storage},
-        sled::SledStorage,
+        sled::{ExpiringKeys, SledStorage},
     },
-    types::{Paginate, UrlData},
+    types::{BloomRebuildStatus, Paginate, UrlData},
 };
 
 use std::pin::Pin;
@@ -26,64 +30,97 @@ use std::future::Future;
 
 #[derive(Clone)]
 pub struct CacheService {
+    hot: Arc<HotSet>,
     l1: Arc<L1Cache>,
     l2: Arc<L2Cache>,
-    bloom: Arc<CacheBloom>,
-    dragonfly: Arc<DatabaseClient>,
-    sled: Option<Arc<SledStorage>>, // Optional Sled
+    bloom: Arc<SwappableBloom>,
+    bloom_rebuild: Arc<parking_lot::Mutex<BloomRebuildStatus>>,
+    dragonfly: Arc<dyn Storage + Send + Sync>,
+    circuit_breaker: Arc<CircuitBreaker>,
+    sled: Option<Arc<SledStorage<Arc<dyn Clock>>>>, // Optional Sled
     ttl_seconds: u64,
     use_sled: bool,
     sled_flush_ms: u64,
+    degraded_mode: bool,
+    degraded: Arc<AtomicBool>,
+    queued_writes: Arc<parking_lot::Mutex<VecDeque<(String, String)>>>,
+    queued_writes_limit: usize,
+    write_back: bool,
+    warmup_keys_per_sec: usize,
+    replicator: Option<Replicator>,
 }
 
+// How often a degraded cache checks whether Dragonfly is back
+const DEGRADED_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// Keys a bloom rebuild inserts before yielding and updating its progress
+const BLOOM_REBUILD_CHUNK: usize = 10_000;
+
 static FLUSH_COUNT: Lazy<IntCounter> = Lazy::new(|| {
     prometheus::register_int_counter!("flush_count_total", "Total Sled flushes").unwrap()
 });
 
 impl CacheService {
-    pub async fn new(config: &Settings) -> Self {
+    pub async fn new(config: &Settings, clock: Arc<dyn Clock>) -> Self {
+        let circuit_breaker = Arc::new(CircuitBreaker::from_config(config.database_urls.clone(), &config.cache));
+        let dragonfly = DatabaseClient::new(config, circuit_breaker)
+            .await
+            .expect("Failed to create DatabaseClient");
+        Self::with_storage(config, Arc::new(dragonfly), clock).await
+    }
+
+    /// A cache backed by `storage` instead of Dragonfly, e.g. in-memory storage for tests and
+    /// benches. Storage without a breaker of its own gets one that never trips.
+    pub async fn with_storage(config: &Settings, storage: Arc<dyn Storage + Send + Sync>, clock: Arc<dyn Clock>) -> Self {
         metrics::init_metrics();
-        let bloom = Arc::new(CacheBloom::new(
+        metrics::set_slow_op_threshold_ms(config.cache.slow_op_threshold_ms.unwrap_or(25));
+        let bloom = Arc::new(SwappableBloom::new(
             config.cache.bloom_bits,
             config.cache.bloom_expected,
             config.cache.bloom_shards,
         ));
-        let l1 = Arc::new(L1Cache::new(
+        let l1 = Arc::new(L1Cache::with_policy(
             config.cache.l1_capacity,
-            config.cache.ttl_seconds,
+            TierPolicy::l1(&config.cache),
         ));
-        let l2 = Arc::new(L2Cache::new(
+        let l2 = Arc::new(L2Cache::with_policy(
             config.cache.l2_capacity,
-            config.cache.ttl_seconds,
+            TierPolicy::l2(&config.cache),
         ));
-        let circuit_breaker = Arc::new(CircuitBreaker::new(
-            config.database_urls.clone(),
-            config.cache.max_failures,
-            Duration::from_secs(config.cache.retry_interval_secs),
-        ));
-        let dragonfly = Arc::new(
-            DatabaseClient::new(config, Arc::clone(&circuit_breaker))
-                .await
-                .expect("Failed to create DatabaseClient"),
-        );
+        let circuit_breaker = storage.circuit_breaker().cloned().unwrap_or_else(|| {
+            Arc::new(CircuitBreaker::from_config(config.database_urls.clone(), &config.cache))
+        });
         let sled = if config.cache.use_sled {
-            Some(Arc::new(SledStorage::new(&config.cache.sled_path, config)))
+            Some(Arc::new(SledStorage::with_clock(&config.cache.sled_path, config, Arc::clone(&clock))))
         } else {
             None
         };
         let cache = Self {
+            hot: Arc::new(HotSet::new(config.cache.hot_set_size.unwrap_or(100))),
             l1,
             l2,
             bloom,
-            dragonfly,
+            bloom_rebuild: Arc::new(parking_lot::Mutex::new(BloomRebuildStatus { state: "idle".into(), ..Default::default() })),
+            dragonfly: storage,
+            circuit_breaker,
             sled,
             ttl_seconds: config.cache.ttl_seconds,
             use_sled: config.cache.use_sled,
             sled_flush_ms: config.cache.sled_flush_ms,
+            degraded_mode: config.cache.degraded_mode.unwrap_or(true) && config.cache.use_sled,
+            degraded: Arc::new(AtomicBool::new(false)),
+            queued_writes: Arc::new(parking_lot::Mutex::new(VecDeque::new())),
+            queued_writes_limit: config.cache.degraded_queue_size.unwrap_or(10_000),
+            write_back: config.cache.write_mode == Some(WriteMode::WriteBack) && config.cache.use_sled,
+            warmup_keys_per_sec: config.cache.warmup_keys_per_sec.unwrap_or(1_000),
+            replicator: Replicator::spawn(config, clock),
         };
 
         // Start flush task if Sled is enabled
         if cache.use_sled {
+            if let Some(sled) = &cache.sled {
+                let sweep_every = Duration::from_secs(config.storage.sled_sweep_interval_secs.unwrap_or(300));
+                Arc::clone(sled).spawn_ttl_sweeper("cache", ExpiringKeys::All, sweep_every);
+            }
             let cache = cache.clone();
             tokio::spawn(async move {
                 let mut interval = tokio::time::interval(Duration::from_millis(cache.sled_flush_ms));
@@ -97,96 +134,153 @@ impl CacheService {
             });
         }
 
+        if cache.write_back {
+            let cache = cache.clone();
+            let flush_every = Duration::from_millis(config.cache.write_back_flush_ms.unwrap_or(50));
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(flush_every);
+                loop {
+                    interval.tick().await;
+                    // While degraded the queue waits for check_degraded to replay it
+                    if !cache.is_degraded().await {
+                        cache.replay_queued_writes().await;
+                        metrics::set_degraded(false, cache.queued_writes.lock().len());
+                    }
+                }
+            });
+        }
+
+        if cache.degraded_mode {
+            let cache = cache.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(DEGRADED_CHECK_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    cache.check_degraded().await;
+                }
+            });
+        }
+
         cache
     }
 
-    pub async fn get(&self, key: &str) -> Result<String, AppError> {
+    /// Whether every Dragonfly node is down and this instance is running off Sled: redirects
+    /// are read from it and link writes are queued until Dragonfly is reachable again.
+    pub async fn is_degraded(&self) -> bool {
+        self.degraded_mode && self.circuit_breaker.all_open().await
+    }
+
+    /// Tracks entering and leaving degraded mode, replaying queued writes once Dragonfly is back.
+    async fn check_degraded(&self) {
+        let degraded = self.is_degraded().await;
+        if self.degraded.swap(degraded, Ordering::Relaxed) != degraded {
+            if degraded {
+                warn!("Every Dragonfly node is unreachable; serving redirects from Sled and queueing writes");
+            } else {
+                info!("Dragonfly is reachable again; leaving degraded mode");
+            }
+        }
+        if !degraded {
+            let replayed = self.replay_queued_writes().await;
+            if replayed > 0 && !self.write_back {
+                info!("Replayed {} link writes queued while degraded", replayed);
+            }
+        }
+        metrics::set_degraded(degraded, self.queued_writes.lock().len());
+    }
+
+    /// Sends queued link writes, from degraded mode or write-back, on to Dragonfly, oldest first.
+    /// Stops at the first failure, which stays at the front of the queue for the next attempt.
+    pub async fn replay_queued_writes(&self) -> usize {
+        let mut replayed = 0;
+        loop {
+            let Some((key, value)) = self.queued_writes.lock().pop_front() else {
+                break;
+            };
+            if let Err(e) = self.dragonfly.set_ex(&key, &value, self.ttl_seconds).await {
+                warn!("Replaying queued write for {} failed: {}", key, e);
+                self.queued_writes.lock().push_front((key, value));
+                break;
+            }
+            metrics::record_cache_insert("dragonfly");
+            replayed += 1;
+        }
+        replayed
+    }
+
+    /// Queues a link write for Dragonfly, unless the queue is already full.
+    fn queue_write(&self, key: &str, value: &str, degraded: bool) -> bool {
+        let mut queued = self.queued_writes.lock();
+        if queued.len() >= self.queued_writes_limit {
+            return false;
+        }
+        queued.push_back((key.to_string(), value.to_string()));
+        metrics::set_degraded(degraded, queued.len());
+        true
+    }
+
+    /// Typed lookup for the redirect path. L1 holds links already parsed, so a hot hit is an
+    /// `Arc` clone; colder tiers keep the JSON form and are parsed once on promotion.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotFound` if no tier has the code, or the storage error that stopped the lookup.
+    pub async fn get_url_data(&self, code: &str) -> Result<Arc<UrlData>, AppError> {
         let start = Instant::now();
-        if let Some(val) = self.l1.get(key).await {
+        if let Some(url_data) = self.l1.get(code).await {
             metrics::record_cache_hit("l1", start);
-            return Ok(val);
+            return Ok(url_data);
         }
+        metrics::record_cache_miss("l1");
+
+        let json = self.get_raw(code, start).await?;
+        let url_data = Arc::new(decode(&json)?);
+        self.l1.insert(code.to_string(), Arc::clone(&url_data)).await;
+        Ok(url_data)
+    }
 
+    /// Looks `key` up below L1, backfilling the string tiers it missed on the way down.
+    async fn get_raw(&self, key: &str, start: Instant) -> Result<String, AppError> {
+        // A bloom "hit" means the key may exist; a miss means it definitely doesn't
         if !self.bloom.contains(key.as_bytes()) {
+            metrics::record_cache_miss("bloom");
             metrics::record_cache_latency("bloom", start);
             return Err(AppError::NotFound("Key not found".into()));
         }
+        metrics::record_cache_hit("bloom", start);
 
         if let Some(val) = self.l2.get(key).await {
             metrics::record_cache_hit("l2", start);
-            self.l1.insert(key.to_string(), val.clone()).await;
             return Ok(val);
         }
+        metrics::record_cache_miss("l2");
 
-        if let Ok(val) = self.dragonfly.get(key).await {
-            metrics::record_cache_hit("dragonfly", start);
-            let key = key.to_string();
-            let val_clone = val.clone();
-            let l1_task = {
-                let key = key.clone();
-                let val_clone = val_clone.clone();
-                async move {
-                    self.l1.insert(key, val_clone).await;
-                    Ok::<(), AppError>(())
-                }
-            };
-            let l2_task = {
-                let key = key.clone();
-                let val_clone = val_clone.clone();
-                async move {
-                    self.l2.insert(key, val_clone).await;
-                    Ok::<(), AppError>(())
-                }
-            };
-            
-
-            let tasks: Vec<Pin<Box<dyn Future<Output = Result<(), AppError>> + Send>>> = vec![
-                Box::pin(l1_task),
-                Box::pin(l2_task),
-            ];
-            future::try_join_all(tasks).await?;
-            return Ok(val);
+        // No point waiting on a connection to nodes the breaker already knows are down
+        let degraded = self.is_degraded().await;
+        if !degraded {
+            if let Ok(val) = self.dragonfly.get_hedged(key).await {
+                metrics::record_cache_hit("dragonfly", start);
+                self.l2.insert(key.to_string(), val.clone()).await;
+                return Ok(val);
+            }
+            metrics::record_cache_miss("dragonfly");
         }
 
         if self.use_sled {
             if let Some(sled) = &self.sled {
                 let sled_start = Instant::now();
-                let url = sled.get(key).await?;
-                metrics::record_cache_latency("sled", sled_start);
-                let key = key.to_string();
-                let url_clone = url.clone();
-                let dragonfly_task = self.dragonfly.set_ex(&key, &url_clone, self.ttl_seconds);
-                let l1_task = {
-                    let key = key.clone();
-                    let url_clone = url_clone.clone();
-                    async move {
-                        self.l1.insert(key, url_clone).await;
-                        Ok::<(), AppError>(())
-                    }
-                };
-                let l2_task = {
-                    let key = key.clone();
-                    let url_clone = url_clone.clone();
-                    async move {
-                        self.l2.insert(key, url_clone).await;
-                        Ok::<(), AppError>(())
-                    }
-                };
-                let bloom_task = {
-                    let key = key.clone();
-                    async move {
-                        self.bloom.insert(key.as_bytes());
-                        Ok::<(), AppError>(())
+                let url = sled.get(key).await.inspect_err(|_| metrics::record_cache_miss("sled"))?;
+                metrics::record_cache_hit("sled", sled_start);
+                self.l2.insert(key.to_string(), url.clone()).await;
+                self.bloom.insert(key.as_bytes());
+                metrics::record_cache_insert("bloom");
+                // Best effort: Sled already answered, a failed backfill shouldn't lose the read
+                if !degraded {
+                    match self.dragonfly.set_ex(key, &url, self.ttl_seconds).await {
+                        Ok(()) => metrics::record_cache_insert("dragonfly"),
+                        Err(e) => warn!("Failed to backfill {} from Sled into Dragonfly: {}", key, e),
                     }
-                };
-
-                let tasks: Vec<Pin<Box<dyn Future<Output = Result<(), AppError>> + Send>>> = vec![
-                    Box::pin(dragonfly_task),
-                    Box::pin(l1_task),
-                    Box::pin(l2_task),
-                    Box::pin(bloom_task),
-                ];
-                future::try_join_all(tasks).await?;
+                }
                 metrics::record_cache_latency("total", start);
                 return Ok(url);
             }
@@ -195,15 +289,41 @@ impl CacheService {
         Err(AppError::NotFound("Key not found".into()))
     }
 
-    pub async fn insert(&self, key: String, value: String) -> Result<(), AppError> {
+    /// Writes a link to storage and this instance's cache tiers.
+    ///
+    /// # Errors
+    ///
+    /// Fails if storage rejects the write, or Dragonfly is down and the degraded write queue is full.
+    pub async fn insert(&self, key: String, url_data: &UrlData) -> Result<(), AppError> {
         let start = Instant::now();
-        self.dragonfly.set_ex(&key, &value, self.ttl_seconds).await?;
+        self.hot.remove(&key);
+        let value = serde_json::to_string(url_data)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        if self.is_degraded().await {
+            // Sled and the in-process tiers take the write now; Dragonfly gets it on recovery
+            if !self.queue_write(&key, &value, true) {
+                return Err(AppError::RedisConnection("Dragonfly is unreachable and the write queue is full".into()));
+            }
+        } else if self.write_back && self.queue_write(&key, &value, false) {
+            // Acknowledged once Sled and the in-process tiers have it; the flush task persists it
+        } else {
+            self.dragonfly.set_ex(&key, &value, self.ttl_seconds).await?;
+            metrics::record_cache_insert("dragonfly");
+            // An older queued value for this key must not overwrite this one when replayed
+            let mut queued = self.queued_writes.lock();
+            if !queued.is_empty() {
+                queued.retain(|(queued_key, _)| *queued_key != key);
+            }
+        }
+        if let Some(replicator) = &self.replicator {
+            replicator.record(&key, Some(&value));
+        }
         let value_clone = value.clone();
         let l1_task = {
             let key = key.clone();
-            let value_clone = value_clone.clone();
+            let url_data = Arc::new(url_data.clone());
             async move {
-                self.l1.insert(key, value_clone).await;
+                self.l1.insert(key, url_data).await;
                 Ok::<(), AppError>(())
             }
         };
@@ -219,6 +339,7 @@ impl CacheService {
             let key = key.clone();
             async move {
                 self.bloom.insert(key.as_bytes());
+                metrics::record_cache_insert("bloom");
                 Ok::<(), AppError>(())
             }
         };
@@ -230,7 +351,11 @@ impl CacheService {
         ];
         if self.use_sled {
             if let Some(sled) = &self.sled {
-                let sled_task: Pin<Box<dyn Future<Output = Result<(), AppError>> + Send>> = Box::pin(sled.set_ex(&key, &value, self.ttl_seconds));
+                let sled_task: Pin<Box<dyn Future<Output = Result<(), AppError>> + Send>> = Box::pin(async move {
+                    sled.set_ex(&key, &value, self.ttl_seconds).await?;
+                    metrics::record_cache_insert("sled");
+                    Ok(())
+                });
                 tasks.push(sled_task);
             }
         }
@@ -241,7 +366,21 @@ impl CacheService {
 
     pub async fn delete(&self, key: &str) -> Result<(), AppError> {
         let start = Instant::now();
-        let dragonfly_task = self.dragonfly.delete_url(key, None, "");
+        self.hot.remove(key);
+        // A queued write replayed after the delete would bring the link back
+        let unqueued = {
+            let mut queued = self.queued_writes.lock();
+            let before = queued.len();
+            queued.retain(|(queued_key, _)| queued_key != key);
+            queued.len() < before
+        };
+        let dragonfly_task = async move {
+            match self.dragonfly.delete_url(key, None, "").await {
+                // The link never got past the queue
+                Err(AppError::NotFound(_)) if unqueued => Ok(()),
+                result => result,
+            }
+        };
         let l1_task = async move {
             self.l1.remove(key).await;
             Ok::<(), AppError>(())
@@ -263,10 +402,100 @@ impl CacheService {
             }
         }
         future::try_join_all(tasks).await?;
+        if let Some(replicator) = &self.replicator {
+            replicator.record(key, None);
+        }
         metrics::record_cache_latency("delete", start);
         Ok(())
     }
 
+    /// Drops `key` from this instance's in-memory tiers, after storage has already removed it.
+    pub async fn evict_local(&self, key: &str) {
+        self.hot.remove(key);
+        self.l1.remove(key).await;
+        self.l2.remove(key).await;
+    }
+
+    /// Drops a link storage has already deleted or burned from every tier above it, including
+    /// the Sled copy and any queued write that would bring it back, and tells peers to drop it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if Sled can't remove its copy.
+    pub async fn purge(&self, key: &str) -> Result<(), AppError> {
+        self.queued_writes.lock().retain(|(queued_key, _)| queued_key != key);
+        self.evict_local(key).await;
+        if self.use_sled
+            && let Some(sled) = &self.sled
+        {
+            sled.remove(key)?;
+        }
+        if let Some(replicator) = &self.replicator {
+            replicator.record(key, None);
+        }
+        Ok(())
+    }
+
+    /// Burns a one-time link: storage tombstones and deletes it, then every tier here drops it
+    /// too. Only the first caller gets `true`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if storage can't record the tombstone or Sled can't drop its copy.
+    pub async fn burn(&self, key: &str, burned_at: u64) -> Result<bool, AppError> {
+        if !self.dragonfly.burn_code(key, burned_at).await? {
+            return Ok(false);
+        }
+        self.purge(key).await?;
+        Ok(true)
+    }
+
+    /// Drops `key` from L1 only, so its next read is served by L2. Lets benches time one tier.
+    pub async fn evict_l1(&self, key: &str) {
+        self.l1.remove(key).await;
+    }
+
+    pub fn circuit_breaker(&self) -> &Arc<CircuitBreaker> {
+        &self.circuit_breaker
+    }
+
+    pub fn dragonfly(&self) -> &Arc<dyn Storage + Send + Sync> {
+        &self.dragonfly
+    }
+
+    pub fn hot_set(&self) -> &HotSet {
+        &self.hot
+    }
+
+    /// Rebuilds the hot set from the clicks seen since the last rebuild. Links that stopped
+    /// resolving are simply left out.
+    pub async fn refresh_hot_set(&self) -> usize {
+        let mut links = std::collections::HashMap::new();
+        for code in self.hot.take_top() {
+            if let Ok(url_data) = self.get_url_data(&code).await {
+                links.insert(code, url_data);
+            }
+        }
+        let count = links.len();
+        self.hot.replace(links);
+        metrics::set_hot_set_size(count);
+        count
+    }
+
+    /// Periodically rebuilds the hot set; a no-op when it is disabled.
+    pub fn spawn_hot_set_refresh(self: Arc<Self>, every: Duration) {
+        if self.hot.capacity() == 0 {
+            return;
+        }
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                self.refresh_hot_set().await;
+            }
+        });
+    }
+
     pub fn contains_key(&self, key: &str) -> bool {
         self.bloom.contains(key.as_bytes())
     }
@@ -286,6 +515,7 @@ impl CacheService {
             async move {
                 if let Ok(value) = dragonfly.get(key).await {
                     sled.set_ex(key, &value, ttl).await?;
+                    metrics::record_cache_insert("sled");
                 }
                 Ok::<(), AppError>(())
             }
@@ -314,6 +544,115 @@ impl CacheService {
         Ok(())
     }
 
+    /// Warms the caches with up to `limit` links from Dragonfly. The bloom filter starts empty,
+    /// so until a link is warmed or re-inserted its lookups stop at the bloom check.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the links can't be listed from storage.
+    pub async fn warmup_from_storage(&self, limit: usize) -> Result<usize, AppError> {
+        let keys = self.matching_links("*", limit).await?;
+        let count = keys.len();
+        self.warmup(keys).await;
+        Ok(count)
+    }
+
+    /// Up to `limit` link codes in Dragonfly matching the glob `pattern`.
+    pub(crate) async fn matching_links(&self, pattern: &str, limit: usize) -> Result<Vec<String>, AppError> {
+        // Links live under their bare code; everything namespaced is other data
+        Ok(self
+            .dragonfly
+            .scan_keys(pattern, 1000)
+            .await?
+            .into_iter()
+            .filter(|key| !key.contains(':'))
+            .take(limit)
+            .collect())
+    }
+
+    pub fn warmup_keys_per_sec(&self) -> usize {
+        self.warmup_keys_per_sec
+    }
+
+    /// Warms `keys` a second's worth (`warmup_keys_per_sec`) at a time, so warming ahead of a
+    /// traffic spike doesn't put one on Dragonfly itself.
+    pub async fn warmup_throttled(&self, keys: Vec<String>) {
+        let mut ticker = tokio::time::interval(Duration::from_secs(1));
+        for batch in keys.chunks(self.warmup_keys_per_sec) {
+            ticker.tick().await;
+            self.warmup(batch.to_vec()).await;
+        }
+    }
+
+    /// Starts rebuilding the bloom filter from every link in storage, in the background. Lookups
+    /// keep using the current filter until the new one is complete and swapped in.
+    pub(crate) fn start_bloom_rebuild(&self) -> Result<BloomRebuildStatus, AppError> {
+        let status = {
+            let mut status = self.bloom_rebuild.lock();
+            if status.state == "running" {
+                return Err(AppError::Conflict("A bloom filter rebuild is already running".into()));
+            }
+            *status = BloomRebuildStatus {
+                state: "running".into(),
+                started_at: Some(chrono::Utc::now().to_rfc3339()),
+                ..Default::default()
+            };
+            status.clone()
+        };
+        // Started before returning, so every insert from here on also lands in the new filter
+        let next = self.bloom.begin_rebuild();
+        let cache = self.clone();
+        tokio::spawn(async move {
+            let start = Instant::now();
+            let result = cache.rebuild_bloom(next).await;
+            let mut status = cache.bloom_rebuild.lock();
+            status.finished_at = Some(chrono::Utc::now().to_rfc3339());
+            match result {
+                Ok(()) => {
+                    status.state = "completed".into();
+                    info!("Rebuilt the bloom filter from {} keys in {:?}", status.keys_inserted, start.elapsed());
+                }
+                Err(e) => {
+                    warn!("Bloom filter rebuild failed, keeping the current filter: {}", e);
+                    status.state = "failed".into();
+                    status.error = Some(e.to_string());
+                }
+            }
+        });
+        Ok(status)
+    }
+
+    pub fn bloom_rebuild_status(&self) -> BloomRebuildStatus {
+        self.bloom_rebuild.lock().clone()
+    }
+
+    async fn rebuild_bloom(&self, next: Arc<CacheBloom>) -> Result<(), AppError> {
+        let result = async {
+            let mut keys = self.dragonfly.scan_keys("*", 1000).await?;
+            // Links only Sled has, e.g. written while degraded, still resolve
+            if let Some(sled) = self.sled.as_ref() {
+                keys.extend(sled.scan_keys("", u32::MAX).await?);
+            }
+            for chunk in keys.chunks(BLOOM_REBUILD_CHUNK) {
+                let links = chunk.iter().filter(|key| !key.contains(':'));
+                let mut inserted = 0;
+                for key in links {
+                    next.insert(key.as_bytes());
+                    inserted += 1;
+                }
+                self.bloom_rebuild.lock().keys_inserted += inserted;
+                tokio::task::yield_now().await;
+            }
+            Ok(())
+        }
+        .await;
+        match result {
+            Ok(()) => self.bloom.finish_rebuild(),
+            Err(_) => self.bloom.abort_rebuild(),
+        }
+        result
+    }
+
     pub async fn warmup(&self, keys: Vec<String>) {
         let start = Instant::now();
         let chunks: Vec<_> = keys.chunks(1000).collect();
@@ -336,16 +675,20 @@ impl CacheService {
                     async move {
                         let op_start = Instant::now();
                         if let Ok(url) = dragonfly.get(&key).await {
-                            l2.insert(key.clone(), url.clone()).await;
-                            l1.insert(key.clone(), url.clone()).await;
+                            if let Ok(url_data) = decode(&url) {
+                                l1.insert(key.clone(), Arc::new(url_data)).await;
+                            }
+                            l2.insert(key.clone(), url).await;
                             bloom.insert(key.as_bytes());
                             metrics::record_cache_hit("warmup", op_start);
                         } else if use_sled {
                             if let Some(sled) = sled.as_ref() {
                                 if let Ok(url) = sled.get(&key).await {
                                     dragonfly.set_ex(&key, &url, ttl).await.ok();
-                                    l2.insert(key.clone(), url.clone()).await;
-                                    l1.insert(key.clone(), url.clone()).await;
+                                    if let Ok(url_data) = decode(&url) {
+                                        l1.insert(key.clone(), Arc::new(url_data)).await;
+                                    }
+                                    l2.insert(key.clone(), url).await;
                                     bloom.insert(key.as_bytes());
                                     metrics::record_cache_hit("warmup", op_start);
                                 }
@@ -360,4 +703,99 @@ impl CacheService {
         metrics::record_cache_latency("warmup", start);
         info!("Cache warmup completed in {:?}", start.elapsed());
     }
-}
\ No newline at end of file
+}
+
+fn decode(json: &str) -> Result<UrlData, AppError> {
+    serde_json::from_str(json).map_err(|e| AppError::Internal(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::SystemClock, test_util::MockStorage};
+
+    #[tokio::test]
+    async fn degraded_cache_reads_from_sled_and_replays_writes() {
+        let dir = std::env::temp_dir().join(format!("hyperlinkr-degraded-{}", std::process::id()));
+        let mut config = Settings::default();
+        config.cache.sled_path = dir.display().to_string();
+        let storage = Arc::new(MockStorage::new());
+        let cache = CacheService::with_storage(&config, storage.clone(), Arc::new(SystemClock)).await;
+        let breaker = Arc::clone(cache.circuit_breaker());
+        for node in breaker.snapshot().await {
+            breaker.trip(&node.node).await;
+        }
+        assert!(cache.is_degraded().await);
+
+        let url_data = UrlData { long_url: "https://example.com/outage".to_string(), ..Default::default() };
+        cache.insert("down1".to_string(), &url_data).await.unwrap();
+        assert!(storage.get("down1").await.is_err());
+        cache.evict_local("down1").await;
+        assert_eq!(cache.get_url_data("down1").await.unwrap().long_url, url_data.long_url);
+
+        for node in breaker.snapshot().await {
+            breaker.reset(&node.node).await;
+        }
+        assert!(!cache.is_degraded().await);
+        assert_eq!(cache.replay_queued_writes().await, 1);
+        assert!(storage.get("down1").await.is_ok());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn write_back_acknowledges_before_dragonfly_has_the_write() {
+        let dir = std::env::temp_dir().join(format!("hyperlinkr-write-back-{}", std::process::id()));
+        let mut config = Settings::default();
+        config.cache.sled_path = dir.display().to_string();
+        config.cache.write_mode = Some(WriteMode::WriteBack);
+        config.cache.write_back_flush_ms = Some(60_000);
+        let storage = Arc::new(MockStorage::new());
+        let cache = CacheService::with_storage(&config, storage.clone(), Arc::new(SystemClock)).await;
+
+        let url_data = UrlData { long_url: "https://example.com/fast".to_string(), ..Default::default() };
+        cache.insert("wb1".to_string(), &url_data).await.unwrap();
+        assert!(storage.get("wb1").await.is_err());
+        assert_eq!(cache.get_url_data("wb1").await.unwrap().long_url, url_data.long_url);
+
+        assert_eq!(cache.replay_queued_writes().await, 1);
+        assert!(storage.get("wb1").await.is_ok());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn burned_links_are_gone_from_every_tier() {
+        let dir = std::env::temp_dir().join(format!("hyperlinkr-burn-{}", std::process::id()));
+        let mut config = Settings::default();
+        config.cache.sled_path = dir.display().to_string();
+        let cache = CacheService::with_storage(&config, Arc::new(MockStorage::new()), Arc::new(SystemClock)).await;
+        let url_data = UrlData { long_url: "https://example.com/once".to_string(), burn_after_read: true, ..Default::default() };
+        cache.insert("once".to_string(), &url_data).await.unwrap();
+        cache.refresh_hot_set().await;
+
+        assert!(cache.burn("once", 1).await.unwrap());
+        assert!(!cache.burn("once", 2).await.unwrap());
+        assert!(cache.hot_set().get("once").is_none());
+        // The Sled copy would otherwise answer and be backfilled into Dragonfly
+        assert!(matches!(cache.get_raw("once", Instant::now()).await, Err(AppError::NotFound(_))));
+        assert!(cache.dragonfly().get("once").await.is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn bloom_rebuild_restores_stored_links() {
+        let storage = Arc::new(MockStorage::new());
+        let cache = CacheService::with_storage(&Settings::default(), storage.clone(), Arc::new(SystemClock)).await;
+        storage.set_ex("stored1", "{}", 60).await.unwrap();
+        assert!(!cache.contains_key("stored1"));
+
+        cache.start_bloom_rebuild().unwrap();
+        assert!(matches!(cache.start_bloom_rebuild(), Err(AppError::Conflict(_))));
+        cache.bloom.insert(b"during");
+        while cache.bloom_rebuild_status().state == "running" {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(cache.bloom_rebuild_status().state, "completed");
+        assert!(cache.contains_key("stored1"));
+        assert!(cache.contains_key("during"));
+    }
+}
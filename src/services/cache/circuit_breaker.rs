@@ -2,14 +2,63 @@ use std::collections::HashMap;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use tracing::{info, warn};
-use rand::seq::SliceRandom;
+use rand::seq::IndexedRandom;
 use rand::rng;
+use serde::Serialize;
+use url::Url;
+use crate::{config::cache::CacheConfig, services::metrics};
+
+/// Where a node's breaker stands. An open breaker turns half-open once `retry_interval` has
+/// passed, letting a few probe requests through; the first to succeed closes it and the first
+/// to fail opens it again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl BreakerState {
+    fn as_str(self) -> &'static str {
+        match self {
+            BreakerState::Closed => "closed",
+            BreakerState::Open => "open",
+            BreakerState::HalfOpen => "half_open",
+        }
+    }
+}
 
 #[derive(Clone)]
 struct NodeState {
     failure_count: u32,
     last_failure: Instant,
-    is_healthy: bool,
+    state: BreakerState,
+    probes: u32, // Requests let through since the breaker went half-open
+    forced_open: bool, // Tripped by an operator; only a manual reset closes it
+}
+
+impl NodeState {
+    fn closed(retry_interval: Duration) -> Self {
+        Self {
+            failure_count: 0,
+            last_failure: Instant::now() - retry_interval,
+            state: BreakerState::Closed,
+            probes: 0,
+            forced_open: false,
+        }
+    }
+}
+
+/// Point-in-time view of one node, as exported to metrics and /v1/admin/circuit-breakers.
+#[derive(Clone, Debug, Serialize)]
+pub struct NodeStatus {
+    pub node: String, // Node URL with credentials removed
+    pub healthy: bool,
+    pub state: BreakerState,
+    pub forced_open: bool,
+    pub failure_count: u32,
+    pub secs_since_last_failure: Option<f64>, // None until the node has failed once
 }
 
 pub struct CircuitBreaker {
@@ -17,66 +66,156 @@ pub struct CircuitBreaker {
     nodes: Vec<String>,
     retry_interval: Duration,
     max_failures: u32,
+    half_open_probes: u32,
 }
 
 impl CircuitBreaker {
     pub fn new(nodes: Vec<String>, max_failures: u32, retry_interval: Duration) -> Self {
-        let state = nodes.iter().map(|node| {
-            (node.clone(), NodeState {
-                failure_count: 0,
-                last_failure: Instant::now() - retry_interval,
-                is_healthy: true,
-            })
-        }).collect();
+        let state = nodes.iter().map(|node| (node.clone(), NodeState::closed(retry_interval))).collect();
         Self {
             state: RwLock::new(state),
             nodes,
             retry_interval,
             max_failures,
+            half_open_probes: 1,
+        }
+    }
+
+    /// A breaker for `nodes` with the thresholds in `[cache]`.
+    pub fn from_config(nodes: Vec<String>, cache: &CacheConfig) -> Self {
+        Self {
+            half_open_probes: cache.half_open_max_probes.unwrap_or(1),
+            ..Self::new(nodes, cache.max_failures, Duration::from_secs(cache.retry_interval_secs))
         }
     }
 
     pub async fn get_healthy_node(&self) -> Option<String> {
-        let state = self.state.read().await;
-        let mut healthy_nodes: Vec<_> = state.iter()
-            .filter(|(_, s)| s.is_healthy || s.last_failure.elapsed() > self.retry_interval)
-            .map(|(node, _)| node)
-            .collect();
-        if healthy_nodes.is_empty() {
+        let node = self.pick_node(None).await;
+        if node.is_none() {
             warn!("No healthy nodes available");
-            return None;
         }
-        healthy_nodes.shuffle(&mut rng());
-        healthy_nodes.first().map(|&node| node.clone())
+        node
     }
 
+    /// A healthy node other than `exclude`, for hedging a read that `exclude` is slow to answer.
+    pub async fn get_healthy_node_except(&self, exclude: &str) -> Option<String> {
+        self.pick_node(Some(exclude)).await
+    }
+
+    /// A random node that would take a request, preferring closed ones; picking a half-open
+    /// node uses up one of its probes.
+    async fn pick_node(&self, exclude: Option<&str>) -> Option<String> {
+        let mut state = self.state.write().await;
+        let mut closed = Vec::new();
+        let mut half_open = Vec::new();
+        for (node, node_state) in state.iter_mut() {
+            if exclude == Some(node.as_str()) {
+                continue;
+            }
+            match self.poll(node, node_state) {
+                BreakerState::Closed => closed.push(node.clone()),
+                BreakerState::HalfOpen if node_state.probes < self.half_open_probes => half_open.push(node.clone()),
+                _ => {}
+            }
+        }
+        if let Some(node) = closed.choose(&mut rng()) {
+            return Some(node.clone());
+        }
+        let node = half_open.choose(&mut rng())?.clone();
+        if let Some(node_state) = state.get_mut(&node) {
+            node_state.probes += 1;
+        }
+        Some(node)
+    }
+
+    /// `node_state`'s state right now, moving an open breaker that has waited out
+    /// `retry_interval` to half-open.
+    fn poll(&self, node: &str, node_state: &mut NodeState) -> BreakerState {
+        if node_state.forced_open {
+            return BreakerState::Open;
+        }
+        if node_state.state == BreakerState::Open && node_state.last_failure.elapsed() > self.retry_interval {
+            node_state.probes = 0;
+            transition(node, node_state, BreakerState::HalfOpen);
+        }
+        node_state.state
+    }
+
+    /// True when no node would be tried: each is open and not yet due for a retry, or half-open
+    /// with its probes already out.
+    pub async fn all_open(&self) -> bool {
+        let state = self.state.read().await;
+        !state.is_empty() && state.values().all(|s| {
+            s.forced_open
+                || match s.state {
+                    BreakerState::Closed => false,
+                    BreakerState::Open => s.last_failure.elapsed() <= self.retry_interval,
+                    BreakerState::HalfOpen => s.probes >= self.half_open_probes,
+                }
+        })
+    }
+
+    /// Counts a failure against `node`. Enough in a row open its breaker; a failed probe
+    /// reopens a half-open one straight away.
     pub async fn record_failure(&self, node: &str) {
         let mut state = self.state.write().await;
         if let Some(node_state) = state.get_mut(node) {
             node_state.failure_count += 1;
             node_state.last_failure = Instant::now();
-            if node_state.failure_count >= self.max_failures {
-                node_state.is_healthy = false;
-                info!("Circuit breaker tripped for node {}", node);
+            let trips = match node_state.state {
+                BreakerState::Closed => node_state.failure_count >= self.max_failures,
+                BreakerState::HalfOpen => true,
+                BreakerState::Open => false,
+            };
+            if trips {
+                transition(node, node_state, BreakerState::Open);
+                info!("Circuit breaker tripped for node {}", redact_node(node));
+            }
+        }
+    }
+
+    /// Records a request `node` answered: failures stop counting as consecutive, and a
+    /// successful probe closes a half-open breaker.
+    pub async fn record_success(&self, node: &str) {
+        {
+            let state = self.state.read().await;
+            match state.get(node) {
+                Some(s) if s.state == BreakerState::Closed && s.failure_count == 0 => return,
+                None => return,
+                _ => {}
+            }
+        }
+        let mut state = self.state.write().await;
+        if let Some(node_state) = state.get_mut(node) {
+            if node_state.forced_open {
+                return;
+            }
+            node_state.failure_count = 0;
+            if node_state.state != BreakerState::Closed {
+                transition(node, node_state, BreakerState::Closed);
+                info!("Circuit breaker closed for node {} after a successful probe", redact_node(node));
             }
         }
     }
 
     pub async fn add_node(&self, node: String) {
         let mut state = self.state.write().await;
-        state.entry(node.clone()).or_insert(NodeState {
-            failure_count: 0,
-            last_failure: Instant::now() - self.retry_interval,
-            is_healthy: true,
-        });
+        state.entry(node.clone()).or_insert(NodeState::closed(self.retry_interval));
         info!("Added node {}", node);
     }
 
+    /// Stops tracking `node`, e.g. once it has been drained out of the ring.
+    pub async fn remove_node(&self, node: &str) {
+        if self.state.write().await.remove(node).is_some() {
+            info!("Removed node {}", redact_node(node));
+        }
+    }
+
     pub async fn reset_unhealthy(&self) {
         let mut state = self.state.write().await;
         for (node, node_state) in state.iter_mut() {
-            if !node_state.is_healthy && node_state.last_failure.elapsed() > self.retry_interval {
-                node_state.is_healthy = true;
+            if node_state.state != BreakerState::Closed && !node_state.forced_open && node_state.last_failure.elapsed() > self.retry_interval {
+                transition(node, node_state, BreakerState::Closed);
                 node_state.failure_count = 0;
                 info!("Reset node {}", node);
             }
@@ -86,4 +225,115 @@ impl CircuitBreaker {
     pub fn get_node_index(&self, node: &str) -> Option<usize> {
         self.nodes.iter().position(|n| n == node)
     }
+
+    pub async fn snapshot(&self) -> Vec<NodeStatus> {
+        let state = self.state.read().await;
+        let mut nodes: Vec<NodeStatus> = state
+            .iter()
+            .map(|(node, s)| NodeStatus {
+                node: redact_node(node),
+                healthy: s.state == BreakerState::Closed && !s.forced_open,
+                state: if s.forced_open { BreakerState::Open } else { s.state },
+                forced_open: s.forced_open,
+                failure_count: s.failure_count,
+                secs_since_last_failure: (s.failure_count > 0 || s.state != BreakerState::Closed)
+                    .then(|| s.last_failure.elapsed().as_secs_f64()),
+            })
+            .collect();
+        nodes.sort_by(|a, b| a.node.cmp(&b.node));
+        nodes
+    }
+
+    /// Opens the breaker for `node` (given as reported by `snapshot`) until `reset` is called.
+    pub async fn trip(&self, node: &str) -> bool {
+        let mut state = self.state.write().await;
+        match state.iter_mut().find(|(url, _)| redact_node(url) == node) {
+            Some((url, node_state)) => {
+                node_state.forced_open = true;
+                node_state.last_failure = Instant::now();
+                transition(url, node_state, BreakerState::Open);
+                warn!("Circuit breaker manually tripped for node {}", node);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Closes the breaker for `node` and clears its failure count.
+    pub async fn reset(&self, node: &str) -> bool {
+        let mut state = self.state.write().await;
+        match state.iter_mut().find(|(url, _)| redact_node(url) == node) {
+            Some((url, node_state)) => {
+                transition(url, node_state, BreakerState::Closed);
+                *node_state = NodeState::closed(self.retry_interval);
+                info!("Circuit breaker manually reset for node {}", node);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn transition(node: &str, node_state: &mut NodeState, to: BreakerState) {
+    if node_state.state != to {
+        metrics::record_circuit_breaker_transition(&redact_node(node), node_state.state.as_str(), to.as_str());
+        node_state.state = to;
+    }
+}
+
+/// Strips credentials so node URLs are safe for metric labels and API responses.
+pub(crate) fn redact_node(node: &str) -> String {
+    match Url::parse(node) {
+        Ok(mut url) => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            url.to_string()
+        }
+        Err(_) => node.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn manual_trip_holds_until_reset() {
+        let node = "redis://:secret@cache-1:6379";
+        let breaker = CircuitBreaker::new(vec![node.to_string()], 3, Duration::ZERO);
+        let label = breaker.snapshot().await[0].node.clone();
+        assert!(!label.contains("secret"));
+
+        assert!(breaker.trip(&label).await);
+        breaker.reset_unhealthy().await;
+        // A zero retry interval would normally re-admit the node immediately
+        assert_eq!(breaker.get_healthy_node().await, None);
+
+        assert!(breaker.reset(&label).await);
+        assert_eq!(breaker.get_healthy_node().await.as_deref(), Some(node));
+        assert!(!breaker.trip("redis://unknown:6379").await);
+    }
+
+    #[tokio::test]
+    async fn half_open_breaker_admits_one_probe_and_closes_on_success() {
+        let node = "redis://cache-1:6379";
+        let breaker = CircuitBreaker::new(vec![node.to_string()], 1, Duration::from_millis(20));
+        breaker.record_failure(node).await;
+        assert_eq!(breaker.get_healthy_node().await, None);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(breaker.get_healthy_node().await.as_deref(), Some(node));
+        // The single probe is out, so nothing else gets through until it reports back
+        assert_eq!(breaker.snapshot().await[0].state, BreakerState::HalfOpen);
+        assert_eq!(breaker.get_healthy_node().await, None);
+        assert!(breaker.all_open().await);
+
+        breaker.record_failure(node).await;
+        assert_eq!(breaker.snapshot().await[0].state, BreakerState::Open);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(breaker.get_healthy_node().await.is_some());
+        breaker.record_success(node).await;
+        let status = &breaker.snapshot().await[0];
+        assert_eq!((status.state, status.failure_count), (BreakerState::Closed, 0));
+    }
 }
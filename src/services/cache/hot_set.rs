@@ -0,0 +1,88 @@
+use std::{collections::HashMap, sync::{atomic::{AtomicU64, Ordering}, Arc}};
+use arc_swap::ArcSwap;
+use dashmap::DashMap;
+use crate::types::UrlData;
+
+/// Read-only snapshot of the most-clicked links, swapped in whole on each rebuild so lookups
+/// never take a lock. Sits in front of L1 for viral links.
+pub struct HotSet {
+    links: ArcSwap<HashMap<String, Arc<UrlData>>>,
+    clicks: DashMap<String, AtomicU64>, // Redirects per code since the last rebuild
+    capacity: usize,
+}
+
+impl HotSet {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            links: ArcSwap::from_pointee(HashMap::new()),
+            clicks: DashMap::new(),
+            capacity,
+        }
+    }
+
+    #[inline(always)]
+    pub fn get(&self, code: &str) -> Option<Arc<UrlData>> {
+        self.links.load().get(code).cloned()
+    }
+
+    pub fn record_click(&self, code: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        // Counted codes only need a shard read lock; a code's first click is the one that allocates
+        if let Some(count) = self.clicks.get(code) {
+            count.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.clicks.entry(code.to_string()).or_default().fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Drains the click counters and returns the busiest codes, most clicked first.
+    pub fn take_top(&self) -> Vec<String> {
+        let mut counts: Vec<(String, u64)> = self.clicks.iter().map(|e| (e.key().clone(), e.value().load(Ordering::Relaxed))).collect();
+        self.clicks.clear();
+        counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts.truncate(self.capacity);
+        counts.into_iter().map(|(code, _)| code).collect()
+    }
+
+    pub fn replace(&self, links: HashMap<String, Arc<UrlData>>) {
+        self.links.store(Arc::new(links));
+    }
+
+    /// Drops a rewritten or deleted link so the snapshot never outlives the tiers below it.
+    pub fn remove(&self, code: &str) {
+        if !self.links.load().contains_key(code) {
+            return;
+        }
+        self.links.rcu(|links| {
+            let mut links = HashMap::clone(links);
+            links.remove(code);
+            links
+        });
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn busiest_codes_are_kept_and_counters_reset() {
+        let hot = HotSet::new(2);
+        for code in ["a", "b", "b", "c", "c", "c"] {
+            hot.record_click(code);
+        }
+        assert_eq!(hot.take_top(), ["c", "b"]);
+        assert!(hot.take_top().is_empty());
+
+        hot.replace(HashMap::from([("c".to_string(), Arc::new(UrlData::default()))]));
+        assert!(hot.get("c").is_some());
+        hot.remove("c");
+        assert!(hot.get("c").is_none());
+    }
+}
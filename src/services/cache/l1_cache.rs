@@ -1,14 +1,18 @@
 use std::sync::Arc;
 use moka::future::Cache;
-use crate::services::metrics;
+use crate::{services::{cache::policy::TierPolicy, metrics}, types::UrlData};
 
 #[derive(Clone)]
 pub struct L1Cache {
-    inner: Arc<Cache<String, String>>,
+    inner: Arc<Cache<String, Arc<UrlData>>>, // Parsed, so hits skip deserialization entirely
 }
 
 impl L1Cache {
     pub fn new(capacity: usize, ttl_seconds: u64) -> Self {
+        Self::with_policy(capacity, TierPolicy::ttl(ttl_seconds))
+    }
+
+    pub fn with_policy(capacity: usize, policy: TierPolicy) -> Self {
         #[cfg(feature = "libnuma")]
         unsafe {
             if lib_numa::numa_available() >= 0 {
@@ -16,31 +20,35 @@ impl L1Cache {
             }
         }
 
+        let mut builder = Cache::builder()
+            .max_capacity(capacity as u64)
+            .eviction_policy(policy.moka_eviction())
+            .eviction_listener(|_key, _value, cause| {
+                if cause.was_evicted() {
+                    metrics::record_cache_eviction("l1", 1);
+                }
+            });
+        if let Some(ttl) = policy.time_to_live {
+            builder = builder.time_to_live(ttl);
+        }
+        if let Some(tti) = policy.time_to_idle {
+            builder = builder.time_to_idle(tti);
+        }
         Self {
-            inner: Arc::new(Cache::builder()
-                .max_capacity(capacity as u64)
-                .time_to_live(std::time::Duration::from_secs(ttl_seconds))
-                .eviction_policy(moka::policy::EvictionPolicy::tiny_lfu())
-                .build()),
+            inner: Arc::new(builder.build()),
         }
     }
 
     #[inline(always)]
-    pub async fn get(&self, key: &str) -> Option<String> {
-        let start = std::time::Instant::now();
-        let val = self.inner.get(key).await;
-        if val.is_some() {
-            metrics::CACHE_HITS.get().unwrap().with_label_values(&["l1"]).inc();
-            metrics::CACHE_LATENCY
-                .get().unwrap().with_label_values(&["l1"])
-                .observe(start.elapsed().as_secs_f64());
-        }
-        val
+    pub async fn get(&self, key: &str) -> Option<Arc<UrlData>> {
+        // Hits and misses are counted by CacheService, which knows the lookup order
+        self.inner.get(key).await
     }
 
     #[inline]
-    pub async fn insert(&self, key: String, value: String) {
+    pub async fn insert(&self, key: String, value: Arc<UrlData>) {
         self.inner.insert(key, value).await;
+        metrics::record_cache_insert("l1");
     }
 
     pub async fn remove(&self, key: &str) {
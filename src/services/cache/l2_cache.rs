@@ -1,6 +1,5 @@
 use moka::future::Cache;
-use std::time::Duration;
-use crate::services::metrics;
+use crate::services::{cache::policy::TierPolicy, metrics};
 
 pub struct L2Cache {
     pub inner: Cache<String, String>,
@@ -8,30 +7,37 @@ pub struct L2Cache {
 
 impl L2Cache {
     pub fn new(capacity: usize, ttl_seconds: u64) -> Self {
-        let inner = Cache::builder()
+        Self::with_policy(capacity, TierPolicy::ttl(ttl_seconds))
+    }
+
+    pub fn with_policy(capacity: usize, policy: TierPolicy) -> Self {
+        let mut builder = Cache::builder()
             .max_capacity(capacity as u64)
-            .time_to_live(Duration::from_secs(ttl_seconds))
-            .eviction_policy(moka::policy::EvictionPolicy::tiny_lfu())
-            .build();
-        Self { inner }
+            .eviction_policy(policy.moka_eviction())
+                .eviction_listener(|_key, _value, cause| {
+                    if cause.was_evicted() {
+                        metrics::record_cache_eviction("l2", 1);
+                    }
+                });
+        if let Some(ttl) = policy.time_to_live {
+            builder = builder.time_to_live(ttl);
+        }
+        if let Some(tti) = policy.time_to_idle {
+            builder = builder.time_to_idle(tti);
+        }
+        Self { inner: builder.build() }
     }
 
     pub async fn get(&self, key: &str) -> Option<String> {
-        let start = std::time::Instant::now();
-        let val = self.inner.get(key).await;
-        if val.is_some() {
-            metrics::CACHE_HITS.get().unwrap().with_label_values(&["l2"]).inc();
-            metrics::CACHE_LATENCY
-                .get().unwrap().with_label_values(&["l2"])
-                .observe(start.elapsed().as_secs_f64());
-        }
-        val
+        // Hits and misses are counted by CacheService, which knows the lookup order
+        self.inner.get(key).await
     }
 
 
     
     pub async fn insert(&self, key: String, value: String) {
         self.inner.insert(key, value).await;
+        metrics::record_cache_insert("l2");
     }
 
     pub async fn remove(&self, key: &str) {
@@ -2,4 +2,6 @@ pub mod bloom_filter;
 pub mod l2_cache;
 pub mod circuit_breaker;
 pub mod l1_cache;
+pub mod hot_set;
+pub mod policy;
 pub mod cache;
\ No newline at end of file
@@ -0,0 +1,64 @@
+use std::time::Duration;
+use crate::config::cache::{CacheConfig, CacheExpiry, EvictionPolicy};
+
+/// Eviction and expiry settings for one in-process tier, resolved from `CacheConfig`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TierPolicy {
+    pub eviction: EvictionPolicy,
+    pub time_to_live: Option<Duration>,
+    pub time_to_idle: Option<Duration>,
+}
+
+impl TierPolicy {
+    /// TinyLFU with entries expiring `ttl_seconds` after they are written.
+    pub fn ttl(ttl_seconds: u64) -> Self {
+        Self::resolve(ttl_seconds, None, None, None)
+    }
+
+    pub fn l1(config: &CacheConfig) -> Self {
+        Self::resolve(config.ttl_seconds, config.l1_eviction_policy, config.l1_expiry, config.l1_idle_secs)
+    }
+
+    pub fn l2(config: &CacheConfig) -> Self {
+        Self::resolve(config.ttl_seconds, config.l2_eviction_policy, config.l2_expiry, config.l2_idle_secs)
+    }
+
+    fn resolve(
+        ttl_seconds: u64,
+        eviction: Option<EvictionPolicy>,
+        expiry: Option<CacheExpiry>,
+        idle_secs: Option<u64>,
+    ) -> Self {
+        let (time_to_live, time_to_idle) = match expiry.unwrap_or_default() {
+            CacheExpiry::Ttl => (Some(ttl_seconds), idle_secs),
+            CacheExpiry::Tti => (None, Some(idle_secs.unwrap_or(ttl_seconds))),
+        };
+        Self {
+            eviction: eviction.unwrap_or_default(),
+            time_to_live: time_to_live.map(Duration::from_secs),
+            time_to_idle: time_to_idle.map(Duration::from_secs),
+        }
+    }
+
+    pub fn moka_eviction(&self) -> moka::policy::EvictionPolicy {
+        match self.eviction {
+            EvictionPolicy::TinyLfu => moka::policy::EvictionPolicy::tiny_lfu(),
+            EvictionPolicy::Lru => moka::policy::EvictionPolicy::lru(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_time_defaults_to_ttl_seconds_only_under_tti() {
+        let ttl = TierPolicy::resolve(60, None, None, Some(5));
+        assert_eq!((ttl.time_to_live, ttl.time_to_idle), (Some(Duration::from_secs(60)), Some(Duration::from_secs(5))));
+
+        let tti = TierPolicy::resolve(60, Some(EvictionPolicy::Lru), Some(CacheExpiry::Tti), None);
+        assert_eq!(tti.eviction, EvictionPolicy::Lru);
+        assert_eq!((tti.time_to_live, tti.time_to_idle), (None, Some(Duration::from_secs(60))));
+    }
+}
@@ -0,0 +1,122 @@
+use moka::future::Cache;
+use std::{sync::Arc, time::Duration};
+use url::Url;
+
+use crate::{
+    errors::AppError,
+    services::storage::storage::Storage,
+    types::{Campaign, UtmDefaults},
+};
+
+const CACHE_CAPACITY: u64 = 10_000;
+// Redirects pick up campaign edits made on other instances within this window
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Campaign records with a read-through cache, since every redirect of a campaign link needs
+/// the campaign's UTM defaults. Membership itself lives on `UrlData::campaign_id`. Users' own
+/// UTM templates are cached alongside for the same reason.
+pub struct CampaignService {
+    db: Arc<dyn Storage + Send + Sync>,
+    cache: Cache<String, Arc<Campaign>>,
+    user_templates: Cache<String, Option<Arc<UtmDefaults>>>,
+}
+
+impl CampaignService {
+    pub fn new(db: Arc<dyn Storage + Send + Sync>) -> Self {
+        Self {
+            db,
+            cache: Cache::builder()
+                .max_capacity(CACHE_CAPACITY)
+                .time_to_live(CACHE_TTL)
+                .build(),
+            user_templates: Cache::builder()
+                .max_capacity(CACHE_CAPACITY)
+                .time_to_live(CACHE_TTL)
+                .build(),
+        }
+    }
+
+    pub(crate) async fn get(&self, id: &str) -> Result<Option<Arc<Campaign>>, AppError> {
+        if let Some(campaign) = self.cache.get(id).await {
+            return Ok(Some(campaign));
+        }
+        let Some(campaign) = self.db.get_campaign(id).await? else {
+            return Ok(None);
+        };
+        let campaign = Arc::new(campaign);
+        self.cache.insert(id.to_string(), Arc::clone(&campaign)).await;
+        Ok(Some(campaign))
+    }
+
+    pub(crate) async fn save(&self, campaign: Campaign) -> Result<Campaign, AppError> {
+        self.db.set_campaign(&campaign).await?;
+        self.cache.insert(campaign.id.clone(), Arc::new(campaign.clone())).await;
+        Ok(campaign)
+    }
+
+    pub(crate) async fn list(&self, user_id: &str) -> Result<Vec<Campaign>, AppError> {
+        self.db.list_campaigns(user_id).await
+    }
+
+    /// The UTM template `user_id` set for all of their links, if any. Users without one are
+    /// cached too, since most links belong to them.
+    pub(crate) async fn user_template(&self, user_id: &str) -> Result<Option<Arc<UtmDefaults>>, AppError> {
+        if let Some(template) = self.user_templates.get(user_id).await {
+            return Ok(template);
+        }
+        let template = self.db.get_user(user_id).await?.and_then(|user| user.utm).map(Arc::new);
+        self.user_templates.insert(user_id.to_string(), template.clone()).await;
+        Ok(template)
+    }
+
+    /// Drops this instance's copy of a user's template after they change it.
+    pub async fn forget_user_template(&self, user_id: &str) {
+        self.user_templates.invalidate(user_id).await;
+    }
+}
+
+/// Adds each UTM default the destination doesn't already carry; explicit parameters win.
+pub fn apply_utm_defaults(long_url: &str, utm: &UtmDefaults) -> String {
+    let Ok(mut url) = Url::parse(long_url) else {
+        return long_url.to_string();
+    };
+    let defaults = [
+        ("utm_source", &utm.utm_source),
+        ("utm_medium", &utm.utm_medium),
+        ("utm_campaign", &utm.utm_campaign),
+        ("utm_term", &utm.utm_term),
+        ("utm_content", &utm.utm_content),
+    ];
+    let missing: Vec<(&str, &str)> = defaults
+        .iter()
+        .filter_map(|(name, value)| value.as_deref().map(|value| (*name, value)))
+        .filter(|(name, _)| !url.query_pairs().any(|(existing, _)| existing == *name))
+        .collect();
+    if missing.is_empty() {
+        return long_url.to_string();
+    }
+    url.query_pairs_mut().extend_pairs(missing);
+    url.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utm_defaults_do_not_override_explicit_parameters() {
+        let utm = UtmDefaults {
+            utm_source: Some("newsletter".into()),
+            utm_medium: Some("email".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            apply_utm_defaults("https://example.com/a?utm_source=twitter", &utm),
+            "https://example.com/a?utm_source=twitter&utm_medium=email"
+        );
+        assert_eq!(
+            apply_utm_defaults("https://example.com/", &UtmDefaults::default()),
+            "https://example.com/"
+        );
+    }
+}
@@ -1,13 +1,17 @@
 use std::sync::atomic::{AtomicU64, Ordering};
 use thiserror::Error;
 use arrayvec::ArrayString;
-use crate::config::settings::Settings;
+use crate::config::{codegen::DEFAULT_BLOCKED_SUBSTRINGS, settings::Settings};
 use prometheus::{Histogram, IntCounter};
 use tracing::debug;
 use once_cell::sync::Lazy;
 
 
 const BASE62_CHARS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const MAX_PREFIX_LEN: usize = 3; // 62^3 covers the 16 shard bits config allows
+const MAX_DIGITS: usize = 11; // Base62 digits in u64::MAX
+/// Longest code `next` can return: a shard prefix followed by the shard's counter.
+pub const MAX_CODE_LEN: usize = MAX_PREFIX_LEN + MAX_DIGITS;
 static CODEGEN_LATENCY: Lazy<Histogram> = Lazy::new(|| {
     prometheus::register_histogram!(
         "codegen_latency_seconds",
@@ -20,6 +24,12 @@ static CODEGEN_OVERFLOW_RETRIES: Lazy<IntCounter> = Lazy::new(|| {
         "Total number of overflow retry attempts"
     ).unwrap()
 });
+static CODEGEN_BLOCKED_CODES: Lazy<IntCounter> = Lazy::new(|| {
+    prometheus::register_int_counter!(
+        "codegen_blocked_codes_total",
+        "Generated codes skipped for containing a blocked substring"
+    ).unwrap()
+});
 static CODEGEN_SHARD_USAGE: Lazy<Histogram> = Lazy::new(|| {
     prometheus::register_histogram!(
         "codegen_shard_usage",
@@ -32,6 +42,8 @@ static CODEGEN_SHARD_USAGE: Lazy<Histogram> = Lazy::new(|| {
 pub enum CodeGenError {
     #[error("Counter overflow detected after multiple attempts")]
     CounterOverflow,
+    #[error("Every generated code contained a blocked substring after multiple attempts")]
+    Blocked,
 }
 
 #[repr(align(64))]
@@ -42,28 +54,41 @@ struct PaddedAtomicU64(AtomicU64);
 #[derive(Debug)]
 pub struct CodeGenerator {
     counters: Box<[PaddedAtomicU64]>,
-    shard_prefixes: Box<[[u8; 2]]>,
+    shard_prefixes: Box<[ArrayString<MAX_PREFIX_LEN>]>,
     lookup_table: Box<[u8]>,
     shard_bits: usize,
     shard_mask: u64,
     chunk: u64,
     lookup_size: usize,
     max_attempts: usize,
+    blocked_substrings: Box<[String]>, // Lowercase
 }
 
 impl CodeGenerator {
     pub fn new(config: &Settings) -> Self {
         let shard_bits = config.codegen.shard_bits;
         let max_attempts = config.codegen.max_attempts;
+        // An empty entry would match every code
+        let blocked_substrings = match &config.codegen.blocked_substrings {
+            Some(substrings) => substrings.iter().map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()).collect(),
+            None => DEFAULT_BLOCKED_SUBSTRINGS.iter().map(|s| s.to_string()).collect(),
+        };
         let shard_mask = (1 << shard_bits) - 1;
         let chunk = 62u64.pow(3);
         let lookup_size = chunk as usize * 3;
 
-        let mut prefixes = vec![[0u8; 2]; 1 << shard_bits].into_boxed_slice();
-        for i in 0..(1 << shard_bits) {
-            prefixes[i][0] = BASE62_CHARS[(i / 62) % 62];
-            prefixes[i][1] = BASE62_CHARS[i % 62];
-        }
+        // Every shard needs its own prefix, or two counters would hand out the same codes
+        let prefix_len = if 1usize << shard_bits <= 62 * 62 { 2 } else { MAX_PREFIX_LEN };
+        let prefixes = (0..1usize << shard_bits)
+            .map(|i| {
+                let mut prefix = ArrayString::new();
+                for place in (0..prefix_len as u32).rev() {
+                    prefix.push(BASE62_CHARS[(i / 62usize.pow(place)) % 62] as char);
+                }
+                prefix
+            })
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
 
         let mut lookup_table = vec![0u8; lookup_size].into_boxed_slice();
         for v in 0..chunk as usize {
@@ -89,11 +114,41 @@ impl CodeGenerator {
             chunk,
             lookup_size,
             max_attempts,
+            blocked_substrings,
         }
     }
 
+    /// The next code that contains none of the blocked substrings. Skipped codes are never
+    /// handed out, so each retry costs one counter value.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CodeGenError::Blocked` if every attempt hit a blocked substring, or
+    /// `CounterOverflow` if the counter couldn't be advanced.
     #[inline(always)]
-    pub fn next(&self) -> Result<ArrayString<13>, CodeGenError> {
+    pub fn next(&self) -> Result<ArrayString<MAX_CODE_LEN>, CodeGenError> {
+        for _ in 0..self.max_attempts {
+            let code = self.next_unfiltered()?;
+            if !self.is_blocked(&code) {
+                return Ok(code);
+            }
+            CODEGEN_BLOCKED_CODES.inc();
+            debug!("Skipping generated code {}: contains a blocked substring", code);
+        }
+        Err(CodeGenError::Blocked)
+    }
+
+    #[inline(always)]
+    fn is_blocked(&self, code: &str) -> bool {
+        if self.blocked_substrings.is_empty() {
+            return false;
+        }
+        let code = code.to_ascii_lowercase();
+        self.blocked_substrings.iter().any(|substring| code.contains(substring.as_str()))
+    }
+
+    #[inline(always)]
+    fn next_unfiltered(&self) -> Result<ArrayString<MAX_CODE_LEN>, CodeGenError> {
         let timer = CODEGEN_LATENCY.start_timer();
         let mut attempts = 0;
 
@@ -121,44 +176,38 @@ impl CodeGenerator {
                 Ordering::Relaxed,
             ) {
                 Ok(_) => {
-                    let prefix = &self.shard_prefixes[shard_id];
-                    let mut buf = ArrayString::<13>::new();
-                    buf.push_str(std::str::from_utf8(prefix).unwrap());
-                    unsafe {
-                        self.encode(current, buf.as_mut_ptr().add(2));
-                        debug!("Generated code: {}", buf);
-                        timer.stop_and_record();
-                        return Ok(buf);
-                    }
+                    let mut digits = [0u8; MAX_DIGITS];
+                    let start = self.encode(current, &mut digits);
+                    let mut buf = ArrayString::<MAX_CODE_LEN>::new();
+                    buf.push_str(&self.shard_prefixes[shard_id]);
+                    buf.push_str(std::str::from_utf8(&digits[start..]).unwrap());
+                    debug!("Generated code: {}", buf);
+                    timer.stop_and_record();
+                    return Ok(buf);
                 }
                 Err(_) => continue,
             }
         }
     }
 
+    /// Writes `num` in base62 at the end of `output`, three digits per table lookup, and
+    /// returns the index of its leading digit.
     #[inline(always)]
-    fn encode(&self, mut num: u64, output: *mut u8) {
-        let mut ptr = unsafe { output.add(10) };
-
-        unsafe {
-            while num >= self.chunk {
-                let rem = (num % self.chunk) as usize;
-                num /= self.chunk;
-                let src = self.lookup_table.as_ptr().add(rem * 3);
-                ptr = ptr.sub(3);
-                ptr.copy_from_nonoverlapping(src, 3);
-            }
-
-            if num >= 62 {
-                let rem = num as usize;
-                let src = self.lookup_table.as_ptr().add(rem * 3);
-                let take = if num >= 62 * 62 { 3 } else { 2 };
-                ptr = ptr.sub(take);
-                ptr.copy_from_nonoverlapping(src.add(3 - take), take);
-            } else {
-                *ptr = BASE62_CHARS[num as usize];
-            }
+    fn encode(&self, mut num: u64, output: &mut [u8; MAX_DIGITS]) -> usize {
+        let mut start = MAX_DIGITS;
+        while num >= self.chunk {
+            let src = (num % self.chunk) as usize * 3;
+            num /= self.chunk;
+            start -= 3;
+            output[start..start + 3].copy_from_slice(&self.lookup_table[src..src + 3]);
         }
+
+        // The leading chunk drops its zero padding
+        let take = if num >= 62 * 62 { 3 } else if num >= 62 { 2 } else { 1 };
+        let src = num as usize * 3 + 3 - take;
+        start -= take;
+        output[start..start + take].copy_from_slice(&self.lookup_table[src..src + take]);
+        start
     }
 
     #[inline(always)]
@@ -186,3 +235,86 @@ impl CodeGenerator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::{collections::HashSet, sync::Arc, thread};
+
+    fn generator(shard_bits: usize) -> CodeGenerator {
+        let mut config = Settings::default();
+        config.codegen.shard_bits = shard_bits;
+        CodeGenerator::new(&config)
+    }
+
+    fn filtered_generator(blocked: &[&str], max_attempts: usize) -> CodeGenerator {
+        let mut config = Settings::default();
+        config.codegen.blocked_substrings = Some(blocked.iter().map(|s| s.to_string()).collect());
+        config.codegen.max_attempts = max_attempts;
+        CodeGenerator::new(&config)
+    }
+
+    #[test]
+    fn codes_with_blocked_substrings_are_skipped() {
+        // Roughly one code in six has an 'a' or 'A', so ten tries always find a clean one
+        let generator = filtered_generator(&["A"], 10);
+        for _ in 0..1_000 {
+            let code = generator.next().unwrap();
+            assert!(!code.to_ascii_lowercase().contains('a'), "{} slipped through", code);
+        }
+
+        let every_char: Vec<String> = BASE62_CHARS.iter().map(|&c| (c as char).to_string()).collect();
+        let generator = filtered_generator(&every_char.iter().map(String::as_str).collect::<Vec<_>>(), 3);
+        assert!(matches!(generator.next(), Err(CodeGenError::Blocked)));
+    }
+
+    fn decode(digits: &[u8]) -> u64 {
+        digits.iter().fold(0, |num, &c| num * 62 + BASE62_CHARS.iter().position(|&b| b == c).unwrap() as u64)
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn encode_round_trips(num in any::<u64>()) {
+            let generator = generator(8);
+            let mut digits = [0u8; MAX_DIGITS];
+            let start = generator.encode(num, &mut digits);
+            // No zero padding, except for zero itself
+            prop_assert!(digits[start] != b'0' || start == MAX_DIGITS - 1);
+            prop_assert_eq!(decode(&digits[start..]), num);
+        }
+
+        #[test]
+        fn shard_prefixes_are_distinct(shard_bits in 8usize..=16) {
+            let generator = generator(shard_bits);
+            let prefixes: HashSet<_> = generator.shard_prefixes.iter().collect();
+            prop_assert_eq!(prefixes.len(), 1 << shard_bits);
+            prop_assert!(generator.shard_prefixes.iter().all(|p| p.len() == generator.shard_prefixes[0].len()));
+        }
+
+        #[test]
+        fn codes_are_unique_base62_across_threads(
+            shard_bits in 8usize..=16,
+            threads in 1usize..8,
+            per_thread in 1usize..500,
+        ) {
+            let generator = Arc::new(generator(shard_bits));
+            let prefix_len = generator.shard_prefixes[0].len();
+            let handles: Vec<_> = (0..threads)
+                .map(|_| {
+                    let generator = Arc::clone(&generator);
+                    thread::spawn(move || (0..per_thread).map(|_| generator.next().unwrap()).collect::<Vec<_>>())
+                })
+                .collect();
+
+            let mut seen = HashSet::new();
+            for code in handles.into_iter().flat_map(|h| h.join().unwrap()) {
+                prop_assert!(code.len() > prefix_len && code.len() <= prefix_len + MAX_DIGITS);
+                prop_assert!(code.bytes().all(|b| BASE62_CHARS.contains(&b)), "{} is not base62", code);
+                prop_assert!(seen.insert(code), "{} was handed out twice", code);
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Finds the Dragonfly nodes behind a DNS name, such as a headless Kubernetes service, instead of
+//! a fixed `database_urls` list. The name is resolved again every `discovery.refresh_secs`:
+//! new addresses are added to every storage client's ring, and addresses that are gone are
+//! drained out of it.
+//!
+//! Draining copies a node's keys off it, so a pod needs to stay up for a refresh interval after
+//! it leaves the service, e.g. with a preStop hook. A drain that fails is retried next time.
+
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
+use tracing::{info, warn};
+use url::Url;
+
+use crate::{
+    config::settings::Settings,
+    errors::AppError,
+    services::{cache::circuit_breaker::redact_node, storage::storage::Storage},
+};
+
+type StorageClients = Vec<(&'static str, Arc<dyn Storage + Send + Sync>)>;
+
+/// The node URLs `discovery.url` currently resolves to, sorted, or None when discovery is off.
+pub(crate) async fn resolve(config: &Settings) -> Result<Option<Vec<String>>, AppError> {
+    let Some(url) = config.discovery.url.as_deref() else {
+        return Ok(None);
+    };
+    let urls = resolve_url(url).await?;
+    if urls.is_empty() {
+        return Err(AppError::RedisConnection(format!("{} resolved to no addresses", redact_node(url))));
+    }
+    Ok(Some(urls.into_iter().collect()))
+}
+
+async fn resolve_url(url: &str) -> Result<BTreeSet<String>, AppError> {
+    let parsed = Url::parse(url).map_err(|e| AppError::RedisConnection(format!("Invalid URL {}: {}", redact_node(url), e)))?;
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| AppError::RedisConnection(format!("No host in URL {}", redact_node(url))))?;
+    let port = parsed.port().unwrap_or(6379);
+    let addrs = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| AppError::RedisConnection(format!("Failed to resolve {}: {}", host, e)))?;
+    Ok(addrs
+        .filter_map(|addr| {
+            let mut node = parsed.clone();
+            node.set_ip_host(addr.ip()).ok()?;
+            Some(node.to_string())
+        })
+        .collect())
+}
+
+/// Re-resolves `discovery.url` every `discovery.refresh_secs` and reconciles `clients` with it,
+/// starting from the nodes in `database_urls`. A no-op when discovery is off.
+pub fn spawn_node_discovery(config: &Settings, clients: StorageClients) {
+    let Some(url) = config.discovery.url.clone() else {
+        return;
+    };
+    let mut known: BTreeSet<String> = config.database_urls.iter().cloned().collect();
+    let refresh = Duration::from_secs(config.discovery.refresh_secs);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(refresh);
+        // The first tick fires straight away, and the nodes were only just resolved
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let resolved = match resolve_url(&url).await {
+                Ok(resolved) if !resolved.is_empty() => resolved,
+                Ok(_) => {
+                    warn!("{} resolved to no addresses, keeping the current nodes", redact_node(&url));
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Node discovery failed, keeping the current nodes: {}", e);
+                    continue;
+                }
+            };
+            for node in resolved.difference(&known).cloned().collect::<Vec<_>>() {
+                match add_node_to_all(&clients, &node).await {
+                    Ok(moved) => {
+                        info!("Discovered node {}, {} keys moved to it", redact_node(&node), moved);
+                        known.insert(node);
+                    }
+                    Err(e) => warn!("Failed to add discovered node {}, retrying next refresh: {}", redact_node(&node), e),
+                }
+            }
+            for node in known.difference(&resolved).cloned().collect::<Vec<_>>() {
+                match remove_node_from_all(&clients, &redact_node(&node)).await {
+                    Ok(moved) => {
+                        info!("Node {} is gone from DNS, {} keys moved off it", redact_node(&node), moved);
+                        known.remove(&node);
+                    }
+                    // Already drained, e.g. through the admin endpoint
+                    Err(AppError::NotFound(_)) => {
+                        known.remove(&node);
+                    }
+                    Err(e) => warn!("Failed to drain node {}, retrying next refresh: {}", redact_node(&node), e),
+                }
+            }
+        }
+    });
+}
+
+/// Adds the node at `url` to every client that doesn't have it yet. The first to add it moves
+/// it the keys it takes over; the rest find them already moved. Returns how many keys moved.
+pub(crate) async fn add_node_to_all(clients: &StorageClients, url: &str) -> Result<u64, AppError> {
+    let mut keys_moved = 0;
+    for (_, client) in clients {
+        match client.add_node(url).await {
+            Ok(moved) => keys_moved += moved,
+            Err(AppError::Conflict(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(keys_moved)
+}
+
+/// Drains `node` (as reported by `pool_stats`) out of every client that has it. Returns how
+/// many keys moved.
+pub(crate) async fn remove_node_from_all(clients: &StorageClients, node: &str) -> Result<u64, AppError> {
+    let (mut matched, mut keys_moved) = (false, 0);
+    for (_, client) in clients {
+        match client.remove_node(node).await {
+            Ok(moved) => {
+                matched = true;
+                keys_moved += moved;
+            }
+            Err(AppError::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    if !matched {
+        return Err(AppError::NotFound(format!("No storage client has node {}", node)));
+    }
+    Ok(keys_moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn resolved_nodes_keep_credentials_and_port() {
+        let nodes = resolve_url("redis://:secret@localhost:6380/0").await.unwrap();
+        assert!(nodes.contains("redis://:secret@127.0.0.1:6380/0"), "{:?}", nodes);
+    }
+}
@@ -1,6 +1,8 @@
+use arc_swap::ArcSwap;
 use once_cell::sync::OnceCell;
 use dashmap::DashMap;
 use std::{
+    io::Read,
     net::IpAddr,
     sync::Arc,
     time::{Duration, Instant},
@@ -12,7 +14,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     config::settings::Settings,
     errors::AppError,
-    services::{metrics, sled::SledStorage, storage::storage::Storage},
+    services::{metrics, sled::{ExpiringKeys, SledStorage}, storage::storage::Storage},
 };
 
 
@@ -28,7 +30,9 @@ pub struct GeoLocation {
     pub longitude: Option<f64>,
 }
 
-static GEOIP_READER: OnceCell<Arc<Reader<Vec<u8>>>> = OnceCell::new();
+const MAXMIND_DOWNLOAD_URL: &str = "https://download.maxmind.com/geoip/databases";
+
+static GEOIP_READER: OnceCell<ArcSwap<Reader<Vec<u8>>>> = OnceCell::new();
 static HOT_CACHE: OnceCell<Arc<DashMap<IpAddr, (GeoLocation, Instant)>>> = OnceCell::new();
 static SLED_GEO: OnceCell<Arc<SledStorage>> = OnceCell::new(); // Now uses geo-specific path
 static GEO_TTL: OnceCell<Duration> = OnceCell::new();
@@ -39,10 +43,12 @@ pub fn init_geo_lookup(settings: &Settings) -> Result<(), AppError> {
     let reader = Reader::open_readfile(&settings.cache.geoip_mmdb_path)
         .map_err(|e| AppError::Internal(format!("Failed to open GeoIP DB at '{}': {}", &settings.cache.geoip_mmdb_path, e)))?;
     
-    GEOIP_READER.get_or_init(|| Arc::new(reader));
+    GEOIP_READER.get_or_init(|| ArcSwap::from_pointee(reader));
 
     HOT_CACHE.get_or_init(|| Arc::new(DashMap::with_capacity(settings.cache.geo_hot_capacity)));
     SLED_GEO.get_or_init(|| Arc::new(SledStorage::new(&settings.cache.geo_sled_path, settings))); // Use geo-specific path
+    let sweep_every = Duration::from_secs(settings.storage.sled_sweep_interval_secs.unwrap_or(300));
+    Arc::clone(SLED_GEO.get().unwrap()).spawn_ttl_sweeper("geo", ExpiringKeys::All, sweep_every);
     GEO_TTL.get_or_init(|| Duration::from_secs(settings.cache.geo_ttl_seconds));
     EVICT_INTERVAL.get_or_init(|| Duration::from_secs(settings.cache.geo_evict_interval_secs));
 
@@ -66,11 +72,111 @@ pub fn init_geo_lookup(settings: &Settings) -> Result<(), AppError> {
     Ok(())
 }
 
+/// Publishes the loaded database's build date and, when MaxMind credentials are configured,
+/// periodically downloads a fresh database and swaps it in without blocking lookups.
+pub(crate) fn spawn_geoip_updater(settings: &Settings) {
+    if let Some(reader) = GEOIP_READER.get() {
+        metrics::record_geoip_build_epoch(reader.load().metadata.build_epoch);
+    }
+    let cache = &settings.cache;
+    let (Some(account_id), Some(license_key)) = (cache.geoip_account_id.clone(), cache.geoip_license_key.clone()) else {
+        return;
+    };
+    let edition = cache.geoip_edition_id.clone().unwrap_or_else(|| "GeoLite2-City".to_string());
+    let mmdb_path = cache.geoip_mmdb_path.clone();
+    let interval = Duration::from_secs(cache.geoip_update_interval_secs.unwrap_or(86_400));
+
+    tokio::spawn(async move {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(300))
+            .build()
+            .expect("Failed to build GeoIP HTTP client");
+        let mut ticker = time::interval(interval);
+        ticker.tick().await; // The database loaded at startup is fresh enough for the first interval
+        loop {
+            ticker.tick().await;
+            match update_geoip_once(&http, &account_id, &license_key, &edition, &mmdb_path).await {
+                Ok(Some(epoch)) => tracing::info!("Loaded {} built at {}", edition, epoch),
+                Ok(None) => tracing::debug!("{} is already up to date", edition),
+                Err(e) => tracing::warn!("GeoIP update failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Returns the new build epoch if a newer database was installed.
+async fn update_geoip_once(
+    http: &reqwest::Client,
+    account_id: &str,
+    license_key: &str,
+    edition: &str,
+    mmdb_path: &str,
+) -> Result<Option<u64>, AppError> {
+    let url = format!("{}/{}/download?suffix=tar.gz", MAXMIND_DOWNLOAD_URL, edition);
+    let archive = http
+        .get(&url)
+        .basic_auth(account_id, Some(license_key))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| AppError::Internal(format!("Failed to download {}: {}", edition, e)))?
+        .bytes()
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to download {}: {}", edition, e)))?;
+
+    let mmdb = tokio::task::spawn_blocking(move || extract_mmdb(&archive))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))??;
+    let epoch = Reader::from_source(mmdb.as_slice())?.metadata.build_epoch;
+    let current = GEOIP_READER
+        .get()
+        .ok_or_else(|| AppError::Internal("GeoIP lookup is not initialized".into()))?;
+    if epoch <= current.load().metadata.build_epoch {
+        return Ok(None);
+    }
+
+    // Persist first (write + rename is atomic) so a restart picks up the same database
+    let tmp_path = format!("{}.tmp", mmdb_path);
+    tokio::fs::write(&tmp_path, &mmdb)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to write {}: {}", tmp_path, e)))?;
+    tokio::fs::rename(&tmp_path, mmdb_path)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to replace {}: {}", mmdb_path, e)))?;
+
+    current.store(Arc::new(Reader::from_source(mmdb)?));
+    // Cached lookups came from the old database; let them refill from the new one
+    if let Some(hot_cache) = HOT_CACHE.get() {
+        hot_cache.clear();
+    }
+    metrics::record_geoip_build_epoch(epoch);
+    Ok(Some(epoch))
+}
+
+fn extract_mmdb(archive: &[u8]) -> Result<Vec<u8>, AppError> {
+    let io_error = |e: std::io::Error| AppError::Internal(format!("Failed to unpack GeoIP archive: {}", e));
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(archive));
+    for entry in tar.entries().map_err(io_error)? {
+        let mut entry = entry.map_err(io_error)?;
+        let is_mmdb = entry.path().map_err(io_error)?.extension().is_some_and(|ext| ext == "mmdb");
+        if is_mmdb {
+            let mut mmdb = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut mmdb).map_err(io_error)?;
+            return Ok(mmdb);
+        }
+    }
+    Err(AppError::Internal("GeoIP archive contains no .mmdb file".into()))
+}
+
 pub async fn lookup_geo(ip: IpAddr) -> Result<Option<GeoLocation>, AppError> {
+    // Without init_geo_lookup (tests, embedders that skip GeoIP) there is nothing to look up
+    let Some(hot_cache) = HOT_CACHE.get() else {
+        return Ok(None);
+    };
     let start_total = Instant::now();
 
     // 1. Hot cache
-    if let Some(mut entry) = HOT_CACHE.get().unwrap().get_mut(&ip) {
+    if let Some(mut entry) = hot_cache.get_mut(&ip) {
         entry.value_mut().1 = Instant::now();
         metrics::record_cache_hit("geo_hot", start_total);
         return Ok(Some(entry.value().0.clone()));
@@ -83,7 +189,7 @@ pub async fn lookup_geo(ip: IpAddr) -> Result<Option<GeoLocation>, AppError> {
         Ok(cached_data) => {
             if let Ok(geo_data) = serde_json::from_str::<GeoLocation>(&cached_data) {
                 // Update hot cache
-                HOT_CACHE.get().unwrap().insert(ip, (geo_data.clone(), Instant::now()));
+                hot_cache.insert(ip, (geo_data.clone(), Instant::now()));
                 metrics::record_cache_hit("geo_sled", sled_start);
                 return Ok(Some(geo_data));
             }
@@ -99,7 +205,7 @@ pub async fn lookup_geo(ip: IpAddr) -> Result<Option<GeoLocation>, AppError> {
 
     // 3. MaxMind lookup
     let mm_start = Instant::now();
-    let reader = GEOIP_READER.get().unwrap();
+    let reader = GEOIP_READER.get().unwrap().load_full();
     let geo_opt = reader
         .lookup::<City>(ip)?
         .map(|record| GeoLocation {
@@ -130,7 +236,7 @@ pub async fn lookup_geo(ip: IpAddr) -> Result<Option<GeoLocation>, AppError> {
         } else {
             tracing::warn!("Failed to serialize geo data for {}", ip);
         }
-        HOT_CACHE.get().unwrap().insert(ip, (loc.clone(), Instant::now()));
+        hot_cache.insert(ip, (loc.clone(), Instant::now()));
     }
 
     metrics::record_cache_latency("geo_total", start_total);
@@ -0,0 +1,111 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::{errors::AppError, middleware::RequestContext, types::{UrlData, User}};
+
+/// Extension points around the request lifecycle, registered through `app::Builder::hook`.
+///
+/// Every method defaults to a no-op, so a hook only implements the events it cares about.
+#[async_trait]
+pub trait LifecycleHook: Send + Sync {
+    /// Runs before a new link is stored. Returning an error rejects the request with it.
+    async fn on_shorten(&self, _code: &str, _url_data: &UrlData, _context: &RequestContext) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    /// Runs before a link is followed. Returning an error is sent to the visitor instead of the redirect.
+    async fn on_redirect(&self, _code: &str, _url_data: &UrlData, _context: &RequestContext) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    /// Runs once a click has been queued for analytics; bot and dropped clicks are not reported.
+    async fn on_click_recorded(&self, _code: &str, _context: &RequestContext) {}
+
+    /// Runs when `user` asks to move their account to `email`. The hook delivers `token` to that
+    /// address; the change only applies once it comes back through `POST /v1/me/email/verify`.
+    async fn on_email_verification(&self, _user: &User, _email: &str, _token: &str) {}
+}
+
+/// Registered hooks, run in registration order.
+#[derive(Clone, Default)]
+pub struct Hooks {
+    hooks: Vec<Arc<dyn LifecycleHook>>,
+}
+
+impl Hooks {
+    pub fn new(hooks: Vec<Arc<dyn LifecycleHook>>) -> Self {
+        Self { hooks }
+    }
+
+    /// Stops at the first hook that rejects.
+    pub(crate) async fn on_shorten(&self, code: &str, url_data: &UrlData, context: &RequestContext) -> Result<(), AppError> {
+        for hook in &self.hooks {
+            hook.on_shorten(code, url_data, context).await.inspect_err(|e| {
+                warn!("Shorten hook rejected code {}: {}", code, e);
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Stops at the first hook that rejects.
+    pub(crate) async fn on_redirect(&self, code: &str, url_data: &UrlData, context: &RequestContext) -> Result<(), AppError> {
+        for hook in &self.hooks {
+            hook.on_redirect(code, url_data, context).await.inspect_err(|e| {
+                warn!("Redirect hook rejected code {}: {}", code, e);
+            })?;
+        }
+        Ok(())
+    }
+
+    pub async fn on_click_recorded(&self, code: &str, context: &RequestContext) {
+        for hook in &self.hooks {
+            hook.on_click_recorded(code, context).await;
+        }
+    }
+
+    pub async fn on_email_verification(&self, user: &User, email: &str, token: &str) {
+        for hook in &self.hooks {
+            hook.on_email_verification(user, email, token).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Counting(AtomicUsize);
+
+    #[async_trait]
+    impl LifecycleHook for Counting {
+        async fn on_shorten(&self, _code: &str, _url_data: &UrlData, _context: &RequestContext) -> Result<(), AppError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    struct RejectAll;
+
+    #[async_trait]
+    impl LifecycleHook for RejectAll {
+        async fn on_shorten(&self, _code: &str, _url_data: &UrlData, _context: &RequestContext) -> Result<(), AppError> {
+            Err(AppError::Forbidden("Quota exceeded".into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn rejecting_hook_stops_the_chain() {
+        let counter = Arc::new(Counting(AtomicUsize::new(0)));
+        let hooks = Hooks::new(vec![counter.clone(), Arc::new(RejectAll), counter.clone()]);
+        let context = RequestContext::default();
+
+        let result = hooks.on_shorten("abc", &UrlData::default(), &context).await;
+        assert!(matches!(result, Err(AppError::Forbidden(_))));
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+        // Unimplemented events fall through to the no-op defaults
+        assert!(hooks.on_redirect("abc", &UrlData::default(), &context).await.is_ok());
+    }
+}
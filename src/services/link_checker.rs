@@ -0,0 +1,275 @@
+use futures::StreamExt;
+use parking_lot::RwLock;
+use reqwest::{redirect, StatusCode};
+use std::{collections::BTreeMap, error::Error as _, sync::Arc, time::Duration};
+use tracing::{debug, info, warn};
+
+use crate::{
+    clock::Clock,
+    config::settings::Settings,
+    errors::AppError,
+    services::{
+        cache::cache::CacheService,
+        metrics,
+        storage::storage::Storage,
+        url_guard::{check_destination, resolve_public_addrs},
+    },
+    types::{BrokenLink, BrokenLinksReport, UrlData},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinkHealth {
+    Ok,
+    Dead(&'static str),        // "404", "410" or "nxdomain"
+    Unreachable(&'static str), // "timeout", "ssl_error", "5xx" or "connect_error"; recorded but never marked dead
+    Unknown,                   // Internal or unparseable destinations aren't probed
+}
+
+impl LinkHealth {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LinkHealth::Ok => "ok",
+            LinkHealth::Dead(reason) | LinkHealth::Unreachable(reason) => reason,
+            LinkHealth::Unknown => "unknown",
+        }
+    }
+}
+
+/// Background scanner that HEADs stored destinations and flags the ones that have rotted.
+pub struct LinkChecker {
+    cache: Arc<CacheService>,
+    db: Arc<dyn Storage + Send + Sync>,
+    clock: Arc<dyn Clock>,
+    interval: Duration,
+    timeout: Duration,
+    concurrency: usize,
+    last_report: RwLock<BrokenLinksReport>,
+}
+
+impl LinkChecker {
+    pub fn new(config: &Settings, cache: Arc<CacheService>, db: Arc<dyn Storage + Send + Sync>, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            cache,
+            db,
+            clock,
+            interval: Duration::from_secs(config.link_health.scan_interval_secs),
+            timeout: Duration::from_millis(config.link_health.request_timeout_ms),
+            concurrency: config.link_health.concurrency,
+            last_report: RwLock::new(BrokenLinksReport::default()),
+        }
+    }
+
+    pub fn spawn(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.interval);
+            loop {
+                interval.tick().await;
+                match self.scan_once().await {
+                    Ok(changed) => info!("Link health scan finished, {} links changed state", changed),
+                    Err(e) => warn!("Link health scan failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Links whose last recorded status wasn't "ok" as of the most recent finished scan.
+    pub fn broken_links(&self) -> BrokenLinksReport {
+        self.last_report.read().clone()
+    }
+
+    /// Probes every stored link once and returns how many changed health state.
+    pub(crate) async fn scan_once(&self) -> Result<usize, AppError> {
+        // Links are stored under their bare code; every other keyspace is namespaced with ':'
+        let codes: Vec<String> = self
+            .db
+            .scan_keys("*", 1000)
+            .await?
+            .into_iter()
+            .filter(|key| !key.contains(':'))
+            .collect();
+
+        let checked: Vec<(bool, Option<BrokenLink>)> = futures::stream::iter(codes)
+            .map(|code| async move {
+                match self.check_code(&code).await {
+                    Ok(checked) => checked,
+                    Err(e) => {
+                        debug!("Skipping health check for {}: {}", code, e);
+                        (false, None)
+                    }
+                }
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+
+        let changed = checked.iter().filter(|(changed, _)| *changed).count();
+        let mut links: Vec<BrokenLink> = checked.into_iter().filter_map(|(_, broken)| broken).collect();
+        links.sort_by(|a, b| a.code.cmp(&b.code));
+        let mut by_status = BTreeMap::new();
+        for link in &links {
+            *by_status.entry(link.status.as_str()).or_insert(0) += 1;
+        }
+        metrics::set_broken_links(&by_status);
+        *self.last_report.write() = BrokenLinksReport {
+            scanned_at: Some(self.clock.now().to_rfc3339()),
+            links,
+        };
+        Ok(changed)
+    }
+
+    /// Probes one link and records the result; returns whether its health state changed and,
+    /// when its recorded status isn't "ok", the entry for the broken-links report.
+    async fn check_code(&self, code: &str) -> Result<(bool, Option<BrokenLink>), AppError> {
+        let json = self.db.get(code).await?;
+        let mut url_data: UrlData = serde_json::from_str(&json)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        if url_data.disabled_at.is_some() || url_data.quarantined {
+            return Ok((false, None));
+        }
+
+        let health = self.probe(&url_data.long_url).await;
+        metrics::record_link_health_check(health.as_str());
+        let changed = self.record(code, &mut url_data, &health).await?;
+        Ok((changed, broken_link(code, &url_data)))
+    }
+
+    async fn record(&self, code: &str, url_data: &mut UrlData, health: &LinkHealth) -> Result<bool, AppError> {
+        // Unreachable says nothing about whether the page still exists, so it leaves `dead` as is
+        let dead = match health {
+            LinkHealth::Unknown => return Ok(false),
+            LinkHealth::Ok => false,
+            LinkHealth::Dead(_) => true,
+            LinkHealth::Unreachable(_) => url_data.dead,
+        };
+        let status = health.as_str().to_string();
+        if url_data.dead == dead && url_data.health_status.as_deref() == Some(status.as_str()) {
+            return Ok(false);
+        }
+        if dead && !url_data.dead {
+            warn!("Destination for {} is dead ({}): {}", code, status, url_data.long_url);
+        }
+        url_data.dead = dead;
+        url_data.health_status = Some(status);
+        url_data.health_checked_at = Some(self.clock.now().to_rfc3339());
+        self.cache.insert(code.to_string(), url_data).await?;
+        Ok(true)
+    }
+
+    pub async fn probe(&self, url: &str) -> LinkHealth {
+        let Ok(parsed) = check_destination(url) else {
+            return LinkHealth::Unknown;
+        };
+        let addrs = match resolve_public_addrs(&parsed).await {
+            Ok(addrs) => addrs,
+            Err(AppError::NotFound(_)) => return LinkHealth::Dead("nxdomain"),
+            Err(_) => return LinkHealth::Unknown,
+        };
+
+        // Pin the connection to the vetted addresses and don't follow redirects into unvetted hosts
+        let host = parsed.host_str().unwrap_or_default();
+        let client = match reqwest::Client::builder()
+            .timeout(self.timeout)
+            .redirect(redirect::Policy::none())
+            .resolve_to_addrs(host, &addrs)
+            .build()
+        {
+            Ok(client) => client,
+            Err(_) => return LinkHealth::Unknown,
+        };
+
+        match client.head(parsed).send().await.map(|r| r.status()) {
+            Ok(StatusCode::NOT_FOUND) => LinkHealth::Dead("404"),
+            Ok(StatusCode::GONE) => LinkHealth::Dead("410"),
+            Ok(status) if status.is_server_error() => LinkHealth::Unreachable("5xx"),
+            Ok(_) => LinkHealth::Ok,
+            Err(e) if e.is_timeout() => LinkHealth::Unreachable("timeout"),
+            Err(e) if is_tls_error(&e) => LinkHealth::Unreachable("ssl_error"),
+            Err(_) => LinkHealth::Unreachable("connect_error"),
+        }
+    }
+}
+
+// reqwest reports handshake and certificate failures as plain connect errors, so the TLS
+// backend's message further down the chain is the only way to tell them apart
+fn is_tls_error(e: &reqwest::Error) -> bool {
+    let mut source = e.source();
+    while let Some(err) = source {
+        let message = err.to_string().to_lowercase();
+        if ["certificate", "tls", "ssl", "handshake"].iter().any(|needle| message.contains(needle)) {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+fn broken_link(code: &str, url_data: &UrlData) -> Option<BrokenLink> {
+    let status = url_data.health_status.as_deref().filter(|status| *status != "ok")?;
+    Some(BrokenLink {
+        code: code.to_string(),
+        long_url: url_data.long_url.clone(),
+        user_id: url_data.user_id.clone(),
+        status: status.to_string(),
+        dead: url_data.dead,
+        checked_at: url_data.health_checked_at.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::SystemClock, test_util::{self, MockStorage}};
+
+    #[tokio::test]
+    async fn scans_report_links_whose_last_status_was_not_ok() {
+        let config = Settings::default();
+        let db: Arc<dyn Storage + Send + Sync> = Arc::new(MockStorage::new());
+        let cache = Arc::new(CacheService::with_storage(&config, Arc::clone(&db), Arc::new(SystemClock)).await);
+        let checker = LinkChecker::new(&config, Arc::clone(&cache), db, Arc::new(SystemClock));
+        assert!(checker.broken_links().scanned_at.is_none());
+
+        // Internal destinations aren't probed, so these keep the status an earlier scan recorded
+        let gone = UrlData {
+            dead: true,
+            health_status: Some("404".into()),
+            ..test_util::url_data("http://127.0.0.1/gone")
+        };
+        let flaky = UrlData { health_status: Some("timeout".into()), ..test_util::url_data("http://127.0.0.1/flaky") };
+        let fine = UrlData { health_status: Some("ok".into()), ..test_util::url_data("http://127.0.0.1/fine") };
+        cache.insert("gone".into(), &gone).await.unwrap();
+        cache.insert("flaky".into(), &flaky).await.unwrap();
+        cache.insert("fine".into(), &fine).await.unwrap();
+
+        assert_eq!(checker.scan_once().await.unwrap(), 0);
+        let report = checker.broken_links();
+        assert!(report.scanned_at.is_some());
+        let links: Vec<_> = report.links.iter().map(|link| (link.code.as_str(), link.status.as_str(), link.dead)).collect();
+        assert_eq!(links, [("flaky", "timeout", false), ("gone", "404", true)]);
+    }
+
+    #[tokio::test]
+    async fn recorded_status_follows_probes_and_only_dead_answers_flip_dead() {
+        let config = Settings::default();
+        let db: Arc<dyn Storage + Send + Sync> = Arc::new(MockStorage::new());
+        let cache = Arc::new(CacheService::with_storage(&config, Arc::clone(&db), Arc::new(SystemClock)).await);
+        let checker = LinkChecker::new(&config, Arc::clone(&cache), db, Arc::new(SystemClock));
+        let mut url_data = test_util::url_data("https://example.com/page");
+        cache.insert("page".into(), &url_data).await.unwrap();
+
+        let steps = [
+            (LinkHealth::Ok, true, false, Some("ok")),
+            (LinkHealth::Ok, false, false, Some("ok")),
+            (LinkHealth::Dead("404"), true, true, Some("404")),
+            (LinkHealth::Unreachable("timeout"), true, true, Some("timeout")),
+            (LinkHealth::Unknown, false, true, Some("timeout")),
+            (LinkHealth::Ok, true, false, Some("ok")),
+            (LinkHealth::Unreachable("5xx"), true, false, Some("5xx")),
+        ];
+        for (health, changed, dead, status) in steps {
+            assert_eq!(checker.record("page", &mut url_data, &health).await.unwrap(), changed, "{:?}", health);
+            let stored = cache.get_url_data("page").await.unwrap();
+            assert_eq!((stored.dead, stored.health_status.as_deref()), (dead, status), "{:?}", health);
+        }
+        assert!(url_data.health_checked_at.is_some());
+    }
+}
@@ -0,0 +1,54 @@
+//! Terms that find a link in `GET /v1/urls/search`. They are written to per-owner inverted
+//! indexes (`user_search:{uid}:{term}`) whenever a link is created or edited, so a search reads
+//! one set per query word instead of scanning every link the owner has.
+
+use std::collections::BTreeSet;
+use url::Url;
+
+use crate::types::UrlData;
+
+// Labels shorter than this, like "co" in example.co.uk, would match nearly everything
+const MIN_LABEL_LEN: usize = 3;
+
+/// The code, the destination host and its labels but the TLD, and each tag with its folders,
+/// all lowercased.
+pub fn search_terms(code: &str, url_data: &UrlData) -> BTreeSet<String> {
+    let mut terms = BTreeSet::from([code.to_lowercase()]);
+    if let Some(host) = Url::parse(&url_data.long_url).ok().and_then(|url| url.host_str().map(str::to_lowercase)) {
+        let host = host.strip_prefix("www.").unwrap_or(&host).to_string();
+        if let Some((labels, _tld)) = host.rsplit_once('.') {
+            terms.extend(labels.split('.').filter(|label| label.len() >= MIN_LABEL_LEN).map(str::to_string));
+        }
+        terms.insert(host);
+    }
+    for tag in &url_data.tags {
+        let tag = tag.to_lowercase();
+        terms.extend(tag.split('/').map(str::to_string));
+        terms.insert(tag);
+    }
+    terms
+}
+
+/// The lowercased words of a query; a link must match every one of them.
+pub fn query_terms(query: &str) -> BTreeSet<String> {
+    query.split_whitespace().map(str::to_lowercase).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    #[test]
+    fn links_are_found_by_code_host_labels_and_tags() {
+        let url_data = UrlData {
+            tags: vec!["Clients/Acme".into()],
+            ..test_util::url_data("https://www.shop.example.co.uk/sale?ref=mail")
+        };
+        let terms = search_terms("AbC123", &url_data);
+        for term in ["abc123", "shop.example.co.uk", "shop", "example", "clients/acme", "clients", "acme"] {
+            assert!(terms.contains(term), "missing {}", term);
+        }
+        assert!(!terms.contains("co") && !terms.contains("uk") && !terms.contains("www"));
+    }
+}
@@ -0,0 +1,46 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use rand::{distr::Alphanumeric, Rng};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Mints a per-user secret for signing redirect URLs. It is stored as-is, since verifying
+/// an HMAC needs the key itself.
+pub fn generate_secret() -> String {
+    rand::rng().sample_iter(&Alphanumeric).take(48).map(char::from).collect()
+}
+
+/// Signature for `?exp=<exp>&sig=<sig>`: base64url HMAC-SHA256 of `{code}:{exp}`.
+pub fn sign(secret: &str, code: &str, exp: i64) -> String {
+    URL_SAFE_NO_PAD.encode(mac(secret, code, exp).finalize().into_bytes())
+}
+
+/// Constant-time check of a presented signature; expiry is the caller's job.
+pub fn verify(secret: &str, code: &str, exp: i64, sig: &str) -> bool {
+    URL_SAFE_NO_PAD
+        .decode(sig)
+        .is_ok_and(|sig| mac(secret, code, exp).verify_slice(&sig).is_ok())
+}
+
+fn mac(secret: &str, code: &str, exp: i64) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}:{}", code, exp).as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_are_bound_to_code_expiry_and_secret() {
+        let secret = generate_secret();
+        let sig = sign(&secret, "abc", 1_700_000_000);
+        assert!(verify(&secret, "abc", 1_700_000_000, &sig));
+        assert!(!verify(&secret, "abc", 1_700_000_001, &sig));
+        assert!(!verify(&secret, "abd", 1_700_000_000, &sig));
+        assert!(!verify(&generate_secret(), "abc", 1_700_000_000, &sig));
+        assert!(!verify(&secret, "abc", 1_700_000_000, "not base64!"));
+    }
+}
@@ -0,0 +1,39 @@
+use once_cell::sync::OnceCell;
+use tracing_subscriber::{fmt, prelude::*, reload, EnvFilter, Registry};
+
+use crate::errors::AppError;
+
+const DEFAULT_FILTER: &str = "info";
+
+static FILTER_HANDLE: OnceCell<reload::Handle<EnvFilter, Registry>> = OnceCell::new();
+
+/// Installs the global subscriber with a reloadable filter, seeded from RUST_LOG.
+pub fn init_tracing() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let (filter, handle) = reload::Layer::new(filter);
+    tracing_subscriber::registry().with(filter).with(fmt::layer()).init();
+    let _ = FILTER_HANDLE.set(handle);
+}
+
+/// Swaps the active filter, e.g. `info,hyperlinkr::services::cache=debug`. Takes effect
+/// immediately for every span and event; nothing is restarted.
+pub(crate) fn set_filter(directives: &str) -> Result<String, AppError> {
+    let handle = FILTER_HANDLE
+        .get()
+        .ok_or_else(|| AppError::Internal("Log level reload is not enabled".into()))?;
+    let filter = EnvFilter::try_new(directives)
+        .map_err(|e| AppError::BadRequest(format!("Invalid log filter '{}': {}", directives, e)))?;
+    handle
+        .reload(filter)
+        .map_err(|e| AppError::Internal(format!("Failed to reload log filter: {}", e)))?;
+    current_filter()
+}
+
+pub(crate) fn current_filter() -> Result<String, AppError> {
+    let handle = FILTER_HANDLE
+        .get()
+        .ok_or_else(|| AppError::Internal("Log level reload is not enabled".into()))?;
+    handle
+        .with_current(|filter| filter.to_string())
+        .map_err(|e| AppError::Internal(format!("Failed to read log filter: {}", e)))
+}
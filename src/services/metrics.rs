@@ -1,18 +1,32 @@
 use once_cell::sync::OnceCell;
 use prometheus::{
-    IntCounterVec, HistogramVec, IntCounter, IntGauge, register_histogram_vec,
-    register_int_counter_vec, register_int_counter, register_int_gauge,
+    IntCounterVec, Histogram, HistogramVec, IntCounter, IntGauge, IntGaugeVec, GaugeVec, register_histogram, register_histogram_vec,
+    register_int_counter_vec, register_int_counter, register_int_gauge, register_int_gauge_vec, register_gauge_vec,
+};
+use std::collections::BTreeMap;
+use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
+use std::time::{Duration, Instant};
+use crate::services::{
+    cache::circuit_breaker::{CircuitBreaker, NodeStatus},
+    storage::storage::{PoolStats, Storage},
 };
-use std::time::Instant;
 
 pub static CACHE_HITS: OnceCell<IntCounterVec> = OnceCell::new();
 pub static CACHE_MISSES: OnceCell<IntCounterVec> = OnceCell::new();
+pub static CACHE_INSERTS: OnceCell<IntCounterVec> = OnceCell::new();
+pub static CACHE_EVICTIONS: OnceCell<IntCounterVec> = OnceCell::new();
 pub static CACHE_LATENCY: OnceCell<HistogramVec> = OnceCell::new();
 pub static DB_LATENCY: OnceCell<HistogramVec> = OnceCell::new();
 pub static DB_ERRORS: OnceCell<IntCounterVec> = OnceCell::new();
+pub static SLOW_STORAGE_OPS: OnceCell<IntCounterVec> = OnceCell::new();
+static SLOW_OP_THRESHOLD_MS: AtomicU64 = AtomicU64::new(25);
 pub static HTTP_REQUESTS: OnceCell<IntCounterVec> = OnceCell::new();
 pub static HTTP_LATENCY: OnceCell<HistogramVec> = OnceCell::new();
 pub static CLICKS_RECORDED: OnceCell<IntCounter> = OnceCell::new();
+pub static BOT_CLICKS: OnceCell<IntCounterVec> = OnceCell::new();
+pub static UNFURL_HITS: OnceCell<IntCounterVec> = OnceCell::new();
+pub static CRAWLER_HITS: OnceCell<IntCounterVec> = OnceCell::new();
+pub static SLED_PURGED: OnceCell<IntCounterVec> = OnceCell::new();
 pub static BATCHES_FLUSHED: OnceCell<IntCounter> = OnceCell::new();
 pub static BATCH_SIZE: OnceCell<HistogramVec> = OnceCell::new();
 pub static ANALYTICS_DROPPED: OnceCell<IntCounter> = OnceCell::new();
@@ -20,7 +34,44 @@ pub static QUEUE_LENGTH: OnceCell<IntGauge> = OnceCell::new();
 pub static ANALYTICS_ERRORS: OnceCell<IntCounterVec> = OnceCell::new();
 pub static SHORT_URLS_CREATED: OnceCell<IntCounter> = OnceCell::new();
 pub static REDIRECTS_SERVED: OnceCell<IntCounter> = OnceCell::new();
+pub static FALLBACK_REDIRECTS: OnceCell<IntCounter> = OnceCell::new();
+pub static HOT_SET_SIZE: OnceCell<IntGauge> = OnceCell::new();
+pub static URL_REPUTATION_CHECKS: OnceCell<IntCounterVec> = OnceCell::new();
+pub static ABUSE_REPORTS: OnceCell<IntCounterVec> = OnceCell::new();
+pub static LINK_HEALTH_CHECKS: OnceCell<IntCounterVec> = OnceCell::new();
+pub static BROKEN_LINKS: OnceCell<IntGaugeVec> = OnceCell::new();
+pub static ACCOUNT_LOCKOUTS: OnceCell<IntCounterVec> = OnceCell::new();
+pub static GEOIP_BUILD_EPOCH: OnceCell<IntGauge> = OnceCell::new();
+pub static CIRCUIT_BREAKER_HEALTHY: OnceCell<IntGaugeVec> = OnceCell::new();
+pub static CIRCUIT_BREAKER_FAILURES: OnceCell<IntGaugeVec> = OnceCell::new();
+pub static CIRCUIT_BREAKER_SINCE_FAILURE: OnceCell<GaugeVec> = OnceCell::new();
+pub static CIRCUIT_BREAKER_TRANSITIONS: OnceCell<IntCounterVec> = OnceCell::new();
+pub static HEDGED_READS: OnceCell<IntCounterVec> = OnceCell::new();
+pub static REDIS_POOL_SIZE: OnceCell<IntGaugeVec> = OnceCell::new();
+pub static REDIS_POOL_IN_USE: OnceCell<IntGaugeVec> = OnceCell::new();
+pub static REDIS_POOL_WAIT: OnceCell<Histogram> = OnceCell::new();
+pub static DEGRADED_MODE: OnceCell<IntGauge> = OnceCell::new();
+pub static DEGRADED_QUEUED_WRITES: OnceCell<IntGauge> = OnceCell::new();
+pub static REPLICATED_MUTATIONS: OnceCell<IntCounterVec> = OnceCell::new();
+pub static REPLICATION_LAG: OnceCell<HistogramVec> = OnceCell::new();
+pub static REPLICATION_BACKLOG: OnceCell<IntGaugeVec> = OnceCell::new();
+pub static TOKIO_WORKERS: OnceCell<IntGauge> = OnceCell::new();
+pub static TOKIO_ALIVE_TASKS: OnceCell<IntGauge> = OnceCell::new();
+pub static TOKIO_GLOBAL_QUEUE_DEPTH: OnceCell<IntGauge> = OnceCell::new();
+pub static TOKIO_WORKER_BUSY_SECONDS: OnceCell<GaugeVec> = OnceCell::new();
+pub static TOKIO_WORKER_PARKS: OnceCell<IntGaugeVec> = OnceCell::new();
+// Only available when built with RUSTFLAGS="--cfg tokio_unstable"
+pub static TOKIO_WORKER_QUEUE_DEPTH: OnceCell<IntGaugeVec> = OnceCell::new();
+pub static TOKIO_BLOCKING_THREADS: OnceCell<IntGaugeVec> = OnceCell::new();
+pub static TOKIO_BLOCKING_QUEUE_DEPTH: OnceCell<IntGauge> = OnceCell::new();
+static INIT: std::sync::Once = std::sync::Once::new();
+
+/// Registers every metric. Safe to call more than once, e.g. by each `CacheService` a test builds.
 pub fn init_metrics() {
+    INIT.call_once(register_metrics);
+}
+
+fn register_metrics() {
     CACHE_HITS.set(
         register_int_counter_vec!(
             "cache_hits_total",
@@ -35,6 +86,20 @@ pub fn init_metrics() {
             &["tier"]
         ).unwrap()
     ).unwrap();
+    CACHE_INSERTS.set(
+        register_int_counter_vec!(
+            "cache_inserts_total",
+            "Number of entries written to a cache tier",
+            &["tier"]
+        ).unwrap()
+    ).unwrap();
+    CACHE_EVICTIONS.set(
+        register_int_counter_vec!(
+            "cache_evictions_total",
+            "Number of entries evicted from a cache tier for capacity or expiry",
+            &["tier"]
+        ).unwrap()
+    ).unwrap();
     CACHE_LATENCY.set(
         register_histogram_vec!(
             "cache_latency_seconds",
@@ -58,6 +123,13 @@ pub fn init_metrics() {
             &["operation"]
         ).unwrap()
     ).unwrap();
+    SLOW_STORAGE_OPS.set(
+        register_int_counter_vec!(
+            "slow_storage_ops_total",
+            "Storage calls that exceeded cache.slow_op_threshold_ms",
+            &["operation"]
+        ).unwrap()
+    ).unwrap();
     HTTP_REQUESTS.set(
         register_int_counter_vec!(
             "http_requests_total",
@@ -79,6 +151,34 @@ pub fn init_metrics() {
             "Total number of clicks recorded"
         ).unwrap()
     ).unwrap();
+    BOT_CLICKS.set(
+        register_int_counter_vec!(
+            "bot_clicks_total",
+            "Redirects served to crawlers and link unfurlers, excluded from click stats",
+            &["bot"]
+        ).unwrap()
+    ).unwrap();
+    UNFURL_HITS.set(
+        register_int_counter_vec!(
+            "unfurl_hits_total",
+            "Preview pages served to chat-app unfurl bots instead of a redirect",
+            &["bot"]
+        ).unwrap()
+    ).unwrap();
+    CRAWLER_HITS.set(
+        register_int_counter_vec!(
+            "crawler_hits_total",
+            "Redirects to crawlers listed in crawlers.disallowed, by bot and the policy applied",
+            &["bot", "policy"]
+        ).unwrap()
+    ).unwrap();
+    SLED_PURGED.set(
+        register_int_counter_vec!(
+            "sled_expired_purged_total",
+            "Expired entries deleted from Sled by the TTL sweeper, by database",
+            &["db"]
+        ).unwrap()
+    ).unwrap();
     BATCHES_FLUSHED.set(
         register_int_counter!(
             "batches_flushed_total",
@@ -124,6 +224,203 @@ pub fn init_metrics() {
             "Total number of redirects served"
         ).unwrap()
     ).unwrap();
+    FALLBACK_REDIRECTS.set(
+        register_int_counter!(
+            "fallback_redirects_total",
+            "Redirects sent to a link's fallback URL because its destination is dead"
+        ).unwrap()
+    ).unwrap();
+    HOT_SET_SIZE.set(
+        register_int_gauge!(
+            "hot_set_size",
+            "Links currently served from the lock-free hot set"
+        ).unwrap()
+    ).unwrap();
+    URL_REPUTATION_CHECKS.set(
+        register_int_counter_vec!(
+            "url_reputation_checks_total",
+            "URL reputation checks by verdict",
+            &["verdict"]
+        ).unwrap()
+    ).unwrap();
+    ABUSE_REPORTS.set(
+        register_int_counter_vec!(
+            "abuse_reports_total",
+            "Abuse reports by outcome (filed, actioned)",
+            &["outcome"]
+        ).unwrap()
+    ).unwrap();
+    LINK_HEALTH_CHECKS.set(
+        register_int_counter_vec!(
+            "link_health_checks_total",
+            "Destination health probes by result",
+            &["status"]
+        ).unwrap()
+    ).unwrap();
+    BROKEN_LINKS.set(
+        register_int_gauge_vec!(
+            "broken_links",
+            "Links whose destination failed the last health scan, by status",
+            &["status"]
+        ).unwrap()
+    ).unwrap();
+    ACCOUNT_LOCKOUTS.set(
+        register_int_counter_vec!(
+            "account_lockouts_total",
+            "Logins locked out after repeated failures, by scope (user, ip) and event (locked, rejected)",
+            &["scope", "event"]
+        ).unwrap()
+    ).unwrap();
+    GEOIP_BUILD_EPOCH.set(
+        register_int_gauge!(
+            "geoip_database_build_epoch_seconds",
+            "Build date of the loaded MaxMind database as a Unix timestamp"
+        ).unwrap()
+    ).unwrap();
+    CIRCUIT_BREAKER_HEALTHY.set(
+        register_int_gauge_vec!(
+            "circuit_breaker_healthy",
+            "1 if the node is accepting traffic, 0 if its breaker is open",
+            &["breaker", "node"]
+        ).unwrap()
+    ).unwrap();
+    CIRCUIT_BREAKER_FAILURES.set(
+        register_int_gauge_vec!(
+            "circuit_breaker_failures",
+            "Consecutive failures counted against the node",
+            &["breaker", "node"]
+        ).unwrap()
+    ).unwrap();
+    CIRCUIT_BREAKER_SINCE_FAILURE.set(
+        register_gauge_vec!(
+            "circuit_breaker_seconds_since_failure",
+            "Seconds since the node last failed; absent until its first failure",
+            &["breaker", "node"]
+        ).unwrap()
+    ).unwrap();
+    CIRCUIT_BREAKER_TRANSITIONS.set(
+        register_int_counter_vec!(
+            "circuit_breaker_transitions_total",
+            "Breaker state changes per node, by the state left and the state entered (closed, open, half_open)",
+            &["node", "from", "to"]
+        ).unwrap()
+    ).unwrap();
+    HEDGED_READS.set(
+        register_int_counter_vec!(
+            "hedged_reads_total",
+            "Reads hedged to a second node, by outcome (win: the hedge answered first, loss: the original node did)",
+            &["outcome"]
+        ).unwrap()
+    ).unwrap();
+    REDIS_POOL_SIZE.set(
+        register_int_gauge_vec!(
+            "redis_pool_connections",
+            "Connections in each node's pool",
+            &["client", "node"]
+        ).unwrap()
+    ).unwrap();
+    REDIS_POOL_IN_USE.set(
+        register_int_gauge_vec!(
+            "redis_pool_connections_in_use",
+            "Pool connections checked out at sample time; equal to the pool size means requests are queueing",
+            &["client", "node"]
+        ).unwrap()
+    ).unwrap();
+    REDIS_POOL_WAIT.set(
+        register_histogram!(
+            "redis_pool_wait_seconds",
+            "Time spent waiting to check a connection out of a pool",
+            vec![0.000001, 0.00001, 0.0001, 0.001, 0.01, 0.1]
+        ).unwrap()
+    ).unwrap();
+    DEGRADED_MODE.set(
+        register_int_gauge!(
+            "degraded_mode",
+            "1 while Dragonfly is unreachable and redirects are served from Sled"
+        ).unwrap()
+    ).unwrap();
+    DEGRADED_QUEUED_WRITES.set(
+        register_int_gauge!(
+            "degraded_queued_writes",
+            "Link writes waiting for Dragonfly to come back"
+        ).unwrap()
+    ).unwrap();
+    REPLICATED_MUTATIONS.set(
+        register_int_counter_vec!(
+            "replicated_mutations_total",
+            "Link mutations shipped to a peer region, by outcome (applied, stale: the peer had a newer one, failed: retried, dropped: queue full)",
+            &["peer", "outcome"]
+        ).unwrap()
+    ).unwrap();
+    REPLICATION_LAG.set(
+        register_histogram_vec!(
+            "replication_lag_seconds",
+            "Time from a link mutation to it being applied in a peer region",
+            &["peer"],
+            vec![0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 30.0, 300.0]
+        ).unwrap()
+    ).unwrap();
+    REPLICATION_BACKLOG.set(
+        register_int_gauge_vec!(
+            "replication_backlog",
+            "Link mutations queued for a peer region",
+            &["peer"]
+        ).unwrap()
+    ).unwrap();
+    TOKIO_WORKERS.set(
+        register_int_gauge!(
+            "tokio_workers",
+            "Number of tokio runtime worker threads"
+        ).unwrap()
+    ).unwrap();
+    TOKIO_ALIVE_TASKS.set(
+        register_int_gauge!(
+            "tokio_alive_tasks",
+            "Number of tasks currently alive in the tokio runtime"
+        ).unwrap()
+    ).unwrap();
+    TOKIO_GLOBAL_QUEUE_DEPTH.set(
+        register_int_gauge!(
+            "tokio_global_queue_depth",
+            "Number of tasks waiting in the runtime's injection queue"
+        ).unwrap()
+    ).unwrap();
+    TOKIO_WORKER_BUSY_SECONDS.set(
+        register_gauge_vec!(
+            "tokio_worker_busy_seconds",
+            "Total time each worker has spent executing tasks",
+            &["worker"]
+        ).unwrap()
+    ).unwrap();
+    TOKIO_WORKER_PARKS.set(
+        register_int_gauge_vec!(
+            "tokio_worker_parks",
+            "Number of times each worker has parked for lack of work",
+            &["worker"]
+        ).unwrap()
+    ).unwrap();
+    if cfg!(tokio_unstable) {
+        TOKIO_WORKER_QUEUE_DEPTH.set(
+            register_int_gauge_vec!(
+                "tokio_worker_local_queue_depth",
+                "Number of tasks in each worker's local run queue",
+                &["worker"]
+            ).unwrap()
+        ).unwrap();
+        TOKIO_BLOCKING_THREADS.set(
+            register_int_gauge_vec!(
+                "tokio_blocking_threads",
+                "Threads in the blocking pool, by state (total, idle)",
+                &["state"]
+            ).unwrap()
+        ).unwrap();
+        TOKIO_BLOCKING_QUEUE_DEPTH.set(
+            register_int_gauge!(
+                "tokio_blocking_queue_depth",
+                "Number of tasks waiting for a blocking pool thread"
+            ).unwrap()
+        ).unwrap();
+    }
 }
 
 pub fn record_cache_hit(layer: &'static str, start: Instant) {
@@ -139,12 +436,24 @@ pub fn record_cache_miss(layer: &'static str) {
     }
 }
 
+pub fn record_cache_insert(layer: &'static str) {
+    if let Some(counter) = CACHE_INSERTS.get() {
+        counter.with_label_values(&[layer]).inc();
+    }
+}
+
 pub fn record_cache_eviction(layer: &'static str, count: u64) {
-    if let Some(counter) = CACHE_MISSES.get() {
+    if let Some(counter) = CACHE_EVICTIONS.get() {
         counter.with_label_values(&[layer]).inc_by(count);
     }
 }
 
+/// Current hit/miss/insert/eviction counts for one tier, as reported by /v1/admin/cache/stats.
+pub fn cache_tier_counts(layer: &str) -> (u64, u64, u64, u64) {
+    let read = |cell: &OnceCell<IntCounterVec>| cell.get().map_or(0, |counter| counter.with_label_values(&[layer]).get());
+    (read(&CACHE_HITS), read(&CACHE_MISSES), read(&CACHE_INSERTS), read(&CACHE_EVICTIONS))
+}
+
 pub fn record_cache_latency(layer: &'static str, start: Instant) {
     if let Some(hist) = CACHE_LATENCY.get() {
         let elapsed = start.elapsed().as_secs_f64();
@@ -159,6 +468,39 @@ pub fn record_db_latency(op: &'static str, start: Instant) {
     }
 }
 
+pub fn set_slow_op_threshold_ms(threshold_ms: u64) {
+    SLOW_OP_THRESHOLD_MS.store(threshold_ms, Ordering::Relaxed);
+}
+
+/// Records a storage call's latency and flags it if it crossed the slow-op threshold.
+/// Only the key's namespace is logged; full keys can carry emails and tokens.
+pub fn record_storage_latency(op: &'static str, key: &str, node: &str, start: Instant) {
+    record_db_latency(op, start);
+    let elapsed = start.elapsed();
+    if elapsed.as_millis() as u64 >= SLOW_OP_THRESHOLD_MS.load(Ordering::Relaxed) {
+        tracing::warn!(
+            operation = op,
+            key_pattern = %key_pattern(key),
+            node = node,
+            latency_ms = elapsed.as_secs_f64() * 1000.0,
+            "Slow storage operation"
+        );
+        if let Some(counter) = SLOW_STORAGE_OPS.get() {
+            counter.with_label_values(&[op]).inc();
+        }
+    }
+}
+
+/// The namespace of a storage key, e.g. "user_urls:*"; bare link codes become "*" and the "-"
+/// placeholder of key-less calls is kept.
+fn key_pattern(key: &str) -> String {
+    match key.split_once(':') {
+        Some((namespace, _)) => format!("{}:*", namespace),
+        None if key == "-" => key.to_string(),
+        None => "*".to_string(),
+    }
+}
+
 pub fn record_db_error(op: &'static str) {
     if let Some(counter) = DB_ERRORS.get() {
         counter.with_label_values(&[op]).inc();
@@ -177,6 +519,30 @@ pub fn record_click() {
     }
 }
 
+pub fn record_bot_click(bot: &str) {
+    if let Some(counter) = BOT_CLICKS.get() {
+        counter.with_label_values(&[bot]).inc();
+    }
+}
+
+pub fn record_unfurl_hit(bot: &str) {
+    if let Some(counter) = UNFURL_HITS.get() {
+        counter.with_label_values(&[bot]).inc();
+    }
+}
+
+pub fn record_crawler_hit(bot: &str, policy: &str) {
+    if let Some(counter) = CRAWLER_HITS.get() {
+        counter.with_label_values(&[bot, policy]).inc();
+    }
+}
+
+pub fn record_sled_purged(db: &str, purged: u64) {
+    if let Some(counter) = SLED_PURGED.get() {
+        counter.with_label_values(&[db]).inc_by(purged);
+    }
+}
+
 pub fn record_batch_flush(size: usize) {
     if let Some(counter) = BATCHES_FLUSHED.get() {
         counter.inc();
@@ -210,6 +576,18 @@ pub fn record_redirect_served() {
     }
 }
 
+pub fn record_fallback_redirect() {
+    if let Some(counter) = FALLBACK_REDIRECTS.get() {
+        counter.inc();
+    }
+}
+
+pub fn set_hot_set_size(size: usize) {
+    if let Some(gauge) = HOT_SET_SIZE.get() {
+        gauge.set(size as i64);
+    }
+}
+
 pub fn record_http_request(endpoint: &str, method: &str, status: u32) {
     if let Some(counter) = HTTP_REQUESTS.get() {
         counter.with_label_values(&[endpoint, method, &status.to_string()]).inc();
@@ -221,4 +599,204 @@ pub fn record_http_latency(endpoint: &str, method: &str, start: Instant) {
         let elapsed = start.elapsed().as_secs_f64();
         hist.with_label_values(&[endpoint, method]).observe(elapsed);
     }
-}
\ No newline at end of file
+}
+
+pub fn record_reputation_check(verdict: &str) {
+    if let Some(counter) = URL_REPUTATION_CHECKS.get() {
+        counter.with_label_values(&[verdict]).inc();
+    }
+}
+
+pub fn record_abuse_report(outcome: &'static str) {
+    if let Some(counter) = ABUSE_REPORTS.get() {
+        counter.with_label_values(&[outcome]).inc();
+    }
+}
+
+pub fn record_link_health_check(status: &str) {
+    if let Some(counter) = LINK_HEALTH_CHECKS.get() {
+        counter.with_label_values(&[status]).inc();
+    }
+}
+
+/// Replaces the previous scan's counts, so a status nothing fails with anymore drops out.
+pub fn set_broken_links(by_status: &BTreeMap<&str, usize>) {
+    if let Some(gauge) = BROKEN_LINKS.get() {
+        gauge.reset();
+        for (status, count) in by_status {
+            gauge.with_label_values(&[*status]).set(*count as i64);
+        }
+    }
+}
+
+pub fn record_account_lockout(scope: &'static str, event: &'static str) {
+    if let Some(counter) = ACCOUNT_LOCKOUTS.get() {
+        counter.with_label_values(&[scope, event]).inc();
+    }
+}
+
+pub fn record_geoip_build_epoch(epoch: u64) {
+    if let Some(gauge) = GEOIP_BUILD_EPOCH.get() {
+        gauge.set(epoch as i64);
+    }
+}
+
+pub fn record_hedged_read(outcome: &'static str) {
+    if let Some(counter) = HEDGED_READS.get() {
+        counter.with_label_values(&[outcome]).inc();
+    }
+}
+
+pub fn record_circuit_breaker_transition(node: &str, from: &'static str, to: &'static str) {
+    if let Some(counter) = CIRCUIT_BREAKER_TRANSITIONS.get() {
+        counter.with_label_values(&[node, from, to]).inc();
+    }
+}
+
+pub fn record_pool_wait(start: Instant) {
+    if let Some(hist) = REDIS_POOL_WAIT.get() {
+        hist.observe(start.elapsed().as_secs_f64());
+    }
+}
+
+pub fn set_degraded(degraded: bool, queued_writes: usize) {
+    if let Some(gauge) = DEGRADED_MODE.get() {
+        gauge.set(degraded as i64);
+    }
+    if let Some(gauge) = DEGRADED_QUEUED_WRITES.get() {
+        gauge.set(queued_writes as i64);
+    }
+}
+
+pub fn record_replicated_mutation(peer: &str, outcome: &str) {
+    if let Some(counter) = REPLICATED_MUTATIONS.get() {
+        counter.with_label_values(&[peer, outcome]).inc();
+    }
+}
+
+pub fn record_replication_lag(peer: &str, lag: Duration) {
+    if let Some(hist) = REPLICATION_LAG.get() {
+        hist.with_label_values(&[peer]).observe(lag.as_secs_f64());
+    }
+}
+
+pub fn set_replication_backlog(peer: &str, queued: usize) {
+    if let Some(gauge) = REPLICATION_BACKLOG.get() {
+        gauge.with_label_values(&[peer]).set(queued as i64);
+    }
+}
+
+pub fn record_pool_stats(client: &str, pools: &[PoolStats]) {
+    for stats in pools {
+        let labels = [client, stats.node.as_str()];
+        if let Some(gauge) = REDIS_POOL_SIZE.get() {
+            gauge.with_label_values(&labels).set(stats.size as i64);
+        }
+        if let Some(gauge) = REDIS_POOL_IN_USE.get() {
+            gauge.with_label_values(&labels).set(stats.in_use as i64);
+        }
+    }
+}
+
+/// Samples every named storage client's pools into the redis_pool_* gauges every `interval`.
+pub fn spawn_pool_metrics(clients: Vec<(&'static str, Arc<dyn Storage + Send + Sync>)>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for (name, client) in &clients {
+                record_pool_stats(name, &client.pool_stats());
+            }
+        }
+    });
+}
+
+pub fn record_runtime_metrics(runtime: &tokio::runtime::RuntimeMetrics) {
+    let workers = runtime.num_workers();
+    if let Some(gauge) = TOKIO_WORKERS.get() {
+        gauge.set(workers as i64);
+    }
+    if let Some(gauge) = TOKIO_ALIVE_TASKS.get() {
+        gauge.set(runtime.num_alive_tasks() as i64);
+    }
+    if let Some(gauge) = TOKIO_GLOBAL_QUEUE_DEPTH.get() {
+        gauge.set(runtime.global_queue_depth() as i64);
+    }
+    for worker in 0..workers {
+        let label = worker.to_string();
+        if let Some(gauge) = TOKIO_WORKER_BUSY_SECONDS.get() {
+            gauge.with_label_values(&[label.as_str()]).set(runtime.worker_total_busy_duration(worker).as_secs_f64());
+        }
+        if let Some(gauge) = TOKIO_WORKER_PARKS.get() {
+            gauge.with_label_values(&[label.as_str()]).set(runtime.worker_park_count(worker) as i64);
+        }
+        #[cfg(tokio_unstable)]
+        if let Some(gauge) = TOKIO_WORKER_QUEUE_DEPTH.get() {
+            gauge.with_label_values(&[label.as_str()]).set(runtime.worker_local_queue_depth(worker) as i64);
+        }
+    }
+    #[cfg(tokio_unstable)]
+    {
+        if let Some(gauge) = TOKIO_BLOCKING_THREADS.get() {
+            gauge.with_label_values(&["total"]).set(runtime.num_blocking_threads() as i64);
+            gauge.with_label_values(&["idle"]).set(runtime.num_idle_blocking_threads() as i64);
+        }
+        if let Some(gauge) = TOKIO_BLOCKING_QUEUE_DEPTH.get() {
+            gauge.set(runtime.blocking_queue_depth() as i64);
+        }
+    }
+}
+
+/// Samples the current runtime's metrics into the tokio_* gauges every `interval`.
+pub fn spawn_runtime_metrics(interval: Duration) {
+    let handle = tokio::runtime::Handle::current();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            record_runtime_metrics(&handle.metrics());
+        }
+    });
+}
+
+pub fn record_circuit_breaker(breaker: &str, nodes: &[NodeStatus]) {
+    for status in nodes {
+        let labels = [breaker, status.node.as_str()];
+        if let Some(gauge) = CIRCUIT_BREAKER_HEALTHY.get() {
+            gauge.with_label_values(&labels).set(status.healthy as i64);
+        }
+        if let Some(gauge) = CIRCUIT_BREAKER_FAILURES.get() {
+            gauge.with_label_values(&labels).set(status.failure_count as i64);
+        }
+        if let (Some(gauge), Some(secs)) = (CIRCUIT_BREAKER_SINCE_FAILURE.get(), status.secs_since_last_failure) {
+            gauge.with_label_values(&labels).set(secs);
+        }
+    }
+}
+
+/// Samples every named breaker into the circuit_breaker_* gauges every `interval`.
+pub fn spawn_circuit_breaker_metrics(breakers: Vec<(&'static str, Arc<CircuitBreaker>)>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for (name, breaker) in &breakers {
+                record_circuit_breaker(name, &breaker.snapshot().await);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_op_logs_keep_only_the_key_namespace() {
+        assert_eq!(key_pattern("user:alice@example.com"), "user:*");
+        assert_eq!(key_pattern("email_verification:secret-token"), "email_verification:*");
+        assert_eq!(key_pattern("session:user-alice:jti"), "session:*");
+        assert_eq!(key_pattern("abc123"), "*");
+        assert_eq!(key_pattern("-"), "-");
+    }
+}
@@ -0,0 +1,32 @@
+use futures::future::BoxFuture;
+use tracing::info;
+
+use crate::{errors::AppError, services::storage::storage::Storage};
+
+/// One storage migration. Steps must be idempotent: `--migrate` runs every step each time
+/// rather than tracking which have been applied.
+pub struct Migration {
+    pub name: &'static str,
+    pub run: for<'a> fn(&'a (dyn Storage + Send + Sync)) -> BoxFuture<'a, Result<u64, AppError>>, // Returns records touched
+}
+
+/// Applied in order by `hyperlinkr --migrate`. New record fields so far deserialize with
+/// `#[serde(default)]`, so no step has been needed yet.
+pub const MIGRATIONS: &[Migration] = &[];
+
+/// Applies every migration in order.
+///
+/// # Errors
+///
+/// Stops at the first migration that fails and returns its error.
+pub async fn run(db: &(dyn Storage + Send + Sync)) -> Result<(), AppError> {
+    if MIGRATIONS.is_empty() {
+        info!("No storage migrations to apply");
+        return Ok(());
+    }
+    for migration in MIGRATIONS {
+        let touched = (migration.run)(db).await?;
+        info!("Migration {} touched {} records", migration.name, touched);
+    }
+    Ok(())
+}
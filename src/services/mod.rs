@@ -5,4 +5,24 @@ pub mod analytics;
 pub mod metrics;
 pub mod ua_parser;
 pub mod geo_lookup;
-pub mod sled;
\ No newline at end of file
+pub mod sled;
+pub mod safe_browsing;pub mod blocklist;
+pub mod url_guard;
+pub mod link_checker;
+pub mod captcha;
+pub mod tokens;
+pub mod password_policy;
+pub mod api_keys;
+pub mod log_level;
+pub mod migrations;
+pub mod hooks;
+pub mod campaigns;
+pub mod link_signing;
+pub mod replication;
+pub mod otlp;
+pub mod usage;
+pub mod discovery;
+pub mod shorten_dedup;
+pub mod link_search;
+pub mod threat_intel;
+pub mod reserved_aliases;
@@ -0,0 +1,167 @@
+//! Pushes the Prometheus registry to an OTLP/HTTP collector, for deployments nothing scrapes.
+//!
+//! Metrics are sent as OTLP JSON on an interval, with cumulative temporality so a collector sees
+//! the same values `/v1/metrics` would serve. Summaries and untyped metrics are not exported.
+
+use chrono::Utc;
+use prometheus::proto::{Metric, MetricFamily, MetricType};
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::config::settings::Settings;
+
+// OTLP's AGGREGATION_TEMPORALITY_CUMULATIVE
+const CUMULATIVE: u8 = 2;
+
+/// Starts pushing metrics to `metrics.otlp_endpoint`; a no-op when it is unset.
+pub fn spawn_otlp_exporter(config: &Settings) {
+    let Some(endpoint) = config.metrics.otlp_endpoint.clone() else {
+        return;
+    };
+    let client = match Client::builder().timeout(Duration::from_millis(config.metrics.otlp_timeout_ms)).build() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("OTLP exporter disabled, failed to build its HTTP client: {}", e);
+            return;
+        }
+    };
+    let headers = config.metrics.otlp_headers.clone();
+    let service_name = config.metrics.service_name.clone();
+    let interval = Duration::from_secs(config.metrics.otlp_interval_secs);
+    let start_ns = unix_nanos();
+    info!("Pushing metrics to {} every {:?}", endpoint, interval);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick fires straight away, before there is anything worth sending
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let body = encode(&prometheus::gather(), &service_name, start_ns, unix_nanos());
+            let mut request = client.post(&endpoint).json(&body);
+            for (name, value) in &headers {
+                request = request.header(name, value);
+            }
+            match request.send().await {
+                Ok(response) if response.status().is_success() => {}
+                Ok(response) => warn!("OTLP collector rejected metrics: {}", response.status()),
+                Err(e) => warn!("Failed to push metrics over OTLP: {}", e),
+            }
+        }
+    });
+}
+
+fn unix_nanos() -> u64 {
+    Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64
+}
+
+/// An OTLP `ExportMetricsServiceRequest` in its JSON encoding. 64-bit integers are strings, as
+/// the protobuf JSON mapping requires.
+pub fn encode(families: &[MetricFamily], service_name: &str, start_ns: u64, now_ns: u64) -> Value {
+    let metrics: Vec<Value> = families
+        .iter()
+        .filter_map(|family| {
+            let points = |point: &dyn Fn(&Metric) -> Value| -> Vec<Value> {
+                family
+                    .get_metric()
+                    .iter()
+                    .map(|metric| {
+                        let mut value = point(metric);
+                        value["attributes"] = attributes(metric);
+                        value["startTimeUnixNano"] = json!(start_ns.to_string());
+                        value["timeUnixNano"] = json!(now_ns.to_string());
+                        value
+                    })
+                    .collect()
+            };
+            let (kind, data) = match family.get_field_type() {
+                MetricType::COUNTER => ("sum", json!({
+                    "dataPoints": points(&|metric| json!({ "asDouble": metric.get_counter().value() })),
+                    "aggregationTemporality": CUMULATIVE,
+                    "isMonotonic": true,
+                })),
+                MetricType::GAUGE => ("gauge", json!({
+                    "dataPoints": points(&|metric| json!({ "asDouble": metric.get_gauge().value() })),
+                })),
+                MetricType::HISTOGRAM => ("histogram", json!({
+                    "dataPoints": points(&histogram_point),
+                    "aggregationTemporality": CUMULATIVE,
+                })),
+                MetricType::SUMMARY | MetricType::UNTYPED => return None,
+            };
+            let mut metric = json!({ "name": family.name(), "description": family.help() });
+            metric[kind] = data;
+            Some(metric)
+        })
+        .collect();
+
+    json!({ "resourceMetrics": [{
+        "resource": { "attributes": [{ "key": "service.name", "value": { "stringValue": service_name } }] },
+        "scopeMetrics": [{ "scope": { "name": "hyperlinkr" }, "metrics": metrics }],
+    }]})
+}
+
+fn attributes(metric: &Metric) -> Value {
+    metric
+        .get_label()
+        .iter()
+        .map(|label| json!({ "key": label.name(), "value": { "stringValue": label.value() } }))
+        .collect()
+}
+
+/// Prometheus buckets are cumulative with an implicit +Inf; OTLP wants per-bucket counts with
+/// one more count than bounds.
+fn histogram_point(metric: &Metric) -> Value {
+    let histogram = metric.get_histogram();
+    let buckets: Vec<_> = histogram.get_bucket().iter().filter(|bucket| bucket.upper_bound().is_finite()).collect();
+    let mut previous = 0;
+    let mut counts: Vec<String> = buckets
+        .iter()
+        .map(|bucket| {
+            let count = bucket.cumulative_count().saturating_sub(previous);
+            previous = bucket.cumulative_count();
+            count.to_string()
+        })
+        .collect();
+    counts.push(histogram.get_sample_count().saturating_sub(previous).to_string());
+    json!({
+        "count": histogram.get_sample_count().to_string(),
+        "sum": histogram.get_sample_sum(),
+        "bucketCounts": counts,
+        "explicitBounds": buckets.iter().map(|bucket| bucket.upper_bound()).collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::{HistogramOpts, HistogramVec, IntCounter, Registry};
+
+    #[test]
+    fn counters_and_histograms_map_to_otlp_json() {
+        let registry = Registry::new();
+        let counter = IntCounter::new("links_total", "Links created").unwrap();
+        let histogram = HistogramVec::new(HistogramOpts::new("lookup_seconds", "Lookups").buckets(vec![0.1, 1.0]), &["tier"]).unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        registry.register(Box::new(histogram.clone())).unwrap();
+        counter.inc_by(3);
+        for value in [0.05, 0.5, 0.5, 5.0] {
+            histogram.with_label_values(&["l1"]).observe(value);
+        }
+
+        let body = encode(&registry.gather(), "hyperlinkr", 1, 2);
+        let metrics = &body["resourceMetrics"][0]["scopeMetrics"][0]["metrics"];
+        let sum = &metrics[0]["sum"];
+        assert_eq!(metrics[0]["name"], "links_total");
+        assert_eq!(sum["isMonotonic"], true);
+        assert_eq!(sum["dataPoints"][0]["asDouble"], 3.0);
+
+        let point = &metrics[1]["histogram"]["dataPoints"][0];
+        assert_eq!(point["count"], "4");
+        assert_eq!(point["bucketCounts"], json!(["1", "2", "1"]));
+        assert_eq!(point["explicitBounds"], json!([0.1, 1.0]));
+        assert_eq!(point["attributes"][0], json!({ "key": "tier", "value": { "stringValue": "l1" } }));
+        assert_eq!(point["timeUnixNano"], "2");
+    }
+}
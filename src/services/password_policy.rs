@@ -0,0 +1,130 @@
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader};
+use tracing::{info, warn};
+
+use crate::{
+    config::{password_policy::PasswordPolicyConfig, settings::Settings},
+    errors::AppError,
+    services::cache::bloom_filter::bloom::CacheBloom,
+};
+
+/// Password strength rules applied when a password is set (registration, and resets once they exist).
+pub struct PasswordPolicy {
+    min_length: usize,
+    require_lowercase: bool,
+    require_uppercase: bool,
+    require_digit: bool,
+    require_symbol: bool,
+    breached: Option<CacheBloom>,
+}
+
+impl PasswordPolicy {
+    pub(crate) fn new(config: &Settings) -> Result<Self, AppError> {
+        let policy = &config.password_policy;
+        let breached = policy
+            .breached_hashes_path
+            .as_ref()
+            .map(|path| load_breached_hashes(path, policy))
+            .transpose()?;
+        Ok(Self {
+            min_length: policy.min_length,
+            require_lowercase: policy.require_lowercase,
+            require_uppercase: policy.require_uppercase,
+            require_digit: policy.require_digit,
+            require_symbol: policy.require_symbol,
+            breached,
+        })
+    }
+
+    /// Returns every rule the password breaks, so the client can show them all at once.
+    pub fn violations(&self, password: &str, username: &str) -> Vec<&'static str> {
+        let mut violations = Vec::new();
+        if password.chars().count() < self.min_length {
+            violations.push("is too short");
+        }
+        if self.require_lowercase && !password.chars().any(|c| c.is_lowercase()) {
+            violations.push("needs a lowercase letter");
+        }
+        if self.require_uppercase && !password.chars().any(|c| c.is_uppercase()) {
+            violations.push("needs an uppercase letter");
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            violations.push("needs a digit");
+        }
+        if self.require_symbol && password.chars().all(|c| c.is_alphanumeric()) {
+            violations.push("needs a symbol");
+        }
+        if !username.is_empty() && password.to_lowercase().contains(&username.to_lowercase()) {
+            violations.push("must not contain the username");
+        }
+        // A bloom hit can be a false positive; rejecting a rare strong password is the safe side
+        if self.breached.as_ref().is_some_and(|bloom| bloom.contains(hash_password(password).as_bytes())) {
+            violations.push("appears in a known data breach");
+        }
+        violations
+    }
+
+    pub(crate) fn check(&self, password: &str, username: &str) -> Result<(), AppError> {
+        let violations = self.violations(password, username);
+        if violations.is_empty() {
+            return Ok(());
+        }
+        Err(AppError::BadRequest(format!("Password {}", violations.join(", "))))
+    }
+}
+
+fn hash_password(password: &str) -> String {
+    format!("{:x}", Sha256::digest(password.as_bytes()))
+}
+
+fn load_breached_hashes(path: &str, policy: &PasswordPolicyConfig) -> Result<CacheBloom, AppError> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| AppError::Internal(format!("Failed to open breached password list {}: {}", path, e)))?;
+    // ~10 bits per entry keeps the false-positive rate around 1%
+    let bloom = CacheBloom::new(policy.breached_expected * 10, policy.breached_expected, 0);
+    let (mut loaded, mut skipped) = (0usize, 0usize);
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| AppError::Internal(format!("Failed to read {}: {}", path, e)))?;
+        let hash = line.trim().to_ascii_lowercase();
+        if hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            bloom.insert(hash.as_bytes());
+            loaded += 1;
+        } else if !hash.is_empty() {
+            skipped += 1;
+        }
+    }
+    if skipped > 0 {
+        warn!("Skipped {} malformed lines in breached password list {}", skipped, path);
+    }
+    info!("Loaded {} breached password hashes from {}", loaded, path);
+    Ok(bloom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_each_broken_rule() {
+        let policy = PasswordPolicy::new(&Settings::default()).unwrap();
+        assert!(policy.check("Correct-Horse-9", "alice").is_ok());
+        assert_eq!(
+            policy.violations("alice123", "alice"),
+            vec!["needs an uppercase letter", "must not contain the username"]
+        );
+    }
+
+    #[test]
+    fn rejects_breached_passwords() {
+        let path = std::env::temp_dir().join(format!("hyperlinkr-breached-{}", std::process::id()));
+        std::fs::write(&path, format!("{}\nnot-a-hash\n", hash_password("Password123")).to_uppercase()).unwrap();
+
+        let mut config = Settings::default();
+        config.password_policy.breached_hashes_path = Some(path.to_string_lossy().into());
+        config.password_policy.breached_expected = 100;
+        let policy = PasswordPolicy::new(&config).unwrap();
+        assert_eq!(policy.violations("Password123", "bob"), vec!["appears in a known data breach"]);
+        assert!(policy.check("Unlisted-Passw0rd", "bob").is_ok());
+        std::fs::remove_file(&path).ok();
+    }
+}
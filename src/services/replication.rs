@@ -0,0 +1,191 @@
+//! Ships link writes to the Dragonfly of each peer region, for active-passive deployments.
+//!
+//! Creates, updates and deletes that go through `CacheService` are stamped with the time they
+//! happened and queued per peer. Each peer has its own worker, so a slow or unreachable region
+//! never holds up the others. A worker applies its queue in order with a script that only lets a
+//! mutation through when it is newer than the last one the peer saw for that code; retries,
+//! reordering and a failed-over region writing back all settle on the latest write.
+
+use std::{sync::Arc, time::Duration};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::{
+    clock::Clock,
+    config::{cache::CacheConfig, settings::Settings},
+    errors::AppError,
+    services::{
+        cache::circuit_breaker::{redact_node, CircuitBreaker},
+        metrics,
+        storage::{dragonfly::DatabaseClient, storage::Storage},
+    },
+};
+
+// Mutations a worker takes off its queue per wakeup
+const BATCH_SIZE: usize = 256;
+
+const APPLY_SCRIPT: &str = r#"
+    local incoming = tonumber(ARGV[1])
+    local current = tonumber(redis.call('GET', KEYS[2]) or '0')
+    if incoming <= current then
+        return 0
+    end
+    if ARGV[2] == 'del' then
+        redis.call('DEL', KEYS[1])
+    else
+        redis.call('SET', KEYS[1], ARGV[3], 'EX', ARGV[4])
+    end
+    redis.call('SET', KEYS[2], ARGV[1], 'EX', ARGV[4])
+    return 1
+"#;
+
+// Last applied mutation time per code, kept next to the link on the peer. The code is its hash
+// tag, so the version lands on the same node as the link.
+fn version_key(code: &str) -> String {
+    format!("replication:version:{{{}}}", code)
+}
+
+/// A link write as replicated: the stored JSON, or `None` for a delete.
+#[derive(Debug, Clone)]
+pub struct LinkMutation {
+    pub code: String,
+    pub value: Option<String>,
+    pub timestamp_ms: i64,
+}
+
+/// Applies `mutation` unless `storage` already has one at least as new for the same code, and
+/// returns whether it did. Versions and deletes expire after `ttl_seconds`, like links do.
+///
+/// # Errors
+///
+/// Fails if `storage` can't be read or written.
+pub async fn apply(storage: &(dyn Storage + Send + Sync), mutation: &LinkMutation, ttl_seconds: u64) -> Result<bool, AppError> {
+    let (op, value) = match &mutation.value {
+        Some(value) => ("set", value.as_str()),
+        None => ("del", ""),
+    };
+    let applied = storage
+        .eval_lua(
+            APPLY_SCRIPT,
+            vec![mutation.code.clone(), version_key(&mutation.code)],
+            vec![mutation.timestamp_ms.to_string(), op.to_string(), value.to_string(), ttl_seconds.to_string()],
+        )
+        .await?;
+    Ok(applied == 1)
+}
+
+struct PeerQueue {
+    label: String,
+    tx: mpsc::Sender<Arc<LinkMutation>>,
+}
+
+/// Queues link mutations for every peer region; cheap to clone.
+#[derive(Clone)]
+pub struct Replicator {
+    peers: Arc<[PeerQueue]>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Replicator {
+    /// Starts a worker per configured peer. `None` when replication is off or has no peers.
+    pub fn spawn(config: &Settings, clock: Arc<dyn Clock>) -> Option<Self> {
+        let replication = &config.replication;
+        if !replication.enabled || replication.peers.is_empty() {
+            return None;
+        }
+        let peers = replication
+            .peers
+            .iter()
+            .map(|url| {
+                let (tx, rx) = mpsc::channel(replication.queue_size);
+                let worker = PeerWorker {
+                    url: url.clone(),
+                    label: redact_node(url),
+                    cache: config.cache.clone(),
+                    global_admins: config.security.global_admins.clone(),
+                    retry_interval: Duration::from_millis(replication.retry_interval_ms),
+                    clock: Arc::clone(&clock),
+                };
+                let label = worker.label.clone();
+                tokio::spawn(worker.run(rx));
+                PeerQueue { label, tx }
+            })
+            .collect();
+        info!("Replicating link writes to {} peer region(s)", replication.peers.len());
+        Some(Self { peers, clock })
+    }
+
+    /// Queues a write of `code` (or its delete, when `value` is `None`) for every peer. A peer
+    /// whose queue is full drops it rather than slowing the write down.
+    pub fn record(&self, code: &str, value: Option<&str>) {
+        let mutation = Arc::new(LinkMutation {
+            code: code.to_string(),
+            value: value.map(str::to_string),
+            timestamp_ms: self.clock.now().timestamp_millis(),
+        });
+        for peer in self.peers.iter() {
+            if peer.tx.try_send(Arc::clone(&mutation)).is_err() {
+                warn!("Replication queue for {} is full; dropped the write of {}", peer.label, code);
+                metrics::record_replicated_mutation(&peer.label, "dropped");
+            }
+        }
+    }
+}
+
+struct PeerWorker {
+    url: String,
+    label: String,
+    cache: CacheConfig,
+    global_admins: Vec<String>,
+    retry_interval: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl PeerWorker {
+    async fn run(self, mut rx: mpsc::Receiver<Arc<LinkMutation>>) {
+        let storage = self.connect().await;
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        while rx.recv_many(&mut batch, BATCH_SIZE).await > 0 {
+            for mutation in batch.drain(..) {
+                self.ship(&storage, &mutation).await;
+            }
+            metrics::set_replication_backlog(&self.label, rx.len());
+        }
+    }
+
+    /// Keeps trying until the peer answers; mutations wait in the queue meanwhile.
+    async fn connect(&self) -> DatabaseClient {
+        loop {
+            let circuit_breaker = Arc::new(CircuitBreaker::from_config(vec![self.url.clone()], &self.cache));
+            match DatabaseClient::connect(std::slice::from_ref(&self.url), &self.cache, self.global_admins.clone(), circuit_breaker).await {
+                Ok(client) => {
+                    info!("Connected to replication peer {}", self.label);
+                    return client;
+                }
+                Err(e) => {
+                    warn!("Failed to connect to replication peer {}: {}", self.label, e);
+                    tokio::time::sleep(self.retry_interval).await;
+                }
+            }
+        }
+    }
+
+    /// Retries until the peer takes the mutation, so later writes never overtake it.
+    async fn ship(&self, storage: &DatabaseClient, mutation: &LinkMutation) {
+        loop {
+            match apply(storage, mutation, self.cache.ttl_seconds).await {
+                Ok(applied) => {
+                    metrics::record_replicated_mutation(&self.label, if applied { "applied" } else { "stale" });
+                    let lag_ms = self.clock.now().timestamp_millis() - mutation.timestamp_ms;
+                    metrics::record_replication_lag(&self.label, Duration::from_millis(lag_ms.max(0) as u64));
+                    return;
+                }
+                Err(e) => {
+                    warn!("Replicating {} to {} failed: {}", mutation.code, self.label, e);
+                    metrics::record_replicated_mutation(&self.label, "failed");
+                    tokio::time::sleep(self.retry_interval).await;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::{collections::BTreeSet, sync::Arc};
+use tracing::info;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::{
+    config::{security::DEFAULT_RESERVED_ALIASES, settings::Settings},
+    errors::AppError,
+    services::storage::storage::Storage,
+};
+
+/// Custom aliases nobody can claim: the built-in page names plus `security.reserved_aliases`.
+///
+/// Admins can reserve or release aliases at runtime. Their changes are kept in storage rather
+/// than in memory, so every replica sees them and they survive restarts.
+pub struct ReservedAliases {
+    configured: BTreeSet<String>,
+    db: Arc<dyn Storage + Send + Sync>,
+}
+
+impl ReservedAliases {
+    pub fn new(config: &Settings, db: Arc<dyn Storage + Send + Sync>) -> Self {
+        let configured: BTreeSet<String> = DEFAULT_RESERVED_ALIASES
+            .iter()
+            .map(|alias| alias.to_string())
+            .chain(config.security.reserved_aliases.iter().flatten().map(|alias| normalize(alias)))
+            .collect();
+        info!("Loaded {} reserved aliases", configured.len());
+        Self { configured, db }
+    }
+
+    /// Rejects a reserved alias the way validating `custom_alias` used to, so clients see the same error.
+    pub(crate) async fn check(&self, alias: &str) -> Result<(), AppError> {
+        let normalized = normalize(alias);
+        if !self.list().await?.contains(&normalized) {
+            return Ok(());
+        }
+        let mut err = ValidationError::new("alias_is_reserved");
+        err.add_param("alias".into(), &normalized);
+        let mut errors = ValidationErrors::new();
+        errors.add("custom_alias", err);
+        Err(AppError::Validation(errors))
+    }
+
+    /// The configured aliases with admin changes applied, sorted.
+    pub(crate) async fn list(&self) -> Result<Vec<String>, AppError> {
+        let mut aliases = self.configured.clone();
+        for (alias, reserved) in self.db.get_reserved_alias_overrides().await? {
+            if reserved {
+                aliases.insert(alias);
+            } else {
+                aliases.remove(&alias);
+            }
+        }
+        Ok(aliases.into_iter().collect())
+    }
+
+    /// Returns false if `alias` was already reserved.
+    pub(crate) async fn add(&self, alias: &str) -> Result<bool, AppError> {
+        self.set(alias, true).await
+    }
+
+    /// Returns false if `alias` wasn't reserved.
+    pub(crate) async fn remove(&self, alias: &str) -> Result<bool, AppError> {
+        self.set(alias, false).await
+    }
+
+    async fn set(&self, alias: &str, reserved: bool) -> Result<bool, AppError> {
+        let alias = normalize(alias);
+        if self.list().await?.contains(&alias) == reserved {
+            return Ok(false);
+        }
+        self.db.set_reserved_alias(&alias, reserved).await?;
+        Ok(true)
+    }
+}
+
+fn normalize(alias: &str) -> String {
+    alias.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::MockStorage;
+
+    #[tokio::test]
+    async fn configured_aliases_extend_the_built_in_ones() {
+        let mut config = Settings::default();
+        config.security.reserved_aliases = Some(vec!["Acme".into()]);
+        let reserved = ReservedAliases::new(&config, Arc::new(MockStorage::new()));
+        assert!(reserved.check("admin").await.is_err());
+        assert!(reserved.check(" ACME ").await.is_err());
+        assert!(reserved.check("promo").await.is_ok());
+
+        assert!(reserved.add("Promo").await.unwrap());
+        assert!(reserved.check("promo").await.is_err());
+        assert!(reserved.remove("blog").await.unwrap());
+        assert!(reserved.check("blog").await.is_ok());
+        assert!(!reserved.remove("blog").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn admin_changes_are_shared_through_storage() {
+        let config = Settings::default();
+        let db: Arc<dyn Storage + Send + Sync> = Arc::new(MockStorage::new());
+        let replica = ReservedAliases::new(&config, Arc::clone(&db));
+        ReservedAliases::new(&config, Arc::clone(&db)).add("promo").await.unwrap();
+        ReservedAliases::new(&config, Arc::clone(&db)).remove("blog").await.unwrap();
+
+        assert!(replica.check("promo").await.is_err());
+        assert!(replica.check("blog").await.is_ok());
+        let restarted = ReservedAliases::new(&config, db);
+        assert!(restarted.list().await.unwrap().contains(&"promo".to_string()));
+        assert!(!restarted.add("promo").await.unwrap());
+    }
+}
@@ -0,0 +1,136 @@
+use moka::future::Cache;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::{Duration, Instant};
+use tracing::warn;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::{config::settings::Settings, services::metrics};
+
+const SAFE_BROWSING_ENDPOINT: &str = "https://safebrowsing.googleapis.com/v4/threatMatches:find";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Verdict {
+    Clean,
+    Malicious(String), // Safe Browsing threat type, e.g. "SOCIAL_ENGINEERING"
+    Unknown,           // Lookup disabled, timed out or failed
+}
+
+impl Verdict {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Verdict::Clean => "clean",
+            Verdict::Malicious(threat) => threat,
+            Verdict::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ThreatMatch {
+    #[serde(rename = "threatType")]
+    threat_type: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ThreatMatchesResponse {
+    #[serde(default)]
+    matches: Vec<ThreatMatch>,
+}
+
+#[derive(Debug, Serialize)]
+struct ThreatEntry<'a> {
+    url: &'a str,
+}
+
+/// Google Safe Browsing (v4 Lookup API) client with a local verdict cache keyed by URL hash.
+pub struct SafeBrowsingClient {
+    http: reqwest::Client,
+    api_key: Option<String>,
+    verdicts: Cache<u64, Verdict>,
+}
+
+impl SafeBrowsingClient {
+    pub(crate) fn new(config: &Settings) -> Self {
+        let timeout = Duration::from_millis(config.security.safe_browsing_timeout_ms.unwrap_or(1_500));
+        let http = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("Failed to build Safe Browsing HTTP client");
+        let verdicts = Cache::builder()
+            .max_capacity(100_000)
+            .time_to_live(Duration::from_secs(3_600))
+            .build();
+        Self {
+            http,
+            api_key: config.security.safe_browsing_api_key.clone(),
+            verdicts,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.api_key.is_some()
+    }
+
+    pub async fn check(&self, url: &str) -> Verdict {
+        let Some(api_key) = &self.api_key else {
+            return Verdict::Unknown;
+        };
+
+        let hash = xxh3_64(url.as_bytes());
+        if let Some(verdict) = self.verdicts.get(&hash).await {
+            metrics::record_reputation_check(verdict.as_str());
+            return verdict;
+        }
+
+        let start = Instant::now();
+        let verdict = match self.lookup(api_key, url).await {
+            Ok(verdict) => {
+                self.verdicts.insert(hash, verdict.clone()).await;
+                verdict
+            }
+            Err(e) => {
+                warn!("Safe Browsing lookup failed for {}: {}", url, e);
+                Verdict::Unknown
+            }
+        };
+        metrics::record_db_latency("safe_browsing_lookup", start);
+        metrics::record_reputation_check(verdict.as_str());
+        verdict
+    }
+
+    /// Caches a verdict as if a lookup had returned it.
+    #[cfg(test)]
+    pub(crate) async fn remember(&self, url: &str, verdict: Verdict) {
+        self.verdicts.insert(xxh3_64(url.as_bytes()), verdict).await;
+    }
+
+    async fn lookup(&self, api_key: &str, url: &str) -> Result<Verdict, reqwest::Error> {
+        let body = json!({
+            "client": {
+                "clientId": "hyperlinkr",
+                "clientVersion": env!("CARGO_PKG_VERSION"),
+            },
+            "threatInfo": {
+                "threatTypes": ["MALWARE", "SOCIAL_ENGINEERING", "UNWANTED_SOFTWARE", "POTENTIALLY_HARMFUL_APPLICATION"],
+                "platformTypes": ["ANY_PLATFORM"],
+                "threatEntryTypes": ["URL"],
+                "threatEntries": [ThreatEntry { url }],
+            }
+        });
+        let response: ThreatMatchesResponse = self
+            .http
+            .post(SAFE_BROWSING_ENDPOINT)
+            .query(&[("key", api_key)])
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(match response.matches.into_iter().next() {
+            Some(threat) => Verdict::Malicious(threat.threat_type),
+            None => Verdict::Clean,
+        })
+    }
+}
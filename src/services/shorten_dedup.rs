@@ -0,0 +1,92 @@
+//! Coalesces identical shorten requests that arrive while the first one is still being handled,
+//! such as double-clicks and client retry storms, so they share one code instead of minting one
+//! each. Only requests that overlap are merged; a repeat after the first has answered is a new
+//! link as before.
+
+use dashmap::{mapref::entry::Entry, DashMap};
+use std::future::Future;
+use tokio::sync::watch;
+
+use crate::{errors::AppError, types::ShortenResponse};
+
+type InFlight = DashMap<String, watch::Receiver<Option<ShortenResponse>>>;
+
+#[derive(Default)]
+pub struct ShortenCoalescer {
+    in_flight: InFlight,
+}
+
+/// Drops the key once its leader finishes, fails or is cancelled.
+struct Release<'a> {
+    in_flight: &'a InFlight,
+    key: &'a str,
+}
+
+impl Drop for Release<'_> {
+    fn drop(&mut self) {
+        self.in_flight.remove(self.key);
+    }
+}
+
+impl ShortenCoalescer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `create` unless a request with the same `key` is already in flight, in which case
+    /// this waits for that one and returns its link. If it fails, each waiter runs `create` for
+    /// itself, so errors reach every caller just as they would have without coalescing.
+    pub(crate) async fn run<F, Fut>(&self, key: String, create: F) -> Result<ShortenResponse, AppError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<ShortenResponse, AppError>>,
+    {
+        let sender = match self.in_flight.entry(key.clone()) {
+            Entry::Occupied(entry) => {
+                let mut receiver = entry.get().clone();
+                drop(entry);
+                if let Ok(response) = receiver.wait_for(Option::is_some).await {
+                    return Ok(response.clone().expect("waited for a response"));
+                }
+                return create().await;
+            }
+            Entry::Vacant(entry) => {
+                let (sender, receiver) = watch::channel(None);
+                entry.insert(receiver);
+                sender
+            }
+        };
+        let _release = Release { in_flight: &self.in_flight, key: &key };
+        let result = create().await;
+        if let Ok(response) = &result {
+            sender.send_replace(Some(response.clone()));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::oneshot;
+
+    #[tokio::test]
+    async fn overlapping_requests_share_the_first_response() {
+        let coalescer = ShortenCoalescer::new();
+        let (release, released) = oneshot::channel::<()>();
+        let response = |code: &str| ShortenResponse { short_url: format!("http://localhost:3000/{}", code), code: code.into(), expiration_date: None };
+
+        let leader = coalescer.run("user-1\nhttps://example.com/".into(), || async {
+            released.await.unwrap();
+            Ok(response("first"))
+        });
+        let follower = coalescer.run("user-1\nhttps://example.com/".into(), || async { Ok(response("second")) });
+        let other = coalescer.run("user-2\nhttps://example.com/".into(), || async { Ok(response("third")) });
+        let (leader, follower, other, _) = tokio::join!(leader, follower, other, async { release.send(()).unwrap() });
+
+        assert_eq!(leader.unwrap().code, "first");
+        assert_eq!(follower.unwrap().code, "first");
+        assert_eq!(other.unwrap().code, "third");
+        assert!(coalescer.in_flight.is_empty());
+    }
+}
@@ -1,17 +1,37 @@
 use async_trait::async_trait;
 use sled::{Db, Batch};
 use bincode::{config, decode_from_slice, encode_to_vec};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Instant, Duration};
 use tracing;
 use crate::{
     config::settings::Settings,
     errors::AppError,
-    services::metrics,
-    types::{Paginate, UrlData, User},
+    services::{link_search::search_terms, metrics},
+    types::{AbuseReport, ApiKey, AuditEvent, Campaign, Notification, Paginate, Session, UrlData, User},
     clock::{Clock, SystemClock},
 };
-use super::storage::storage::Storage;
+use super::storage::storage::{pinned_first, Storage};
+
+/// Which keys of a Sled database carry the expiry suffix `set_ex` writes, and so can be swept.
+#[derive(Debug, Clone, Copy)]
+pub enum ExpiringKeys {
+    /// Databases that only ever hold `set_ex` values, like the cache and geo tiers
+    All,
+    /// The storage backend: links under bare codes, revoked tokens and code reservations
+    Storage,
+}
+
+impl ExpiringKeys {
+    fn matches(self, key: &[u8]) -> bool {
+        match self {
+            ExpiringKeys::All => true,
+            // Everything else lives under a `prefix:` and has no expiry suffix to misread
+            ExpiringKeys::Storage => !key.contains(&b':') || key.starts_with(b"token:") || key.starts_with(b"reserved:"),
+        }
+    }
+}
 
 pub struct SledStorage<C: Clock = SystemClock> {
     db: Arc<Db>,
@@ -25,14 +45,14 @@ impl SledStorage {
     pub fn new(path: &str, config: &Settings) -> Self {
         Self::with_clock(path, config, SystemClock)
     }
-    
-    // Legacy method for backward compatibility - uses storage.sled_path
-    pub fn new_storage(config: &Settings) -> Self {
-        Self::new(&config.storage.sled_path, config)
-    }
 }
 
 impl<C: Clock> SledStorage<C> {
+    // Legacy method for backward compatibility - uses storage.sled_path
+    pub fn new_storage(config: &Settings, clock: C) -> Self {
+        Self::with_clock(&config.storage.sled_path, config, clock)
+    }
+
     pub fn with_clock(path: &str, config: &Settings, clock: C) -> Self {
         let sled_config = sled::Config::new()
             .path(path)
@@ -76,6 +96,87 @@ impl<C: Clock> SledStorage<C> {
     fn url_index_prefix(user_id: &str) -> Vec<u8> {
         format!("index:user_urls:{}:", user_id).into_bytes()
     }
+
+    fn tag_index_key(user_id: &str, tag: &str, code: &str) -> Vec<u8> {
+        format!("index:user_tag:{}:{}:{}", user_id, tag, code).into_bytes()
+    }
+
+    fn tag_index_prefix(user_id: &str, tag: &str) -> Vec<u8> {
+        format!("index:user_tag:{}:{}:", user_id, tag).into_bytes()
+    }
+
+    fn search_index_key(user_id: &str, term: &str, code: &str) -> Vec<u8> {
+        format!("index:user_search:{}:{}:{}", user_id, term, code).into_bytes()
+    }
+
+    fn search_index_prefix(user_id: &str, term: &str) -> Vec<u8> {
+        format!("index:user_search:{}:{}:", user_id, term).into_bytes()
+    }
+
+    fn code_report_prefix(code: &str) -> Vec<u8> {
+        format!("code_reports:{}:", code).into_bytes()
+    }
+
+    /// Deletes the entries among `keys` whose expiry has passed, which reads already treat as
+    /// missing. Returns how many were deleted.
+    pub(crate) fn purge_expired(&self, keys: ExpiringKeys) -> Result<u64, AppError> {
+        let start = Instant::now();
+        let now = self.clock.now().timestamp() as u64;
+        let mut purged = 0;
+        for entry in self.db.iter() {
+            let (key, value) = entry.map_err(AppError::Sled)?;
+            if !(keys.matches(&key) && Self::split_expiry(&value).is_some_and(|(_, expiry)| expiry <= now)) {
+                continue;
+            }
+            // Left alone if it was rewritten since it was read
+            let swapped = self.db.compare_and_swap(&key, Some(&value), None as Option<&[u8]>).map_err(AppError::Sled)?;
+            if swapped.is_ok() {
+                purged += 1;
+            }
+        }
+        metrics::record_storage_latency("purge_expired_sled", "-", "sled", start);
+        Ok(purged)
+    }
+
+    /// Removes `key` outright, whatever it holds. For tiers that only cache values owned elsewhere.
+    pub(crate) fn remove(&self, key: &str) -> Result<(), AppError> {
+        self.db.remove(key).map_err(AppError::Sled)?;
+        Ok(())
+    }
+
+    /// Splits a value written with an expiry suffix (see `set_ex`) into payload and expiry timestamp.
+    fn split_expiry(bytes: &[u8]) -> Option<(&[u8], u64)> {
+        if bytes.len() < 8 {
+            return None;
+        }
+        let (payload, expiry_bytes) = bytes.split_at(bytes.len() - 8);
+        let expiry = u64::from_le_bytes(expiry_bytes.try_into().ok()?);
+        Some((payload, expiry))
+    }
+}
+
+impl<C: Clock + Send + Sync + 'static> SledStorage<C> {
+    /// Runs `purge_expired` every `interval` off the async workers, counting deletions under
+    /// `db` in `sled_expired_purged_total`.
+    pub fn spawn_ttl_sweeper(self: Arc<Self>, db: &'static str, keys: ExpiringKeys, interval: Duration) {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let storage = Arc::clone(&self);
+                match tokio::task::spawn_blocking(move || storage.purge_expired(keys)).await {
+                    Ok(Ok(purged)) => {
+                        metrics::record_sled_purged(db, purged);
+                        if purged > 0 {
+                            tracing::debug!("Purged {} expired entries from the {} Sled database", purged, db);
+                        }
+                    }
+                    Ok(Err(e)) => tracing::warn!("Sweeping the {} Sled database failed: {}", db, e),
+                    Err(e) => tracing::warn!("Sweeping the {} Sled database panicked: {}", db, e),
+                }
+            }
+        });
+    }
 }
 
 #[async_trait]
@@ -84,9 +185,15 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
         let start = Instant::now();
         let bytes = self.db.get(key.as_bytes()).map_err(|e| AppError::Sled(e))?
             .ok_or_else(|| AppError::NotFound(key.into()))?;
-        let result = String::from_utf8(bytes.to_vec())
+        // Values carry the expiry suffix `set_ex` wrote; expired ones read as missing
+        let now = self.clock.now().timestamp() as u64;
+        let payload = match Self::split_expiry(&bytes) {
+            Some((payload, expiry)) if expiry > now => payload,
+            _ => return Err(AppError::NotFound(key.into())),
+        };
+        let result = String::from_utf8(payload.to_vec())
             .map_err(|e| AppError::Internal(e.to_string()))?;
-        metrics::record_db_latency("get_sled", start);
+        metrics::record_storage_latency("get_sled", key, "sled", start);
         Ok(result)
     }
 
@@ -96,7 +203,7 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
         let mut data = value.as_bytes().to_vec();
         data.extend_from_slice(expiry.to_le_bytes().as_ref());
         self.db.insert(key.as_bytes(), data).map_err(|e| AppError::Sled(e))?;
-        metrics::record_db_latency("set_ex_sled", start);
+        metrics::record_storage_latency("set_ex_sled", key, "sled", start);
         Ok(())
     }
 
@@ -115,7 +222,7 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
         batch.insert(key.as_bytes(), encode_to_vec(&new_data, config)
             .map_err(|e| AppError::Internal(e.to_string()))?);
         self.db.apply_batch(batch).map_err(|e| AppError::Sled(e))?;
-        metrics::record_db_latency("zadd_sled", start);
+        metrics::record_storage_latency("zadd_sled", key, "sled", start);
         Ok(())
     }
 
@@ -155,7 +262,7 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
             false
         };
         self.db.apply_batch(batch).map_err(|e| AppError::Sled(e))?;
-        metrics::record_db_latency("rate_limit_sled", start);
+        metrics::record_storage_latency("rate_limit_sled", key, "sled", start);
         Ok(allowed)
     }
 
@@ -174,7 +281,7 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
             .skip(start_idx)
             .take(end_idx.saturating_sub(start_idx))
             .collect();
-        metrics::record_db_latency("zrange_sled", start_time);
+        metrics::record_storage_latency("zrange_sled", key, "sled", start_time);
         Ok(result)
     }
 
@@ -206,20 +313,21 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
                 .map_err(|e| AppError::Internal(e.to_string()))?);
         }
         self.db.apply_batch(batch).map_err(|e| AppError::Sled(e))?;
-        metrics::record_db_latency("zadd_batch_sled", start);
+        metrics::record_storage_latency("zadd_batch_sled", "-", "sled", start);
         Ok(())
     }
 
     async fn delete_url(&self, code: &str, user_id: Option<&str>, user_email: &str) -> Result<(), AppError> {
         let start = Instant::now();
-        let key = format!("url:{}", code);
         let is_admin = self.global_admins.iter().any(|admin| admin == user_email);
         let mut batch = Batch::default();
 
-        let data = self.db.get(&key).map_err(|e| AppError::Sled(e))?;
-        if let Some(bytes) = data {
-            let url_data: UrlData = decode_from_slice(&bytes, config::standard())
-                .map(|(data, _)| data)
+        let data = self.db.get(code).map_err(AppError::Sled)?;
+        // The link carries the expiry suffix `set_ex` wrote; an expired one reads as missing
+        let now = self.clock.now().timestamp() as u64;
+        let payload = data.as_deref().and_then(Self::split_expiry).filter(|(_, expiry)| *expiry > now);
+        if let Some((payload, _)) = payload {
+            let url_data: UrlData = serde_json::from_slice(payload)
                 .map_err(|e| AppError::Internal(e.to_string()))?;
 
             let is_owner = url_data.user_id.as_deref() == user_id || url_data.user_id.is_none();
@@ -227,16 +335,26 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
                 return Err(AppError::Unauthorized("Not authorized to delete this URL".into()));
             }
 
-            batch.remove(key.as_str());
+            batch.remove(code);
             if let Some(uid) = user_id {
                 batch.remove(Self::url_index_key(uid, code));
             }
+            // An admin deleting the link is not its owner
+            if let Some(owner) = url_data.user_id.as_deref() {
+                batch.remove(format!("pinned:{}:{}", owner, code).as_str());
+                for tag in &url_data.tags {
+                    batch.remove(Self::tag_index_key(owner, tag, code));
+                }
+                for term in search_terms(code, &url_data) {
+                    batch.remove(Self::search_index_key(owner, &term, code));
+                }
+            }
             self.db.apply_batch(batch).map_err(|e| AppError::Sled(e))?;
         } else {
             return Err(AppError::NotFound(format!("URL {} not found", code)));
         }
 
-        metrics::record_db_latency("delete_url_sled", start);
+        metrics::record_storage_latency("delete_url_sled", code, "sled", start);
         Ok(())
     }
 
@@ -251,10 +369,153 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
             batch.insert(Self::url_index_key(user_id, code), vec![1u8]);
         }
         self.db.apply_batch(batch).map_err(|e| AppError::Sled(e))?;
-        metrics::record_db_latency("set_url_sled", start);
+        metrics::record_storage_latency("set_url_sled", &key, "sled", start);
+        Ok(())
+    }
+
+    async fn transfer_url(&self, code: &str, url_data: &UrlData, from_user_id: &str) -> Result<(), AppError> {
+        let start = Instant::now();
+        let to_user_id = url_data
+            .user_id
+            .as_deref()
+            .ok_or_else(|| AppError::Internal(format!("Transferred URL {} has no owner", code)))?;
+        // The record keeps the expiry suffix it was written with by `set_ex`
+        let expiry = self.db.get(code).map_err(AppError::Sled)?
+            .and_then(|bytes| Self::split_expiry(&bytes).map(|(_, expiry)| expiry))
+            .ok_or_else(|| AppError::NotFound(format!("URL {} not found", code)))?;
+        let mut data = serde_json::to_string(url_data)
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .into_bytes();
+        data.extend_from_slice(expiry.to_le_bytes().as_ref());
+
+        let mut batch = Batch::default();
+        batch.insert(code, data);
+        batch.remove(Self::url_index_key(from_user_id, code));
+        batch.insert(Self::url_index_key(to_user_id, code), vec![1u8]);
+        self.db.apply_batch(batch).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("transfer_url_sled", code, "sled", start);
+        Ok(())
+    }
+
+    async fn add_user_url(&self, user_id: &str, code: &str) -> Result<(), AppError> {
+        let start = Instant::now();
+        self.db.insert(Self::url_index_key(user_id, code), vec![1u8]).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("add_user_url_sled", code, "sled", start);
+        Ok(())
+    }
+
+    async fn list_user_codes(&self, user_id: &str) -> Result<Vec<String>, AppError> {
+        let start = Instant::now();
+        let prefix = Self::url_index_prefix(user_id);
+        let mut codes = Vec::new();
+        for entry in self.db.scan_prefix(&prefix) {
+            let (key, _) = entry.map_err(AppError::Sled)?;
+            codes.push(String::from_utf8(key[prefix.len()..].to_vec()).map_err(|e| AppError::Internal(e.to_string()))?);
+        }
+        metrics::record_storage_latency("list_user_codes_sled", user_id, "sled", start);
+        Ok(codes)
+    }
+
+    async fn pin_url(&self, user_id: &str, code: &str, pinned_at: u64) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("pinned:{}:{}", user_id, code);
+        self.db.insert(key.as_str(), pinned_at.to_le_bytes().as_ref()).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("pin_url_sled", &key, "sled", start);
+        Ok(())
+    }
+
+    async fn unpin_url(&self, user_id: &str, code: &str) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("pinned:{}:{}", user_id, code);
+        self.db.remove(key.as_str()).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("unpin_url_sled", &key, "sled", start);
+        Ok(())
+    }
+
+    async fn list_pinned(&self, user_id: &str) -> Result<Vec<String>, AppError> {
+        let start = Instant::now();
+        let prefix = format!("pinned:{}:", user_id);
+        let mut pinned = Vec::new();
+        for entry in self.db.scan_prefix(prefix.as_str()) {
+            let (key, value) = entry.map_err(AppError::Sled)?;
+            let code = String::from_utf8(key[prefix.len()..].to_vec())
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            let pinned_at = value.as_ref().try_into().map(u64::from_le_bytes).unwrap_or(0);
+            pinned.push((pinned_at, code));
+        }
+        pinned.sort_by(|a, b| b.cmp(a));
+        metrics::record_storage_latency("list_pinned_sled", &prefix, "sled", start);
+        Ok(pinned.into_iter().map(|(_, code)| code).collect())
+    }
+
+    async fn tag_url(&self, user_id: &str, code: &str, tags: &[String]) -> Result<(), AppError> {
+        let start = Instant::now();
+        let mut batch = Batch::default();
+        for tag in tags {
+            batch.insert(Self::tag_index_key(user_id, tag, code), vec![1u8]);
+        }
+        self.db.apply_batch(batch).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("tag_url_sled", code, "sled", start);
         Ok(())
     }
 
+    async fn untag_url(&self, user_id: &str, code: &str, tags: &[String]) -> Result<(), AppError> {
+        let start = Instant::now();
+        let mut batch = Batch::default();
+        for tag in tags {
+            batch.remove(Self::tag_index_key(user_id, tag, code));
+        }
+        self.db.apply_batch(batch).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("untag_url_sled", code, "sled", start);
+        Ok(())
+    }
+
+    async fn list_tagged_codes(&self, user_id: &str, tag: &str) -> Result<Vec<String>, AppError> {
+        let start = Instant::now();
+        let prefix = Self::tag_index_prefix(user_id, tag);
+        let mut codes = Vec::new();
+        for entry in self.db.scan_prefix(&prefix) {
+            let (key, _) = entry.map_err(AppError::Sled)?;
+            codes.push(String::from_utf8(key[prefix.len()..].to_vec()).map_err(|e| AppError::Internal(e.to_string()))?);
+        }
+        metrics::record_storage_latency("list_tagged_codes_sled", user_id, "sled", start);
+        Ok(codes)
+    }
+
+    async fn index_search_terms(&self, user_id: &str, code: &str, terms: &[String]) -> Result<(), AppError> {
+        let start = Instant::now();
+        let mut batch = Batch::default();
+        for term in terms {
+            batch.insert(Self::search_index_key(user_id, term, code), vec![1u8]);
+        }
+        self.db.apply_batch(batch).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("index_search_terms_sled", code, "sled", start);
+        Ok(())
+    }
+
+    async fn remove_search_terms(&self, user_id: &str, code: &str, terms: &[String]) -> Result<(), AppError> {
+        let start = Instant::now();
+        let mut batch = Batch::default();
+        for term in terms {
+            batch.remove(Self::search_index_key(user_id, term, code));
+        }
+        self.db.apply_batch(batch).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("remove_search_terms_sled", code, "sled", start);
+        Ok(())
+    }
+
+    async fn search_codes(&self, user_id: &str, term: &str) -> Result<Vec<String>, AppError> {
+        let start = Instant::now();
+        let prefix = Self::search_index_prefix(user_id, term);
+        let mut codes = Vec::new();
+        for entry in self.db.scan_prefix(&prefix) {
+            let (key, _) = entry.map_err(AppError::Sled)?;
+            codes.push(String::from_utf8(key[prefix.len()..].to_vec()).map_err(|e| AppError::Internal(e.to_string()))?);
+        }
+        metrics::record_storage_latency("search_codes_sled", user_id, "sled", start);
+        Ok(codes)
+    }
+
     async fn list_urls(&self, user_id: Option<&str>, page: u64, per_page: u64) -> Result<Paginate<UrlData>, AppError> {
         let start = Instant::now();
         let is_admin = user_id.is_none();
@@ -286,6 +547,7 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
                     })
                 })
                 .collect();
+            let codes = pinned_first(codes, &self.list_pinned(uid).await?);
             total_items = codes.len() as u64;
             let start_idx = offset.min(total_items) as usize;
             let end_idx = (offset + per_page).min(total_items) as usize;
@@ -302,7 +564,7 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
         }
 
         let total_pages = if total_items == 0 { 1 } else { (total_items + per_page - 1) / per_page };
-        metrics::record_db_latency("list_urls_sled", start);
+        metrics::record_storage_latency("list_urls_sled", "-", "sled", start);
         Ok(Paginate {
             items,
             page,
@@ -321,7 +583,15 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
             .map_err(|e| AppError::Internal(e.to_string()))?);
         batch.insert(email_key.as_str(), user.id.as_bytes());
         self.db.apply_batch(batch).map_err(|e| AppError::Sled(e))?;
-        metrics::record_db_latency("set_user_sled", start);
+        metrics::record_storage_latency("set_user_sled", &key, "sled", start);
+        Ok(())
+    }
+
+    async fn delete_user_email(&self, email: &str) -> Result<(), AppError> {
+        let start = Instant::now();
+        let email_key = format!("user_email:{}", email);
+        self.db.remove(email_key.as_str()).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("delete_user_email_sled", &email_key, "sled", start);
         Ok(())
     }
 
@@ -349,14 +619,14 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
                 .map_err(|e| AppError::Internal(e.to_string())))
             .transpose()?;
 
-        metrics::record_db_latency("get_user_sled", start);
+        metrics::record_storage_latency("get_user_sled", &key, "sled", start);
         Ok(user)
     }
 
     async fn count_users(&self) -> Result<u64, AppError> {
         let start = Instant::now();
         let count = self.db.scan_prefix("user:").count() as u64;
-        metrics::record_db_latency("count_users_sled", start);
+        metrics::record_storage_latency("count_users_sled", "-", "sled", start);
         Ok(count)
     }
 
@@ -367,7 +637,7 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
         } else {
             self.db.scan_prefix("url:").count() as u64
         };
-        metrics::record_db_latency("count_urls_sled", start);
+        metrics::record_storage_latency("count_urls_sled", "-", "sled", start);
         Ok(count)
     }
 
@@ -378,7 +648,7 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
         let mut data = vec![1u8];
         data.extend_from_slice(&expiry.to_le_bytes().as_ref());
         self.db.insert(&key, data).map_err(|e| AppError::Sled(e))?;
-        metrics::record_db_latency("blacklist_token_sled", start);
+        metrics::record_storage_latency("blacklist_token_sled", &key, "sled", start);
         Ok(())
     }
 
@@ -386,7 +656,7 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
         let start = Instant::now();
         let key = format!("token:{}", token);
         let exists = self.db.get(&key).map_err(|e| AppError::Sled(e))?.is_some();
-        metrics::record_db_latency("is_token_blacklisted_sled", start);
+        metrics::record_storage_latency("is_token_blacklisted_sled", &key, "sled", start);
         Ok(exists)
     }
 
@@ -399,7 +669,7 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
                 entry.ok().map(|(key, _)| String::from_utf8(key.to_vec()).ok()).flatten()
             })
             .collect();
-        metrics::record_db_latency("scan_keys_sled", start);
+        metrics::record_storage_latency("scan_keys_sled", prefix, "sled", start);
         Ok(keys)
     }
 
@@ -416,4 +686,510 @@ impl<C: Clock + Send + Sync> Storage for SledStorage<C> {
     async fn is_global_admin(&self, user_email: &str) -> Result<bool, AppError> {
         Ok(self.global_admins.iter().any(|admin| admin == user_email))
     }
-}
\ No newline at end of file
+
+    async fn reserve_codes(&self, user_id: &str, codes: &[String], ttl_seconds: u64) -> Result<(), AppError> {
+        let start = Instant::now();
+        let now = self.clock.now().timestamp() as u64;
+        let mut batch = Batch::default();
+        for code in codes {
+            let key = format!("reserved:{}", code);
+            if let Some(bytes) = self.db.get(&key).map_err(AppError::Sled)?
+                && Self::split_expiry(&bytes).is_some_and(|(_, expiry)| expiry > now)
+            {
+                return Err(AppError::Conflict(format!("Code {} is already reserved", code)));
+            }
+            let mut data = user_id.as_bytes().to_vec();
+            data.extend_from_slice(&(now + ttl_seconds).to_le_bytes());
+            batch.insert(key.as_str(), data);
+        }
+        self.db.apply_batch(batch).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("reserve_codes_sled", "reserved:", "sled", start);
+        Ok(())
+    }
+
+    async fn get_code_reservation(&self, code: &str) -> Result<Option<String>, AppError> {
+        let start = Instant::now();
+        let key = format!("reserved:{}", code);
+        let now = self.clock.now().timestamp() as u64;
+        let owner = match self.db.get(&key).map_err(AppError::Sled)? {
+            Some(bytes) => match Self::split_expiry(&bytes) {
+                Some((payload, expiry)) if expiry > now => Some(
+                    String::from_utf8(payload.to_vec()).map_err(|e| AppError::Internal(e.to_string()))?,
+                ),
+                _ => None,
+            },
+            None => None,
+        };
+        metrics::record_storage_latency("get_code_reservation_sled", &key, "sled", start);
+        Ok(owner)
+    }
+
+    async fn release_code_reservation(&self, code: &str) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("reserved:{}", code);
+        self.db.remove(&key).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("release_code_reservation_sled", &key, "sled", start);
+        Ok(())
+    }
+
+    async fn set_reserved_alias(&self, alias: &str, reserved: bool) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("reserved_alias:{}", alias);
+        self.db.insert(key.as_str(), vec![u8::from(reserved)]).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("set_reserved_alias_sled", &key, "sled", start);
+        Ok(())
+    }
+
+    async fn get_reserved_alias_overrides(&self) -> Result<HashMap<String, bool>, AppError> {
+        let start = Instant::now();
+        let prefix = "reserved_alias:";
+        let mut overrides = HashMap::new();
+        for entry in self.db.scan_prefix(prefix) {
+            let (key, value) = entry.map_err(AppError::Sled)?;
+            let alias = String::from_utf8(key[prefix.len()..].to_vec()).map_err(|e| AppError::Internal(e.to_string()))?;
+            overrides.insert(alias, value.first() == Some(&1));
+        }
+        metrics::record_storage_latency("get_reserved_alias_overrides_sled", prefix, "sled", start);
+        Ok(overrides)
+    }
+
+    async fn burn_code(&self, code: &str, burned_at: u64) -> Result<bool, AppError> {
+        let start = Instant::now();
+        let key = format!("burned:{}", code);
+        let claimed = self.db
+            .compare_and_swap(key.as_str(), None as Option<&[u8]>, Some(burned_at.to_le_bytes().as_ref()))
+            .map_err(AppError::Sled)?
+            .is_ok();
+        if claimed {
+            self.db.remove(code).map_err(AppError::Sled)?;
+        }
+        metrics::record_storage_latency("burn_code_sled", &key, "sled", start);
+        Ok(claimed)
+    }
+
+    async fn is_code_burned(&self, code: &str) -> Result<bool, AppError> {
+        let start = Instant::now();
+        let key = format!("burned:{}", code);
+        let burned = self.db.contains_key(key.as_str()).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("is_code_burned_sled", &key, "sled", start);
+        Ok(burned)
+    }
+
+    async fn next_rotation_cursor(&self, code: &str) -> Result<u64, AppError> {
+        let start = Instant::now();
+        let key = format!("rotator:{}", code);
+        let cursor = self.db
+            .update_and_fetch(key.as_str(), |old| {
+                let current = old
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .map(u64::from_le_bytes)
+                    .unwrap_or(0);
+                Some((current + 1).to_le_bytes().to_vec())
+            })
+            .map_err(AppError::Sled)?
+            .and_then(|bytes| bytes.as_ref().try_into().ok())
+            .map(u64::from_le_bytes)
+            .unwrap_or(1);
+        metrics::record_storage_latency("next_rotation_cursor_sled", &key, "sled", start);
+        Ok(cursor)
+    }
+
+    async fn incr_click_count(&self, code: &str) -> Result<u64, AppError> {
+        let start = Instant::now();
+        let key = format!("clicks:{}", code);
+        let count = self.db
+            .update_and_fetch(key.as_str(), |old| {
+                let current = old
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .map(u64::from_le_bytes)
+                    .unwrap_or(0);
+                Some((current + 1).to_le_bytes().to_vec())
+            })
+            .map_err(AppError::Sled)?
+            .and_then(|bytes| bytes.as_ref().try_into().ok())
+            .map(u64::from_le_bytes)
+            .unwrap_or(1);
+        metrics::record_storage_latency("incr_click_count_sled", &key, "sled", start);
+        Ok(count)
+    }
+
+    async fn add_report(&self, report: &AbuseReport) -> Result<(), AppError> {
+        let start = Instant::now();
+        let data = encode_to_vec(report, config::standard())
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let mut index_key = Self::code_report_prefix(&report.code);
+        index_key.extend_from_slice(report.id.as_bytes());
+        let mut batch = Batch::default();
+        batch.insert(format!("report:{}", report.id).as_str(), data);
+        batch.insert(index_key, vec![1u8]);
+        self.db.apply_batch(batch).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("add_report_sled", "-", "sled", start);
+        Ok(())
+    }
+
+    async fn list_reports(&self, page: u64, per_page: u64) -> Result<Paginate<AbuseReport>, AppError> {
+        let start = Instant::now();
+        let per_page = per_page.clamp(1, 100);
+        let offset = page.saturating_sub(1) * per_page;
+
+        let mut reports = Vec::new();
+        for entry in self.db.scan_prefix("report:") {
+            let (_key, value) = entry.map_err(AppError::Sled)?;
+            let report: AbuseReport = decode_from_slice(&value, config::standard())
+                .map(|(data, _)| data)
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            reports.push(report);
+        }
+        reports.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+        let total_items = reports.len() as u64;
+        let items = reports.into_iter().skip(offset as usize).take(per_page as usize).collect();
+        let total_pages = if total_items == 0 { 1 } else { total_items.div_ceil(per_page) };
+        metrics::record_storage_latency("list_reports_sled", "-", "sled", start);
+        Ok(Paginate {
+            items,
+            page,
+            per_page,
+            total_items,
+            total_pages,
+        })
+    }
+
+    async fn resolve_reports(&self, code: &str) -> Result<u64, AppError> {
+        let start = Instant::now();
+        let prefix = Self::code_report_prefix(code);
+        let mut batch = Batch::default();
+        let mut resolved = 0;
+        for entry in self.db.scan_prefix(&prefix) {
+            let (key, _) = entry.map_err(AppError::Sled)?;
+            let id = &key[prefix.len()..];
+            let mut report_key = b"report:".to_vec();
+            report_key.extend_from_slice(id);
+            batch.remove(report_key);
+            batch.remove(key);
+            resolved += 1;
+        }
+        self.db.apply_batch(batch).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("resolve_reports_sled", "code_reports:", "sled", start);
+        Ok(resolved)
+    }
+
+    async fn add_notification(&self, user_id: &str, notification: &Notification) -> Result<(), AppError> {
+        let start = Instant::now();
+        // Timestamp in the key keeps the prefix scan in chronological order
+        let key = format!(
+            "notifications:{}:{:020}:{}",
+            user_id,
+            self.clock.now().timestamp_micros(),
+            notification.id
+        );
+        let data = encode_to_vec(notification, config::standard())
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.db.insert(key.as_str(), data).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("add_notification_sled", &key, "sled", start);
+        Ok(())
+    }
+
+    async fn list_notifications(&self, user_id: &str, limit: u64) -> Result<Vec<Notification>, AppError> {
+        let start = Instant::now();
+        let prefix = format!("notifications:{}:", user_id);
+        let notifications = self.db.scan_prefix(prefix.as_str())
+            .rev()
+            .take(limit.clamp(1, 100) as usize)
+            .map(|entry| {
+                let (_key, value) = entry.map_err(AppError::Sled)?;
+                decode_from_slice::<Notification, _>(&value, config::standard())
+                    .map(|(data, _)| data)
+                    .map_err(|e| AppError::Internal(e.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        metrics::record_storage_latency("list_notifications_sled", &prefix, "sled", start);
+        Ok(notifications)
+    }
+
+    async fn add_session(&self, user_id: &str, session: &Session, _ttl_seconds: u64) -> Result<(), AppError> {
+        let start = Instant::now();
+        // Expired sessions are filtered by the caller and dropped on the next revoke
+        let key = format!("session:{}:{}", user_id, session.jti);
+        let data = encode_to_vec(session, config::standard())
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.db.insert(key.as_str(), data).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("add_session_sled", &key, "sled", start);
+        Ok(())
+    }
+
+    async fn list_sessions(&self, user_id: &str) -> Result<Vec<Session>, AppError> {
+        let start = Instant::now();
+        let prefix = format!("session:{}:", user_id);
+        let sessions = self.db.scan_prefix(prefix.as_str())
+            .map(|entry| {
+                let (_key, value) = entry.map_err(AppError::Sled)?;
+                decode_from_slice::<Session, _>(&value, config::standard())
+                    .map(|(data, _)| data)
+                    .map_err(|e| AppError::Internal(e.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        metrics::record_storage_latency("list_sessions_sled", &prefix, "sled", start);
+        Ok(sessions)
+    }
+
+    async fn remove_sessions(&self, user_id: &str) -> Result<Vec<Session>, AppError> {
+        let start = Instant::now();
+        let prefix = format!("session:{}:", user_id);
+        let mut batch = Batch::default();
+        let mut sessions = Vec::new();
+        for entry in self.db.scan_prefix(prefix.as_str()) {
+            let (key, value) = entry.map_err(AppError::Sled)?;
+            let (session, _) = decode_from_slice::<Session, _>(&value, config::standard())
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            sessions.push(session);
+            batch.remove(key);
+        }
+        self.db.apply_batch(batch).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("remove_sessions_sled", &prefix, "sled", start);
+        Ok(sessions)
+    }
+
+    async fn remove_session(&self, user_id: &str, jti: &str) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("session:{}:{}", user_id, jti);
+        self.db.remove(key.as_str()).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("remove_session_sled", &key, "sled", start);
+        Ok(())
+    }
+
+    async fn record_login_failure(&self, subject: &str, window_secs: u64) -> Result<u64, AppError> {
+        let start = Instant::now();
+        let key = format!("login_failures:{}", subject);
+        let now = self.clock.now().timestamp() as u64;
+        // Stored as (count, window_start); a window that has elapsed starts over
+        let (count, window_start) = self.db.get(key.as_str()).map_err(AppError::Sled)?
+            .and_then(|v| decode_from_slice::<(u64, u64), _>(&v, config::standard()).ok())
+            .map(|(data, _)| data)
+            .filter(|&(_, window_start)| now < window_start + window_secs)
+            .unwrap_or((0, now));
+        let data = encode_to_vec((count + 1, window_start), config::standard())
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.db.insert(key.as_str(), data).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("record_login_failure_sled", &key, "sled", start);
+        Ok(count + 1)
+    }
+
+    async fn clear_login_failures(&self, subject: &str) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("login_failures:{}", subject);
+        self.db.remove(key.as_str()).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("clear_login_failures_sled", &key, "sled", start);
+        Ok(())
+    }
+
+    async fn lock_account(&self, subject: &str, until: u64, _ttl_seconds: u64) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("lockout:{}", subject);
+        self.db.insert(key.as_str(), until.to_le_bytes().as_ref()).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("lock_account_sled", &key, "sled", start);
+        Ok(())
+    }
+
+    async fn get_account_lock(&self, subject: &str) -> Result<Option<u64>, AppError> {
+        let start = Instant::now();
+        let key = format!("lockout:{}", subject);
+        let until = self.db.get(key.as_str()).map_err(AppError::Sled)?
+            .and_then(|v| v.as_ref().try_into().ok().map(u64::from_le_bytes))
+            .filter(|&until| until > self.clock.now().timestamp() as u64);
+        metrics::record_storage_latency("get_account_lock_sled", &key, "sled", start);
+        Ok(until)
+    }
+
+    async fn set_api_key(&self, key: &ApiKey) -> Result<(), AppError> {
+        let start = Instant::now();
+        let data = encode_to_vec(key, config::standard())
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let mut batch = Batch::default();
+        batch.insert(format!("apikey:{}", key.id).as_str(), data);
+        batch.insert(format!("apikeys:{}:{}", key.user_id, key.id).as_str(), key.id.as_bytes());
+        self.db.apply_batch(batch).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("set_api_key_sled", "-", "sled", start);
+        Ok(())
+    }
+
+    async fn get_api_key(&self, id: &str) -> Result<Option<ApiKey>, AppError> {
+        let start = Instant::now();
+        let api_key = self.db.get(format!("apikey:{}", id).as_str()).map_err(AppError::Sled)?
+            .map(|value| decode_from_slice::<ApiKey, _>(&value, config::standard()).map(|(data, _)| data))
+            .transpose()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        metrics::record_storage_latency("get_api_key_sled", "apikey:", "sled", start);
+        Ok(api_key)
+    }
+
+    async fn list_api_keys(&self, user_id: &str) -> Result<Vec<ApiKey>, AppError> {
+        let start = Instant::now();
+        let mut keys = Vec::new();
+        for entry in self.db.scan_prefix(format!("apikeys:{}:", user_id).as_str()) {
+            let (_key, id) = entry.map_err(AppError::Sled)?;
+            let id = String::from_utf8(id.to_vec()).map_err(|e| AppError::Internal(e.to_string()))?;
+            if let Some(key) = self.get_api_key(&id).await? {
+                keys.push(key);
+            }
+        }
+        metrics::record_storage_latency("list_api_keys_sled", "-", "sled", start);
+        Ok(keys)
+    }
+
+    async fn delete_api_key(&self, user_id: &str, id: &str) -> Result<bool, AppError> {
+        let start = Instant::now();
+        let index_key = format!("apikeys:{}:{}", user_id, id);
+        let removed = self.db.remove(index_key.as_str()).map_err(AppError::Sled)?.is_some();
+        if removed {
+            self.db.remove(format!("apikey:{}", id).as_str()).map_err(AppError::Sled)?;
+        }
+        metrics::record_storage_latency("delete_api_key_sled", &index_key, "sled", start);
+        Ok(removed)
+    }
+
+    async fn set_signing_secret(&self, user_id: &str, secret: &str) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("signing_secret:{}", user_id);
+        self.db.insert(key.as_str(), secret.as_bytes()).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("set_signing_secret_sled", &key, "sled", start);
+        Ok(())
+    }
+
+    async fn get_signing_secret(&self, user_id: &str) -> Result<Option<String>, AppError> {
+        let start = Instant::now();
+        let key = format!("signing_secret:{}", user_id);
+        let secret = self.db.get(key.as_str()).map_err(AppError::Sled)?
+            .map(|value| String::from_utf8(value.to_vec()))
+            .transpose()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        metrics::record_storage_latency("get_signing_secret_sled", &key, "sled", start);
+        Ok(secret)
+    }
+
+    async fn set_campaign(&self, campaign: &Campaign) -> Result<(), AppError> {
+        let start = Instant::now();
+        let data = encode_to_vec(campaign, config::standard())
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let mut batch = Batch::default();
+        batch.insert(format!("campaign:{}", campaign.id).as_str(), data);
+        batch.insert(format!("campaigns:{}:{}", campaign.user_id, campaign.id).as_str(), campaign.id.as_bytes());
+        self.db.apply_batch(batch).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("set_campaign_sled", "campaign:", "sled", start);
+        Ok(())
+    }
+
+    async fn get_campaign(&self, id: &str) -> Result<Option<Campaign>, AppError> {
+        let start = Instant::now();
+        let campaign = self.db.get(format!("campaign:{}", id).as_str()).map_err(AppError::Sled)?
+            .map(|value| decode_from_slice::<Campaign, _>(&value, config::standard()).map(|(data, _)| data))
+            .transpose()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        metrics::record_storage_latency("get_campaign_sled", "campaign:", "sled", start);
+        Ok(campaign)
+    }
+
+    async fn list_campaigns(&self, user_id: &str) -> Result<Vec<Campaign>, AppError> {
+        let start = Instant::now();
+        let mut campaigns = Vec::new();
+        for entry in self.db.scan_prefix(format!("campaigns:{}:", user_id).as_str()) {
+            let (_key, id) = entry.map_err(AppError::Sled)?;
+            let id = String::from_utf8(id.to_vec()).map_err(|e| AppError::Internal(e.to_string()))?;
+            if let Some(campaign) = self.get_campaign(&id).await? {
+                campaigns.push(campaign);
+            }
+        }
+        metrics::record_storage_latency("list_campaigns_sled", "campaigns:", "sled", start);
+        Ok(campaigns)
+    }
+
+    async fn add_audit_event(&self, event: &AuditEvent) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("audit:{:020}:{}", self.clock.now().timestamp_micros(), event.id);
+        let data = encode_to_vec(event, config::standard())
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.db.insert(key.as_str(), data).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("add_audit_event_sled", &key, "sled", start);
+        Ok(())
+    }
+
+    async fn list_audit_events(&self, limit: u64) -> Result<Vec<AuditEvent>, AppError> {
+        let start = Instant::now();
+        let events = self.db.scan_prefix("audit:")
+            .rev()
+            .take(limit.clamp(1, 1000) as usize)
+            .map(|entry| {
+                let (_key, value) = entry.map_err(AppError::Sled)?;
+                decode_from_slice::<AuditEvent, _>(&value, config::standard())
+                    .map(|(data, _)| data)
+                    .map_err(|e| AppError::Internal(e.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        metrics::record_storage_latency("list_audit_events_sled", "-", "sled", start);
+        Ok(events)
+    }
+
+    async fn incr_usage(&self, counts: Vec<(String, String, u64)>, ttl_seconds: u64) -> Result<(), AppError> {
+        let start = Instant::now();
+        let now = self.clock.now().timestamp() as u64;
+        let mut by_key: HashMap<String, Vec<(String, u64)>> = HashMap::new();
+        for (key, field, count) in counts {
+            by_key.entry(key).or_default().push((field, count));
+        }
+        let mut batch = Batch::default();
+        for (key, fields) in by_key {
+            // Stored as (counts, expires_at); an expired key starts over
+            let (mut totals, expires_at) = self.db.get(key.as_str()).map_err(AppError::Sled)?
+                .and_then(|v| decode_from_slice::<(HashMap<String, u64>, u64), _>(&v, config::standard()).ok())
+                .map(|(data, _)| data)
+                .filter(|&(_, expires_at)| expires_at > now)
+                .unwrap_or_else(|| (HashMap::new(), now + ttl_seconds));
+            for (field, count) in fields {
+                *totals.entry(field).or_default() += count;
+            }
+            let data = encode_to_vec((totals, expires_at), config::standard())
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            batch.insert(key.as_str(), data);
+        }
+        self.db.apply_batch(batch).map_err(AppError::Sled)?;
+        metrics::record_storage_latency("incr_usage_sled", "-", "sled", start);
+        Ok(())
+    }
+
+    async fn get_usage(&self, key: &str) -> Result<HashMap<String, u64>, AppError> {
+        let start = Instant::now();
+        let now = self.clock.now().timestamp() as u64;
+        let counts = self.db.get(key).map_err(AppError::Sled)?
+            .and_then(|v| decode_from_slice::<(HashMap<String, u64>, u64), _>(&v, config::standard()).ok())
+            .map(|(data, _)| data)
+            .filter(|&(_, expires_at)| expires_at > now)
+            .map(|(counts, _)| counts)
+            .unwrap_or_default();
+        metrics::record_storage_latency("get_usage_sled", key, "sled", start);
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::MockClock;
+
+    #[tokio::test]
+    async fn sweeps_only_expired_entries_with_an_expiry_suffix() {
+        let dir = std::env::temp_dir().join(format!("hyperlinkr-sled-sweep-{}", std::process::id()));
+        let clock = MockClock::new(chrono::Utc::now());
+        let storage = SledStorage::with_clock(dir.to_str().unwrap(), &Settings::default(), Clone::clone(&clock));
+        storage.set_ex("short", "{}", 60).await.unwrap();
+        storage.set_ex("long", "{}", 3600).await.unwrap();
+        storage.blacklist_token("revoked", 60).await.unwrap();
+        storage.db.insert("user:alice", b"no expiry suffix here".to_vec()).unwrap();
+
+        clock.advance(chrono::Duration::seconds(120));
+        assert_eq!(storage.purge_expired(ExpiringKeys::Storage).unwrap(), 2);
+        assert!(storage.get("long").await.is_ok());
+        assert!(!storage.is_token_blacklisted("revoked").await.unwrap());
+        assert!(storage.db.contains_key("user:alice").unwrap());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,91 +1,85 @@
 use async_trait::async_trait;
 use fred::{
     clients::ExclusivePool as FredPool,
-    prelude::{Blocking::Block, KeysInterface, LuaInterface, SetsInterface, SortedSetsInterface, TransactionInterface},
+    clients::Client,
+    prelude::{Blocking::Block, ClientLike, HashesInterface, KeysInterface, ListInterface, LuaInterface, SetsInterface, SortedSetsInterface, TransactionInterface},
     types::{
         config::{Config, ConnectionConfig, PerformanceConfig, ReconnectPolicy, Server, ServerConfig},
-        scan::{ScanResult, ScanType, Scanner}, Expiration
+        scan::{ScanResult, ScanType, Scanner}, Expiration, SetOptions, Value
     },
 };
+use arc_swap::ArcSwap;
 use futures::StreamExt;
 use serde_json;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use url::Url;
-use xxhash_rust::xxh3::xxh3_64;
 use crate::{
-    config::settings::Settings,
+    config::{cache::{CacheConfig, ReconnectStrategy}, settings::Settings},
     errors::AppError,
     services::{
-        cache::circuit_breaker::CircuitBreaker,
+        cache::circuit_breaker::{redact_node, CircuitBreaker},
+        link_search::search_terms,
         metrics,
     },
-    types::{Paginate, UrlData, User},
+    types::{AbuseReport, ApiKey, AuditEvent, Campaign, Notification, Paginate, Session, UrlData, User},
 };
-use super::storage::Storage;
+use super::{hash_ring::HashRing, storage::{pinned_first, PoolStats, Storage}};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use tracing::{info, warn};
+
+// Reports live on a single node so the open-report index and report bodies stay together
+const REPORTS_INDEX_KEY: &str = "reports:open";
+const MAX_NOTIFICATIONS: i64 = 100;
+const AUDIT_LOG_KEY: &str = "audit:log";
+const MAX_AUDIT_EVENTS: i64 = 100_000;
+const RESERVED_ALIASES_KEY: &str = "reserved_aliases:overrides"; // Hash of alias => "1" reserved / "0" released
+
+/// A node's URL and connection pool, as handed out for one command.
+type NodePool = (Arc<str>, Arc<FredPool>);
+
+/// The nodes and which keys each owns, replaced whole when a node is added or removed.
+#[derive(Clone)]
+struct Topology {
+    ring: HashRing,
+    pools: Vec<NodePool>,
+}
+
+impl Topology {
+    fn pool(&self, node: &str) -> Option<NodePool> {
+        self.pools.iter().find(|(url, _)| &**url == node).cloned()
+    }
+}
 
 pub struct DatabaseClient {
-    pools: Vec<(String, FredPool)>, // (URL, Pool) pairs
+    topology: ArcSwap<Topology>,
+    topology_changes: Mutex<()>, // Held while a node is added or removed, so changes don't interleave
+    cache: CacheConfig, // For pools to nodes added later
     circuit_breaker: Arc<CircuitBreaker>,
     global_admins: Vec<String>,
+    hedge_after: Option<Duration>, // Read budget before get_hedged also asks another node
 }
 
 impl DatabaseClient {
     pub async fn new(config: &Settings, circuit_breaker: Arc<CircuitBreaker>) -> Result<Self, AppError> {
-        let mut pools = Vec::new();
-
-        for url in &config.database_urls {
-            let parsed_url = Url::parse(url)
-                .map_err(|e| AppError::RedisConnection(format!("Invalid URL {}: {}", url, e)))?;
-            let host = parsed_url
-                .host_str()
-                .ok_or_else(|| AppError::RedisConnection(format!("No host in URL {}", url)))?
-                .to_string();
-            let port = parsed_url.port().unwrap_or(6379);
-
-            let redis_config = Config {
-                server: ServerConfig::Centralized {
-                    server: Server { host: host.into(), port },
-
-                },
-
-                blocking: Block,
-                ..Default::default()
-            };
-
-            let perf_config = PerformanceConfig {
-                default_command_timeout: Duration::from_millis(50), // Tighten for sub-ms
-                max_feed_count: config.cache.redis_max_feed_count,
-                broadcast_channel_capacity: config.cache.redis_broadcast_channel_capacity,
-
-                ..Default::default()
-            };
-
-            let connection_config = ConnectionConfig {
-                connection_timeout: Duration::from_millis(10),
-                max_command_attempts: 2,
-                
-                
-                ..Default::default()
-            };
-
-            let policy = ReconnectPolicy::new_linear(3, 10, 50);
-
-            let pool = FredPool::new(
-                redis_config,
-                Some(perf_config),
-                Some(connection_config),
-                Some(policy),
-                config.cache.redis_pool_size as usize,
-            )
-            .map_err(|e| AppError::RedisConnection(e.to_string()))?;
-
-            pool.connect().await;
-            pool.wait_for_connect()
-                .await
-                .map_err(|e| AppError::RedisConnection(e.to_string()))?;
+        Self::connect(&config.database_urls, &config.cache, config.security.global_admins.clone(), circuit_breaker).await
+    }
 
-            pools.push((url.clone(), pool));
+    /// A client for `urls` instead of `database_urls`, e.g. a peer region's Dragonfly.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `urls` is empty or a node's connection pool can't be set up.
+    pub async fn connect(
+        urls: &[String],
+        cache: &CacheConfig,
+        global_admins: Vec<String>,
+        circuit_breaker: Arc<CircuitBreaker>,
+    ) -> Result<Self, AppError> {
+        let mut pools = Vec::new();
+        for url in urls {
+            pools.push((Arc::from(url.as_str()), Arc::new(connect_pool(url, cache).await?)));
         }
 
         if pools.is_empty() {
@@ -93,84 +87,372 @@ impl DatabaseClient {
         }
 
         Ok(Self {
-            pools,
+            topology: ArcSwap::from_pointee(Topology {
+                ring: HashRing::new(pools.iter().map(|(url, _)| Arc::clone(url))),
+                pools,
+            }),
+            topology_changes: Mutex::new(()),
+            cache: cache.clone(),
             circuit_breaker,
-            global_admins: config.security.global_admins.clone(),
+            global_admins,
+            hedge_after: cache.hedge_after_ms.map(Duration::from_millis),
         })
     }
 
-    fn get_pool_for_key(&self, key: &str) -> Result<(&str, &FredPool), AppError> {
-        if self.pools.is_empty() {
-            return Err(AppError::RedisConnection("No pools available".into()));
+    pub fn circuit_breaker(&self) -> &Arc<CircuitBreaker> {
+        &self.circuit_breaker
+    }
+
+    pub fn pool_stats(&self) -> Vec<PoolStats> {
+        self.topology
+            .load()
+            .pools
+            .iter()
+            .map(|(url, pool)| PoolStats {
+                node: redact_node(url),
+                size: pool.clients().len(),
+                in_use: pool.clients().iter().filter(|client| client.try_lock().is_err()).count(),
+            })
+            .collect()
+    }
+
+    /// `get` for latency-sensitive reads: if the key's node hasn't answered within the hedge
+    /// budget, the same read goes to another healthy node and the first value back wins. Only
+    /// useful when the nodes replicate each other; a miss on the hedge never beats the original.
+    pub(crate) async fn get_hedged(&self, key: &str) -> Result<String, AppError> {
+        let Some(budget) = self.hedge_after else {
+            return self.get(key).await;
+        };
+        let (node, pool) = self.get_pool_for_key(key)?;
+        let primary = self.get_on(&node, &pool, key);
+        tokio::pin!(primary);
+        if let Ok(result) = tokio::time::timeout(budget, &mut primary).await {
+            return result;
+        }
+        let Some((hedge_node, hedge_pool)) = self
+            .circuit_breaker
+            .get_healthy_node_except(&node)
+            .await
+            .and_then(|hedge_node| self.topology.load().pool(&hedge_node))
+        else {
+            return primary.await;
+        };
+        let hedge = self.get_on(&hedge_node, &hedge_pool, key);
+        tokio::pin!(hedge);
+
+        let (first, primary_won) = tokio::select! {
+            result = &mut primary => (result, true),
+            result = &mut hedge => (result, false),
+        };
+        match first {
+            Ok(value) => {
+                metrics::record_hedged_read(if primary_won { "loss" } else { "win" });
+                Ok(value)
+            }
+            Err(e) if primary_won => {
+                let result = hedge.await.map_err(|_| e);
+                if result.is_ok() {
+                    metrics::record_hedged_read("win");
+                }
+                result
+            }
+            Err(_) => {
+                metrics::record_hedged_read("loss");
+                primary.await
+            }
         }
-        let hash = xxh3_64(key.as_bytes());
-        let index = (hash % self.pools.len() as u64) as usize;
-        let (url, pool) = &self.pools[index];
-        Ok((url.as_str(), pool))
     }
 
-    async fn get_pool(&self) -> Result<(&str, &FredPool), AppError> {
+    async fn get_on(&self, node: &str, pool: &FredPool, key: &str) -> Result<String, AppError> {
+        let start = Instant::now();
+        let client = acquire(pool).await;
+        let data: Option<String> = (*client).get(key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("get_dragonfly", key, node, start).await;
+        data.ok_or_else(|| AppError::NotFound("Key not found".into()))
+    }
+
+    /// Records a command `node` answered, which closes its breaker if it was a half-open probe.
+    async fn succeeded(&self, op: &'static str, key: &str, node: &str, start: Instant) {
+        metrics::record_storage_latency(op, key, node, start);
+        self.circuit_breaker.record_success(node).await;
+    }
+
+    /// Reserves `code` for `user_id`, or returns false if someone already holds it.
+    async fn reserve_code(&self, user_id: &str, code: &str, ttl_seconds: u64) -> Result<bool, AppError> {
+        let start = Instant::now();
+        let key = format!("reserved:{}", code);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let reserved: Option<String> = (*client)
+            .set(&key, user_id, Some(Expiration::EX(ttl_seconds as i64)), Some(SetOptions::NX), false)
+            .await
+            .map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+        self.succeeded("reserve_code_dragonfly", &key, &node, start).await;
+        Ok(reserved.is_some())
+    }
+
+    fn get_pool_for_key(&self, key: &str) -> Result<NodePool, AppError> {
+        let topology = self.topology.load();
+        topology
+            .ring
+            .node_for(key)
+            .and_then(|node| topology.pool(node))
+            .ok_or_else(|| AppError::RedisConnection("No pools available".into()))
+    }
+
+    async fn get_pool(&self) -> Result<NodePool, AppError> {
         let node = self
             .circuit_breaker
             .get_healthy_node()
             .await
             .ok_or_else(|| AppError::RedisConnection("No healthy nodes available".into()))?;
-        self.pools
-            .iter()
-            .find(|(url, _)| url == &node)
-            .map(|(url, pool)| (url.as_str(), pool))
+        self.topology
+            .load()
+            .pool(&node)
             .ok_or_else(|| AppError::RedisConnection(format!("Pool for node {} not found", node)))
     }
+
+    /// Connects to `url` and gives it its share of the ring. The keys it takes over are copied
+    /// to it before it starts serving them, then deleted from their old nodes. Returns how many
+    /// keys moved.
+    pub(crate) async fn add_node(&self, url: &str) -> Result<u64, AppError> {
+        let _change = self.topology_changes.lock().await;
+        let current = self.topology.load_full();
+        if current.pool(url).is_some() {
+            return Err(AppError::Conflict(format!("Node {} is already in the ring", redact_node(url))));
+        }
+        let pool = Arc::new(connect_pool(url, &self.cache).await?);
+        let node: Arc<str> = Arc::from(url);
+        let mut next = (*current).clone();
+        next.ring.add(Arc::clone(&node));
+        next.pools.push((Arc::clone(&node), pool));
+
+        let moved = self.copy_misplaced(&current.pools, &next.ring, &next).await?;
+        self.circuit_breaker.add_node(url.to_string()).await;
+        self.topology.store(Arc::new(next));
+        let count = self.delete_moved(&moved).await;
+        info!("Added node {} to the ring, {} keys moved to it", redact_node(url), count);
+        Ok(count)
+    }
+
+    /// Takes `node` (as reported by `pool_stats`) out of the ring, after copying every key it
+    /// holds to the node that owns it next. Its data is left in place. Returns how many keys moved.
+    pub(crate) async fn remove_node(&self, node: &str) -> Result<u64, AppError> {
+        let _change = self.topology_changes.lock().await;
+        let current = self.topology.load_full();
+        let Some((url, pool)) = current.pools.iter().find(|(url, _)| redact_node(url) == node).cloned() else {
+            return Err(AppError::NotFound(format!("Node {} is not in the ring", node)));
+        };
+        if current.pools.len() == 1 {
+            return Err(AppError::BadRequest("Can't remove the only node".into()));
+        }
+        let mut next = (*current).clone();
+        next.ring.remove(&url);
+        next.pools.retain(|(other, _)| *other != url);
+
+        let moved = self.copy_misplaced(&[(Arc::clone(&url), Arc::clone(&pool))], &next.ring, &next).await?;
+        self.topology.store(Arc::new(next));
+        self.circuit_breaker.remove_node(&url).await;
+        if let Err(e) = pool.quit().await {
+            warn!("Failed to close connections to removed node {}: {}", node, e);
+        }
+        info!("Removed node {} from the ring, {} keys moved off it", node, moved.len());
+        Ok(moved.len() as u64)
+    }
+
+    /// Moves every key that isn't on the node the ring assigns it to, e.g. after upgrading from
+    /// modulo sharding or after an add that failed part way. Returns how many keys moved.
+    pub(crate) async fn rebalance(&self) -> Result<u64, AppError> {
+        let _change = self.topology_changes.lock().await;
+        let current = self.topology.load_full();
+        let moved = self.copy_misplaced(&current.pools, &current.ring, &current).await?;
+        let count = self.delete_moved(&moved).await;
+        info!("Rebalanced the ring, {} keys moved", count);
+        Ok(count)
+    }
+
+    /// Copies each key on `sources` that `ring` places elsewhere to its owner in `topology`,
+    /// keeping its TTL. Returns where each copied key came from.
+    async fn copy_misplaced(&self, sources: &[NodePool], ring: &HashRing, topology: &Topology) -> Result<Vec<(NodePool, String)>, AppError> {
+        let mut moved = Vec::new();
+        for (node, pool) in sources {
+            let client = acquire(pool).await;
+            let mut keys = Vec::new();
+            let mut scanner = (*client).scan("*", Some(1000), None);
+            while let Some(page_result) = scanner.next().await {
+                let scan_page: ScanResult = page_result.map_err(|e| AppError::RedisConnection(e.to_string()))?;
+                keys.extend(
+                    scan_page
+                        .results()
+                        .as_ref()
+                        .map(|v| v.iter().filter_map(|k| k.clone().into_string()).collect::<Vec<_>>())
+                        .unwrap_or_default(),
+                );
+                if !scan_page.has_more() {
+                    break;
+                }
+            }
+            for key in keys {
+                let Some(owner) = ring.node_for(&key).filter(|owner| *owner != node) else {
+                    continue;
+                };
+                let (_, target_pool) = topology
+                    .pool(owner)
+                    .ok_or_else(|| AppError::RedisConnection(format!("Pool for node {} not found", owner)))?;
+                let pttl: i64 = (*client).pttl(&key).await.map_err(|e| AppError::RedisConnection(e.to_string()))?;
+                let dump: Value = (*client).dump(&key).await.map_err(|e| AppError::RedisConnection(e.to_string()))?;
+                // Expired between the scan and the dump
+                if pttl == -2 || matches!(dump, Value::Null) {
+                    continue;
+                }
+                let target = acquire(&target_pool).await;
+                let _: () = (*target)
+                    .restore(&key, pttl.max(0), dump, true, false, None, None)
+                    .await
+                    .map_err(|e| AppError::RedisConnection(e.to_string()))?;
+                moved.push(((Arc::clone(node), Arc::clone(pool)), key));
+            }
+        }
+        Ok(moved)
+    }
+
+    /// Deletes keys from the nodes they were copied off; a key left behind is only wasted space.
+    async fn delete_moved(&self, moved: &[(NodePool, String)]) -> u64 {
+        for ((node, pool), key) in moved {
+            let client = acquire(pool).await;
+            if let Err(e) = (*client).del::<(), _>(key).await {
+                warn!("Failed to delete moved key {} from {}: {}", key, redact_node(node), e);
+            }
+        }
+        moved.len() as u64
+    }
+}
+
+/// A warmed-up pool of connections to the node at `url`.
+async fn connect_pool(url: &str, cache: &CacheConfig) -> Result<FredPool, AppError> {
+    let parsed_url = Url::parse(url)
+        .map_err(|e| AppError::RedisConnection(format!("Invalid URL {}: {}", url, e)))?;
+    let host = parsed_url
+        .host_str()
+        .ok_or_else(|| AppError::RedisConnection(format!("No host in URL {}", url)))?
+        .to_string();
+    let port = parsed_url.port().unwrap_or(6379);
+
+    let redis_config = Config {
+        server: ServerConfig::Centralized {
+            server: Server { host: host.into(), port },
+
+        },
+
+        blocking: Block,
+        ..Default::default()
+    };
+
+    let perf_config = PerformanceConfig {
+        default_command_timeout: Duration::from_secs(cache.redis_command_timeout_secs),
+        max_feed_count: cache.redis_max_feed_count,
+        broadcast_channel_capacity: cache.redis_broadcast_channel_capacity,
+    };
+
+    let connection_config = ConnectionConfig {
+        connection_timeout: Duration::from_millis(cache.redis_connection_timeout_ms),
+        max_command_attempts: cache.redis_max_command_attempts,
+        ..Default::default()
+    };
+
+    let pool = FredPool::new(
+        redis_config,
+        Some(perf_config),
+        Some(connection_config),
+        Some(reconnect_policy(cache)),
+        cache.redis_pool_size as usize,
+    )
+    .map_err(|e| AppError::RedisConnection(e.to_string()))?;
+
+    pool.connect().await;
+    pool.wait_for_connect()
+        .await
+        .map_err(|e| AppError::RedisConnection(e.to_string()))?;
+    // Ping every connection so the first requests don't pay for lazy handshakes
+    for client in pool.clients() {
+        let _: String = client.lock().await.ping(None).await
+            .map_err(|e| AppError::RedisConnection(format!("Warmup ping to {} failed: {}", redact_node(url), e)))?;
+    }
+    info!("Warmed {} connections to {}", pool.clients().len(), redact_node(url));
+    Ok(pool)
+}
+
+fn reconnect_policy(cache: &CacheConfig) -> ReconnectPolicy {
+    let millis = |ms: u64| u32::try_from(ms).unwrap_or(u32::MAX);
+    let (attempts, delay, max_delay) = (
+        cache.redis_reconnect_max_attempts,
+        millis(cache.redis_reconnect_delay_ms),
+        millis(cache.redis_reconnect_max_delay_ms),
+    );
+    let mut policy = match cache.redis_reconnect_strategy.unwrap_or_default() {
+        ReconnectStrategy::Constant => ReconnectPolicy::new_constant(attempts, delay),
+        ReconnectStrategy::Linear => ReconnectPolicy::new_linear(attempts, max_delay, delay),
+        ReconnectStrategy::Exponential => ReconnectPolicy::new_exponential(attempts, delay, max_delay, 2),
+    };
+    policy.set_jitter(cache.redis_reconnect_jitter_ms.unwrap_or(50));
+    policy
+}
+
+/// Checks a connection out of `pool`, timing how long the caller queued for it.
+async fn acquire(pool: &FredPool) -> OwnedMutexGuard<Client> {
+    let start = Instant::now();
+    let client = pool.acquire().await;
+    metrics::record_pool_wait(start);
+    client
 }
 
 #[async_trait]
 impl Storage for DatabaseClient {
     async fn get(&self, key: &str) -> Result<String, AppError> {
-        let start = Instant::now();
         let (node, pool) = self.get_pool_for_key(key)?;
-        let client = pool.acquire().await;
-        let data: Option<String> = (*client).get(key).await.map_err(|e| {
-            futures::executor::block_on(self.circuit_breaker.record_failure(node));
-            AppError::RedisConnection(e.to_string())
-        })?;
-        metrics::record_db_latency("get_dragonfly", start);
-        data.ok_or_else(|| AppError::NotFound("Key not found".into()))
+        self.get_on(&node, &pool, key).await
     }
 
     async fn set_ex(&self, key: &str, value: &str, ttl: u64) -> Result<(), AppError> {
         let start = Instant::now();
         let (node, pool) = self.get_pool_for_key(key)?;
-        let client = pool.acquire().await;
+        let client = acquire(&pool).await;
         let _: () = (*client)
             .set(key, value, Some(Expiration::EX(ttl as i64)), None, false)
             .await
             .map_err(|e| {
-                futures::executor::block_on(self.circuit_breaker.record_failure(node));
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
                 AppError::RedisConnection(e.to_string())
             })?;
-        metrics::record_db_latency("set_ex_dragonfly", start);
+        self.succeeded("set_ex_dragonfly", key, &node, start).await;
         Ok(())
     }
 
     async fn zadd(&self, key: &str, score: u64, member: u64) -> Result<(), AppError> {
         let start = Instant::now();
         let (node, pool) = self.get_pool_for_key(key)?;
-        let client = pool.acquire().await;
+        let client = acquire(&pool).await;
         let _: () = (*client)
             .zadd(key, None, None, false, false, (score as f64, member))
             .await
             .map_err(|e| {
-                futures::executor::block_on(self.circuit_breaker.record_failure(node));
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
                 AppError::RedisConnection(e.to_string())
             })?;
-        metrics::record_db_latency("zadd_dragonfly", start);
+        self.succeeded("zadd_dragonfly", key, &node, start).await;
         Ok(())
     }
 
     async fn rate_limit(&self, key: &str, limit: u64, window_secs: i64) -> Result<bool, AppError> {
         let start = Instant::now();
         let (node, pool) = self.get_pool_for_key(key)?;
-        let client = pool.acquire().await;
+        let client = acquire(&pool).await;
         let now_ts = chrono::Utc::now().timestamp();
         let now_u64 = now_ts as u64;
         let tx = (*client).multi();
@@ -180,65 +462,70 @@ impl Storage for DatabaseClient {
         let _ = tx.expire::<i64, &str>(key, window_secs as i64, Some(fred::types::ExpireOptions::LT)).await;
 
         let results: Vec<i64> = tx.exec(false).await.map_err(|e| {
-            futures::executor::block_on(self.circuit_breaker.record_failure(node));
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
             AppError::RedisConnection(e.to_string())
         })?;
         let count = results.get(1).copied().unwrap_or(0);
-        metrics::record_db_latency("rate_limit_dragonfly", start);
+        self.succeeded("rate_limit_dragonfly", key, &node, start).await;
         Ok(count < limit as i64)
     }
 
     async fn zrange(&self, key: &str, start: i64, stop: i64) -> Result<Vec<(u64, u64)>, AppError> {
         let start_time = Instant::now();
         let (node, pool) = self.get_pool_for_key(key)?;
-        let client = pool.acquire().await;
+        let client = acquire(&pool).await;
         let result: Vec<(u64, u64)> = (*client)
             .zrange(key, start, stop, None, false, None, true)
             .await
             .map_err(|e| {
-                futures::executor::block_on(self.circuit_breaker.record_failure(node));
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
                 AppError::RedisConnection(e.to_string())
             })?;
-        metrics::record_db_latency("zrange_dragonfly", start_time);
+        self.succeeded("zrange_dragonfly", key, &node, start_time).await;
         Ok(result)
     }
 
     async fn zadd_batch(&self, operations: Vec<(String, u64, u64)>, expire_secs: i64) -> Result<(), AppError> {
         let start = Instant::now();
-        let mut grouped = std::collections::HashMap::new();
+        // Group by node, then by key, so a flush costs one round-trip per node instead of per key
+        let mut by_node: HashMap<Arc<str>, (Arc<FredPool>, HashMap<String, Vec<(u64, u64)>>)> = HashMap::new();
         for (key, score, member) in operations {
-            grouped
+            let (node, pool) = self.get_pool_for_key(&key)?;
+            by_node
+                .entry(node)
+                .or_insert_with(|| (pool, HashMap::new()))
+                .1
                 .entry(key)
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push((score, member));
         }
 
-        for (key, ops) in grouped {
-            let (node, pool) = self.get_pool_for_key(&key)?;
-            let client = pool.acquire().await;
-            let tx = (*client).multi();
-            for (score, member) in ops {
-                let _ = tx.zadd::<(), _, _>(&key, None, None, false, false, (score as f64, member)).await;
+        for (node, (pool, keys)) in by_node {
+            let client = acquire(&pool).await;
+            let pipeline = (*client).pipeline();
+            for (key, ops) in keys {
+                for (score, member) in ops {
+                    let _ = pipeline.zadd::<(), _, _>(&key, None, None, false, false, (score as f64, member)).await;
+                }
+                let _ = pipeline.expire::<(), _>(&key, expire_secs, None).await;
             }
-            let _ = tx.expire::<(), _>(&key, expire_secs, None).await;
-            let _: () = tx.exec(true).await.map_err(|e| {
-                 futures::executor::block_on(self.circuit_breaker.record_failure(node));
+            let _: () = pipeline.all().await.map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
                 AppError::RedisConnection(e.to_string())
             })?;
         }
-        metrics::record_db_latency("zadd_batch_dragonfly", start);
+        metrics::record_storage_latency("zadd_batch_dragonfly", "-", "*", start);
         Ok(())
     }
 
     async fn delete_url(&self, code: &str, user_id: Option<&str>, user_email: &str) -> Result<(), AppError> {
         let start = Instant::now();
-        let key = format!("url:{}", code);
         let index_key = user_id.map(|uid| format!("user_urls:{}", uid));
-        let (node, pool) = self.get_pool_for_key(&key)?;
-        let client = pool.acquire().await;
+        let (node, pool) = self.get_pool_for_key(code)?;
+        let client = acquire(&pool).await;
 
-        let data: Option<String> = (*client).get(&key).await.map_err(|e| {
-             futures::executor::block_on(self.circuit_breaker.record_failure(node));
+        let data: Option<String> = (*client).get(code).await.map_err(|e| {
+             futures::executor::block_on(self.circuit_breaker.record_failure(&node));
             AppError::RedisConnection(e.to_string())
         })?;
 
@@ -253,19 +540,28 @@ impl Storage for DatabaseClient {
             }
 
             let tx = (*client).multi();
-            let _ = tx.del::<(), _>(&key).await;
+            let _ = tx.del::<(), _>(code).await;
             if let Some(ref ikey) = index_key {
                 let _ = tx.srem::<(), _, _>(ikey, code).await;
             }
             let _: () = tx.exec(true).await.map_err(|e| {
-                 futures::executor::block_on(self.circuit_breaker.record_failure(node));
+                 futures::executor::block_on(self.circuit_breaker.record_failure(&node));
                 AppError::RedisConnection(e.to_string())
             })?;
+
+            // The owner's indexes hash to their own nodes, so they can't join the transaction
+            // above, and an admin deleting the link is not its owner
+            if let Some(owner) = url_data.user_id.as_deref() {
+                self.unpin_url(owner, code).await?;
+                self.untag_url(owner, code, &url_data.tags).await?;
+                let terms: Vec<String> = search_terms(code, &url_data).into_iter().collect();
+                self.remove_search_terms(owner, code, &terms).await?;
+            }
         } else {
             return Err(AppError::NotFound(format!("URL {} not found", code)));
         }
 
-        metrics::record_db_latency("delete_url_dragonfly", start);
+        self.succeeded("delete_url_dragonfly", code, &node, start).await;
         Ok(())
     }
 
@@ -277,21 +573,213 @@ impl Storage for DatabaseClient {
         let index_key = url_data.user_id.as_deref().map(|uid| format!("user_urls:{}", uid));
 
         let (node, pool) = self.get_pool_for_key(&key)?;
-        let client = pool.acquire().await;
+        let client = acquire(&pool).await;
         let tx = (*client).multi();
         let _ = tx.set::<(), _, _>(&key, &data, None, None, false).await;
         if let Some(ref ikey) = index_key {
             let _ = tx.sadd::<(), _, _>(ikey, code).await;
         }
         let _: () = tx.exec(true).await.map_err(|e| {
-             futures::executor::block_on(self.circuit_breaker.record_failure(node));
+             futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+
+        self.succeeded("set_url_dragonfly", &key, &node, start).await;
+        Ok(())
+    }
+
+    async fn add_user_url(&self, user_id: &str, code: &str) -> Result<(), AppError> {
+        let start = Instant::now();
+        let index_key = format!("user_urls:{}", user_id);
+        let (node, pool) = self.get_pool_for_key(&index_key)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client).sadd(&index_key, code).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("add_user_url_dragonfly", &index_key, &node, start).await;
+        Ok(())
+    }
+
+    async fn list_user_codes(&self, user_id: &str) -> Result<Vec<String>, AppError> {
+        let start = Instant::now();
+        let index_key = format!("user_urls:{}", user_id);
+        let (node, pool) = self.get_pool_for_key(&index_key)?;
+        let client = acquire(&pool).await;
+        let codes: Vec<String> = (*client).smembers(&index_key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
             AppError::RedisConnection(e.to_string())
         })?;
+        self.succeeded("list_user_codes_dragonfly", &index_key, &node, start).await;
+        Ok(codes)
+    }
 
-        metrics::record_db_latency("set_url_dragonfly", start);
+    async fn transfer_url(&self, code: &str, url_data: &UrlData, from_user_id: &str) -> Result<(), AppError> {
+        let start = Instant::now();
+        let to_user_id = url_data
+            .user_id
+            .as_deref()
+            .ok_or_else(|| AppError::Internal(format!("Transferred URL {} has no owner", code)))?;
+        let data = serde_json::to_string(url_data)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let (node, pool) = self.get_pool_for_key(code)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client)
+            .set(code, &data, Some(Expiration::KEEPTTL), None, false)
+            .await
+            .map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+        self.succeeded("transfer_url_dragonfly", code, &node, start).await;
+
+        // Each owner's index hashes to its own node, so the move can't be one transaction.
+        // Index under the new owner first: if the removal then fails, the link shows up in
+        // both listings but only the new owner can change it
+        self.add_user_url(to_user_id, code).await?;
+        let start = Instant::now();
+        let from_key = format!("user_urls:{}", from_user_id);
+        let (node, pool) = self.get_pool_for_key(&from_key)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client).srem(&from_key, code).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("transfer_url_dragonfly", &from_key, &node, start).await;
         Ok(())
     }
 
+    async fn pin_url(&self, user_id: &str, code: &str, pinned_at: u64) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("pinned:{}", user_id);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client)
+            .zadd(&key, None, None, false, false, (pinned_at as f64, code))
+            .await
+            .map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+        self.succeeded("pin_url_dragonfly", &key, &node, start).await;
+        Ok(())
+    }
+
+    async fn unpin_url(&self, user_id: &str, code: &str) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("pinned:{}", user_id);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client).zrem(&key, code).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("unpin_url_dragonfly", &key, &node, start).await;
+        Ok(())
+    }
+
+    async fn list_pinned(&self, user_id: &str) -> Result<Vec<String>, AppError> {
+        let start = Instant::now();
+        let key = format!("pinned:{}", user_id);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let codes: Vec<String> = (*client).zrevrange(&key, 0, -1, false).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("list_pinned_dragonfly", &key, &node, start).await;
+        Ok(codes)
+    }
+
+    async fn tag_url(&self, user_id: &str, code: &str, tags: &[String]) -> Result<(), AppError> {
+        // Each tag's set may live on a different node
+        for tag in tags {
+            let start = Instant::now();
+            let key = format!("user_tag:{}:{}", user_id, tag);
+            let (node, pool) = self.get_pool_for_key(&key)?;
+            let client = acquire(&pool).await;
+            let _: () = (*client).sadd(&key, code).await.map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+            self.succeeded("tag_url_dragonfly", &key, &node, start).await;
+        }
+        Ok(())
+    }
+
+    async fn untag_url(&self, user_id: &str, code: &str, tags: &[String]) -> Result<(), AppError> {
+        for tag in tags {
+            let start = Instant::now();
+            let key = format!("user_tag:{}:{}", user_id, tag);
+            let (node, pool) = self.get_pool_for_key(&key)?;
+            let client = acquire(&pool).await;
+            let _: () = (*client).srem(&key, code).await.map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+            self.succeeded("untag_url_dragonfly", &key, &node, start).await;
+        }
+        Ok(())
+    }
+
+    async fn list_tagged_codes(&self, user_id: &str, tag: &str) -> Result<Vec<String>, AppError> {
+        let start = Instant::now();
+        let key = format!("user_tag:{}:{}", user_id, tag);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let codes: Vec<String> = (*client).smembers(&key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("list_tagged_codes_dragonfly", &key, &node, start).await;
+        Ok(codes)
+    }
+
+    async fn index_search_terms(&self, user_id: &str, code: &str, terms: &[String]) -> Result<(), AppError> {
+        // Like tags, each term's set may live on a different node
+        for term in terms {
+            let start = Instant::now();
+            let key = format!("user_search:{}:{}", user_id, term);
+            let (node, pool) = self.get_pool_for_key(&key)?;
+            let client = acquire(&pool).await;
+            let _: () = (*client).sadd(&key, code).await.map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+            self.succeeded("index_search_terms_dragonfly", &key, &node, start).await;
+        }
+        Ok(())
+    }
+
+    async fn remove_search_terms(&self, user_id: &str, code: &str, terms: &[String]) -> Result<(), AppError> {
+        for term in terms {
+            let start = Instant::now();
+            let key = format!("user_search:{}:{}", user_id, term);
+            let (node, pool) = self.get_pool_for_key(&key)?;
+            let client = acquire(&pool).await;
+            let _: () = (*client).srem(&key, code).await.map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+            self.succeeded("remove_search_terms_dragonfly", &key, &node, start).await;
+        }
+        Ok(())
+    }
+
+    async fn search_codes(&self, user_id: &str, term: &str) -> Result<Vec<String>, AppError> {
+        let start = Instant::now();
+        let key = format!("user_search:{}:{}", user_id, term);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let codes: Vec<String> = (*client).smembers(&key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("search_codes_dragonfly", &key, &node, start).await;
+        Ok(codes)
+    }
+
     async fn list_urls(
         &self,
         user_id: Option<&str>,
@@ -304,7 +792,7 @@ impl Storage for DatabaseClient {
         let offset = page.saturating_sub(1) * per_page;
 
         let (node, pool) = self.get_pool().await?;
-        let client = pool.acquire().await;
+        let client = acquire(&pool).await;
 
         let mut items = Vec::new();
         let mut total_items: u64 = 0;
@@ -317,7 +805,7 @@ impl Storage for DatabaseClient {
 
             while let Some(page_result) = scanner.next().await {
                 let scan_page: ScanResult = page_result.map_err(|e| {
-                    futures::executor::block_on(self.circuit_breaker.record_failure(node));
+                    futures::executor::block_on(self.circuit_breaker.record_failure(&node));
                     AppError::RedisConnection(e.to_string())
                 })?;
                 let keys = scan_page.results().as_ref().map(|v| v.clone()).unwrap_or_default();
@@ -327,7 +815,7 @@ impl Storage for DatabaseClient {
                 }
 
                 let results: Vec<Option<String>> = pipeline.all().await.map_err(|e| {
-                     futures::executor::block_on(self.circuit_breaker.record_failure(node));
+                     futures::executor::block_on(self.circuit_breaker.record_failure(&node));
                     AppError::RedisConnection(e.to_string())
                 })?;
 
@@ -353,9 +841,11 @@ impl Storage for DatabaseClient {
                 .smembers(&index_key)
                 .await
                 .map_err(|e| {
-                     futures::executor::block_on(self.circuit_breaker.record_failure(node));
+                     futures::executor::block_on(self.circuit_breaker.record_failure(&node));
                     AppError::RedisConnection(e.to_string())
                 })?;
+            // The pinned set hashes to its own node
+            let codes = pinned_first(codes, &self.list_pinned(uid).await?);
             total_items = codes.len() as u64;
 
             let start_idx = offset.min(total_items) as usize;
@@ -368,7 +858,7 @@ impl Storage for DatabaseClient {
             }
 
             let results: Vec<Option<String>> = pipeline.all().await.map_err(|e| {
-                 futures::executor::block_on(self.circuit_breaker.record_failure(node));
+                 futures::executor::block_on(self.circuit_breaker.record_failure(&node));
                 AppError::RedisConnection(e.to_string())
             })?;
 
@@ -380,7 +870,7 @@ impl Storage for DatabaseClient {
         }
 
         let total_pages = if total_items == 0 { 1 } else { (total_items + per_page - 1) / per_page };
-        metrics::record_db_latency("list_urls_dragonfly", start);
+        self.succeeded("list_urls_dragonfly", "-", &node, start).await;
         Ok(Paginate {
             items,
             page,
@@ -398,23 +888,36 @@ impl Storage for DatabaseClient {
             .map_err(|e| AppError::Internal(e.to_string()))?;
 
         let (node, pool) = self.get_pool_for_key(&key)?;
-        let client = pool.acquire().await;
+        let client = acquire(&pool).await;
         let tx = (*client).multi();
         let _ = tx.set::<(), _, _>(&key, &data, None, None, false).await;
         let _ = tx.set::<(), _, _>(&email_key, &user.id, None, None, false).await;
         let _: () = tx.exec(true).await.map_err(|e| {
-             futures::executor::block_on(self.circuit_breaker.record_failure(node));
+             futures::executor::block_on(self.circuit_breaker.record_failure(&node));
             AppError::RedisConnection(e.to_string())
         })?;
 
-        metrics::record_db_latency("set_user_dragonfly", start);
+        self.succeeded("set_user_dragonfly", &key, &node, start).await;
+        Ok(())
+    }
+
+    async fn delete_user_email(&self, email: &str) -> Result<(), AppError> {
+        let start = Instant::now();
+        let email_key = format!("user_email:{}", email);
+        let (node, pool) = self.get_pool_for_key(&email_key)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client).del(&email_key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("delete_user_email_dragonfly", &email_key, &node, start).await;
         Ok(())
     }
 
     async fn get_user(&self, id_or_email: &str) -> Result<Option<User>, AppError> {
         let start = Instant::now();
         let (node, pool) = self.get_pool_for_key(id_or_email)?;
-        let client = pool.acquire().await;
+        let client = acquire(&pool).await;
 
         let key = if id_or_email.contains('@') {
             let email_key = format!("user_email:{}", id_or_email);
@@ -422,7 +925,7 @@ impl Storage for DatabaseClient {
                 Ok(Some(id)) => format!("user:{}", id),
                 Ok(None) => return Ok(None),
                 Err(e) => {
-                     futures::executor::block_on(self.circuit_breaker.record_failure(node));
+                     futures::executor::block_on(self.circuit_breaker.record_failure(&node));
                     return Err(AppError::RedisConnection(e.to_string()));
                 }
             }
@@ -431,7 +934,7 @@ impl Storage for DatabaseClient {
         };
 
         let data: Option<String> = (*client).get(&key).await.map_err(|e| {
-             futures::executor::block_on(self.circuit_breaker.record_failure(node));
+             futures::executor::block_on(self.circuit_breaker.record_failure(&node));
             AppError::RedisConnection(e.to_string())
         })?;
 
@@ -440,14 +943,14 @@ impl Storage for DatabaseClient {
             .transpose()
             .map_err(|e| AppError::Internal(e.to_string()))?;
 
-        metrics::record_db_latency("get_user_dragonfly", start);
+        self.succeeded("get_user_dragonfly", id_or_email, &node, start).await;
         Ok(user)
     }
 
     async fn count_users(&self) -> Result<u64, AppError> {
         let start = Instant::now();
         let (node, pool) = self.get_pool().await?;
-        let client = pool.acquire().await;
+        let client = acquire(&pool).await;
         let pattern = "user:*".to_string();
         let scan_count = Some(1000u32);
         let mut scanner = (*client).scan(pattern, scan_count, Some(ScanType::String));
@@ -455,7 +958,7 @@ impl Storage for DatabaseClient {
 
         while let Some(page_result) = scanner.next().await {
             let scan_page: ScanResult = page_result.map_err(|e| {
-                futures::executor::block_on(self.circuit_breaker.record_failure(node));
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
                 AppError::RedisConnection(e.to_string())
             })?;
             count += scan_page.results().as_ref().map(|v| v.len()).unwrap_or(0) as u64;
@@ -464,22 +967,26 @@ impl Storage for DatabaseClient {
             }
         }
 
-        metrics::record_db_latency("count_users_dragonfly", start);
+        self.succeeded("count_users_dragonfly", "-", &node, start).await;
         Ok(count)
     }
 
     async fn count_urls(&self, user_id: Option<&str>) -> Result<u64, AppError> {
         let start = Instant::now();
-        let (node, pool) = self.get_pool().await?;
-        let client = pool.acquire().await;
+        // A user's index lives on the node its key hashes to, like any other key
+        let index_key = user_id.map(|uid| format!("user_urls:{}", uid));
+        let (node, pool) = match &index_key {
+            Some(index_key) => self.get_pool_for_key(index_key)?,
+            None => self.get_pool().await?,
+        };
+        let client = acquire(&pool).await;
 
-        let count = if let Some(uid) = user_id {
-            let index_key = format!("user_urls:{}", uid);
+        let count = if let Some(index_key) = index_key {
             (*client)
                 .scard(&index_key)
                 .await
                 .map_err(|e| {
-                     futures::executor::block_on(self.circuit_breaker.record_failure(node));
+                     futures::executor::block_on(self.circuit_breaker.record_failure(&node));
                     AppError::RedisConnection(e.to_string())
                 })?
         } else {
@@ -489,7 +996,7 @@ impl Storage for DatabaseClient {
             let mut total: u64 = 0;
             while let Some(page_result) = scanner.next().await {
                 let scan_page: ScanResult = page_result.map_err(|e| {
-                    futures::executor::block_on(self.circuit_breaker.record_failure(node));
+                    futures::executor::block_on(self.circuit_breaker.record_failure(&node));
                     AppError::RedisConnection(e.to_string())
                 })?;
                 total += scan_page.results().as_ref().map(|v| v.len()).unwrap_or(0) as u64;
@@ -500,7 +1007,7 @@ impl Storage for DatabaseClient {
             total
         };
 
-        metrics::record_db_latency("count_urls_dragonfly", start);
+        self.succeeded("count_urls_dragonfly", "-", &node, start).await;
         Ok(count)
     }
 
@@ -508,15 +1015,15 @@ impl Storage for DatabaseClient {
         let start = Instant::now();
         let key = format!("token:{}", token);
         let (node, pool) = self.get_pool_for_key(&key)?;
-        let client = pool.acquire().await;
+        let client = acquire(&pool).await;
         let _: () = (*client)
             .set(&key, "1", Some(Expiration::EX(expiry_secs as i64)), None, false)
             .await
             .map_err(|e| {
-                 futures::executor::block_on(self.circuit_breaker.record_failure(node));
+                 futures::executor::block_on(self.circuit_breaker.record_failure(&node));
                 AppError::RedisConnection(e.to_string())
             })?;
-        metrics::record_db_latency("blacklist_token_dragonfly", start);
+        self.succeeded("blacklist_token_dragonfly", &key, &node, start).await;
         Ok(())
     }
 
@@ -524,25 +1031,25 @@ impl Storage for DatabaseClient {
         let start = Instant::now();
         let key = format!("token:{}", token);
         let (node, pool) = self.get_pool_for_key(&key)?;
-        let client = pool.acquire().await;
+        let client = acquire(&pool).await;
         let exists: bool = (*client).exists(&key).await.map_err(|e| {
-             futures::executor::block_on(self.circuit_breaker.record_failure(node));
+             futures::executor::block_on(self.circuit_breaker.record_failure(&node));
             AppError::RedisConnection(e.to_string())
         })?;
-        metrics::record_db_latency("is_token_blacklisted_dragonfly", start);
+        self.succeeded("is_token_blacklisted_dragonfly", &key, &node, start).await;
         Ok(exists)
     }
 
     async fn scan_keys(&self, pattern: &str, count: u32) -> Result<Vec<String>, AppError> {
         let start = Instant::now();
         let (node, pool) = self.get_pool().await?;
-        let client = pool.acquire().await;
+        let client = acquire(&pool).await;
         let mut scanner = (*client).scan(pattern.to_string(), Some(count), Some(ScanType::String));
         let mut keys = Vec::new();
 
         while let Some(page_result) = scanner.next().await {
             let scan_page: ScanResult = page_result.map_err(|e| {
-                futures::executor::block_on(self.circuit_breaker.record_failure(node));
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
                 AppError::RedisConnection(e.to_string())
             })?;
             keys.extend(
@@ -558,7 +1065,7 @@ impl Storage for DatabaseClient {
             }
         }
 
-        metrics::record_db_latency("scan_keys_dragonfly", start);
+        self.succeeded("scan_keys_dragonfly", "-", &node, start).await;
         Ok(keys.into_iter().flatten().collect())
     }
 
@@ -569,23 +1076,685 @@ impl Storage for DatabaseClient {
         args: Vec<String>,
     ) -> Result<i64, AppError> {
         let start = Instant::now();
-        let (node, pool) = self.get_pool().await?;
-        let client = pool.acquire().await;
+        // Scripts run where their first key lives; the others must share its node
+        let (node, pool) = match keys.first() {
+            Some(key) => self.get_pool_for_key(key)?,
+            None => self.get_pool().await?,
+        };
+        let client = acquire(&pool).await;
         let result: i64 = (*client)
             .eval(script, keys, args)
             .await
             .map_err(|e| {
-                futures::executor::block_on(self.circuit_breaker.record_failure(node));
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
                 AppError::RedisConnection(e.to_string())
             })?;
-        metrics::record_db_latency("eval_lua_dragonfly", start);
+        self.succeeded("eval_lua_dragonfly", "-", &node, start).await;
         Ok(result)
     }
 
+    fn circuit_breaker(&self) -> Option<&Arc<CircuitBreaker>> {
+        Some(&self.circuit_breaker)
+    }
+
+    fn pool_stats(&self) -> Vec<PoolStats> {
+        DatabaseClient::pool_stats(self)
+    }
+
+    async fn add_node(&self, url: &str) -> Result<u64, AppError> {
+        DatabaseClient::add_node(self, url).await
+    }
+
+    async fn remove_node(&self, node: &str) -> Result<u64, AppError> {
+        DatabaseClient::remove_node(self, node).await
+    }
+
+    async fn rebalance(&self) -> Result<u64, AppError> {
+        DatabaseClient::rebalance(self).await
+    }
+
+    async fn get_hedged(&self, key: &str) -> Result<String, AppError> {
+        DatabaseClient::get_hedged(self, key).await
+    }
+
     async fn is_global_admin(&self, email: &str) -> Result<bool, AppError> {
         let start = Instant::now();
         let is_admin = self.global_admins.iter().any(|admin| admin == email);
         metrics::record_db_latency("is_global_admin_dragonfly", start);
         Ok(is_admin)
     }
-}
\ No newline at end of file
+
+    async fn reserve_codes(&self, user_id: &str, codes: &[String], ttl_seconds: u64) -> Result<(), AppError> {
+        let start = Instant::now();
+        let mut reserved = Vec::with_capacity(codes.len());
+        for code in codes {
+            match self.reserve_code(user_id, code, ttl_seconds).await {
+                Ok(true) => reserved.push(code),
+                outcome => {
+                    // Reservations hash to different nodes, so undo the ones this batch already took
+                    for code in reserved {
+                        if let Err(e) = self.release_code_reservation(code).await {
+                            warn!("Failed to release reservation of {} after a partial batch: {}", code, e);
+                        }
+                    }
+                    outcome?;
+                    return Err(AppError::Conflict(format!("Code {} is already reserved", code)));
+                }
+            }
+        }
+        metrics::record_storage_latency("reserve_codes_dragonfly", "reserved:", "*", start);
+        Ok(())
+    }
+
+    async fn get_code_reservation(&self, code: &str) -> Result<Option<String>, AppError> {
+        let start = Instant::now();
+        let key = format!("reserved:{}", code);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let owner: Option<String> = (*client).get(&key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("get_code_reservation_dragonfly", &key, &node, start).await;
+        Ok(owner)
+    }
+
+    async fn release_code_reservation(&self, code: &str) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("reserved:{}", code);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client).del(&key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("release_code_reservation_dragonfly", &key, &node, start).await;
+        Ok(())
+    }
+
+    async fn set_reserved_alias(&self, alias: &str, reserved: bool) -> Result<(), AppError> {
+        let start = Instant::now();
+        let (node, pool) = self.get_pool_for_key(RESERVED_ALIASES_KEY)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client)
+            .hset(RESERVED_ALIASES_KEY, (alias, if reserved { "1" } else { "0" }))
+            .await
+            .map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+        self.succeeded("set_reserved_alias_dragonfly", RESERVED_ALIASES_KEY, &node, start).await;
+        Ok(())
+    }
+
+    async fn get_reserved_alias_overrides(&self) -> Result<HashMap<String, bool>, AppError> {
+        let start = Instant::now();
+        let (node, pool) = self.get_pool_for_key(RESERVED_ALIASES_KEY)?;
+        let client = acquire(&pool).await;
+        let overrides: HashMap<String, String> = (*client).hgetall(RESERVED_ALIASES_KEY).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("get_reserved_alias_overrides_dragonfly", RESERVED_ALIASES_KEY, &node, start).await;
+        Ok(overrides.into_iter().map(|(alias, reserved)| (alias, reserved == "1")).collect())
+    }
+
+    async fn burn_code(&self, code: &str, burned_at: u64) -> Result<bool, AppError> {
+        let start = Instant::now();
+        // The NX tombstone is the claim; concurrent redirects lose it and see the link as gone
+        let key = format!("burned:{}", code);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let claimed: Option<String> = (*client)
+            .set(&key, burned_at, None, Some(SetOptions::NX), false)
+            .await
+            .map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+        if claimed.is_some() {
+            let (node, pool) = self.get_pool_for_key(code)?;
+            let client = acquire(&pool).await;
+            let _: () = (*client).del(code).await.map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+        }
+        self.succeeded("burn_code_dragonfly", &key, &node, start).await;
+        Ok(claimed.is_some())
+    }
+
+    async fn is_code_burned(&self, code: &str) -> Result<bool, AppError> {
+        let start = Instant::now();
+        let key = format!("burned:{}", code);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let burned: bool = (*client).exists(&key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("is_code_burned_dragonfly", &key, &node, start).await;
+        Ok(burned)
+    }
+
+    async fn next_rotation_cursor(&self, code: &str) -> Result<u64, AppError> {
+        let start = Instant::now();
+        let key = format!("rotator:{}", code);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let cursor: u64 = (*client).incr(&key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("next_rotation_cursor_dragonfly", &key, &node, start).await;
+        Ok(cursor)
+    }
+
+    async fn incr_click_count(&self, code: &str) -> Result<u64, AppError> {
+        let start = Instant::now();
+        let key = format!("clicks:{}", code);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let count: u64 = (*client).incr(&key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("incr_click_count_dragonfly", &key, &node, start).await;
+        Ok(count)
+    }
+
+    async fn add_report(&self, report: &AbuseReport) -> Result<(), AppError> {
+        let start = Instant::now();
+        let data = serde_json::to_string(report)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let (node, pool) = self.get_pool_for_key(REPORTS_INDEX_KEY)?;
+        let client = acquire(&pool).await;
+        let tx = (*client).multi();
+        let _ = tx.set::<(), _, _>(format!("report:{}", report.id), &data, None, None, false).await;
+        let _ = tx.sadd::<(), _, _>(REPORTS_INDEX_KEY, &report.id).await;
+        let _ = tx.sadd::<(), _, _>(format!("code_reports:{}", report.code), &report.id).await;
+        let _: () = tx.exec(true).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("add_report_dragonfly", REPORTS_INDEX_KEY, &node, start).await;
+        Ok(())
+    }
+
+    async fn list_reports(&self, page: u64, per_page: u64) -> Result<Paginate<AbuseReport>, AppError> {
+        let start = Instant::now();
+        let per_page = per_page.clamp(1, 100);
+        let offset = page.saturating_sub(1) * per_page;
+        let (node, pool) = self.get_pool_for_key(REPORTS_INDEX_KEY)?;
+        let client = acquire(&pool).await;
+
+        let ids: Vec<String> = (*client).smembers(REPORTS_INDEX_KEY).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        let mut reports = Vec::with_capacity(ids.len());
+        if !ids.is_empty() {
+            let pipeline = (*client).pipeline();
+            for id in &ids {
+                let _ = pipeline.get::<String, _>(format!("report:{}", id)).await;
+            }
+            let results: Vec<Option<String>> = pipeline.all().await.map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+            for json_str in results.into_iter().flatten() {
+                let report: AbuseReport = serde_json::from_str(&json_str)
+                    .map_err(|e| AppError::Internal(e.to_string()))?;
+                reports.push(report);
+            }
+        }
+        // Newest first; RFC 3339 timestamps sort lexicographically
+        reports.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+        let total_items = reports.len() as u64;
+        let items = reports.into_iter().skip(offset as usize).take(per_page as usize).collect();
+        let total_pages = if total_items == 0 { 1 } else { total_items.div_ceil(per_page) };
+        self.succeeded("list_reports_dragonfly", REPORTS_INDEX_KEY, &node, start).await;
+        Ok(Paginate {
+            items,
+            page,
+            per_page,
+            total_items,
+            total_pages,
+        })
+    }
+
+    async fn resolve_reports(&self, code: &str) -> Result<u64, AppError> {
+        let start = Instant::now();
+        let code_key = format!("code_reports:{}", code);
+        let (node, pool) = self.get_pool_for_key(REPORTS_INDEX_KEY)?;
+        let client = acquire(&pool).await;
+
+        let ids: Vec<String> = (*client).smembers(&code_key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        if !ids.is_empty() {
+            let report_keys: Vec<String> = ids.iter().map(|id| format!("report:{}", id)).collect();
+            let tx = (*client).multi();
+            let _ = tx.del::<(), _>(report_keys).await;
+            let _ = tx.srem::<(), _, _>(REPORTS_INDEX_KEY, ids.clone()).await;
+            let _ = tx.del::<(), _>(&code_key).await;
+            let _: () = tx.exec(true).await.map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+        }
+        self.succeeded("resolve_reports_dragonfly", REPORTS_INDEX_KEY, &node, start).await;
+        Ok(ids.len() as u64)
+    }
+
+    async fn add_notification(&self, user_id: &str, notification: &Notification) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("notifications:{}", user_id);
+        let data = serde_json::to_string(notification)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let tx = (*client).multi();
+        let _ = tx.lpush::<(), _, _>(&key, data).await;
+        let _ = tx.ltrim::<(), _>(&key, 0, MAX_NOTIFICATIONS - 1).await;
+        let _: () = tx.exec(true).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("add_notification_dragonfly", &key, &node, start).await;
+        Ok(())
+    }
+
+    async fn list_notifications(&self, user_id: &str, limit: u64) -> Result<Vec<Notification>, AppError> {
+        let start = Instant::now();
+        let key = format!("notifications:{}", user_id);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let entries: Vec<String> = (*client)
+            .lrange(&key, 0, limit.clamp(1, MAX_NOTIFICATIONS as u64) as i64 - 1)
+            .await
+            .map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+        let notifications = entries
+            .iter()
+            .map(|json_str| serde_json::from_str(json_str).map_err(|e| AppError::Internal(e.to_string())))
+            .collect::<Result<Vec<Notification>, _>>()?;
+        self.succeeded("list_notifications_dragonfly", &key, &node, start).await;
+        Ok(notifications)
+    }
+
+    async fn add_session(&self, user_id: &str, session: &Session, ttl_seconds: u64) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("sessions:{}", user_id);
+        let data = serde_json::to_string(session)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        // The hash lives as long as the newest token; older entries are filtered on read
+        let tx = (*client).multi();
+        let _ = tx.hset::<(), _, _>(&key, (session.jti.as_str(), data)).await;
+        let _ = tx.expire::<(), _>(&key, ttl_seconds as i64, None).await;
+        let _: () = tx.exec(true).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("add_session_dragonfly", &key, &node, start).await;
+        Ok(())
+    }
+
+    async fn list_sessions(&self, user_id: &str) -> Result<Vec<Session>, AppError> {
+        let start = Instant::now();
+        let key = format!("sessions:{}", user_id);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let entries: HashMap<String, String> = (*client).hgetall(&key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        let sessions = entries
+            .values()
+            .map(|json_str| serde_json::from_str(json_str).map_err(|e| AppError::Internal(e.to_string())))
+            .collect::<Result<Vec<Session>, _>>()?;
+        self.succeeded("list_sessions_dragonfly", &key, &node, start).await;
+        Ok(sessions)
+    }
+
+    async fn remove_sessions(&self, user_id: &str) -> Result<Vec<Session>, AppError> {
+        let start = Instant::now();
+        let sessions = self.list_sessions(user_id).await?;
+        let key = format!("sessions:{}", user_id);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client).del(&key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("remove_sessions_dragonfly", &key, &node, start).await;
+        Ok(sessions)
+    }
+
+    async fn remove_session(&self, user_id: &str, jti: &str) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("sessions:{}", user_id);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client).hdel(&key, jti).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("remove_session_dragonfly", &key, &node, start).await;
+        Ok(())
+    }
+
+    async fn record_login_failure(&self, subject: &str, window_secs: u64) -> Result<u64, AppError> {
+        let start = Instant::now();
+        let key = format!("login_failures:{}", subject);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        // NX keeps the window anchored at the first failure instead of sliding on every attempt
+        let tx = (*client).multi();
+        let _ = tx.incr::<i64, _>(&key).await;
+        let _ = tx.expire::<i64, _>(&key, window_secs as i64, Some(fred::types::ExpireOptions::NX)).await;
+        let results: Vec<i64> = tx.exec(false).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("record_login_failure_dragonfly", &key, &node, start).await;
+        Ok(results.first().copied().unwrap_or(0).max(0) as u64)
+    }
+
+    async fn clear_login_failures(&self, subject: &str) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("login_failures:{}", subject);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client).del(&key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("clear_login_failures_dragonfly", &key, &node, start).await;
+        Ok(())
+    }
+
+    async fn lock_account(&self, subject: &str, until: u64, ttl_seconds: u64) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("lockout:{}", subject);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client)
+            .set(&key, until, Some(Expiration::EX(ttl_seconds as i64)), None, false)
+            .await
+            .map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+        self.succeeded("lock_account_dragonfly", &key, &node, start).await;
+        Ok(())
+    }
+
+    async fn get_account_lock(&self, subject: &str) -> Result<Option<u64>, AppError> {
+        let start = Instant::now();
+        let key = format!("lockout:{}", subject);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let until: Option<u64> = (*client).get(&key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("get_account_lock_dragonfly", &key, &node, start).await;
+        Ok(until)
+    }
+
+    async fn set_api_key(&self, key: &ApiKey) -> Result<(), AppError> {
+        let start = Instant::now();
+        let data = serde_json::to_string(key)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        // Record first, then the owner's index, so a listed id always resolves
+        let record_key = format!("apikey:{}", key.id);
+        let (node, pool) = self.get_pool_for_key(&record_key)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client).set(&record_key, data, None, None, false).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+
+        let index_key = format!("apikeys:{}", key.user_id);
+        let (node, pool) = self.get_pool_for_key(&index_key)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client).sadd(&index_key, &key.id).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("set_api_key_dragonfly", &record_key, &node, start).await;
+        Ok(())
+    }
+
+    async fn get_api_key(&self, id: &str) -> Result<Option<ApiKey>, AppError> {
+        let start = Instant::now();
+        let record_key = format!("apikey:{}", id);
+        let (node, pool) = self.get_pool_for_key(&record_key)?;
+        let client = acquire(&pool).await;
+        let data: Option<String> = (*client).get(&record_key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        let api_key = data
+            .map(|json_str| serde_json::from_str(&json_str))
+            .transpose()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.succeeded("get_api_key_dragonfly", &record_key, &node, start).await;
+        Ok(api_key)
+    }
+
+    async fn list_api_keys(&self, user_id: &str) -> Result<Vec<ApiKey>, AppError> {
+        let start = Instant::now();
+        let index_key = format!("apikeys:{}", user_id);
+        let (node, pool) = self.get_pool_for_key(&index_key)?;
+        let client = acquire(&pool).await;
+        let ids: Vec<String> = (*client).smembers(&index_key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        let mut keys = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(key) = self.get_api_key(&id).await? {
+                keys.push(key);
+            }
+        }
+        self.succeeded("list_api_keys_dragonfly", &index_key, &node, start).await;
+        Ok(keys)
+    }
+
+    async fn delete_api_key(&self, user_id: &str, id: &str) -> Result<bool, AppError> {
+        let start = Instant::now();
+        let index_key = format!("apikeys:{}", user_id);
+        let (node, pool) = self.get_pool_for_key(&index_key)?;
+        let client = acquire(&pool).await;
+        let removed: i64 = (*client).srem(&index_key, id).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        // Only the owner's index can authorize deleting the record
+        if removed > 0 {
+            let record_key = format!("apikey:{}", id);
+            let (node, pool) = self.get_pool_for_key(&record_key)?;
+            let client = acquire(&pool).await;
+            let _: () = (*client).del(&record_key).await.map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+        }
+        self.succeeded("delete_api_key_dragonfly", &index_key, &node, start).await;
+        Ok(removed > 0)
+    }
+
+    async fn set_signing_secret(&self, user_id: &str, secret: &str) -> Result<(), AppError> {
+        let start = Instant::now();
+        let key = format!("signing_secret:{}", user_id);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client).set(&key, secret, None, None, false).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("set_signing_secret_dragonfly", &key, &node, start).await;
+        Ok(())
+    }
+
+    async fn get_signing_secret(&self, user_id: &str) -> Result<Option<String>, AppError> {
+        let start = Instant::now();
+        let key = format!("signing_secret:{}", user_id);
+        let (node, pool) = self.get_pool_for_key(&key)?;
+        let client = acquire(&pool).await;
+        let secret: Option<String> = (*client).get(&key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("get_signing_secret_dragonfly", &key, &node, start).await;
+        Ok(secret)
+    }
+
+    async fn set_campaign(&self, campaign: &Campaign) -> Result<(), AppError> {
+        let start = Instant::now();
+        let data = serde_json::to_string(campaign)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let record_key = format!("campaign:{}", campaign.id);
+        let (node, pool) = self.get_pool_for_key(&record_key)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client).set(&record_key, data, None, None, false).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+
+        let index_key = format!("campaigns:{}", campaign.user_id);
+        let (node, pool) = self.get_pool_for_key(&index_key)?;
+        let client = acquire(&pool).await;
+        let _: () = (*client).sadd(&index_key, &campaign.id).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("set_campaign_dragonfly", &record_key, &node, start).await;
+        Ok(())
+    }
+
+    async fn get_campaign(&self, id: &str) -> Result<Option<Campaign>, AppError> {
+        let start = Instant::now();
+        let record_key = format!("campaign:{}", id);
+        let (node, pool) = self.get_pool_for_key(&record_key)?;
+        let client = acquire(&pool).await;
+        let data: Option<String> = (*client).get(&record_key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        let campaign = data
+            .map(|json_str| serde_json::from_str(&json_str))
+            .transpose()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.succeeded("get_campaign_dragonfly", &record_key, &node, start).await;
+        Ok(campaign)
+    }
+
+    async fn list_campaigns(&self, user_id: &str) -> Result<Vec<Campaign>, AppError> {
+        let start = Instant::now();
+        let index_key = format!("campaigns:{}", user_id);
+        let (node, pool) = self.get_pool_for_key(&index_key)?;
+        let client = acquire(&pool).await;
+        let ids: Vec<String> = (*client).smembers(&index_key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        let mut campaigns = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(campaign) = self.get_campaign(&id).await? {
+                campaigns.push(campaign);
+            }
+        }
+        self.succeeded("list_campaigns_dragonfly", &index_key, &node, start).await;
+        Ok(campaigns)
+    }
+
+    async fn add_audit_event(&self, event: &AuditEvent) -> Result<(), AppError> {
+        let start = Instant::now();
+        let data = serde_json::to_string(event)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let (node, pool) = self.get_pool_for_key(AUDIT_LOG_KEY)?;
+        let client = acquire(&pool).await;
+        let tx = (*client).multi();
+        let _ = tx.lpush::<(), _, _>(AUDIT_LOG_KEY, data).await;
+        let _ = tx.ltrim::<(), _>(AUDIT_LOG_KEY, 0, MAX_AUDIT_EVENTS - 1).await;
+        let _: () = tx.exec(true).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("add_audit_event_dragonfly", AUDIT_LOG_KEY, &node, start).await;
+        Ok(())
+    }
+
+    async fn list_audit_events(&self, limit: u64) -> Result<Vec<AuditEvent>, AppError> {
+        let start = Instant::now();
+        let (node, pool) = self.get_pool_for_key(AUDIT_LOG_KEY)?;
+        let client = acquire(&pool).await;
+        let entries: Vec<String> = (*client)
+            .lrange(AUDIT_LOG_KEY, 0, limit.clamp(1, 1000) as i64 - 1)
+            .await
+            .map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+        let events = entries
+            .iter()
+            .map(|json_str| serde_json::from_str(json_str).map_err(|e| AppError::Internal(e.to_string())))
+            .collect::<Result<Vec<AuditEvent>, _>>()?;
+        self.succeeded("list_audit_events_dragonfly", AUDIT_LOG_KEY, &node, start).await;
+        Ok(events)
+    }
+
+    async fn incr_usage(&self, counts: Vec<(String, String, u64)>, ttl_seconds: u64) -> Result<(), AppError> {
+        let start = Instant::now();
+        // One pipeline per node, as in zadd_batch
+        let mut by_node: HashMap<Arc<str>, (Arc<FredPool>, HashMap<String, Vec<(String, u64)>>)> = HashMap::new();
+        for (key, field, count) in counts {
+            let (node, pool) = self.get_pool_for_key(&key)?;
+            by_node
+                .entry(node)
+                .or_insert_with(|| (pool, HashMap::new()))
+                .1
+                .entry(key)
+                .or_default()
+                .push((field, count));
+        }
+
+        for (node, (pool, keys)) in by_node {
+            let client = acquire(&pool).await;
+            let pipeline = (*client).pipeline();
+            for (key, fields) in keys {
+                for (field, count) in fields {
+                    let _ = pipeline.hincrby::<(), _, _>(&key, field, count as i64).await;
+                }
+                let _ = pipeline.expire::<(), _>(&key, ttl_seconds as i64, Some(fred::types::ExpireOptions::NX)).await;
+            }
+            let _: () = pipeline.all().await.map_err(|e| {
+                futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+                AppError::RedisConnection(e.to_string())
+            })?;
+        }
+        metrics::record_storage_latency("incr_usage_dragonfly", "-", "*", start);
+        Ok(())
+    }
+
+    async fn get_usage(&self, key: &str) -> Result<HashMap<String, u64>, AppError> {
+        let start = Instant::now();
+        let (node, pool) = self.get_pool_for_key(key)?;
+        let client = acquire(&pool).await;
+        let counts: HashMap<String, u64> = (*client).hgetall(key).await.map_err(|e| {
+            futures::executor::block_on(self.circuit_breaker.record_failure(&node));
+            AppError::RedisConnection(e.to_string())
+        })?;
+        self.succeeded("get_usage_dragonfly", key, &node, start).await;
+        Ok(counts)
+    }
+}
@@ -0,0 +1,93 @@
+//! Consistent hashing over the Dragonfly nodes. Each node owns the arcs of the ring ending at
+//! its points, so adding or removing one only moves the keys that node gains or loses, instead
+//! of nearly every key as `hash % nodes` would.
+
+use std::{collections::BTreeMap, sync::Arc};
+use xxhash_rust::xxh3::xxh3_64;
+
+// Points per node; enough that each node's share stays within a few percent of even
+const VIRTUAL_NODES: usize = 160;
+
+#[derive(Clone, Debug, Default)]
+pub struct HashRing {
+    points: BTreeMap<u64, Arc<str>>,
+}
+
+impl HashRing {
+    pub fn new(nodes: impl IntoIterator<Item = Arc<str>>) -> Self {
+        let mut ring = Self::default();
+        for node in nodes {
+            ring.add(node);
+        }
+        ring
+    }
+
+    pub fn add(&mut self, node: Arc<str>) {
+        for i in 0..VIRTUAL_NODES {
+            self.points.insert(point(&node, i), Arc::clone(&node));
+        }
+    }
+
+    pub fn remove(&mut self, node: &str) {
+        self.points.retain(|_, owner| &**owner != node);
+    }
+
+    /// The node `key` lives on: the first point at or after its hash, wrapping around. A key
+    /// with a `{tag}` hashes on the tag alone, as in Redis Cluster, so `{code}` keys share the
+    /// node of `code`.
+    pub fn node_for(&self, key: &str) -> Option<&Arc<str>> {
+        let hash = xxh3_64(hash_tag(key).as_bytes());
+        self.points
+            .range(hash..)
+            .next()
+            .or_else(|| self.points.iter().next())
+            .map(|(_, node)| node)
+    }
+}
+
+fn hash_tag(key: &str) -> &str {
+    if let Some(open) = key.find('{')
+        && let Some(len) = key[open + 1..].find('}')
+        && len > 0
+    {
+        return &key[open + 1..open + 1 + len];
+    }
+    key
+}
+
+fn point(node: &str, replica: usize) -> u64 {
+    xxh3_64(format!("{}#{}", node, replica).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adding_a_node_only_moves_the_keys_it_takes() {
+        let nodes: Vec<Arc<str>> = ["redis://a:6379", "redis://b:6379", "redis://c:6379"].map(Arc::from).to_vec();
+        let before = HashRing::new(nodes.clone());
+        let mut after = before.clone();
+        after.add(Arc::from("redis://d:6379"));
+
+        let keys: Vec<String> = (0..10_000).map(|i| format!("code{}", i)).collect();
+        let moved: Vec<&String> = keys.iter().filter(|key| before.node_for(key) != after.node_for(key)).collect();
+        // Every key that moved went to the new node, and it took roughly its quarter
+        assert!(moved.iter().all(|key| &**after.node_for(key).unwrap() == "redis://d:6379"));
+        assert!((1_500..3_500).contains(&moved.len()), "moved {}", moved.len());
+
+        after.remove("redis://d:6379");
+        assert!(keys.iter().all(|key| before.node_for(key) == after.node_for(key)));
+    }
+
+    #[test]
+    fn hash_tagged_keys_share_the_node_of_their_tag() {
+        let ring = HashRing::new(["redis://a:6379", "redis://b:6379", "redis://c:6379"].map(Arc::from));
+        for i in 0..1_000 {
+            let code = format!("code{}", i);
+            assert_eq!(ring.node_for(&format!("replication:version:{{{}}}", code)), ring.node_for(&code));
+        }
+        // An empty tag doesn't count, so such keys still spread over the ring
+        assert_eq!(hash_tag("odd{}key"), "odd{}key");
+    }
+}
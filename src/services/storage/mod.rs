@@ -1,2 +1,3 @@
 pub mod dragonfly;
+pub mod hash_ring;
 pub mod storage;
\ No newline at end of file
@@ -1,6 +1,16 @@
 use async_trait::async_trait;
+use std::{collections::HashMap, sync::Arc};
 use crate::errors::AppError;
-use crate::types::{Paginate, UrlData, User};
+use crate::services::cache::circuit_breaker::CircuitBreaker;
+use crate::types::{AbuseReport, ApiKey, AuditEvent, Campaign, Notification, Paginate, Session, UrlData, User};
+
+/// Point-in-time occupancy of one node's connection pool.
+#[derive(Clone, Debug)]
+pub struct PoolStats {
+    pub node: String, // Node URL with credentials removed
+    pub size: usize,
+    pub in_use: usize,
+}
 
 #[async_trait]
 pub trait Storage {
@@ -14,21 +24,137 @@ pub trait Storage {
     async fn scan_keys(&self, pattern: &str, count: u32) -> Result<Vec<String>, AppError>;
 
    
+    // Deletes the link stored under its bare code, as `set_ex` wrote it, and its owner's indexes
     async fn delete_url(&self, code: &str, user_id: Option<&str>, user_email: &str) -> Result<(), AppError>;
     async fn list_urls(&self, user_id: Option<&str>, page: u64, per_page: u64) -> Result<Paginate<UrlData>, AppError>;
     async fn set_url(&self, code: &str, url_data: &UrlData) -> Result<(), AppError>;
+    // Rewrites the record for its new owner, then moves it between both owners' indexes. Not atomic
+    // where the indexes live on other nodes: a failed move can leave the link in both indexes
+    async fn transfer_url(&self, code: &str, url_data: &UrlData, from_user_id: &str) -> Result<(), AppError>;
+    async fn add_user_url(&self, user_id: &str, code: &str) -> Result<(), AppError>; // Indexes a link under its owner, see `count_urls`
+    async fn list_user_codes(&self, user_id: &str) -> Result<Vec<String>, AppError>; // Codes in the owner's index, unordered
+    async fn pin_url(&self, user_id: &str, code: &str, pinned_at: u64) -> Result<(), AppError>;
+    async fn unpin_url(&self, user_id: &str, code: &str) -> Result<(), AppError>;
+    async fn list_pinned(&self, user_id: &str) -> Result<Vec<String>, AppError>; // Most recently pinned first
+    async fn tag_url(&self, user_id: &str, code: &str, tags: &[String]) -> Result<(), AppError>; // Adds `code` to each `user_tag:{uid}:{tag}` index
+    async fn untag_url(&self, user_id: &str, code: &str, tags: &[String]) -> Result<(), AppError>;
+    async fn list_tagged_codes(&self, user_id: &str, tag: &str) -> Result<Vec<String>, AppError>; // Unordered
+    async fn index_search_terms(&self, user_id: &str, code: &str, terms: &[String]) -> Result<(), AppError>; // See `link_search`
+    async fn remove_search_terms(&self, user_id: &str, code: &str, terms: &[String]) -> Result<(), AppError>;
+    async fn search_codes(&self, user_id: &str, term: &str) -> Result<Vec<String>, AppError>; // Unordered
     async fn set_user(&self, user: &User) -> Result<(), AppError>;
     async fn get_user(&self, id_or_email: &str) -> Result<Option<User>, AppError>;
+    async fn delete_user_email(&self, email: &str) -> Result<(), AppError>;
     async fn count_users(&self) -> Result<u64, AppError>;
     async fn count_urls(&self, user_id: Option<&str>) -> Result<u64, AppError>;
     async fn blacklist_token(&self, token: &str, expiry_secs: u64) -> Result<(), AppError>;
     async fn is_token_blacklisted(&self, token: &str) -> Result<bool, AppError>;
+    async fn add_session(&self, user_id: &str, session: &Session, ttl_seconds: u64) -> Result<(), AppError>;
+    async fn list_sessions(&self, user_id: &str) -> Result<Vec<Session>, AppError>;
+    async fn remove_sessions(&self, user_id: &str) -> Result<Vec<Session>, AppError>;
+    async fn remove_session(&self, user_id: &str, jti: &str) -> Result<(), AppError>;
+    async fn record_login_failure(&self, subject: &str, window_secs: u64) -> Result<u64, AppError>;
+    async fn clear_login_failures(&self, subject: &str) -> Result<(), AppError>;
+    async fn lock_account(&self, subject: &str, until: u64, ttl_seconds: u64) -> Result<(), AppError>;
+    async fn get_account_lock(&self, subject: &str) -> Result<Option<u64>, AppError>;
     async fn is_global_admin(&self, email: &str) -> Result<bool, AppError>;
 
+    async fn reserve_codes(&self, user_id: &str, codes: &[String], ttl_seconds: u64) -> Result<(), AppError>;
+    async fn get_code_reservation(&self, code: &str) -> Result<Option<String>, AppError>;
+    async fn release_code_reservation(&self, code: &str) -> Result<(), AppError>;
+    // Admin changes to the configured reserved aliases, shared by every replica: true reserves, false releases
+    async fn set_reserved_alias(&self, alias: &str, reserved: bool) -> Result<(), AppError>;
+    async fn get_reserved_alias_overrides(&self) -> Result<HashMap<String, bool>, AppError>;
+    /// Tombstones a one-time link and deletes its record. Only the first caller gets `true`.
+    async fn burn_code(&self, code: &str, burned_at: u64) -> Result<bool, AppError>;
+    async fn is_code_burned(&self, code: &str) -> Result<bool, AppError>;
+    /// Advances a rotator link's shared cursor and returns its new value, starting at 1.
+    async fn next_rotation_cursor(&self, code: &str) -> Result<u64, AppError>;
+    /// Counts one redirect of a `max_clicks` link and returns its total so far, starting at 1.
+    async fn incr_click_count(&self, code: &str) -> Result<u64, AppError>;
+
+    async fn add_report(&self, report: &AbuseReport) -> Result<(), AppError>;
+    async fn list_reports(&self, page: u64, per_page: u64) -> Result<Paginate<AbuseReport>, AppError>;
+    /// Removes every open report against `code`, returning how many were resolved.
+    async fn resolve_reports(&self, code: &str) -> Result<u64, AppError>;
+    async fn add_notification(&self, user_id: &str, notification: &Notification) -> Result<(), AppError>;
+    async fn list_notifications(&self, user_id: &str, limit: u64) -> Result<Vec<Notification>, AppError>;
+    async fn set_api_key(&self, key: &ApiKey) -> Result<(), AppError>;
+    async fn get_api_key(&self, id: &str) -> Result<Option<ApiKey>, AppError>;
+    async fn list_api_keys(&self, user_id: &str) -> Result<Vec<ApiKey>, AppError>;
+    async fn delete_api_key(&self, user_id: &str, id: &str) -> Result<bool, AppError>;
+    async fn set_signing_secret(&self, user_id: &str, secret: &str) -> Result<(), AppError>;
+    async fn get_signing_secret(&self, user_id: &str) -> Result<Option<String>, AppError>;
+    async fn set_campaign(&self, campaign: &Campaign) -> Result<(), AppError>;
+    async fn get_campaign(&self, id: &str) -> Result<Option<Campaign>, AppError>;
+    async fn list_campaigns(&self, user_id: &str) -> Result<Vec<Campaign>, AppError>;
+    async fn add_audit_event(&self, event: &AuditEvent) -> Result<(), AppError>;
+    async fn list_audit_events(&self, limit: u64) -> Result<Vec<AuditEvent>, AppError>;
+    /// Adds each `(key, field, count)` to a usage counter; a key expires `ttl_seconds` after it
+    /// was first counted into.
+    async fn incr_usage(&self, counts: Vec<(String, String, u64)>, ttl_seconds: u64) -> Result<(), AppError>;
+    async fn get_usage(&self, key: &str) -> Result<HashMap<String, u64>, AppError>; // Empty when nothing was counted
+
+    /// Runs `script` on the node that owns `keys[0]`. Every other key must hash there too, e.g.
+    /// by carrying `{keys[0]}` as a hash tag.
     async fn eval_lua(
         &self,
         script: &str,
         keys: Vec<String>,
         args: Vec<String>,
     ) -> Result<i64, AppError>;
-}
\ No newline at end of file
+
+    /// `get` for the redirect path; storage with replicas may race a second node when the first
+    /// is slow.
+    async fn get_hedged(&self, key: &str) -> Result<String, AppError> {
+        self.get(key).await
+    }
+
+    /// Breaker guarding the backend nodes, for storage that has one.
+    fn circuit_breaker(&self) -> Option<&Arc<CircuitBreaker>> {
+        None
+    }
+
+    /// Connection pool occupancy per node, for storage that pools connections.
+    fn pool_stats(&self) -> Vec<PoolStats> {
+        Vec::new()
+    }
+
+    /// Adds a node to storage sharded across several, moving it the keys it now owns. Returns
+    /// how many moved.
+    async fn add_node(&self, _url: &str) -> Result<u64, AppError> {
+        Err(AppError::BadRequest("This storage has no nodes to add to".into()))
+    }
+
+    /// Drains `node` (as reported by `pool_stats`) into the remaining nodes and drops it.
+    /// Returns how many keys moved.
+    async fn remove_node(&self, _node: &str) -> Result<u64, AppError> {
+        Err(AppError::BadRequest("This storage has no nodes to remove".into()))
+    }
+
+    /// Moves every key onto the node that owns it. Returns how many moved.
+    async fn rebalance(&self) -> Result<u64, AppError> {
+        Ok(0)
+    }
+}
+
+/// Orders a user's codes for listing: pinned ones first, in pin order, then the rest as stored.
+pub fn pinned_first(codes: Vec<String>, pinned: &[String]) -> Vec<String> {
+    let (mut pinned_codes, rest): (Vec<String>, Vec<String>) = codes.into_iter().partition(|code| pinned.contains(code));
+    pinned_codes.sort_by_key(|code| pinned.iter().position(|p| p == code));
+    pinned_codes.extend(rest);
+    pinned_codes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinned_codes_lead_in_pin_order() {
+        let codes = ["a", "b", "c", "d"].map(String::from).to_vec();
+        // "gone" was pinned but is no longer in the user's index
+        let pinned = ["c", "gone", "a"].map(String::from);
+        assert_eq!(pinned_first(codes, &pinned), ["c", "a", "b", "d"]);
+    }
+}
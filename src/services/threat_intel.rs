@@ -0,0 +1,200 @@
+//! Screening of destinations against the domain blocklist and Google Safe Browsing. Links are
+//! screened when they are shortened or repointed, and again by a periodic re-scan, since a page
+//! that was clean then can be compromised or its domain blocklisted later.
+
+use futures::StreamExt;
+use std::{sync::Arc, time::Duration};
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::settings::Settings,
+    errors::AppError,
+    services::{
+        blocklist::DomainBlocklist,
+        cache::cache::CacheService,
+        safe_browsing::{SafeBrowsingClient, Verdict},
+        storage::storage::Storage,
+        url_guard::check_destination,
+    },
+    types::UrlData,
+};
+
+// Re-scan lookups mostly hit the verdict cache, so a handful in flight is plenty
+const RESCAN_CONCURRENCY: usize = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Threat {
+    Blocklisted(String), // The matching pattern, e.g. "*.tk"
+    Flagged(String),     // Safe Browsing threat type, e.g. "SOCIAL_ENGINEERING"
+}
+
+impl Threat {
+    /// Recorded as the link's `reputation` when a re-scan quarantines it.
+    pub fn reputation(&self) -> &str {
+        match self {
+            Threat::Blocklisted(_) => "BLOCKLISTED",
+            Threat::Flagged(threat) => threat,
+        }
+    }
+}
+
+pub struct ThreatIntel {
+    safe_browsing: SafeBrowsingClient,
+    blocklist: Arc<DomainBlocklist>,
+}
+
+impl ThreatIntel {
+    /// Shares `blocklist` with the admin endpoints, so patterns they add apply immediately.
+    pub fn new(config: &Settings, blocklist: Arc<DomainBlocklist>) -> Self {
+        Self {
+            safe_browsing: SafeBrowsingClient::new(config),
+            blocklist,
+        }
+    }
+
+    pub fn safe_browsing_enabled(&self) -> bool {
+        self.safe_browsing.is_enabled()
+    }
+
+    /// The Safe Browsing verdict for a destination, or the threat it poses. The blocklist is
+    /// consulted first since it needs no lookup.
+    pub(crate) async fn assess(&self, url: &str, host: &str) -> Result<Verdict, Threat> {
+        if let Some(pattern) = self.blocklist.matching_pattern(host) {
+            return Err(Threat::Blocklisted(pattern));
+        }
+        match self.safe_browsing.check(url).await {
+            Verdict::Malicious(threat) => Err(Threat::Flagged(threat)),
+            verdict => Ok(verdict),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) async fn remember_verdict(&self, url: &str, verdict: Verdict) {
+        self.safe_browsing.remember(url, verdict).await;
+    }
+}
+
+/// Background task that re-screens every stored link and quarantines the ones whose
+/// destinations have since been blocklisted or flagged.
+pub struct ThreatRescanner {
+    cache: Arc<CacheService>,
+    db: Arc<dyn Storage + Send + Sync>,
+    intel: Arc<ThreatIntel>,
+}
+
+impl ThreatRescanner {
+    pub fn new(cache: Arc<CacheService>, db: Arc<dyn Storage + Send + Sync>, intel: Arc<ThreatIntel>) -> Self {
+        Self { cache, db, intel }
+    }
+
+    pub fn spawn(self: Arc<Self>, every: Duration) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                match self.scan_once().await {
+                    Ok(quarantined) => info!("Threat re-scan finished, {} links quarantined", quarantined),
+                    Err(e) => warn!("Threat re-scan failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Screens every stored link once and returns how many were quarantined.
+    pub(crate) async fn scan_once(&self) -> Result<usize, AppError> {
+        // Links are stored under their bare code; every other keyspace is namespaced with ':'
+        let codes: Vec<String> = self
+            .db
+            .scan_keys("*", 1000)
+            .await?
+            .into_iter()
+            .filter(|key| !key.contains(':'))
+            .collect();
+
+        let quarantined = futures::stream::iter(codes)
+            .map(|code| async move {
+                match self.check_code(&code).await {
+                    Ok(quarantined) => quarantined,
+                    Err(e) => {
+                        debug!("Skipping threat re-scan for {}: {}", code, e);
+                        false
+                    }
+                }
+            })
+            .buffer_unordered(RESCAN_CONCURRENCY)
+            .filter(|quarantined| futures::future::ready(*quarantined))
+            .count()
+            .await;
+        Ok(quarantined)
+    }
+
+    async fn check_code(&self, code: &str) -> Result<bool, AppError> {
+        let json = self.db.get(code).await?;
+        let mut url_data: UrlData = serde_json::from_str(&json)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        if url_data.disabled_at.is_some() || url_data.quarantined {
+            return Ok(false);
+        }
+
+        let Some(threat) = self.first_threat(&url_data).await else {
+            return Ok(false);
+        };
+        warn!("Quarantining {}: a destination is now {:?}", code, threat);
+        url_data.quarantined = true;
+        url_data.reputation = Some(threat.reputation().to_string());
+        self.cache.insert(code.to_string(), &url_data).await?;
+        Ok(true)
+    }
+
+    async fn first_threat(&self, url_data: &UrlData) -> Option<Threat> {
+        for url in destinations(url_data) {
+            let Ok(destination) = check_destination(url) else {
+                continue;
+            };
+            if let Err(threat) = self.intel.assess(url, destination.host_str().unwrap_or_default()).await {
+                return Some(threat);
+            }
+        }
+        None
+    }
+}
+
+/// Every URL a visitor of the link can end up at.
+fn destinations(url_data: &UrlData) -> impl Iterator<Item = &String> {
+    std::iter::once(&url_data.long_url)
+        .chain(&url_data.rotation)
+        .chain(&url_data.fallback_url)
+        .chain(url_data.schedule.iter().map(|entry| &entry.url))
+        .chain(url_data.variants.iter().map(|variant| &variant.url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::SystemClock, test_util::{self, MockStorage}};
+
+    #[tokio::test]
+    async fn rescans_quarantine_links_whose_destinations_were_blocklisted_later() {
+        let config = Settings::default();
+        let db: Arc<dyn Storage + Send + Sync> = Arc::new(MockStorage::new());
+        let cache = Arc::new(CacheService::with_storage(&config, Arc::clone(&db), Arc::new(SystemClock)).await);
+        let blocklist = Arc::new(DomainBlocklist::new(&config).unwrap());
+        let rescanner = ThreatRescanner::new(Arc::clone(&cache), db, Arc::new(ThreatIntel::new(&config, Arc::clone(&blocklist))));
+        let mirrored = UrlData {
+            rotation: vec!["https://mirror.example.net/page".into()],
+            ..test_util::url_data("https://example.com/page")
+        };
+        cache.insert("mirrored".into(), &mirrored).await.unwrap();
+        cache.insert("other".into(), &test_util::url_data("https://example.org/")).await.unwrap();
+        assert_eq!(rescanner.scan_once().await.unwrap(), 0);
+
+        blocklist.add("*.example.net").unwrap();
+        assert_eq!(rescanner.scan_once().await.unwrap(), 1);
+        let url_data = cache.get_url_data("mirrored").await.unwrap();
+        assert!(url_data.quarantined);
+        assert_eq!(url_data.reputation.as_deref(), Some("BLOCKLISTED"));
+        assert!(!cache.get_url_data("other").await.unwrap().quarantined);
+        // Already quarantined links are left for moderators
+        assert_eq!(rescanner.scan_once().await.unwrap(), 0);
+    }
+}
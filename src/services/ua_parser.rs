@@ -1,36 +1,39 @@
-use once_cell::sync::Lazy;
+use moka::sync::Cache;
+use once_cell::sync::{Lazy, OnceCell};
+use regex::{Captures, Regex, RegexBuilder};
 use std::collections::HashMap;
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::{config::settings::Settings, errors::AppError};
+
+const BUILTIN_REGEXES: &str = include_str!("uap_regexes.yaml");
+const MAX_CACHED_UA_LEN: usize = 512;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct UAInfo {
     pub browser: Option<String>,
+    pub browser_version: Option<String>, // e.g. "124.0.6367"
     pub os: Option<String>,
+    pub os_version: Option<String>, // e.g. "17.4"
+    pub device_family: Option<String>, // uap device family, e.g. "iPhone" or "Samsung SM-G991B"
     pub device_type: String,
+    pub is_bot: bool,
+    pub bot_name: Option<String>, // e.g. "Googlebot", "Slackbot", "curl"; None for unnamed crawlers
 }
 
-// Browsers - more specific patterns first
-static BROWSER_PATTERNS: Lazy<[(&str, &str); 8]> = Lazy::new(|| [
-    ("edg/", "Edge"),
-    ("opr/", "Opera"),
-    ("opera", "Opera"),
-    ("firefox", "Firefox"),
-    ("chrome", "Chrome"),
-    ("safari", "Safari"),
-    ("msie", "Internet Explorer"),
-    ("trident", "Internet Explorer"),
-]);
+// uap-core's device family for crawlers, link unfurlers and scripted clients
+const BOT_DEVICE_FAMILY: &str = "Spider";
 
-// OS - more specific patterns first
-static OS_PATTERNS: Lazy<[(&str, &str); 8]> = Lazy::new(|| [
-    ("windows phone", "Windows Phone"),
-    ("windows nt", "Windows"),
-    ("iphone os", "iOS"),
-    ("ipad os", "iOS"),
-    ("mac os x", "macOS"),
-    ("android", "Android"),
-    ("linux", "Linux"),
-    ("freebsd", "FreeBSD"),
-]);
+// Bots that fetch a link only to render a preview card in a chat or feed
+const LINK_PREVIEW_BOTS: [&str; 14] = [
+    "Slackbot", "Slack-ImgProxy", "Twitterbot", "LinkedInBot", "Discordbot", "TelegramBot", "Pinterestbot",
+    "redditbot", "SkypeUriPreview", "Embedly", "Iframely", "facebookexternalhit", "meta-externalagent", "WhatsApp",
+];
+
+/// Whether a `UAInfo::bot_name` belongs to a link unfurler rather than a crawler.
+pub fn is_link_preview_bot(bot_name: &str) -> bool {
+    LINK_PREVIEW_BOTS.contains(&bot_name)
+}
 
 // Devices - priority order (tablet > mobile > fallback)
 static DEVICE_PATTERNS: Lazy<[(&str, &str); 8]> = Lazy::new(|| [
@@ -53,31 +56,306 @@ static OS_DEVICE_FALLBACK: Lazy<HashMap<&str, &str>> = Lazy::new(|| {
     ])
 });
 
-/// High-performance UA parser using substring match
-pub fn parse_user_agent(ua: &str) -> UAInfo {
-    let ua = ua.as_bytes();
-    
+/// One uap-core rule: a regex plus optional replacements for the family and up to three
+/// version parts. Replacements may reference capture groups as `$1`..`$9`.
+struct Rule {
+    regex: Regex,
+    family: Option<String>,
+    versions: [Option<String>; 3],
+}
+
+impl Rule {
+    /// uap semantics: family defaults to group 1 and versions to groups 2..4.
+    fn apply(&self, caps: &Captures) -> (Option<String>, Option<String>) {
+        let group = |i: usize| caps.get(i).map(|m| m.as_str().to_string()).filter(|g| !g.is_empty());
+        let family = match &self.family {
+            Some(template) => Some(expand(template, caps)),
+            None => group(1),
+        }
+        .filter(|family| !family.is_empty());
+        let parts: Vec<String> = (0..3)
+            .map_while(|i| match &self.versions[i] {
+                Some(template) => Some(expand(template, caps)).filter(|v| !v.is_empty()),
+                None => group(i + 2),
+            })
+            .collect();
+        (family, (!parts.is_empty()).then(|| parts.join(".")))
+    }
+}
+
+fn expand(template: &str, caps: &Captures) -> String {
+    let mut out = template.to_string();
+    for i in (1..=9).rev() {
+        let placeholder = format!("${}", i);
+        if out.contains(&placeholder) {
+            out = out.replace(&placeholder, caps.get(i).map_or("", |m| m.as_str()));
+        }
+    }
+    out.trim().to_string()
+}
+
+pub struct UaParser {
+    browsers: Vec<Rule>,
+    oses: Vec<Rule>,
+    devices: Vec<Rule>,
+    cache: Cache<String, UAInfo>,
+}
+
+impl UaParser {
+    /// Loads a uap-core `regexes.yaml`. Rules the `regex` crate can't compile (a handful use
+    /// lookaround) are skipped with a warning rather than failing startup.
+    pub(crate) fn from_yaml(yaml: &str, cache_capacity: u64) -> Result<Self, AppError> {
+        let docs = YamlLoader::load_from_str(yaml)
+            .map_err(|e| AppError::Internal(format!("Invalid UA regexes: {}", e)))?;
+        let doc = docs.first().ok_or_else(|| AppError::Internal("Empty UA regexes file".into()))?;
+        let parser = Self {
+            browsers: load_rules(&doc["user_agent_parsers"], "family_replacement", ["v1_replacement", "v2_replacement", "v3_replacement"]),
+            oses: load_rules(&doc["os_parsers"], "os_replacement", ["os_v1_replacement", "os_v2_replacement", "os_v3_replacement"]),
+            devices: load_rules(&doc["device_parsers"], "device_replacement", ["", "", ""]),
+            cache: Cache::builder().max_capacity(cache_capacity).build(),
+        };
+        tracing::info!(
+            "Loaded {} browser, {} OS and {} device UA rules",
+            parser.browsers.len(),
+            parser.oses.len(),
+            parser.devices.len()
+        );
+        Ok(parser)
+    }
+
+    pub fn parse(&self, ua: &str) -> UAInfo {
+        // Very long UAs are almost always junk; don't let them evict useful entries
+        if ua.len() > MAX_CACHED_UA_LEN {
+            return self.parse_uncached(ua);
+        }
+        if let Some(info) = self.cache.get(ua) {
+            return info;
+        }
+        let info = self.parse_uncached(ua);
+        self.cache.insert(ua.to_string(), info.clone());
+        info
+    }
+
+    fn parse_uncached(&self, ua: &str) -> UAInfo {
+        let (browser, browser_version) = first_match(&self.browsers, ua);
+        let (os, os_version) = first_match(&self.oses, ua);
+        let (device_family, _) = first_match(&self.devices, ua);
+        let is_bot = device_family.as_deref() == Some(BOT_DEVICE_FAMILY);
+        // Bot rules come first in user_agent_parsers, so the matched family is the bot's name
+        let bot_name = browser.clone().filter(|family| is_bot && family != "Other");
+
+        let ua_bytes = ua.as_bytes();
+        let device_type = DEVICE_PATTERNS
+            .iter()
+            .find(|(pattern, _)| ua_bytes.windows(pattern.len()).any(|window| window.eq_ignore_ascii_case(pattern.as_bytes())))
+            .map(|(_, kind)| kind.to_string())
+            .or_else(|| os.as_ref().and_then(|os| OS_DEVICE_FALLBACK.get(os.as_str()).map(|v| v.to_string())))
+            .unwrap_or_else(|| "desktop".to_string());
+        let device_type = if is_bot { "bot".to_string() } else { device_type };
 
-    let browser = BROWSER_PATTERNS
+        UAInfo {
+            browser,
+            browser_version,
+            os,
+            os_version,
+            device_family,
+            device_type,
+            is_bot,
+            bot_name,
+        }
+    }
+}
+
+fn load_rules(section: &Yaml, family_key: &str, version_keys: [&str; 3]) -> Vec<Rule> {
+    let field = |entry: &Yaml, key: &str| (!key.is_empty()).then(|| entry[key].as_str().map(str::to_string)).flatten();
+    let mut skipped = 0;
+    let rules: Vec<Rule> = section
+        .as_vec()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
         .iter()
-        .find(|(pattern, _)| ua.windows(pattern.len()).any(|window| window.eq_ignore_ascii_case(pattern.as_bytes())))
-        .map(|(_, name)| name.to_string());
+        .filter_map(|entry| {
+            let pattern = entry["regex"].as_str()?;
+            let regex = RegexBuilder::new(pattern)
+                .case_insensitive(entry["regex_flag"].as_str() == Some("i"))
+                .build()
+                .map_err(|_| skipped += 1)
+                .ok()?;
+            Some(Rule {
+                regex,
+                family: field(entry, family_key),
+                versions: version_keys.map(|key| field(entry, key)),
+            })
+        })
+        .collect();
+    if skipped > 0 {
+        tracing::warn!("Skipped {} UA rules the regex engine does not support", skipped);
+    }
+    rules
+}
 
-    let os = OS_PATTERNS
+fn first_match(rules: &[Rule], ua: &str) -> (Option<String>, Option<String>) {
+    rules
         .iter()
-        .find(|(pattern, _)| ua.windows(pattern.len()).any(|window| window.eq_ignore_ascii_case(pattern.as_bytes())))
-        .map(|(_, name)| name.to_string());
+        .find_map(|rule| rule.regex.captures(ua).map(|caps| rule.apply(&caps)))
+        .unwrap_or((None, None))
+}
+
+static UA_PARSER: OnceCell<UaParser> = OnceCell::new();
 
-    let device_type = DEVICE_PATTERNS
+/// Loads the configured uap-core regexes, falling back to the built-in subset.
+///
+/// # Errors
+///
+/// Fails if the configured regexes file can't be read or parsed.
+pub fn init_ua_parser(settings: &Settings) -> Result<(), AppError> {
+    let capacity = settings.cache.ua_cache_capacity.unwrap_or(10_000);
+    let parser = match &settings.cache.ua_regexes_path {
+        Some(path) => {
+            let yaml = std::fs::read_to_string(path)
+                .map_err(|e| AppError::Internal(format!("Failed to read UA regexes at '{}': {}", path, e)))?;
+            UaParser::from_yaml(&yaml, capacity)?
+        }
+        None => UaParser::from_yaml(BUILTIN_REGEXES, capacity)?,
+    };
+    let _ = UA_PARSER.set(parser);
+    Ok(())
+}
+
+/// Parses a User-Agent with the shared parser, memoized per UA string.
+pub(crate) fn parse_user_agent(ua: &str) -> UAInfo {
+    UA_PARSER
+        .get_or_init(|| UaParser::from_yaml(BUILTIN_REGEXES, 10_000).expect("Built-in UA regexes are valid"))
+        .parse(ua)
+}
+
+/// Raw `Sec-CH-UA*` header values. Chromium browsers send these alongside a frozen UA string
+/// whose platform and version no longer reflect the real device.
+#[derive(Clone, Debug, Default)]
+pub struct ClientHints<'a> {
+    pub brands: Option<&'a str>,   // Sec-CH-UA, e.g. `"Chromium";v="124", "Google Chrome";v="124"`
+    pub platform: Option<&'a str>, // Sec-CH-UA-Platform, e.g. `"Windows"`
+    pub mobile: Option<&'a str>,   // Sec-CH-UA-Mobile, `?1` or `?0`
+}
+
+impl ClientHints<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.brands.is_none() && self.platform.is_none() && self.mobile.is_none()
+    }
+}
+
+// Brand names as sent in Sec-CH-UA → the names parse_user_agent reports
+static HINT_BRANDS: Lazy<HashMap<&str, &str>> = Lazy::new(|| {
+    HashMap::from([
+        ("Google Chrome", "Chrome"),
+        ("Microsoft Edge", "Edge"),
+        ("Opera", "Opera"),
+        ("Brave", "Brave"),
+        ("Vivaldi", "Vivaldi"),
+        ("Samsung Internet", "Samsung Internet"),
+        ("Chromium", "Chromium"),
+    ])
+});
+
+/// Overrides UA-derived fields with client hints where present; hints win because the UA
+/// string is deliberately reduced.
+pub fn apply_client_hints(mut info: UAInfo, hints: &ClientHints) -> UAInfo {
+    if let Some(browser) = hints.brands.and_then(brand_from_hint) {
+        info.browser = Some(browser);
+    }
+    if let Some(platform) = hints.platform.map(|p| p.trim().trim_matches('"')).filter(|p| !p.is_empty()) {
+        info.os = Some(match platform {
+            "Chrome OS" | "Chromium OS" => "ChromeOS".to_string(),
+            other => other.to_string(),
+        });
+    }
+    // Crawlers don't send client hints; a UA that says bot stays a bot
+    if info.is_bot {
+        return info;
+    }
+    match hints.mobile.map(str::trim) {
+        Some("?1") => info.device_type = "mobile".to_string(),
+        // ?0 covers tablets too, so only correct a phone guess made from the reduced UA
+        Some("?0") if info.device_type == "mobile" => info.device_type = "desktop".to_string(),
+        _ => {}
+    }
+    info
+}
+
+fn brand_from_hint(header: &str) -> Option<String> {
+    let brands: Vec<&str> = header
+        .split(',')
+        .filter_map(|entry| entry.split(';').next())
+        .map(|brand| brand.trim().trim_matches('"'))
+        // GREASE entries ("Not-A.Brand", "Not_A Brand", ...) exist only to keep parsers honest
+        .filter(|brand| !brand.is_empty() && !brand.starts_with("Not"))
+        .collect();
+    // Prefer the specific product over the Chromium engine entry every Chromium browser lists
+    brands
         .iter()
-        .find(|(pattern, _)| ua.windows(pattern.len()).any(|window| window.eq_ignore_ascii_case(pattern.as_bytes())))
-        .map(|(_, kind)| kind.to_string())
-        .or_else(|| os.as_ref().and_then(|os| OS_DEVICE_FALLBACK.get(os.as_str()).map(|v| v.to_string())))
-        .unwrap_or_else(|| "desktop".to_string());
-
-    UAInfo {
-        browser,
-        os,
-        device_type,
+        .find(|brand| **brand != "Chromium")
+        .or_else(|| brands.first())
+        .map(|brand| HINT_BRANDS.get(brand).copied().unwrap_or(brand).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_families_and_versions() {
+        let info = parse_user_agent(
+            "Mozilla/5.0 (iPhone; CPU iPhone OS 17_4 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Mobile/15E148 Safari/604.1",
+        );
+        assert_eq!(info.browser.as_deref(), Some("Mobile Safari"));
+        assert_eq!(info.browser_version.as_deref(), Some("17.4"));
+        assert_eq!(info.os.as_deref(), Some("iOS"));
+        assert_eq!(info.os_version.as_deref(), Some("17.4"));
+        assert_eq!(info.device_family.as_deref(), Some("iPhone"));
+        assert_eq!(info.device_type, "mobile");
+
+        let info = parse_user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0");
+        assert_eq!((info.browser.as_deref(), info.browser_version.as_deref()), (Some("Firefox"), Some("125.0")));
+        assert_eq!((info.os.as_deref(), info.os_version.as_deref()), (Some("Windows"), Some("10")));
+        assert_eq!(info.device_type, "desktop");
+        assert!(!info.is_bot);
+    }
+
+    #[test]
+    fn classifies_bots() {
+        let cases = [
+            ("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)", Some("Googlebot")),
+            ("Slackbot-LinkExpanding 1.0 (+https://api.slack.com/robots)", Some("Slackbot")),
+            ("facebookexternalhit/1.1 (+http://www.facebook.com/externalhit_uatext.php)", Some("facebookexternalhit")),
+            ("curl/8.5.0", Some("curl")),
+            (
+                "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) HeadlessChrome/124.0.6367.60 Safari/537.36",
+                Some("HeadlessChrome"),
+            ),
+            ("SomeNewsCrawler (+https://example.com/crawler)", None),
+        ];
+        for (ua, name) in cases {
+            let info = parse_user_agent(ua);
+            assert!(info.is_bot, "{} should be a bot", ua);
+            assert_eq!(info.bot_name.as_deref(), name, "{}", ua);
+            assert_eq!(info.device_type, "bot");
+        }
+        assert!(is_link_preview_bot("Slackbot"));
+        assert!(!is_link_preview_bot("Googlebot"));
+    }
+
+    #[test]
+    fn client_hints_override_reduced_ua() {
+        // Chrome's reduced UA on an Android phone claims "Linux; Android 10; K"
+        let ua = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+        let hints = ClientHints {
+            brands: Some(r#""Chromium";v="124", "Microsoft Edge";v="124", "Not-A.Brand";v="99""#),
+            platform: Some(r#""Android""#),
+            mobile: Some("?1"),
+        };
+        let info = apply_client_hints(parse_user_agent(ua), &hints);
+        assert_eq!(info.browser.as_deref(), Some("Edge"));
+        assert_eq!(info.os.as_deref(), Some("Android"));
+        assert_eq!(info.device_type, "mobile");
     }
 }
@@ -0,0 +1,123 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use url::{Host, Url};
+
+use crate::errors::AppError;
+
+const ALLOWED_SCHEMES: [&str; 2] = ["http", "https"];
+
+/// Returns true for addresses we must never connect to on behalf of a user: loopback, private,
+/// link-local, CGNAT, unspecified, multicast, broadcast and documentation ranges.
+pub fn is_forbidden_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_forbidden_ipv4(v4),
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_forbidden_ipv4(v4),
+            None => is_forbidden_ipv6(v6),
+        },
+    }
+}
+
+fn is_forbidden_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || a == 0
+        || (a == 100 && (64..128).contains(&b)) // CGNAT 100.64.0.0/10
+}
+
+fn is_forbidden_ipv6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || (first & 0xfe00) == 0xfc00 // Unique local fc00::/7
+        || (first & 0xffc0) == 0xfe80 // Link-local fe80::/10
+        || (first == 0x2001 && ip.segments()[1] == 0x0db8) // Documentation 2001:db8::/32
+}
+
+/// Cheap checks that need no DNS: http(s) scheme, a host, and no literal internal IP.
+pub(crate) fn check_destination(url: &str) -> Result<Url, AppError> {
+    let parsed = Url::parse(url).map_err(|e| AppError::InvalidUrl(e.to_string()))?;
+    if !ALLOWED_SCHEMES.contains(&parsed.scheme()) {
+        return Err(AppError::InvalidUrl(format!("Scheme {} is not allowed", parsed.scheme())));
+    }
+    let forbidden = match parsed.host() {
+        None => return Err(AppError::InvalidUrl("URL has no host".into())),
+        Some(Host::Ipv4(ip)) => is_forbidden_ip(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_forbidden_ip(IpAddr::V6(ip)),
+        Some(Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost") || domain.ends_with(".localhost"),
+    };
+    if forbidden {
+        return Err(AppError::InvalidUrl("URL points at an internal address".into()));
+    }
+    Ok(parsed)
+}
+
+/// Resolves a destination that passed [`check_destination`] and refuses it if any address is
+/// internal. Anything that fetches a user-supplied URL (previews, scanners) must connect only to
+/// the addresses returned here, so a second DNS lookup can't be rebound to an internal host.
+/// A host that doesn't resolve is reported as `NotFound`.
+pub(crate) async fn resolve_public_addrs(parsed: &Url) -> Result<Vec<SocketAddr>, AppError> {
+    let addrs = resolve_host(parsed)
+        .await
+        .map_err(|e| AppError::NotFound(format!("Failed to resolve {}: {}", parsed, e)))?;
+    if addrs.iter().any(|addr| is_forbidden_ip(addr.ip())) {
+        return Err(AppError::InvalidUrl("URL resolves to an internal address".into()));
+    }
+    Ok(addrs)
+}
+
+/// Raw DNS resolution of a parsed URL's host and port, with no address filtering.
+async fn resolve_host(parsed: &Url) -> std::io::Result<Vec<SocketAddr>> {
+    let host = parsed.host_str().unwrap_or_default().trim_start_matches('[').trim_end_matches(']');
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+    if addrs.is_empty() {
+        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("{} did not resolve", host)));
+    }
+    Ok(addrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn internal_destinations_are_refused_without_dns() {
+        let cases = [
+            ("https://example.com/path", true),
+            ("http://93.184.216.34/", true),
+            ("http://[2606:2800:220:1::]/", true),
+            ("http://127.0.0.1/", false),
+            ("http://10.1.2.3/", false),
+            ("http://169.254.169.254/latest/meta-data", false),
+            ("http://100.64.0.1/", false),      // CGNAT
+            ("http://100.127.255.254/", false), // CGNAT
+            ("http://100.128.0.1/", true),      // Just past CGNAT
+            ("http://0.0.0.0/", false),
+            ("http://[::1]/", false),
+            ("http://[::ffff:127.0.0.1]/", false), // IPv4-mapped loopback
+            ("http://[::ffff:10.0.0.1]/", false),  // IPv4-mapped private
+            ("http://[fc00::1]/", false),          // Unique local
+            ("http://[fd12:3456::1]/", false),     // Unique local
+            ("http://[fe80::1]/", false),
+            ("http://[2001:db8::1]/", false),
+            ("http://localhost/", false),
+            ("http://LOCALHOST:8080/", false),
+            ("http://api.localhost/", false),
+            ("ftp://example.com/file", false),
+            ("file:///etc/passwd", false),
+            ("javascript:alert(1)", false),
+            ("data:text/html,hi", false),
+            ("not a url", false),
+        ];
+        for (url, allowed) in cases {
+            assert_eq!(check_destination(url).is_ok(), allowed, "{}", url);
+        }
+    }
+}
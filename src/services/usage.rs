@@ -0,0 +1,219 @@
+//! Request counts per user and per API key, by endpoint and status, so customers can see how
+//! much of their quota they have used before they start getting 429s.
+//!
+//! Requests are counted in memory and added to storage every `usage.flush_interval_ms`, so a
+//! request never waits on storage to be counted; a crash loses at most one interval. Daily
+//! counts live under `usage:<subject>:<day>`, where the subject is `user:<id>`, `key:<id>` or
+//! `all` for the admin rollup. Each user also gets a counter per rate-limit window, which is
+//! what `/v1/usage` holds up against the limits.
+
+use chrono::{Duration as ChronoDuration, NaiveDate};
+use dashmap::DashMap;
+use futures::future::try_join_all;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tracing::warn;
+
+use crate::{
+    clock::Clock,
+    config::settings::Settings,
+    errors::AppError,
+    services::storage::storage::Storage,
+    types::{DailyUsage, EndpointUsage},
+};
+
+pub const ALL_SUBJECT: &str = "all";
+// Route of requests that matched nothing, so scanners can't add a field per path
+const UNMATCHED_ROUTE: &str = "unmatched";
+const DEFAULT_LOOKBACK_DAYS: u64 = 30;
+
+pub fn user_subject(user_id: &str) -> String {
+    format!("user:{}", user_id)
+}
+
+pub fn key_subject(api_key_id: &str) -> String {
+    format!("key:{}", api_key_id)
+}
+
+/// Counts waiting for the next flush: storage key to field to count.
+type Pending = DashMap<String, HashMap<String, u64>>;
+
+pub struct UsageTracker {
+    enabled: bool,
+    storage: Arc<dyn Storage + Send + Sync>,
+    clock: Arc<dyn Clock>,
+    retention_days: u64,
+    window_secs: u64,
+    daily: Pending,
+    windows: Pending,
+}
+
+impl UsageTracker {
+    pub fn new(config: &Settings, storage: Arc<dyn Storage + Send + Sync>, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            enabled: config.usage.enabled,
+            storage,
+            clock,
+            retention_days: config.usage.retention_days,
+            window_secs: config.rate_limit.window_size_seconds.unwrap_or(60),
+            daily: DashMap::new(),
+            windows: DashMap::new(),
+        }
+    }
+
+    /// Days a report covers: `days` when asked, within what is kept.
+    pub fn lookback(&self, days: Option<u64>) -> u64 {
+        days.unwrap_or(DEFAULT_LOOKBACK_DAYS).clamp(1, self.retention_days)
+    }
+
+    pub fn window_secs(&self) -> u64 {
+        self.window_secs
+    }
+
+    /// Writes buffered counts to storage every `interval`.
+    pub fn spawn_flush(self: Arc<Self>, interval: Duration) {
+        if !self.enabled {
+            return;
+        }
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.flush().await {
+                    warn!("Failed to flush API usage, retrying next interval: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Counts one request against the admin rollup and, when it was authenticated, its user and
+    /// API key. `route` is the matched route template, `rate_class` the limiter bucket it fell in.
+    pub fn record(&self, user_id: Option<&str>, api_key_id: Option<&str>, route: Option<&str>, rate_class: &str, status: u16) {
+        if !self.enabled {
+            return;
+        }
+        let now = self.clock.now();
+        let day = now.format("%Y-%m-%d").to_string();
+        let field = format!("{} {}", route.unwrap_or(UNMATCHED_ROUTE), status);
+        let subjects = [
+            Some(ALL_SUBJECT.to_string()),
+            user_id.map(user_subject),
+            api_key_id.map(key_subject),
+        ];
+        for subject in subjects.into_iter().flatten() {
+            add(&self.daily, daily_key(&subject, &day), field.clone(), 1);
+        }
+        if let Some(user_id) = user_id {
+            let window = now.timestamp() as u64 / self.window_secs;
+            add(&self.windows, window_key(user_id, window), rate_class.to_string(), 1);
+        }
+    }
+
+    /// Adds everything counted so far to storage. Counts that fail to land are kept for the next
+    /// flush.
+    pub(crate) async fn flush(&self) -> Result<(), AppError> {
+        let daily_ttl = self.retention_days * 86_400;
+        let window_ttl = self.window_secs * 2;
+        let mut result = Ok(());
+        for (pending, ttl) in [(&self.daily, daily_ttl), (&self.windows, window_ttl)] {
+            let counts = take(pending);
+            if counts.is_empty() {
+                continue;
+            }
+            if let Err(e) = self.storage.incr_usage(counts.clone(), ttl).await {
+                for (key, field, count) in counts {
+                    add(pending, key, field, count);
+                }
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    /// `subject`'s counts for each of the last `days` days, newest first, including any not yet
+    /// flushed.
+    pub(crate) async fn daily(&self, subject: &str, days: u64) -> Result<Vec<DailyUsage>, AppError> {
+        let today = self.clock.now().date_naive();
+        let dates: Vec<NaiveDate> = (0..days.min(self.retention_days)).map(|d| today - ChronoDuration::days(d as i64)).collect();
+        let lookups = dates.iter().map(|date| self.counts(&self.daily, daily_key(subject, &date.format("%Y-%m-%d").to_string())));
+        let counts = try_join_all(lookups).await?;
+        Ok(dates.into_iter().zip(counts).map(|(date, counts)| daily_usage(date, counts)).collect())
+    }
+
+    /// Requests `user_id` has made in the current rate-limit window, per limiter bucket.
+    pub(crate) async fn current_window(&self, user_id: &str) -> Result<HashMap<String, u64>, AppError> {
+        let window = self.clock.now().timestamp() as u64 / self.window_secs;
+        self.counts(&self.windows, window_key(user_id, window)).await
+    }
+
+    async fn counts(&self, pending: &Pending, key: String) -> Result<HashMap<String, u64>, AppError> {
+        let mut counts = self.storage.get_usage(&key).await?;
+        if let Some(buffered) = pending.get(&key) {
+            for (field, count) in buffered.iter() {
+                *counts.entry(field.clone()).or_default() += count;
+            }
+        }
+        Ok(counts)
+    }
+}
+
+fn daily_key(subject: &str, day: &str) -> String {
+    format!("usage:{}:{}", subject, day)
+}
+
+fn window_key(user_id: &str, window: u64) -> String {
+    format!("usage:user:{}:window:{}", user_id, window)
+}
+
+fn add(pending: &Pending, key: String, field: String, count: u64) {
+    *pending.entry(key).or_default().entry(field).or_default() += count;
+}
+
+fn take(pending: &Pending) -> Vec<(String, String, u64)> {
+    let mut counts = Vec::new();
+    pending.retain(|key, fields| {
+        counts.extend(fields.drain().map(|(field, count)| (key.clone(), field, count)));
+        false
+    });
+    counts
+}
+
+fn daily_usage(date: NaiveDate, counts: HashMap<String, u64>) -> DailyUsage {
+    let mut endpoints: Vec<EndpointUsage> = counts
+        .into_iter()
+        .filter_map(|(field, count)| {
+            let (endpoint, status) = field.rsplit_once(' ')?;
+            Some(EndpointUsage { endpoint: endpoint.to_string(), status: status.parse().ok()?, count })
+        })
+        .collect();
+    endpoints.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.endpoint.cmp(&b.endpoint)));
+    DailyUsage {
+        date: date.format("%Y-%m-%d").to_string(),
+        total: endpoints.iter().map(|endpoint| endpoint.count).sum(),
+        endpoints,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::SystemClock, test_util::MockStorage};
+
+    #[tokio::test]
+    async fn counts_survive_a_flush_and_split_by_subject() {
+        let storage = Arc::new(MockStorage::new());
+        let tracker = UsageTracker::new(&Settings::default(), storage.clone(), Arc::new(SystemClock));
+        tracker.record(Some("u1"), Some("k1"), Some("POST /v1/shorten"), "shorten", 200);
+        tracker.record(Some("u1"), None, Some("POST /v1/shorten"), "shorten", 429);
+        tracker.record(None, None, None, "other", 404);
+        tracker.flush().await.unwrap();
+        tracker.record(Some("u1"), None, Some("POST /v1/shorten"), "shorten", 200);
+
+        let user = &tracker.daily(&user_subject("u1"), 1).await.unwrap()[0];
+        assert_eq!(user.total, 3);
+        assert_eq!(user.endpoints[0].endpoint, "POST /v1/shorten");
+        assert_eq!((user.endpoints[0].status, user.endpoints[0].count), (200, 2));
+        assert_eq!(tracker.daily(&key_subject("k1"), 1).await.unwrap()[0].total, 1);
+        assert_eq!(tracker.daily(ALL_SUBJECT, 1).await.unwrap()[0].total, 4);
+        assert_eq!(tracker.current_window("u1").await.unwrap()["shorten"], 3);
+    }
+}
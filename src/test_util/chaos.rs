@@ -0,0 +1,504 @@
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use rand::Rng;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+use std::collections::HashMap;
+use std::time::Duration;
+use crate::{
+    errors::AppError,
+    services::{cache::circuit_breaker::CircuitBreaker, storage::storage::{PoolStats, Storage}},
+    types::{AbuseReport, ApiKey, AuditEvent, Campaign, Notification, Paginate, Session, UrlData, User},
+};
+
+/// Wraps any `Storage` and injects latency, errors and partially applied batches, so tests can
+/// drive circuit breakers, fallbacks and retries without a misbehaving backend.
+///
+/// Faults can be changed while the wrapper is in use, e.g. to fail a few calls and then heal.
+pub struct ChaosStorage {
+    inner: Arc<dyn Storage + Send + Sync>,
+    faults: RwLock<Faults>,
+    injected: AtomicU64, // Errors returned so far, partial batches included
+}
+
+#[derive(Clone, Debug, Default)]
+struct Faults {
+    latency: Option<Duration>,
+    error_rate: f64,
+    fail_next: u64,
+    partial_batches: bool,
+}
+
+/// Looks like a dropped connection, so callers take their real failure path.
+fn injected_error() -> AppError {
+    AppError::RedisConnection("injected fault".into())
+}
+
+impl ChaosStorage {
+    pub fn new(inner: Arc<dyn Storage + Send + Sync>) -> Self {
+        Self {
+            inner,
+            faults: RwLock::new(Faults::default()),
+            injected: AtomicU64::new(0),
+        }
+    }
+
+    /// Delays every call by `latency` before it reaches the wrapped storage.
+    pub fn with_latency(self, latency: Duration) -> Self {
+        self.set_latency(Some(latency));
+        self
+    }
+
+    /// Fails each call with probability `rate`, clamped to `0.0..=1.0`.
+    pub fn with_error_rate(self, rate: f64) -> Self {
+        self.set_error_rate(rate);
+        self
+    }
+
+    /// Makes `zadd_batch` write only the first half of a batch and then fail.
+    pub fn with_partial_batches(self) -> Self {
+        self.faults.write().partial_batches = true;
+        self
+    }
+
+    pub fn set_latency(&self, latency: Option<Duration>) {
+        self.faults.write().latency = latency;
+    }
+
+    pub fn set_error_rate(&self, rate: f64) {
+        self.faults.write().error_rate = rate.clamp(0.0, 1.0);
+    }
+
+    /// Fails the next `calls` calls outright, before any random errors are rolled.
+    pub fn fail_next(&self, calls: u64) {
+        self.faults.write().fail_next = calls;
+    }
+
+    /// Clears every fault; later calls go straight through.
+    pub fn heal(&self) {
+        *self.faults.write() = Faults::default();
+    }
+
+    pub fn injected_errors(&self) -> u64 {
+        self.injected.load(Ordering::Relaxed)
+    }
+
+    async fn inject(&self) -> Result<(), AppError> {
+        let (latency, fail) = {
+            let mut faults = self.faults.write();
+            let fail = if faults.fail_next > 0 {
+                faults.fail_next -= 1;
+                true
+            } else {
+                faults.error_rate > 0.0 && rand::rng().random_bool(faults.error_rate)
+            };
+            (faults.latency, fail)
+        };
+        if let Some(latency) = latency {
+            tokio::time::sleep(latency).await;
+        }
+        if fail {
+            self.injected.fetch_add(1, Ordering::Relaxed);
+            return Err(injected_error());
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for ChaosStorage {
+    async fn get(&self, key: &str) -> Result<String, AppError> {
+        self.inject().await?;
+        self.inner.get(key).await
+    }
+
+    async fn set_ex(&self, key: &str, value: &str, ttl_seconds: u64) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.set_ex(key, value, ttl_seconds).await
+    }
+
+    async fn zadd(&self, key: &str, score: u64, member: u64) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.zadd(key, score, member).await
+    }
+
+    async fn rate_limit(&self, key: &str, limit: u64, window_secs: i64) -> Result<bool, AppError> {
+        self.inject().await?;
+        self.inner.rate_limit(key, limit, window_secs).await
+    }
+
+    async fn zrange(&self, key: &str, start: i64, end: i64) -> Result<Vec<(u64, u64)>, AppError> {
+        self.inject().await?;
+        self.inner.zrange(key, start, end).await
+    }
+
+    async fn zadd_batch(&self, mut operations: Vec<(String, u64, u64)>, expire_secs: i64) -> Result<(), AppError> {
+        self.inject().await?;
+        // A partial failure writes the first half of the batch and reports the whole call failed
+        if self.faults.read().partial_batches && operations.len() > 1 {
+            operations.truncate(operations.len() / 2);
+            self.inner.zadd_batch(operations, expire_secs).await?;
+            self.injected.fetch_add(1, Ordering::Relaxed);
+            return Err(injected_error());
+        }
+        self.inner.zadd_batch(operations, expire_secs).await
+    }
+
+    async fn scan_keys(&self, pattern: &str, count: u32) -> Result<Vec<String>, AppError> {
+        self.inject().await?;
+        self.inner.scan_keys(pattern, count).await
+    }
+
+    async fn delete_url(&self, code: &str, user_id: Option<&str>, user_email: &str) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.delete_url(code, user_id, user_email).await
+    }
+
+    async fn list_urls(&self, user_id: Option<&str>, page: u64, per_page: u64) -> Result<Paginate<UrlData>, AppError> {
+        self.inject().await?;
+        self.inner.list_urls(user_id, page, per_page).await
+    }
+
+    async fn set_url(&self, code: &str, url_data: &UrlData) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.set_url(code, url_data).await
+    }
+
+    async fn transfer_url(&self, code: &str, url_data: &UrlData, from_user_id: &str) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.transfer_url(code, url_data, from_user_id).await
+    }
+
+    async fn add_user_url(&self, user_id: &str, code: &str) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.add_user_url(user_id, code).await
+    }
+
+    async fn list_user_codes(&self, user_id: &str) -> Result<Vec<String>, AppError> {
+        self.inject().await?;
+        self.inner.list_user_codes(user_id).await
+    }
+
+    async fn pin_url(&self, user_id: &str, code: &str, pinned_at: u64) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.pin_url(user_id, code, pinned_at).await
+    }
+
+    async fn unpin_url(&self, user_id: &str, code: &str) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.unpin_url(user_id, code).await
+    }
+
+    async fn list_pinned(&self, user_id: &str) -> Result<Vec<String>, AppError> {
+        self.inject().await?;
+        self.inner.list_pinned(user_id).await
+    }
+
+    async fn tag_url(&self, user_id: &str, code: &str, tags: &[String]) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.tag_url(user_id, code, tags).await
+    }
+
+    async fn untag_url(&self, user_id: &str, code: &str, tags: &[String]) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.untag_url(user_id, code, tags).await
+    }
+
+    async fn list_tagged_codes(&self, user_id: &str, tag: &str) -> Result<Vec<String>, AppError> {
+        self.inject().await?;
+        self.inner.list_tagged_codes(user_id, tag).await
+    }
+
+    async fn index_search_terms(&self, user_id: &str, code: &str, terms: &[String]) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.index_search_terms(user_id, code, terms).await
+    }
+
+    async fn remove_search_terms(&self, user_id: &str, code: &str, terms: &[String]) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.remove_search_terms(user_id, code, terms).await
+    }
+
+    async fn search_codes(&self, user_id: &str, term: &str) -> Result<Vec<String>, AppError> {
+        self.inject().await?;
+        self.inner.search_codes(user_id, term).await
+    }
+
+    async fn set_user(&self, user: &User) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.set_user(user).await
+    }
+
+    async fn get_user(&self, id_or_email: &str) -> Result<Option<User>, AppError> {
+        self.inject().await?;
+        self.inner.get_user(id_or_email).await
+    }
+
+    async fn delete_user_email(&self, email: &str) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.delete_user_email(email).await
+    }
+
+    async fn count_users(&self) -> Result<u64, AppError> {
+        self.inject().await?;
+        self.inner.count_users().await
+    }
+
+    async fn count_urls(&self, user_id: Option<&str>) -> Result<u64, AppError> {
+        self.inject().await?;
+        self.inner.count_urls(user_id).await
+    }
+
+    async fn blacklist_token(&self, token: &str, expiry_secs: u64) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.blacklist_token(token, expiry_secs).await
+    }
+
+    async fn is_token_blacklisted(&self, token: &str) -> Result<bool, AppError> {
+        self.inject().await?;
+        self.inner.is_token_blacklisted(token).await
+    }
+
+    async fn add_session(&self, user_id: &str, session: &Session, ttl_seconds: u64) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.add_session(user_id, session, ttl_seconds).await
+    }
+
+    async fn list_sessions(&self, user_id: &str) -> Result<Vec<Session>, AppError> {
+        self.inject().await?;
+        self.inner.list_sessions(user_id).await
+    }
+
+    async fn remove_sessions(&self, user_id: &str) -> Result<Vec<Session>, AppError> {
+        self.inject().await?;
+        self.inner.remove_sessions(user_id).await
+    }
+
+    async fn remove_session(&self, user_id: &str, jti: &str) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.remove_session(user_id, jti).await
+    }
+
+    async fn record_login_failure(&self, subject: &str, window_secs: u64) -> Result<u64, AppError> {
+        self.inject().await?;
+        self.inner.record_login_failure(subject, window_secs).await
+    }
+
+    async fn clear_login_failures(&self, subject: &str) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.clear_login_failures(subject).await
+    }
+
+    async fn lock_account(&self, subject: &str, until: u64, ttl_seconds: u64) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.lock_account(subject, until, ttl_seconds).await
+    }
+
+    async fn get_account_lock(&self, subject: &str) -> Result<Option<u64>, AppError> {
+        self.inject().await?;
+        self.inner.get_account_lock(subject).await
+    }
+
+    async fn is_global_admin(&self, email: &str) -> Result<bool, AppError> {
+        self.inject().await?;
+        self.inner.is_global_admin(email).await
+    }
+
+    async fn reserve_codes(&self, user_id: &str, codes: &[String], ttl_seconds: u64) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.reserve_codes(user_id, codes, ttl_seconds).await
+    }
+
+    async fn get_code_reservation(&self, code: &str) -> Result<Option<String>, AppError> {
+        self.inject().await?;
+        self.inner.get_code_reservation(code).await
+    }
+
+    async fn release_code_reservation(&self, code: &str) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.release_code_reservation(code).await
+    }
+
+    async fn set_reserved_alias(&self, alias: &str, reserved: bool) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.set_reserved_alias(alias, reserved).await
+    }
+
+    async fn get_reserved_alias_overrides(&self) -> Result<HashMap<String, bool>, AppError> {
+        self.inject().await?;
+        self.inner.get_reserved_alias_overrides().await
+    }
+
+    async fn burn_code(&self, code: &str, burned_at: u64) -> Result<bool, AppError> {
+        self.inject().await?;
+        self.inner.burn_code(code, burned_at).await
+    }
+
+    async fn is_code_burned(&self, code: &str) -> Result<bool, AppError> {
+        self.inject().await?;
+        self.inner.is_code_burned(code).await
+    }
+
+    async fn next_rotation_cursor(&self, code: &str) -> Result<u64, AppError> {
+        self.inject().await?;
+        self.inner.next_rotation_cursor(code).await
+    }
+
+    async fn incr_click_count(&self, code: &str) -> Result<u64, AppError> {
+        self.inject().await?;
+        self.inner.incr_click_count(code).await
+    }
+
+    async fn add_report(&self, report: &AbuseReport) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.add_report(report).await
+    }
+
+    async fn list_reports(&self, page: u64, per_page: u64) -> Result<Paginate<AbuseReport>, AppError> {
+        self.inject().await?;
+        self.inner.list_reports(page, per_page).await
+    }
+
+    async fn resolve_reports(&self, code: &str) -> Result<u64, AppError> {
+        self.inject().await?;
+        self.inner.resolve_reports(code).await
+    }
+
+    async fn add_notification(&self, user_id: &str, notification: &Notification) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.add_notification(user_id, notification).await
+    }
+
+    async fn list_notifications(&self, user_id: &str, limit: u64) -> Result<Vec<Notification>, AppError> {
+        self.inject().await?;
+        self.inner.list_notifications(user_id, limit).await
+    }
+
+    async fn set_api_key(&self, key: &ApiKey) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.set_api_key(key).await
+    }
+
+    async fn get_api_key(&self, id: &str) -> Result<Option<ApiKey>, AppError> {
+        self.inject().await?;
+        self.inner.get_api_key(id).await
+    }
+
+    async fn list_api_keys(&self, user_id: &str) -> Result<Vec<ApiKey>, AppError> {
+        self.inject().await?;
+        self.inner.list_api_keys(user_id).await
+    }
+
+    async fn delete_api_key(&self, user_id: &str, id: &str) -> Result<bool, AppError> {
+        self.inject().await?;
+        self.inner.delete_api_key(user_id, id).await
+    }
+
+    async fn set_signing_secret(&self, user_id: &str, secret: &str) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.set_signing_secret(user_id, secret).await
+    }
+
+    async fn get_signing_secret(&self, user_id: &str) -> Result<Option<String>, AppError> {
+        self.inject().await?;
+        self.inner.get_signing_secret(user_id).await
+    }
+
+    async fn set_campaign(&self, campaign: &Campaign) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.set_campaign(campaign).await
+    }
+
+    async fn get_campaign(&self, id: &str) -> Result<Option<Campaign>, AppError> {
+        self.inject().await?;
+        self.inner.get_campaign(id).await
+    }
+
+    async fn list_campaigns(&self, user_id: &str) -> Result<Vec<Campaign>, AppError> {
+        self.inject().await?;
+        self.inner.list_campaigns(user_id).await
+    }
+
+    async fn add_audit_event(&self, event: &AuditEvent) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.add_audit_event(event).await
+    }
+
+    async fn list_audit_events(&self, limit: u64) -> Result<Vec<AuditEvent>, AppError> {
+        self.inject().await?;
+        self.inner.list_audit_events(limit).await
+    }
+
+    async fn incr_usage(&self, counts: Vec<(String, String, u64)>, ttl_seconds: u64) -> Result<(), AppError> {
+        self.inject().await?;
+        self.inner.incr_usage(counts, ttl_seconds).await
+    }
+
+    async fn get_usage(&self, key: &str) -> Result<HashMap<String, u64>, AppError> {
+        self.inject().await?;
+        self.inner.get_usage(key).await
+    }
+
+    async fn eval_lua(&self, script: &str, keys: Vec<String>, args: Vec<String>) -> Result<i64, AppError> {
+        self.inject().await?;
+        self.inner.eval_lua(script, keys, args).await
+    }
+
+    async fn get_hedged(&self, key: &str) -> Result<String, AppError> {
+        self.inject().await?;
+        self.inner.get_hedged(key).await
+    }
+
+    fn circuit_breaker(&self) -> Option<&Arc<CircuitBreaker>> {
+        self.inner.circuit_breaker()
+    }
+
+    fn pool_stats(&self) -> Vec<PoolStats> {
+        self.inner.pool_stats()
+    }
+
+    async fn add_node(&self, url: &str) -> Result<u64, AppError> {
+        self.inject().await?;
+        self.inner.add_node(url).await
+    }
+
+    async fn remove_node(&self, node: &str) -> Result<u64, AppError> {
+        self.inject().await?;
+        self.inner.remove_node(node).await
+    }
+
+    async fn rebalance(&self) -> Result<u64, AppError> {
+        self.inject().await?;
+        self.inner.rebalance().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::MockStorage;
+
+    #[tokio::test]
+    async fn faults_are_injected_until_healed() {
+        let inner = Arc::new(MockStorage::new());
+        let chaos = ChaosStorage::new(inner.clone()).with_partial_batches();
+
+        chaos.fail_next(2);
+        assert!(matches!(chaos.set_ex("a", "1", 60).await, Err(AppError::RedisConnection(_))));
+        assert!(chaos.get("a").await.is_err());
+        chaos.set_ex("a", "1", 60).await.unwrap();
+        assert_eq!(chaos.get("a").await.unwrap(), "1");
+
+        let batch = (0..4).map(|ts| ("stats:a".to_string(), ts, ts)).collect();
+        assert!(chaos.zadd_batch(batch, 60).await.is_err());
+        assert_eq!(inner.zrange("stats:a", 0, -1).await.unwrap(), [(0, 0), (1, 1)]);
+        assert_eq!(chaos.injected_errors(), 3);
+
+        chaos.set_error_rate(1.0);
+        assert!(chaos.count_users().await.is_err());
+        chaos.heal();
+        assert_eq!(chaos.count_users().await.unwrap(), 0);
+    }
+}
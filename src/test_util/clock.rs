@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use crate::clock::Clock;
+
+/// A clock that only moves when told to. Clones share the same time, so a test can keep one
+/// handle and advance the clock a service was built with.
+#[derive(Clone)]
+pub struct MockClock(Arc<Mutex<DateTime<Utc>>>);
+
+impl MockClock {
+    pub fn new(time: DateTime<Utc>) -> Self {
+        Self(Arc::new(Mutex::new(time)))
+    }
+
+    pub fn set(&self, time: DateTime<Utc>) {
+        *self.0.lock() = time;
+    }
+
+    pub fn advance(&self, by: chrono::Duration) {
+        *self.0.lock() += by;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock()
+    }
+}
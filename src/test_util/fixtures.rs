@@ -0,0 +1,37 @@
+use crate::types::{UrlData, User};
+
+const MIN_BCRYPT_COST: u32 = 4;
+
+/// An anonymous link to `long_url` with every optional field unset; adjust with struct update syntax.
+pub fn url_data(long_url: &str) -> UrlData {
+    UrlData {
+        long_url: long_url.to_string(),
+        created_at: "2030-01-01T00:00:00+00:00".to_string(),
+        ..Default::default()
+    }
+}
+
+/// A user with id `user-{username}` and email `{username}@example.com` who can't log in.
+pub fn user(username: &str) -> User {
+    User {
+        id: format!("user-{}", username),
+        username: username.to_string(),
+        email: format!("{}@example.com", username),
+        password_hash: String::new(),
+        created_at: "2030-01-01T00:00:00+00:00".to_string(),
+        plan: None,
+        utm: None,
+    }
+}
+
+/// As [`user`], with `password` hashed at bcrypt's minimum cost so logins work but stay fast.
+///
+/// # Panics
+///
+/// If bcrypt fails to hash, which it never does for a valid cost.
+pub fn user_with_password(username: &str, password: &str) -> User {
+    User {
+        password_hash: bcrypt::hash(password, MIN_BCRYPT_COST).expect("bcrypt accepts any password"),
+        ..user(username)
+    }
+}
@@ -0,0 +1,15 @@
+//! In-memory stand-ins for tests, ours and downstream: a [`MockStorage`], a controllable
+//! [`MockClock`], a fault-injecting [`ChaosStorage`] wrapper and fixtures for the common
+//! records, plus [`test_state`] for handler tests. Enabled by the `test-util` feature.
+
+mod chaos;
+mod clock;
+mod fixtures;
+mod state;
+mod storage;
+
+pub use chaos::ChaosStorage;
+pub use clock::MockClock;
+pub use fixtures::{url_data, user, user_with_password};
+pub use state::{test_app, test_state, test_state_with};
+pub use storage::MockStorage;
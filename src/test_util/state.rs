@@ -0,0 +1,32 @@
+use std::sync::Arc;
+use crate::{
+    app::{App, Builder},
+    config::settings::Settings,
+    handlers::shorten::AppState,
+    services::storage::storage::Storage,
+};
+use super::MockStorage;
+
+/// State for the default config over a fresh [`MockStorage`], with background tasks off.
+pub async fn test_state() -> AppState {
+    test_state_with(Settings::default()).await
+}
+
+/// As [`test_state`], for `config`.
+pub async fn test_state_with(config: Settings) -> AppState {
+    test_app(config, Arc::new(MockStorage::new())).await.state
+}
+
+/// The whole app, router included, for `config` over `storage`, with background tasks off.
+///
+/// # Panics
+///
+/// If the app fails to build, which fails the calling test.
+pub async fn test_app(config: Settings, storage: Arc<dyn Storage + Send + Sync>) -> App {
+    Builder::new(config)
+        .storage(storage)
+        .background_tasks(false)
+        .build()
+        .await
+        .expect("test app should build")
+}
@@ -0,0 +1,719 @@
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use crate::{
+    clock::{Clock, SystemClock},
+    errors::AppError,
+    services::{link_search::search_terms, storage::storage::{pinned_first, Storage}},
+    types::{AbuseReport, ApiKey, AuditEvent, Campaign, Notification, Paginate, Session, UrlData, User},
+};
+
+/// `Storage` kept in process memory, with the same key layout as `SledStorage`.
+///
+/// Expiries are checked against the injected clock, so a [`MockClock`](super::MockClock) can age
+/// out links, reservations and lockouts without sleeping.
+pub struct MockStorage {
+    state: Mutex<State>,
+    clock: Arc<dyn Clock>,
+    global_admins: Vec<String>,
+}
+
+#[derive(Default)]
+struct State {
+    values: BTreeMap<String, (String, Option<u64>)>, // Value and the Unix second it expires at
+    sorted_sets: HashMap<String, Vec<(u64, u64)>>,
+    sequence: u64, // Orders notifications and audit events even when the clock stands still
+}
+
+impl State {
+    fn get(&self, key: &str, now: u64) -> Option<&str> {
+        self.values
+            .get(key)
+            .filter(|(_, expiry)| expiry.is_none_or(|expiry| expiry > now))
+            .map(|(value, _)| value.as_str())
+    }
+
+    fn set(&mut self, key: String, value: String) {
+        self.values.insert(key, (value, None));
+    }
+
+    fn set_until(&mut self, key: String, value: String, expiry: u64) {
+        self.values.insert(key, (value, Some(expiry)));
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        self.values.remove(key).is_some()
+    }
+
+    fn keys_with_prefix(&self, prefix: &str) -> Vec<String> {
+        self.values
+            .range(prefix.to_string()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    fn next_sequence(&mut self) -> u64 {
+        self.sequence += 1;
+        self.sequence
+    }
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<String, AppError> {
+    serde_json::to_string(value).map_err(|e| AppError::Internal(e.to_string()))
+}
+
+fn decode<T: DeserializeOwned>(value: &str) -> Result<T, AppError> {
+    serde_json::from_str(value).map_err(|e| AppError::Internal(e.to_string()))
+}
+
+fn paginate<T>(items: Vec<T>, page: u64, per_page: u64) -> Paginate<T> {
+    let per_page = per_page.clamp(1, 100);
+    let offset = page.saturating_sub(1) * per_page;
+    let total_items = items.len() as u64;
+    let total_pages = if total_items == 0 { 1 } else { total_items.div_ceil(per_page) };
+    Paginate {
+        items: items.into_iter().skip(offset as usize).take(per_page as usize).collect(),
+        page,
+        per_page,
+        total_items,
+        total_pages,
+    }
+}
+
+impl MockStorage {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            clock: Arc::new(SystemClock),
+            global_admins: Vec::new(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_global_admins(mut self, admins: Vec<String>) -> Self {
+        self.global_admins = admins;
+        self
+    }
+
+    fn now(&self) -> u64 {
+        self.clock.now().timestamp() as u64
+    }
+}
+
+impl Default for MockStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Storage for MockStorage {
+    async fn get(&self, key: &str) -> Result<String, AppError> {
+        let now = self.now();
+        self.state
+            .lock()
+            .get(key, now)
+            .map(str::to_owned)
+            .ok_or_else(|| AppError::NotFound("Key not found".into()))
+    }
+
+    async fn set_ex(&self, key: &str, value: &str, ttl_seconds: u64) -> Result<(), AppError> {
+        let expiry = self.now() + ttl_seconds;
+        self.state.lock().set_until(key.to_string(), value.to_string(), expiry);
+        Ok(())
+    }
+
+    async fn zadd(&self, key: &str, score: u64, member: u64) -> Result<(), AppError> {
+        let mut state = self.state.lock();
+        let set = state.sorted_sets.entry(key.to_string()).or_default();
+        set.retain(|&(_, m)| m != member);
+        set.push((score, member));
+        set.sort_by_key(|&(s, _)| s);
+        Ok(())
+    }
+
+    async fn rate_limit(&self, key: &str, limit: u64, window_secs: i64) -> Result<bool, AppError> {
+        let now = self.now();
+        let mut state = self.state.lock();
+        // Fixed window stored as (count, window_start), as in SledStorage
+        let (count, window_start) = match state.get(key, now) {
+            Some(value) => decode::<(u64, u64)>(value)?,
+            None => (0, now),
+        };
+        let (count, window_start) = if now >= window_start + window_secs as u64 { (0, now) } else { (count, window_start) };
+        if count >= limit {
+            return Ok(false);
+        }
+        state.set(key.to_string(), encode(&(count + 1, window_start))?);
+        Ok(true)
+    }
+
+    async fn zrange(&self, key: &str, start: i64, end: i64) -> Result<Vec<(u64, u64)>, AppError> {
+        let data = self.state.lock().sorted_sets.get(key).cloned().unwrap_or_default();
+        let start_idx = start.max(0) as usize;
+        let end_idx = if end < 0 { data.len() } else { (end + 1) as usize };
+        Ok(data.into_iter().skip(start_idx).take(end_idx.saturating_sub(start_idx)).collect())
+    }
+
+    async fn zadd_batch(&self, operations: Vec<(String, u64, u64)>, _expire_secs: i64) -> Result<(), AppError> {
+        for (key, score, member) in operations {
+            self.zadd(&key, score, member).await?;
+        }
+        Ok(())
+    }
+
+    async fn scan_keys(&self, pattern: &str, count: u32) -> Result<Vec<String>, AppError> {
+        let mut keys = self.state.lock().keys_with_prefix(pattern.trim_end_matches('*'));
+        keys.truncate(count as usize);
+        Ok(keys)
+    }
+
+    async fn delete_url(&self, code: &str, user_id: Option<&str>, user_email: &str) -> Result<(), AppError> {
+        let now = self.now();
+        let mut state = self.state.lock();
+        let url_data: UrlData = match state.get(code, now) {
+            Some(value) => decode(value)?,
+            None => return Err(AppError::NotFound(format!("URL {} not found", code))),
+        };
+        let is_admin = self.global_admins.iter().any(|admin| admin == user_email);
+        let is_owner = url_data.user_id.as_deref() == user_id || url_data.user_id.is_none();
+        if !is_owner && !is_admin {
+            return Err(AppError::Unauthorized("Not authorized to delete this URL".into()));
+        }
+        state.remove(code);
+        if let Some(uid) = user_id {
+            state.remove(&format!("index:user_urls:{}:{}", uid, code));
+        }
+        if let Some(owner) = url_data.user_id.as_deref() {
+            state.remove(&format!("pinned:{}:{}", owner, code));
+            for tag in &url_data.tags {
+                state.remove(&format!("index:user_tag:{}:{}:{}", owner, tag, code));
+            }
+            for term in search_terms(code, &url_data) {
+                state.remove(&format!("index:user_search:{}:{}:{}", owner, term, code));
+            }
+        }
+        Ok(())
+    }
+
+    async fn list_urls(&self, user_id: Option<&str>, page: u64, per_page: u64) -> Result<Paginate<UrlData>, AppError> {
+        let codes = match user_id {
+            Some(uid) => {
+                let prefix = format!("index:user_urls:{}:", uid);
+                let codes = self.state.lock().keys_with_prefix(&prefix).into_iter().map(|key| key[prefix.len()..].to_string()).collect();
+                pinned_first(codes, &self.list_pinned(uid).await?)
+            }
+            None => self.state.lock().keys_with_prefix("url:").into_iter().map(|key| key["url:".len()..].to_string()).collect(),
+        };
+        let now = self.now();
+        let state = self.state.lock();
+        let urls = codes
+            .iter()
+            .filter_map(|code| state.get(&format!("url:{}", code), now))
+            .map(decode)
+            .collect::<Result<Vec<UrlData>, _>>()?;
+        Ok(paginate(urls, page, per_page))
+    }
+
+    async fn set_url(&self, code: &str, url_data: &UrlData) -> Result<(), AppError> {
+        let mut state = self.state.lock();
+        state.set(format!("url:{}", code), encode(url_data)?);
+        if let Some(user_id) = &url_data.user_id {
+            state.set(format!("index:user_urls:{}:{}", user_id, code), String::new());
+        }
+        Ok(())
+    }
+
+    async fn transfer_url(&self, code: &str, url_data: &UrlData, from_user_id: &str) -> Result<(), AppError> {
+        let to_user_id = url_data
+            .user_id
+            .as_deref()
+            .ok_or_else(|| AppError::Internal(format!("Transferred URL {} has no owner", code)))?;
+        let value = encode(url_data)?;
+        let mut state = self.state.lock();
+        // The record keeps the expiry it was written with by `set_ex`
+        let expiry = match state.values.get(code) {
+            Some((_, expiry)) => *expiry,
+            None => return Err(AppError::NotFound(format!("URL {} not found", code))),
+        };
+        state.values.insert(code.to_string(), (value, expiry));
+        state.remove(&format!("index:user_urls:{}:{}", from_user_id, code));
+        state.set(format!("index:user_urls:{}:{}", to_user_id, code), String::new());
+        Ok(())
+    }
+
+    async fn add_user_url(&self, user_id: &str, code: &str) -> Result<(), AppError> {
+        self.state.lock().set(format!("index:user_urls:{}:{}", user_id, code), String::new());
+        Ok(())
+    }
+
+    async fn list_user_codes(&self, user_id: &str) -> Result<Vec<String>, AppError> {
+        let prefix = format!("index:user_urls:{}:", user_id);
+        Ok(self.state.lock().keys_with_prefix(&prefix).into_iter().map(|key| key[prefix.len()..].to_string()).collect())
+    }
+
+    async fn pin_url(&self, user_id: &str, code: &str, pinned_at: u64) -> Result<(), AppError> {
+        self.state.lock().set(format!("pinned:{}:{}", user_id, code), pinned_at.to_string());
+        Ok(())
+    }
+
+    async fn unpin_url(&self, user_id: &str, code: &str) -> Result<(), AppError> {
+        self.state.lock().remove(&format!("pinned:{}:{}", user_id, code));
+        Ok(())
+    }
+
+    async fn list_pinned(&self, user_id: &str) -> Result<Vec<String>, AppError> {
+        let prefix = format!("pinned:{}:", user_id);
+        let state = self.state.lock();
+        let mut pinned: Vec<(u64, String)> = state
+            .keys_with_prefix(&prefix)
+            .into_iter()
+            .map(|key| {
+                let pinned_at = state.values[&key].0.parse().unwrap_or(0);
+                (pinned_at, key[prefix.len()..].to_string())
+            })
+            .collect();
+        pinned.sort_by(|a, b| b.cmp(a));
+        Ok(pinned.into_iter().map(|(_, code)| code).collect())
+    }
+
+    async fn tag_url(&self, user_id: &str, code: &str, tags: &[String]) -> Result<(), AppError> {
+        let mut state = self.state.lock();
+        for tag in tags {
+            state.set(format!("index:user_tag:{}:{}:{}", user_id, tag, code), String::new());
+        }
+        Ok(())
+    }
+
+    async fn untag_url(&self, user_id: &str, code: &str, tags: &[String]) -> Result<(), AppError> {
+        let mut state = self.state.lock();
+        for tag in tags {
+            state.remove(&format!("index:user_tag:{}:{}:{}", user_id, tag, code));
+        }
+        Ok(())
+    }
+
+    async fn list_tagged_codes(&self, user_id: &str, tag: &str) -> Result<Vec<String>, AppError> {
+        let prefix = format!("index:user_tag:{}:{}:", user_id, tag);
+        Ok(self.state.lock().keys_with_prefix(&prefix).into_iter().map(|key| key[prefix.len()..].to_string()).collect())
+    }
+
+    async fn index_search_terms(&self, user_id: &str, code: &str, terms: &[String]) -> Result<(), AppError> {
+        let mut state = self.state.lock();
+        for term in terms {
+            state.set(format!("index:user_search:{}:{}:{}", user_id, term, code), String::new());
+        }
+        Ok(())
+    }
+
+    async fn remove_search_terms(&self, user_id: &str, code: &str, terms: &[String]) -> Result<(), AppError> {
+        let mut state = self.state.lock();
+        for term in terms {
+            state.remove(&format!("index:user_search:{}:{}:{}", user_id, term, code));
+        }
+        Ok(())
+    }
+
+    async fn search_codes(&self, user_id: &str, term: &str) -> Result<Vec<String>, AppError> {
+        let prefix = format!("index:user_search:{}:{}:", user_id, term);
+        Ok(self.state.lock().keys_with_prefix(&prefix).into_iter().map(|key| key[prefix.len()..].to_string()).collect())
+    }
+
+    async fn set_user(&self, user: &User) -> Result<(), AppError> {
+        let mut state = self.state.lock();
+        state.set(format!("user:{}", user.id), encode(user)?);
+        state.set(format!("user_email:{}", user.email), user.id.clone());
+        Ok(())
+    }
+
+    async fn get_user(&self, id_or_email: &str) -> Result<Option<User>, AppError> {
+        let now = self.now();
+        let state = self.state.lock();
+        let id = if id_or_email.contains('@') {
+            match state.get(&format!("user_email:{}", id_or_email), now) {
+                Some(id) => id.to_string(),
+                None => return Ok(None),
+            }
+        } else {
+            id_or_email.to_string()
+        };
+        state.get(&format!("user:{}", id), now).map(decode).transpose()
+    }
+
+    async fn delete_user_email(&self, email: &str) -> Result<(), AppError> {
+        self.state.lock().remove(&format!("user_email:{}", email));
+        Ok(())
+    }
+
+    async fn count_users(&self) -> Result<u64, AppError> {
+        Ok(self.state.lock().keys_with_prefix("user:").len() as u64)
+    }
+
+    async fn count_urls(&self, user_id: Option<&str>) -> Result<u64, AppError> {
+        let prefix = match user_id {
+            Some(uid) => format!("index:user_urls:{}:", uid),
+            None => "url:".to_string(),
+        };
+        Ok(self.state.lock().keys_with_prefix(&prefix).len() as u64)
+    }
+
+    async fn blacklist_token(&self, token: &str, expiry_secs: u64) -> Result<(), AppError> {
+        let expiry = self.now() + expiry_secs;
+        self.state.lock().set_until(format!("token:{}", token), String::new(), expiry);
+        Ok(())
+    }
+
+    async fn is_token_blacklisted(&self, token: &str) -> Result<bool, AppError> {
+        let now = self.now();
+        Ok(self.state.lock().get(&format!("token:{}", token), now).is_some())
+    }
+
+    async fn add_session(&self, user_id: &str, session: &Session, ttl_seconds: u64) -> Result<(), AppError> {
+        let expiry = self.now() + ttl_seconds;
+        self.state.lock().set_until(format!("session:{}:{}", user_id, session.jti), encode(session)?, expiry);
+        Ok(())
+    }
+
+    async fn list_sessions(&self, user_id: &str) -> Result<Vec<Session>, AppError> {
+        let now = self.now();
+        let state = self.state.lock();
+        state
+            .keys_with_prefix(&format!("session:{}:", user_id))
+            .iter()
+            .filter_map(|key| state.get(key, now))
+            .map(decode)
+            .collect()
+    }
+
+    async fn remove_sessions(&self, user_id: &str) -> Result<Vec<Session>, AppError> {
+        let sessions = self.list_sessions(user_id).await?;
+        let mut state = self.state.lock();
+        for key in state.keys_with_prefix(&format!("session:{}:", user_id)) {
+            state.remove(&key);
+        }
+        Ok(sessions)
+    }
+
+    async fn remove_session(&self, user_id: &str, jti: &str) -> Result<(), AppError> {
+        self.state.lock().remove(&format!("session:{}:{}", user_id, jti));
+        Ok(())
+    }
+
+    async fn record_login_failure(&self, subject: &str, window_secs: u64) -> Result<u64, AppError> {
+        let now = self.now();
+        let key = format!("login_failures:{}", subject);
+        let mut state = self.state.lock();
+        let count = state.get(&key, now).map(decode::<u64>).transpose()?.unwrap_or(0) + 1;
+        // Like Dragonfly's INCR + EXPIRE NX: the window starts with the first failure
+        let expiry = state.values.get(&key).and_then(|(_, expiry)| *expiry).filter(|&expiry| expiry > now);
+        state.set_until(key, count.to_string(), expiry.unwrap_or(now + window_secs));
+        Ok(count)
+    }
+
+    async fn clear_login_failures(&self, subject: &str) -> Result<(), AppError> {
+        self.state.lock().remove(&format!("login_failures:{}", subject));
+        Ok(())
+    }
+
+    async fn lock_account(&self, subject: &str, until: u64, ttl_seconds: u64) -> Result<(), AppError> {
+        let expiry = self.now() + ttl_seconds;
+        self.state.lock().set_until(format!("lockout:{}", subject), until.to_string(), expiry);
+        Ok(())
+    }
+
+    async fn get_account_lock(&self, subject: &str) -> Result<Option<u64>, AppError> {
+        let now = self.now();
+        Ok(self
+            .state
+            .lock()
+            .get(&format!("lockout:{}", subject), now)
+            .and_then(|until| until.parse().ok())
+            .filter(|&until| until > now))
+    }
+
+    async fn is_global_admin(&self, email: &str) -> Result<bool, AppError> {
+        Ok(self.global_admins.iter().any(|admin| admin == email))
+    }
+
+    async fn reserve_codes(&self, user_id: &str, codes: &[String], ttl_seconds: u64) -> Result<(), AppError> {
+        let now = self.now();
+        let mut state = self.state.lock();
+        if let Some(code) = codes.iter().find(|code| state.get(&format!("reserved:{}", code), now).is_some()) {
+            return Err(AppError::Conflict(format!("Code {} is already reserved", code)));
+        }
+        for code in codes {
+            state.set_until(format!("reserved:{}", code), user_id.to_string(), now + ttl_seconds);
+        }
+        Ok(())
+    }
+
+    async fn get_code_reservation(&self, code: &str) -> Result<Option<String>, AppError> {
+        let now = self.now();
+        Ok(self.state.lock().get(&format!("reserved:{}", code), now).map(str::to_owned))
+    }
+
+    async fn release_code_reservation(&self, code: &str) -> Result<(), AppError> {
+        self.state.lock().remove(&format!("reserved:{}", code));
+        Ok(())
+    }
+
+    async fn set_reserved_alias(&self, alias: &str, reserved: bool) -> Result<(), AppError> {
+        self.state.lock().set(format!("reserved_alias:{}", alias), reserved.to_string());
+        Ok(())
+    }
+
+    async fn get_reserved_alias_overrides(&self) -> Result<HashMap<String, bool>, AppError> {
+        let now = self.now();
+        let state = self.state.lock();
+        let prefix = "reserved_alias:";
+        Ok(state
+            .keys_with_prefix(prefix)
+            .into_iter()
+            .map(|key| {
+                let reserved = state.get(&key, now) == Some("true");
+                (key[prefix.len()..].to_string(), reserved)
+            })
+            .collect())
+    }
+
+    async fn burn_code(&self, code: &str, burned_at: u64) -> Result<bool, AppError> {
+        let key = format!("burned:{}", code);
+        let mut state = self.state.lock();
+        if state.values.contains_key(&key) {
+            return Ok(false);
+        }
+        state.set(key, burned_at.to_string());
+        state.remove(code);
+        Ok(true)
+    }
+
+    async fn is_code_burned(&self, code: &str) -> Result<bool, AppError> {
+        Ok(self.state.lock().values.contains_key(&format!("burned:{}", code)))
+    }
+
+    async fn next_rotation_cursor(&self, code: &str) -> Result<u64, AppError> {
+        let key = format!("rotator:{}", code);
+        let mut state = self.state.lock();
+        let cursor = state.values.get(&key).and_then(|(value, _)| value.parse::<u64>().ok()).unwrap_or(0) + 1;
+        state.set(key, cursor.to_string());
+        Ok(cursor)
+    }
+
+    async fn incr_click_count(&self, code: &str) -> Result<u64, AppError> {
+        let key = format!("clicks:{}", code);
+        let mut state = self.state.lock();
+        let count = state.values.get(&key).and_then(|(value, _)| value.parse::<u64>().ok()).unwrap_or(0) + 1;
+        state.set(key, count.to_string());
+        Ok(count)
+    }
+
+    async fn add_report(&self, report: &AbuseReport) -> Result<(), AppError> {
+        let mut state = self.state.lock();
+        state.set(format!("report:{}", report.id), encode(report)?);
+        state.set(format!("code_reports:{}:{}", report.code, report.id), report.id.clone());
+        Ok(())
+    }
+
+    async fn list_reports(&self, page: u64, per_page: u64) -> Result<Paginate<AbuseReport>, AppError> {
+        let state = self.state.lock();
+        let mut reports = state
+            .keys_with_prefix("report:")
+            .iter()
+            .map(|key| decode::<AbuseReport>(&state.values[key].0))
+            .collect::<Result<Vec<_>, _>>()?;
+        reports.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(paginate(reports, page, per_page))
+    }
+
+    async fn resolve_reports(&self, code: &str) -> Result<u64, AppError> {
+        let mut state = self.state.lock();
+        let index_keys = state.keys_with_prefix(&format!("code_reports:{}:", code));
+        for key in &index_keys {
+            let id = state.values[key].0.clone();
+            state.remove(&format!("report:{}", id));
+            state.remove(key);
+        }
+        Ok(index_keys.len() as u64)
+    }
+
+    async fn add_notification(&self, user_id: &str, notification: &Notification) -> Result<(), AppError> {
+        let mut state = self.state.lock();
+        let sequence = state.next_sequence();
+        state.set(format!("notifications:{}:{:020}", user_id, sequence), encode(notification)?);
+        Ok(())
+    }
+
+    async fn list_notifications(&self, user_id: &str, limit: u64) -> Result<Vec<Notification>, AppError> {
+        let state = self.state.lock();
+        state
+            .keys_with_prefix(&format!("notifications:{}:", user_id))
+            .iter()
+            .rev()
+            .take(limit.clamp(1, 100) as usize)
+            .map(|key| decode(&state.values[key].0))
+            .collect()
+    }
+
+    async fn set_api_key(&self, key: &ApiKey) -> Result<(), AppError> {
+        let mut state = self.state.lock();
+        state.set(format!("apikey:{}", key.id), encode(key)?);
+        state.set(format!("apikeys:{}:{}", key.user_id, key.id), key.id.clone());
+        Ok(())
+    }
+
+    async fn get_api_key(&self, id: &str) -> Result<Option<ApiKey>, AppError> {
+        let now = self.now();
+        self.state.lock().get(&format!("apikey:{}", id), now).map(decode).transpose()
+    }
+
+    async fn list_api_keys(&self, user_id: &str) -> Result<Vec<ApiKey>, AppError> {
+        let state = self.state.lock();
+        state
+            .keys_with_prefix(&format!("apikeys:{}:", user_id))
+            .iter()
+            .filter_map(|key| state.values.get(&format!("apikey:{}", state.values[key].0)))
+            .map(|(value, _)| decode(value))
+            .collect()
+    }
+
+    async fn delete_api_key(&self, user_id: &str, id: &str) -> Result<bool, AppError> {
+        let mut state = self.state.lock();
+        let removed = state.remove(&format!("apikeys:{}:{}", user_id, id));
+        if removed {
+            state.remove(&format!("apikey:{}", id));
+        }
+        Ok(removed)
+    }
+
+    async fn set_signing_secret(&self, user_id: &str, secret: &str) -> Result<(), AppError> {
+        self.state.lock().set(format!("signing_secret:{}", user_id), secret.to_string());
+        Ok(())
+    }
+
+    async fn get_signing_secret(&self, user_id: &str) -> Result<Option<String>, AppError> {
+        let now = self.now();
+        Ok(self.state.lock().get(&format!("signing_secret:{}", user_id), now).map(str::to_owned))
+    }
+
+    async fn set_campaign(&self, campaign: &Campaign) -> Result<(), AppError> {
+        let mut state = self.state.lock();
+        state.set(format!("campaign:{}", campaign.id), encode(campaign)?);
+        state.set(format!("campaigns:{}:{}", campaign.user_id, campaign.id), campaign.id.clone());
+        Ok(())
+    }
+
+    async fn get_campaign(&self, id: &str) -> Result<Option<Campaign>, AppError> {
+        let now = self.now();
+        self.state.lock().get(&format!("campaign:{}", id), now).map(decode).transpose()
+    }
+
+    async fn list_campaigns(&self, user_id: &str) -> Result<Vec<Campaign>, AppError> {
+        let state = self.state.lock();
+        state
+            .keys_with_prefix(&format!("campaigns:{}:", user_id))
+            .iter()
+            .filter_map(|key| state.values.get(&format!("campaign:{}", state.values[key].0)))
+            .map(|(value, _)| decode(value))
+            .collect()
+    }
+
+    async fn add_audit_event(&self, event: &AuditEvent) -> Result<(), AppError> {
+        let mut state = self.state.lock();
+        let sequence = state.next_sequence();
+        state.set(format!("audit:{:020}", sequence), encode(event)?);
+        Ok(())
+    }
+
+    async fn list_audit_events(&self, limit: u64) -> Result<Vec<AuditEvent>, AppError> {
+        let state = self.state.lock();
+        state
+            .keys_with_prefix("audit:")
+            .iter()
+            .rev()
+            .take(limit.clamp(1, 1000) as usize)
+            .map(|key| decode(&state.values[key].0))
+            .collect()
+    }
+
+    async fn incr_usage(&self, counts: Vec<(String, String, u64)>, ttl_seconds: u64) -> Result<(), AppError> {
+        let now = self.now();
+        let mut state = self.state.lock();
+        for (key, field, count) in counts {
+            // Kept as JSON under its own expiry, which the first increment sets
+            let (mut totals, expiry) = match state.values.get(&key).filter(|(_, expiry)| expiry.is_none_or(|e| e > now)) {
+                Some((json, expiry)) => (decode::<HashMap<String, u64>>(json)?, expiry.unwrap_or(now + ttl_seconds)),
+                None => (HashMap::new(), now + ttl_seconds),
+            };
+            *totals.entry(field).or_default() += count;
+            state.set_until(key, encode(&totals)?, expiry);
+        }
+        Ok(())
+    }
+
+    async fn get_usage(&self, key: &str) -> Result<HashMap<String, u64>, AppError> {
+        let state = self.state.lock();
+        state.get(key, self.now()).map_or_else(|| Ok(HashMap::new()), decode)
+    }
+
+    async fn eval_lua(&self, _script: &str, _keys: Vec<String>, _args: Vec<String>) -> Result<i64, AppError> {
+        Err(AppError::Internal("Lua scripting not supported in MockStorage".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{url_data, MockClock};
+
+    #[tokio::test]
+    async fn expiries_follow_the_injected_clock() {
+        let clock = MockClock::new(chrono::Utc::now());
+        let storage = MockStorage::new().with_clock(Arc::new(Clone::clone(&clock)));
+
+        storage.set_ex("abc", &encode(&url_data("https://example.com")).unwrap(), 60).await.unwrap();
+        storage.reserve_codes("user-1", &["xyz".to_string()], 60).await.unwrap();
+        assert!(storage.get("abc").await.is_ok());
+        assert!(storage.reserve_codes("user-2", &["xyz".to_string()], 60).await.is_err());
+
+        clock.advance(chrono::Duration::seconds(61));
+        assert!(matches!(storage.get("abc").await, Err(AppError::NotFound(_))));
+        assert_eq!(storage.get_code_reservation("xyz").await.unwrap(), None);
+        assert!(storage.burn_code("abc", 1).await.unwrap());
+        assert!(!storage.burn_code("abc", 2).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn conflicting_reservation_batches_reserve_nothing() {
+        let storage = MockStorage::new();
+        storage.reserve_codes("user-1", &["b".to_string()], 60).await.unwrap();
+        let batch = ["a".to_string(), "b".to_string(), "c".to_string()];
+        assert!(matches!(storage.reserve_codes("user-2", &batch, 60).await, Err(AppError::Conflict(_))));
+        assert_eq!(storage.get_code_reservation("a").await.unwrap(), None);
+        assert_eq!(storage.get_code_reservation("b").await.unwrap().as_deref(), Some("user-1"));
+        assert_eq!(storage.get_code_reservation("c").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn admin_deletes_clear_the_owners_indexes() {
+        let storage = MockStorage::new().with_global_admins(vec!["admin@example.com".into()]);
+        let tags = vec!["work".to_string()];
+        let owned = UrlData { user_id: Some("owner".into()), tags: tags.clone(), ..url_data("https://example.com") };
+        storage.set_ex("abc", &encode(&owned).unwrap(), 3600).await.unwrap();
+        storage.pin_url("owner", "abc", 1).await.unwrap();
+        storage.tag_url("owner", "abc", &tags).await.unwrap();
+        let terms: Vec<String> = search_terms("abc", &owned).into_iter().collect();
+        storage.index_search_terms("owner", "abc", &terms).await.unwrap();
+
+        storage.delete_url("abc", Some("admin"), "admin@example.com").await.unwrap();
+        assert!(storage.list_pinned("owner").await.unwrap().is_empty());
+        assert!(storage.list_tagged_codes("owner", "work").await.unwrap().is_empty());
+        assert!(storage.search_codes("owner", "example").await.unwrap().is_empty());
+    }
+}
@@ -25,6 +25,57 @@ pub struct AuthResponse {
     pub is_admin: bool,
 }
 
+// Profile returned by /v1/me; never includes the password hash
+#[derive(Clone, Debug, Serialize)]
+pub struct UserProfile {
+    pub id: String,
+    pub username: String,
+    pub email: String,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub utm: Option<UtmDefaults>, // Template for all of the user's links, see PUT /v1/me/utm
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_email: Option<String>, // Requested address awaiting POST /v1/me/email/verify
+}
+
+impl From<User> for UserProfile {
+    fn from(user: User) -> Self {
+        Self { id: user.id, username: user.username, email: user.email, created_at: user.created_at, utm: user.utm, pending_email: None }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Validate)]
+pub struct UpdateProfileRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub username: Option<String>,
+    #[validate(email)]
+    pub email: Option<String>,
+    #[validate(length(min = 8, max = 100))]
+    pub current_password: String, // Re-verifies the caller before identity changes
+}
+
+#[derive(Clone, Debug, Deserialize, Validate)]
+pub struct VerifyEmailRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub token: String, // Delivered to the new address by the `on_email_verification` hook
+}
+
+/// What a verification token stands for, stored under `email_verification:{token}`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailVerification {
+    pub user_id: String,
+    pub email: String,
+    pub previous_email: String, // The token only applies while the account still has this address
+}
+
+#[derive(Clone, Debug, Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    #[validate(length(min = 8, max = 100))]
+    pub current_password: String,
+    #[validate(length(min = 8, max = 100))]
+    pub new_password: String,
+}
+
 // Delete account request
 #[derive(Clone, Debug, Deserialize, Serialize, Validate)]
 pub struct DeleteAccountRequest {
@@ -33,33 +84,487 @@ pub struct DeleteAccountRequest {
     pub password: String,
 }
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{collections::{BTreeMap, HashMap}, sync::Arc};
 use validator::Validate;
-use crate::validator::{validate_url, validate_custom_alias, validate_rfc3339_date};
+use crate::clock::Clock;
+use crate::errors::ErrorCode;
+use crate::validator::{validate_api_key_scopes, validate_url, validate_custom_alias, validate_rfc3339, validate_rfc3339_date, validate_rotation, validate_schedule, validate_variants, validate_redirect_status, validate_tags};
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
+#[validate(context = "Arc<dyn Clock>")]
 pub struct ShortenRequest {
     #[validate(url, custom(function = "validate_url"))]
     pub url: String,
     #[validate(length(min = 1, max = 20), custom(function = "validate_custom_alias"))]
     pub custom_alias: Option<String>,
-    #[validate(custom(function = "validate_rfc3339_date"))]
+    #[validate(custom(function = "validate_rfc3339_date", use_context))]
     pub expiration_date: Option<String>,
+    pub captcha_token: Option<String>, // hCaptcha/Turnstile response, required for anonymous or flagged callers
+    pub privacy_mode: Option<bool>, // Record clicks without geo or device dimensions
+    #[validate(nested)]
+    pub open_graph: Option<OpenGraph>,
+    pub burn_after_read: Option<bool>, // One-time link, destroyed by its first redirect
+    #[validate(range(min = 1))]
+    pub max_clicks: Option<u64>, // Redirects allowed in total; 410 afterwards
+    #[validate(custom(function = "validate_rfc3339"))]
+    pub active_from: Option<String>, // 404 until then
+    #[validate(custom(function = "validate_rfc3339_date", use_context))]
+    pub active_until: Option<String>, // 410 from then on, like expiration_date but kept on record
+    #[validate(nested, custom(function = "validate_schedule"))]
+    pub schedule: Option<Vec<ScheduledDestination>>, // Landing pages that take over from url at set times
+    #[validate(custom(function = "validate_rotation"))]
+    pub rotation: Option<Vec<String>>, // Mirrors served in turn alongside url
+    #[validate(nested, custom(function = "validate_variants"))]
+    pub variants: Option<Vec<SplitVariant>>, // A/B destinations that share the traffic by weight instead of url
+    pub split_mode: Option<SplitMode>, // How a variant is picked, defaults to sticky
+    #[validate(nested)]
+    pub utm: Option<UtmDefaults>, // Added to the destination on redirect unless it already sets them
+    pub forward_query: Option<bool>, // Pass the redirect's own query parameters on to the destination
+    #[validate(custom(function = "validate_redirect_status"))]
+    pub redirect_status: Option<u16>, // 301, 302, 307 or 308; defaults to redirects.default_status
+    #[validate(url, custom(function = "validate_url"))]
+    pub fallback_url: Option<String>, // Used while url is down
+    #[validate(range(min = 1, max = 1_000_000))]
+    pub max_redirects_per_minute: Option<u64>,
+    pub signed: Option<bool>, // Redirects need `exp` and `sig` signed with the owner's secret
+    #[validate(custom(function = "validate_tags"))]
+    pub tags: Option<Vec<String>>, // Labels to file the link under; "a/b" nests b in folder a
+}
+
+// GET /v1/quick; `key` is read by the auth middleware, not the handler
+#[derive(Debug, Deserialize)]
+pub struct QuickShortenQuery {
+    pub url: String,
 }
 
+// Query pair on signed links, see services::link_signing
+#[derive(Debug, Default, Deserialize)]
+pub struct SignedLinkQuery {
+    pub exp: Option<i64>, // Unix seconds
+    pub sig: Option<String>,
+}
+
+// GET /v1/redirect/{code} with `Accept: application/json`
+#[derive(Debug, Default, Deserialize)]
+pub struct ResolveQuery {
+    #[serde(default)]
+    pub count: bool, // Record the lookup as a click, as a redirect would
+}
+
+// What a short link resolves to, for clients that asked for JSON instead of a redirect
 #[derive(Debug, Serialize)]
+pub struct ResolvedLink {
+    pub code: String,
+    pub long_url: String,
+    pub destination: String, // Where a redirect would go now: scheduled page or fallback, UTM templates applied
+    pub created_at: String, // ISO 8601
+    pub expires_at: Option<String>, // ISO 8601
+    pub health_status: Option<String>,
+    pub dead: bool,
+    pub burn_after_read: bool,
+    pub max_clicks: Option<u64>,
+    pub active_from: Option<String>, // ISO 8601
+    pub active_until: Option<String>, // ISO 8601
+    pub variant: Option<String>, // A/B variant the destination came from
+    pub signed: bool,
+    pub trial: bool,
+    pub campaign_id: Option<String>,
+    pub open_graph: Option<OpenGraph>,
+    pub counted: bool, // Whether this lookup was recorded as a click
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct ShortenResponse {
     pub short_url: String, // e.g., "https://api.hyperlinkr.com/abc123"
     pub code: String, // e.g., "abc123"
     pub expiration_date: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, bincode::Encode, bincode::Decode)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ReserveCodesRequest {
+    #[validate(range(min = 1, max = 10000))]
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReserveCodesResponse {
+    pub codes: Vec<String>,
+    pub expires_at: String, // ISO 8601, reservation lapses after this
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct BlocklistEntryRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub pattern: String, // e.g., "spam.example" or "*.tk"
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlocklistResponse {
+    pub patterns: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReservedAliasRequest {
+    #[validate(length(min = 1, max = 20), custom(function = "validate_custom_alias"))]
+    pub alias: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReservedAliasesResponse {
+    pub aliases: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct BrokenLinksReport {
+    pub scanned_at: Option<String>, // ISO 8601, None until the first scan finishes
+    pub links: Vec<BrokenLink>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BrokenLink {
+    pub code: String,
+    pub long_url: String,
+    pub user_id: Option<String>,
+    pub status: String, // "404", "410", "nxdomain", "timeout", "ssl_error", "5xx" or "connect_error"
+    pub dead: bool,
+    pub checked_at: Option<String>, // ISO 8601, when the status last changed
+}
+
+#[derive(Debug, Serialize)]
+pub struct CircuitBreakerStatus {
+    pub breaker: String, // "cache", "rate_limit" or "analytics"
+    pub nodes: Vec<crate::services::cache::circuit_breaker::NodeStatus>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CircuitBreakerActionRequest {
+    #[validate(length(min = 1))]
+    pub breaker: Option<String>, // Omit to apply to every breaker
+    #[validate(length(min = 1))]
+    pub node: String, // As reported by GET /v1/admin/circuit-breakers
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct AddNodeRequest {
+    #[validate(url)]
+    pub url: String, // e.g. "redis://dragonfly-4:6379"
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RemoveNodeRequest {
+    #[validate(length(min = 1))]
+    pub node: String, // As reported by GET /v1/admin/circuit-breakers
+}
+
+#[derive(Debug, Serialize)]
+pub struct NodeChangeResponse {
+    pub node: Option<String>, // Node URL with credentials removed; None for a rebalance
+    pub keys_moved: u64,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct LogLevelRequest {
+    #[validate(length(min = 1, max = 1024))]
+    pub filter: String, // EnvFilter directives, e.g. "info,hyperlinkr::services::cache=debug"
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogLevelResponse {
+    pub filter: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheTierStats {
+    pub tier: String, // "l1", "l2", "bloom", "dragonfly" or "sled"
+    pub hits: u64,
+    pub misses: u64,
+    pub inserts: u64,
+    pub evictions: u64,
+    pub hit_ratio: Option<f64>, // hits / (hits + misses), None before the first lookup
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheStatsResponse {
+    pub tiers: Vec<CacheTierStats>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CacheWarmupRequest {
+    #[validate(length(min = 1, max = 256))]
+    pub pattern: Option<String>, // Glob over link codes, e.g. "launch*"
+    #[validate(length(min = 1, max = 10000))]
+    pub codes: Option<Vec<String>>,
+    #[validate(range(min = 1, max = 100000))]
+    pub limit: Option<usize>, // Most links a pattern warms, defaults to 10k
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheWarmupResponse {
+    pub keys: usize, // Links queued for warming
+    pub keys_per_sec: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BloomRebuildStatus {
+    pub state: String, // "idle", "running", "completed" or "failed"
+    pub keys_inserted: u64,
+    pub started_at: Option<String>, // ISO 8601
+    pub finished_at: Option<String>, // ISO 8601
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ReportRequest {
+    #[validate(length(min = 1, max = 50))]
+    pub reason: String, // e.g., "phishing", "malware", "spam"
+    #[validate(length(max = 2000))]
+    pub details: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, bincode::Encode, bincode::Decode)]
+pub struct AbuseReport {
+    pub id: String, // CUID
+    pub code: String,
+    pub reason: String,
+    pub details: Option<String>,
+    pub reporter_ip: Option<String>,
+    pub created_at: String, // ISO 8601
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct DisableLinkRequest {
+    #[validate(length(min = 1, max = 500))]
+    pub reason: String, // Shown to the owner in their notification
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetPlanRequest {
+    pub plan: Option<String>, // One of quota.plans; None puts the user back on the default plan
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, bincode::Encode, bincode::Decode)]
+pub struct Notification {
+    pub id: String, // CUID
+    pub code: Option<String>, // Link the notice is about, if any
+    pub message: String,
+    pub created_at: String, // ISO 8601
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PageQuery {
+    pub page: Option<u64>, // Defaults to 1
+    pub per_page: Option<u64>, // Defaults to 20, capped at 100
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UrlFilterQuery {
+    pub tag: Option<String>, // Only links carrying this exact tag
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String, // Whitespace-separated words, matched case-insensitively
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, bincode::Encode, bincode::Decode)]
 pub struct UrlData {
     pub long_url: String,
     pub user_id: Option<String>, // CUID, None for anonymous
     pub created_at: String, // ISO 8601
     pub expires_at: Option<String>, // ISO 8601
+    #[serde(default)]
+    pub reputation: Option<String>, // Safe Browsing verdict at shorten time, e.g. "clean", "MALWARE"
+    #[serde(default)]
+    pub quarantined: bool, // Flagged destination kept for review but never redirected to
+    #[serde(default)]
+    pub disabled_at: Option<String>, // ISO 8601, set when a moderator tombstones the link
+    #[serde(default)]
+    pub disabled_reason: Option<String>,
+    #[serde(default)]
+    pub health_status: Option<String>, // Last destination scan result, e.g. "ok", "404", "nxdomain", "timeout", "ssl_error"
+    #[serde(default)]
+    pub health_checked_at: Option<String>, // ISO 8601
+    #[serde(default)]
+    pub dead: bool, // Destination confirmed gone by the health scanner
+    #[serde(default)]
+    pub privacy_mode: bool, // Clicks skip geo lookup and UA parsing
+    #[serde(default)]
+    pub campaign_id: Option<String>, // Campaign whose UTM defaults apply on redirect
+    #[serde(default)]
+    pub open_graph: Option<OpenGraph>, // Served to link-preview bots instead of the redirect
+    #[serde(default)]
+    pub history: Vec<DestinationChange>, // Previous destinations, oldest first
+    #[serde(default)]
+    pub burn_after_read: bool, // Deleted by its first redirect; 410 afterwards
+    #[serde(default)]
+    pub rotation: Vec<String>, // Mirrors cycled round-robin with long_url, one per redirect
+    #[serde(default)]
+    pub fallback_url: Option<String>, // Served instead of long_url while the health scanner has it marked dead
+    #[serde(default)]
+    pub max_redirects_per_minute: Option<u64>, // Owner-set cap across all visitors
+    #[serde(default)]
+    pub signed: bool, // Only reachable through URLs signed with the owner's signing secret
+    #[serde(default)]
+    pub pending_transfer: Option<PendingTransfer>, // Offered to another account, awaiting their acceptance
+    #[serde(default)]
+    pub pinned: bool, // Listed ahead of the owner's other links; order lives in the owner's pinned set
+    #[serde(default)]
+    pub trial: bool, // Created anonymously under `trial`; always expires and never has a custom alias
+    #[serde(default)]
+    pub max_clicks: Option<u64>, // Redirects allowed in total, counted in storage under `clicks:<code>`
+    #[serde(default)]
+    pub active_from: Option<String>, // ISO 8601; not found before this
+    #[serde(default)]
+    pub active_until: Option<String>, // ISO 8601; gone from this on
+    #[serde(default)]
+    pub schedule: Vec<ScheduledDestination>, // Sorted by starts_at; the latest one started replaces long_url
+    #[serde(default)]
+    pub variants: Vec<SplitVariant>, // Replace long_url on redirect, one picked per visit by weight
+    #[serde(default)]
+    pub split_mode: SplitMode,
+    #[serde(default)]
+    pub utm: Option<UtmDefaults>, // Takes precedence over the campaign's and the owner's UTM templates
+    #[serde(default)]
+    pub forward_query: bool, // Query parameters sent to the short URL are copied onto the destination
+    #[serde(default)]
+    pub redirect_status: Option<u16>, // Overrides redirects.default_status, e.g. 301 for permanent SEO links
+    #[serde(default = "active")]
+    pub is_active: bool, // Switched off by the owner; redirects answer 410 but data and analytics stay
+    #[serde(default)]
+    pub tags: Vec<String>, // Mirrored in the owner's `user_tag:{uid}:{tag}` indexes
+}
+
+fn active() -> bool {
+    true
+}
+
+// Spelled out because a derived default would create every link switched off
+impl Default for UrlData {
+    fn default() -> Self {
+        Self {
+            long_url: Default::default(),
+            user_id: Default::default(),
+            created_at: Default::default(),
+            expires_at: Default::default(),
+            reputation: Default::default(),
+            quarantined: Default::default(),
+            disabled_at: Default::default(),
+            disabled_reason: Default::default(),
+            health_status: Default::default(),
+            health_checked_at: Default::default(),
+            dead: Default::default(),
+            privacy_mode: Default::default(),
+            campaign_id: Default::default(),
+            open_graph: Default::default(),
+            history: Default::default(),
+            burn_after_read: Default::default(),
+            rotation: Default::default(),
+            fallback_url: Default::default(),
+            max_redirects_per_minute: Default::default(),
+            signed: Default::default(),
+            pending_transfer: Default::default(),
+            pinned: Default::default(),
+            trial: Default::default(),
+            max_clicks: Default::default(),
+            active_from: Default::default(),
+            active_until: Default::default(),
+            schedule: Default::default(),
+            variants: Default::default(),
+            split_mode: Default::default(),
+            utm: Default::default(),
+            forward_query: Default::default(),
+            redirect_status: Default::default(),
+            is_active: true,
+            tags: Default::default(),
+        }
+    }
+}
+
+// A landing page that a link switches to at `starts_at`, until the next one starts
+#[derive(Clone, Debug, Serialize, Deserialize, Validate, bincode::Encode, bincode::Decode)]
+pub struct ScheduledDestination {
+    #[validate(url, custom(function = "validate_url"))]
+    pub url: String,
+    #[validate(custom(function = "validate_rfc3339"))]
+    pub starts_at: String, // ISO 8601
+}
+
+// One arm of an A/B split; clicks on it are counted under its name as well as the link's
+#[derive(Clone, Debug, Serialize, Deserialize, Validate, bincode::Encode, bincode::Decode)]
+pub struct SplitVariant {
+    #[validate(length(min = 1, max = 20), custom(function = "validate_custom_alias"))]
+    pub name: String,
+    #[validate(url, custom(function = "validate_url"))]
+    pub url: String,
+    #[validate(range(min = 1, max = 10_000))]
+    pub weight: u32, // Share of the traffic relative to the other variants' weights
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, bincode::Encode, bincode::Decode)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitMode {
+    /// The same visitor IP always lands on the same variant
+    #[default]
+    Sticky,
+    /// Every redirect draws a variant afresh
+    Random,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, bincode::Encode, bincode::Decode)]
+pub struct DestinationChange {
+    pub url: String,
+    pub replaced_at: String, // ISO 8601
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[validate(context = "Arc<dyn Clock>")]
+pub struct UpdateDestinationRequest {
+    #[validate(url, custom(function = "validate_url"))]
+    pub url: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    #[validate(custom(function = "validate_rfc3339_date", use_context))]
+    pub expiration_date: Option<Option<String>>, // Absent leaves the expiry alone; null removes it
+    #[validate(custom(function = "validate_tags"))]
+    pub tags: Option<Vec<String>>, // Replaces the link's tags; [] clears them
+}
+
+// Tells a field sent as null (Some(None)) apart from one left out (None)
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, bincode::Encode, bincode::Decode)]
+pub struct PendingTransfer {
+    pub to_user_id: String,
+    pub requested_at: String, // ISO 8601
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct TransferRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub to: String, // Recipient's user id or email
+}
+
+#[derive(Debug, Serialize)]
+pub struct DestinationHistoryResponse {
+    pub code: String,
+    pub current: String,
+    pub history: Vec<DestinationChange>,
+}
+
+// Owner-supplied preview card for chat apps and social feeds
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Validate, bincode::Encode, bincode::Decode)]
+pub struct OpenGraph {
+    #[validate(length(min = 1, max = 200))]
+    pub title: Option<String>,
+    #[validate(length(min = 1, max = 500))]
+    pub description: Option<String>,
+    #[validate(url, custom(function = "validate_url"))]
+    pub image: Option<String>,
 }
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, bincode::Encode, bincode::Decode)]
 #[serde(rename_all = "lowercase")]
@@ -77,14 +582,259 @@ pub struct User {
     pub email: String,
     pub password_hash: String,
     pub created_at: String, // ISO 8601
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<String>, // Quota plan, see `quota.plans`; the default plan when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utm: Option<UtmDefaults>, // Added to the destination of each of the user's links on redirect
 }
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, bincode::Encode, bincode::Decode)]
 pub struct AuthToken {
+    #[serde(rename = "sub")]
     pub user_id: Option<String>, // CUID, None for anonymous
     pub username: String,
     pub email: String,
-    pub expires_at: String, // ISO 8601
     pub is_admin: bool, // True if email in global_admins
+    pub exp: u64, // Unix seconds
+    pub iat: u64, // Unix seconds
+    pub jti: String, // CUID, unique per issued token
+    pub aud: String, // Deployment or tenant that issued it; verified tokens were issued here
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonator: Option<String>, // Admin user id when a support admin is acting as this user
+}
+
+// One issued token, tracked per user so sessions can be listed and revoked
+#[derive(Clone, Debug, Serialize, Deserialize, bincode::Encode, bincode::Decode)]
+pub struct Session {
+    pub jti: String,
+    pub issued_at: u64, // Unix seconds
+    pub expires_at: u64, // Unix seconds
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImpersonationResponse {
+    pub token: String,
+    pub user_id: String,
+    pub impersonator: String,
+    pub expires_at: u64, // Unix seconds
+}
+
+// Security-relevant action, kept for support and compliance review
+#[derive(Clone, Debug, Serialize, Deserialize, bincode::Encode, bincode::Decode)]
+pub struct AuditEvent {
+    pub id: String, // CUID
+    pub actor_id: String, // User who performed the action
+    pub acting_as: Option<String>, // Impersonated user, if any
+    pub action: String, // e.g. "impersonate.start" or "POST /v1/shorten"
+    pub status: Option<u16>, // Response status for request events
+    pub ip: Option<String>,
+    pub created_at: String, // ISO 8601
+}
+
+// Long-lived credential for scripts and dashboards, limited to its scopes
+#[derive(Clone, Debug, Serialize, Deserialize, bincode::Encode, bincode::Decode)]
+pub struct ApiKey {
+    pub id: String, // CUID, also embedded in the key itself
+    pub user_id: String,
+    pub name: String,
+    pub key_hash: String, // SHA-256 hex of the full key; the key is only shown once
+    pub scopes: Vec<String>, // e.g. ["links:read", "analytics:read"]
+    pub created_at: String, // ISO 8601
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateApiKeyRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(min = 1), custom(function = "validate_api_key_scopes"))]
+    pub scopes: Vec<String>,
+}
+
+// ApiKey without its hash, for responses
+#[derive(Debug, Serialize)]
+pub struct ApiKeyInfo {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: String,
+}
+
+impl From<ApiKey> for ApiKeyInfo {
+    fn from(key: ApiKey) -> Self {
+        Self { id: key.id, name: key.name, scopes: key.scopes, created_at: key.created_at }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    pub days: Option<u64>, // Defaults to 30, capped at usage.retention_days
+}
+
+// Requests to one route that ended in one status
+#[derive(Debug, Serialize)]
+pub struct EndpointUsage {
+    pub endpoint: String, // Method and route template, e.g. "POST /v1/shorten"
+    pub status: u16,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DailyUsage {
+    pub date: String, // UTC day, YYYY-MM-DD
+    pub total: u64,
+    pub endpoints: Vec<EndpointUsage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiKeyUsage {
+    pub id: String,
+    pub name: String,
+    pub days: Vec<DailyUsage>,
+}
+
+// How much of one per-user rate limit the current window has used
+#[derive(Debug, Serialize)]
+pub struct RateLimitUsage {
+    pub endpoint: String, // Limiter bucket: shorten, redirect or other
+    pub limit: u64,
+    pub used: u64,
+    pub remaining: u64,
+    pub window_seconds: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageReport {
+    pub user_id: String,
+    pub days: Vec<DailyUsage>,
+    pub api_keys: Vec<ApiKeyUsage>,
+    pub rate_limits: Vec<RateLimitUsage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiKeyCreatedResponse {
+    pub key: String, // Shown once
+    pub api_key: ApiKeyInfo,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SigningSecretResponse {
+    pub secret: String, // Replaces any previous secret, invalidating URLs it signed
+}
+
+// UTM parameters added to a link's destination unless it already sets them; set per link, per
+// campaign or per user
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Validate, bincode::Encode, bincode::Decode)]
+pub struct UtmDefaults {
+    #[validate(length(min = 1, max = 100))]
+    pub utm_source: Option<String>,
+    #[validate(length(min = 1, max = 100))]
+    pub utm_medium: Option<String>,
+    #[validate(length(min = 1, max = 100))]
+    pub utm_campaign: Option<String>,
+    #[validate(length(min = 1, max = 100))]
+    pub utm_term: Option<String>,
+    #[validate(length(min = 1, max = 100))]
+    pub utm_content: Option<String>,
+}
+
+impl UtmDefaults {
+    pub fn is_empty(&self) -> bool {
+        [&self.utm_source, &self.utm_medium, &self.utm_campaign, &self.utm_term, &self.utm_content]
+            .iter()
+            .all(|value| value.is_none())
+    }
+}
+
+// Named group of one user's links, reported on together
+#[derive(Clone, Debug, Serialize, Deserialize, bincode::Encode, bincode::Decode)]
+pub struct Campaign {
+    pub id: String, // CUID
+    pub user_id: String,
+    pub name: String,
+    pub codes: Vec<String>,
+    pub utm: UtmDefaults,
+    pub created_at: String, // ISO 8601
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateCampaignRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[serde(default)]
+    #[validate(length(max = 500))]
+    pub codes: Vec<String>,
+    #[serde(default)]
+    #[validate(nested)]
+    pub utm: UtmDefaults,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CampaignCodesRequest {
+    #[validate(length(min = 1, max = 500))]
+    pub codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CampaignCodeStats {
+    pub code: String,
+    pub clicks: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CampaignAnalyticsResponse {
+    pub campaign_id: String,
+    pub total_clicks: u64,
+    pub codes: Vec<CampaignCodeStats>,
+    pub daily: BTreeMap<String, u64>, // YYYY-MM-DD -> clicks across all codes
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DashboardLinkCounts {
+    pub total: u64,
+    pub active: u64, // Redirecting: not disabled, quarantined, dead or expired
+    pub pinned: u64,
+}
+
+// One link in GET /v1/urls
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LinkListItem {
+    pub code: String,
+    #[serde(flatten)]
+    pub url_data: UrlData,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DashboardLink {
+    pub code: String,
+    pub long_url: String,
+    pub created_at: String,
+    pub clicks_30d: u64,
+}
+
+// One line of the dashboard's activity feed, newest first
+#[derive(Debug, Serialize)]
+pub struct DashboardActivity {
+    pub kind: String, // "link_created" or "notification"
+    pub code: Option<String>,
+    pub message: String,
+    pub at: String, // ISO 8601
+}
+
+#[derive(Debug, Serialize)]
+pub struct DashboardResponse {
+    pub links: DashboardLinkCounts,
+    pub clicks_7d: u64,
+    pub clicks_30d: u64,
+    pub daily: BTreeMap<String, u64>, // YYYY-MM-DD -> clicks across all links, last 30 days
+    pub top_links: Vec<DashboardLink>, // By clicks over the last 30 days
+    pub recent_activity: Vec<DashboardActivity>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionsResponse {
+    pub current: Option<String>, // jti of the token making the request
+    pub sessions: Vec<Session>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
@@ -100,8 +850,10 @@ pub struct DeleteResponse {
 
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
+    pub code: ErrorCode,
     pub message: String,
-    pub details: Option<String>, // e.g., validation errors
+    pub details: Option<serde_json::Value>, // e.g., the failing fields, or a quota's limit and usage
+    pub request_id: Option<String>, // Same as the x-request-id response header, for support tickets
 }
 
 #[derive(Debug, Serialize)]
@@ -130,10 +882,11 @@ pub struct AnalyticsRequest {
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
+#[validate(context = "Arc<dyn Clock>")]
 pub struct AnalyticsFilters {
-    #[validate(custom(function = "validate_rfc3339_date"))]
+    #[validate(custom(function = "validate_rfc3339_date", use_context))]
     pub start_date: Option<String>, // ISO 8601
-    #[validate(custom(function = "validate_rfc3339_date"))]
+    #[validate(custom(function = "validate_rfc3339_date", use_context))]
     pub end_date: Option<String>, // ISO 8601
     #[validate(length(min = 1))]
     pub country: Option<String>, // e.g., "US", "IN"
@@ -167,4 +920,17 @@ pub struct Paginate<T> {
     pub per_page: u64,
     pub total_items: u64,
     pub total_pages: u64,
+}
+
+impl<T> Paginate<T> {
+    /// Page `page` of `total_items`, holding `items`. An empty listing still has one page.
+    pub fn new(items: Vec<T>, page: u64, per_page: u64, total_items: u64) -> Self {
+        Self {
+            items,
+            page,
+            per_page,
+            total_items,
+            total_pages: total_items.div_ceil(per_page).max(1),
+        }
+    }
 }
\ No newline at end of file
@@ -3,12 +3,25 @@ use regex::Regex;
 use validator::ValidationError;
 use once_cell::sync::Lazy;
 use chrono::{DateTime, Utc};
-use crate::clock::{Clock, SystemClock};
+use std::sync::Arc;
+use crate::clock::Clock;
+use crate::config::settings::Settings;
+use crate::services::api_keys::SCOPES;
+use crate::types::{ScheduledDestination, SplitVariant};
 
 static ALPHANUMERIC_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-zA-Z0-9]+$").unwrap());
 static MALICIOUS_URL_REGEX: Lazy<Regex> = Lazy::new(|| 
     Regex::new(r"(?i)^javascript:|^data:|<script|eval\(|onload=").unwrap()
 );
+const MAX_ROTATION_DESTINATIONS: usize = 20;
+const MAX_SCHEDULED_DESTINATIONS: usize = 50;
+const MAX_SPLIT_VARIANTS: usize = 10;
+const MAX_TAGS: usize = 20;
+// Slashes nest tags into folders, e.g. "clients/acme"
+static TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-zA-Z0-9_-]{1,32}(/[a-zA-Z0-9_-]{1,32})*$").unwrap());
+const DEFAULT_KNOWN_SHORTENERS: [&str; 10] = [
+    "bit.ly", "t.co", "tinyurl.com", "goo.gl", "ow.ly", "is.gd", "buff.ly", "rebrand.ly", "cutt.ly", "shorturl.at",
+];
 
 
 pub fn validate_email_list(emails: &Vec<String>) -> Result<(), ValidationError> {
@@ -26,6 +39,93 @@ pub fn validate_email_list(emails: &Vec<String>) -> Result<(), ValidationError>
     Ok(())
 }
 
+pub(crate) fn validate_rotation(urls: &[String]) -> Result<(), ValidationError> {
+    if urls.len() > MAX_ROTATION_DESTINATIONS {
+        let mut err = ValidationError::new("too_many_destinations");
+        err.add_param("max".into(), &MAX_ROTATION_DESTINATIONS);
+        return Err(err);
+    }
+    urls.iter().try_for_each(|url| validate_url(url))
+}
+
+pub(crate) fn validate_schedule(schedule: &[ScheduledDestination]) -> Result<(), ValidationError> {
+    if schedule.len() > MAX_SCHEDULED_DESTINATIONS {
+        let mut err = ValidationError::new("too_many_destinations");
+        err.add_param("max".into(), &MAX_SCHEDULED_DESTINATIONS);
+        return Err(err);
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_variants(variants: &[SplitVariant]) -> Result<(), ValidationError> {
+    if !(2..=MAX_SPLIT_VARIANTS).contains(&variants.len()) {
+        let mut err = ValidationError::new("invalid_variant_count");
+        err.add_param("min".into(), &2);
+        err.add_param("max".into(), &MAX_SPLIT_VARIANTS);
+        return Err(err);
+    }
+    // Clicks are counted per name, so two variants can't share one
+    if variants.iter().enumerate().any(|(i, v)| variants[..i].iter().any(|w| w.name == v.name)) {
+        return Err(ValidationError::new("duplicate_variant_name"));
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    if tags.len() > MAX_TAGS {
+        let mut err = ValidationError::new("too_many_tags");
+        err.add_param("max".into(), &MAX_TAGS);
+        return Err(err);
+    }
+    if let Some(tag) = tags.iter().find(|tag| tag.len() > 64 || !TAG_REGEX.is_match(tag)) {
+        let mut err = ValidationError::new("invalid_tag");
+        err.add_param("value".into(), tag);
+        return Err(err);
+    }
+    if tags.iter().enumerate().any(|(i, tag)| tags[..i].contains(tag)) {
+        return Err(ValidationError::new("duplicate_tag"));
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_redirect_status(status: u16) -> Result<(), ValidationError> {
+    if ![301, 302, 307, 308].contains(&status) {
+        let mut err = ValidationError::new("invalid_redirect_status");
+        err.add_param("value".into(), &status);
+        return Err(err);
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_captcha_provider(value: &str) -> Result<(), ValidationError> {
+    if !["hcaptcha", "turnstile"].contains(&value) {
+        let mut err = ValidationError::new("invalid_captcha_provider");
+        err.add_param("value".into(), &value);
+        return Err(err);
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_jwt_algorithm(value: &str) -> Result<(), ValidationError> {
+    if !["HS256", "RS256", "ES256"].contains(&value) {
+        let mut err = ValidationError::new("invalid_jwt_algorithm");
+        err.add_param("value".into(), &value);
+        return Err(err);
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_api_key_scopes(scopes: &Vec<String>) -> Result<(), ValidationError> {
+    for scope in scopes {
+        if !SCOPES.contains(&scope.as_str()) {
+            let mut err = ValidationError::new("invalid_scope");
+            err.add_param("value".into(), scope);
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
 pub fn validate_same_site(value: &str) -> Result<(), ValidationError> {
     if !["strict", "lax", "none"].contains(&value.to_lowercase().as_str()) {
         let mut err = ValidationError::new("invalid_same_site");
@@ -57,22 +157,35 @@ pub fn validate_url(url: &str) -> Result<(), ValidationError> {
     Ok(())
 }
 
+/// Returns true if `host` is one of our own domains or a known URL shortener, either of which can
+/// chain back to us and create a redirect loop.
+pub fn is_redirect_loop_host(host: &str, config: &Settings) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    let matches = |domain: &str| {
+        let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+        !domain.is_empty() && (host == domain || host.ends_with(&format!(".{}", domain)))
+    };
+
+    let base_host = url::Url::parse(&config.base_url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string));
+    if base_host.as_deref().is_some_and(matches) || matches(&config.security.domain) {
+        return true;
+    }
+    match &config.security.known_shorteners {
+        Some(shorteners) => shorteners.iter().any(|d| matches(d)),
+        None => DEFAULT_KNOWN_SHORTENERS.iter().any(|d| matches(d)),
+    }
+}
+
+/// Length and characters only; reserved aliases are configurable, so `ReservedAliases` checks those.
 pub fn validate_custom_alias(alias: &str) -> Result<(), ValidationError> {
-    static RESERVED_ALIASES: [&str; 16] = [
-        "home", "about", "contact", "help", "terms", "privacy", "login", "signup",
-        "dashboard", "settings", "profile", "admin", "api", "docs", "support", "blog"
-    ];
     let normalized = alias.trim().to_lowercase();
     if normalized.len() < 1 || normalized.len() > 20 {
         let mut err = ValidationError::new("invalid_alias_length");
         err.add_param("length".into(), &normalized.len());
         return Err(err);
     }
-    if RESERVED_ALIASES.contains(&normalized.as_str()) {
-        let mut err = ValidationError::new("alias_is_reserved");
-        err.add_param("alias".into(), &normalized);
-        return Err(err);
-    }
     if !ALPHANUMERIC_REGEX.is_match(&normalized) {
         let mut err = ValidationError::new("invalid_custom_alias");
         err.add_param("alias".into(), &normalized);
@@ -81,7 +194,17 @@ pub fn validate_custom_alias(alias: &str) -> Result<(), ValidationError> {
     Ok(())
 }
 
-pub fn validate_rfc3339_date(date: &str) -> Result<(), ValidationError> {
+/// Any well-formed RFC 3339 timestamp, past ones included.
+pub(crate) fn validate_rfc3339(date: &str) -> Result<(), ValidationError> {
+    DateTime::parse_from_rfc3339(date).map(|_| ()).map_err(|_e| {
+        let mut err = ValidationError::new("invalid_rfc3339_date");
+        err.add_param("value".into(), &date);
+        err
+    })
+}
+
+/// Takes the clock as validation context so expiry checks can run against a fixed time in tests.
+pub(crate) fn validate_rfc3339_date(date: &str, clock: &Arc<dyn Clock>) -> Result<(), ValidationError> {
     let parsed = DateTime::parse_from_rfc3339(date)
         .map_err(|_e| {
             let mut err = ValidationError::new("invalid_rfc3339_date");
@@ -89,7 +212,7 @@ pub fn validate_rfc3339_date(date: &str) -> Result<(), ValidationError> {
             err
         })?;
     let date_utc = parsed.with_timezone(&Utc);
-    let now = SystemClock.now();
+    let now = clock.now();
     if date_utc <= now {
         let mut err = ValidationError::new("date_must_be_in_future");
         err.add_param("date".into(), &date_utc.to_rfc3339());
@@ -98,3 +221,20 @@ pub fn validate_rfc3339_date(date: &str) -> Result<(), ValidationError> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::MockClock;
+
+    #[test]
+    fn expiry_is_checked_against_the_injected_clock() {
+        let now = DateTime::parse_from_rfc3339("2030-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let clock: Arc<dyn Clock> = Arc::new(MockClock::new(now));
+
+        assert!(validate_rfc3339_date("2030-01-02T00:00:00Z", &clock).is_ok());
+        let err = validate_rfc3339_date("2029-12-31T00:00:00Z", &clock).unwrap_err();
+        assert_eq!(err.code, "date_must_be_in_future");
+        assert!(validate_rfc3339_date("tomorrow", &clock).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+//! End-to-end tests against a real Dragonfly started with testcontainers.
+//!
+//! Needs a Docker daemon: `cargo test --features it --test it`.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::connect_info::MockConnectInfo,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use hyperlinkr::{
+    app::Builder,
+    config::{cache::CacheConfig, settings::Settings},
+    services::{
+        cache::circuit_breaker::CircuitBreaker,
+        replication::{self, LinkMutation},
+        storage::{dragonfly::DatabaseClient, storage::Storage},
+    },
+};
+use serde_json::{json, Value};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+use testcontainers::{
+    core::{IntoContainerPort, WaitFor},
+    runners::AsyncRunner,
+    ContainerAsync, GenericImage,
+};
+use tower::ServiceExt;
+
+const DRAGONFLY_PORT: u16 = 6379;
+
+async fn start_dragonfly() -> (ContainerAsync<GenericImage>, String) {
+    let container = GenericImage::new("docker.dragonflydb.io/dragonflydb/dragonfly", "latest")
+        .with_exposed_port(DRAGONFLY_PORT.tcp())
+        .with_wait_for(WaitFor::message_on_stderr("listening on port"))
+        .start()
+        .await
+        .expect("Failed to start Dragonfly; is Docker running?");
+    let host = container.get_host().await.unwrap();
+    let port = container.get_host_port_ipv4(DRAGONFLY_PORT).await.unwrap();
+    (container, format!("redis://{}:{}", host, port))
+}
+
+fn test_config(database_url: String) -> Settings {
+    let mut config = Settings::default();
+    config.database_urls = vec![database_url];
+    config.cache.use_sled = false;
+    config.cache.sled_flush_ms = 100; // Also the analytics flush interval
+    config
+}
+
+async fn router(database_url: String) -> Router {
+    router_with(test_config(database_url)).await
+}
+
+async fn router_with(config: Settings) -> Router {
+    let app = Builder::new(config).background_tasks(false).build().await.unwrap();
+    app.router.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))))
+}
+
+async fn client(url: &str) -> DatabaseClient {
+    let circuit_breaker = Arc::new(CircuitBreaker::new(vec![url.to_string()], 3, Duration::from_secs(1)));
+    DatabaseClient::connect(&[url.to_string()], &CacheConfig::default(), Vec::new(), circuit_breaker).await.unwrap()
+}
+
+fn shorten_request(url: &str) -> Request<Body> {
+    Request::post("/v1/shorten")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(json!({ "url": url }).to_string()))
+        .unwrap()
+}
+
+async fn json_body(response: axum::response::Response) -> Value {
+    serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn shorten_redirect_and_count_the_click() {
+    let (_dragonfly, url) = start_dragonfly().await;
+    let router = router(url).await;
+
+    let response = router.clone().oneshot(shorten_request("https://example.com/it")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let code = json_body(response).await["data"]["code"].as_str().unwrap().to_string();
+
+    let response = router
+        .clone()
+        .oneshot(Request::get(format!("/v1/redirect/{}", code)).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert!(response.status().is_redirection());
+    assert_eq!(response.headers()[header::LOCATION], "https://example.com/it");
+
+    // Clicks reach storage on the next analytics flush
+    let mut clicks = 0;
+    for _ in 0..50 {
+        let response = router
+            .clone()
+            .oneshot(Request::get(format!("/v1/analytics/{}", code)).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        clicks = json_body(response).await["data"]["analytics"].as_array().unwrap().len();
+        if clicks > 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(clicks, 1);
+}
+
+#[tokio::test]
+async fn unknown_codes_are_not_found() {
+    let (_dragonfly, url) = start_dragonfly().await;
+    let router = router(url).await;
+
+    let response = router
+        .oneshot(Request::get("/v1/redirect/nope123").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn links_replicate_to_the_peer_region() {
+    let (_primary, primary_url) = start_dragonfly().await;
+    let (_secondary, secondary_url) = start_dragonfly().await;
+    let mut config = test_config(primary_url);
+    config.replication.enabled = true;
+    config.replication.peers = vec![secondary_url.clone()];
+    let router = router_with(config).await;
+
+    let response = router.oneshot(shorten_request("https://example.com/replicated")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let code = json_body(response).await["data"]["code"].as_str().unwrap().to_string();
+
+    let peer = client(&secondary_url).await;
+    let mut replicated = None;
+    for _ in 0..50 {
+        replicated = peer.get(&code).await.ok();
+        if replicated.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(replicated.unwrap().contains("https://example.com/replicated"));
+}
+
+#[tokio::test]
+async fn older_mutations_lose_to_newer_ones() {
+    let (_dragonfly, url) = start_dragonfly().await;
+    let peer = client(&url).await;
+    let write = |value: &str, timestamp_ms| LinkMutation {
+        code: "conflict".to_string(),
+        value: Some(value.to_string()),
+        timestamp_ms,
+    };
+
+    assert!(replication::apply(&peer, &write("newer", 2_000), 60).await.unwrap());
+    // A retry or a reordered write from before the newer one is ignored
+    assert!(!replication::apply(&peer, &write("older", 1_000), 60).await.unwrap());
+    assert_eq!(peer.get("conflict").await.unwrap(), "newer");
+
+    let delete = LinkMutation { code: "conflict".to_string(), value: None, timestamp_ms: 3_000 };
+    assert!(replication::apply(&peer, &delete, 60).await.unwrap());
+    assert!(peer.get("conflict").await.is_err());
+    // The delete's version outlives the link, so a late write doesn't resurrect it
+    assert!(!replication::apply(&peer, &write("late", 2_500), 60).await.unwrap());
+}
+
+#[tokio::test]
+async fn mutations_apply_on_the_node_that_owns_the_code() {
+    let nodes = [start_dragonfly().await, start_dragonfly().await, start_dragonfly().await];
+    let urls: Vec<String> = nodes.iter().map(|(_, url)| url.clone()).collect();
+    let circuit_breaker = Arc::new(CircuitBreaker::new(urls.clone(), 3, Duration::from_secs(1)));
+    let ring = DatabaseClient::connect(&urls, &CacheConfig::default(), Vec::new(), circuit_breaker).await.unwrap();
+    let codes: Vec<String> = (0..30).map(|i| format!("spread{}", i)).collect();
+
+    for code in &codes {
+        let write = LinkMutation { code: code.clone(), value: Some(format!("v-{}", code)), timestamp_ms: 2_000 };
+        assert!(replication::apply(&ring, &write, 60).await.unwrap());
+        assert_eq!(ring.get(code).await.unwrap(), format!("v-{}", code));
+        // The version sits with the link, so an older write is still recognised as older
+        let stale = LinkMutation { timestamp_ms: 1_000, value: Some("stale".into()), ..write };
+        assert!(!replication::apply(&ring, &stale, 60).await.unwrap());
+    }
+
+    for url in &urls {
+        let node = client(url).await;
+        for code in &codes {
+            let has_link = node.get(code).await.is_ok();
+            let has_version = node.get(&format!("replication:version:{{{}}}", code)).await.is_ok();
+            assert_eq!(has_link, has_version, "{} on {}", code, url);
+        }
+    }
+}